@@ -0,0 +1,182 @@
+//! Bridge MCP servers between stdio and HTTP.
+//!
+//! `rmcp-bridge stdio <URL>` serves a remote streamable HTTP (or SSE) server on stdio, for
+//! clients which only launch local servers. `rmcp-bridge http -- <COMMAND>` serves a stdio
+//! server as a streamable HTTP endpoint, with a child process per session.
+//!
+//! Both directions relay requests either way, so sampling and roots requests of the server
+//! reach the client. Logs go to stderr, filtered by `RUST_LOG`.
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use reqwest::header::{HeaderName, HeaderValue};
+use rmcp::{
+    ServiceExt,
+    gateway::{Gateway, Namespacing, Upstream},
+    model::Implementation,
+    transport::{
+        ConfigureCommandExt, SseClientTransport, StreamableHttpClientTransport, TokioChildProcess,
+        auth::AuthClient,
+        common::http_header::parse_header,
+        sse_client::SseClientConfig,
+        stdio,
+        streamable_http_client::{StreamableHttpClient, StreamableHttpClientTransportConfig},
+        streamable_http_server::{
+            StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+        },
+    },
+};
+use tokio::process::Command;
+use tracing_subscriber::EnvFilter;
+
+mod oauth;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "rmcp-bridge",
+    version,
+    about = "Bridge MCP servers between stdio and HTTP"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Mode,
+}
+
+#[derive(Debug, Subcommand)]
+enum Mode {
+    /// Serve a remote HTTP server on stdio
+    Stdio(StdioArgs),
+    /// Serve a stdio child process as a streamable HTTP endpoint
+    Http(HttpArgs),
+}
+
+#[derive(Debug, Args)]
+struct StdioArgs {
+    /// URL of the remote server
+    url: String,
+    /// Connect with the legacy SSE transport, the URL being the SSE endpoint
+    #[arg(long)]
+    sse: bool,
+    /// Header sent with every request, like `Authorization: Bearer <token>`
+    #[arg(short = 'H', long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    headers: Vec<(HeaderName, HeaderValue)>,
+    #[command(flatten)]
+    oauth: oauth::OAuthArgs,
+}
+
+#[derive(Debug, Args)]
+struct HttpArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8000")]
+    bind: SocketAddr,
+    /// Path of the endpoint
+    #[arg(long, default_value = "/mcp")]
+    path: String,
+    /// Start a child process for every request instead of every session
+    #[arg(long)]
+    stateless: bool,
+    /// Interval of the keep alive pings of the event streams, in seconds
+    #[arg(long, default_value_t = 15)]
+    keep_alive: u64,
+    /// The command of the server, and its arguments
+    #[arg(required = true, last = true)]
+    command: Vec<String>,
+}
+
+fn gateway(upstream: Upstream) -> Gateway {
+    Gateway::new()
+        .upstream(upstream)
+        .namespacing(Namespacing::None)
+        .with_info(Implementation {
+            name: env!("CARGO_PKG_NAME").to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+        })
+}
+
+fn http_upstream<C>(client: C, args: &StdioArgs) -> Upstream
+where
+    C: StreamableHttpClient + rmcp::transport::sse_client::SseClient + Sync,
+{
+    let url = args.url.clone();
+    if args.sse {
+        Upstream::new("remote", move || {
+            SseClientTransport::start_with_client(
+                client.clone(),
+                SseClientConfig {
+                    sse_endpoint: url.clone().into(),
+                    ..Default::default()
+                },
+            )
+        })
+    } else {
+        Upstream::new("remote", move || {
+            let transport = StreamableHttpClientTransport::with_client(
+                client.clone(),
+                StreamableHttpClientTransportConfig::with_uri(url.clone()),
+            );
+            std::future::ready(Ok::<_, std::convert::Infallible>(transport))
+        })
+    }
+}
+
+async fn serve_stdio(args: StdioArgs) -> Result<()> {
+    let client = reqwest::Client::builder()
+        .default_headers(args.headers.iter().cloned().collect())
+        .build()
+        .context("failed to build the http client")?;
+    let upstream = if args.oauth.oauth {
+        let manager = args.oauth.authorize(&args.url, client.clone()).await?;
+        http_upstream(AuthClient::new(client, manager), &args)
+    } else {
+        http_upstream(client, &args)
+    };
+    gateway(upstream).serve(stdio()).await?.waiting().await?;
+    Ok(())
+}
+
+async fn serve_http(args: HttpArgs) -> Result<()> {
+    let (program, program_args) = args
+        .command
+        .split_first()
+        .context("missing the server command")?;
+    let (program, program_args) = (program.clone(), program_args.to_vec());
+    let upstream = Upstream::new("local", move || {
+        let command = Command::new(&program).configure(|command| {
+            command.args(&program_args);
+        });
+        std::future::ready(TokioChildProcess::new(command))
+    });
+    let gateway = gateway(upstream);
+    let service = StreamableHttpService::new(
+        move || Ok(gateway.clone()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig {
+            stateful_mode: !args.stateless,
+            sse_keep_alive: (args.keep_alive > 0).then(|| Duration::from_secs(args.keep_alive)),
+            ..Default::default()
+        },
+    );
+    let router = axum::Router::new().nest_service(&args.path, service);
+    let listener = tokio::net::TcpListener::bind(args.bind).await?;
+    tracing::info!("serving on http://{}{}", listener.local_addr()?, args.path);
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .init();
+    match Cli::parse().command {
+        Mode::Stdio(args) => serve_stdio(args).await,
+        Mode::Http(args) => serve_http(args).await,
+    }
+}
@@ -0,0 +1,181 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::http::request::Parts;
+use rmcp::{
+    ErrorData, ServiceExt,
+    model::*,
+    service::{RequestContext, RoleServer},
+    testing::{MockClient, MockServer},
+    transport::{
+        ConfigureCommandExt, StreamableHttpClientTransport, TokioChildProcess,
+        streamable_http_server::{
+            StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+        },
+    },
+};
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+
+const BRIDGE: &str = env!("CARGO_BIN_EXE_rmcp-bridge");
+
+/// A server whose tool asks the client for a completion, and reports the `x-test` header.
+fn sampling_server() -> MockServer {
+    let tool = Tool::new("ask", "ask the client", Arc::new(JsonObject::default()));
+    MockServer::new().tool(tool, |_, context: RequestContext<RoleServer>| async move {
+        let header = context
+            .extensions
+            .get::<Parts>()
+            .and_then(|parts| parts.headers.get("x-test"))
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("none")
+            .to_owned();
+        let result = context
+            .peer
+            .create_message(CreateMessageRequestParam {
+                messages: vec![SamplingMessage {
+                    role: Role::User,
+                    content: Content::text("hello"),
+                }],
+                model_preferences: None,
+                system_prompt: None,
+                include_context: None,
+                temperature: None,
+                max_tokens: 16,
+                stop_sequences: None,
+                metadata: None,
+            })
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        let reply = result.message.content.as_text().unwrap().text.clone();
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "{header} {reply}"
+        ))]))
+    })
+}
+
+fn sampling_client() -> MockClient {
+    MockClient::new()
+        .with_info(ClientInfo {
+            capabilities: ClientCapabilities::builder().enable_sampling().build(),
+            ..Default::default()
+        })
+        .on_request(|_, _| async {
+            Ok(ClientResult::CreateMessageResult(CreateMessageResult {
+                message: SamplingMessage {
+                    role: Role::Assistant,
+                    content: Content::text("hi"),
+                },
+                model: "test".into(),
+                stop_reason: None,
+            }))
+        })
+}
+
+async fn serve_http() -> anyhow::Result<(String, CancellationToken)> {
+    let service = StreamableHttpService::new(
+        || Ok(sampling_server()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig::default(),
+    );
+    let router = axum::Router::new().nest_service("/mcp", service);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let ct = CancellationToken::new();
+    let shutdown = ct.clone();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+    });
+    Ok((format!("http://{addr}/mcp"), ct))
+}
+
+async fn ask<S: rmcp::Service<rmcp::RoleClient>>(
+    client: &rmcp::service::RunningService<rmcp::RoleClient, S>,
+) -> anyhow::Result<String> {
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "ask".into(),
+            arguments: None,
+        })
+        .await?;
+    Ok(result.content.unwrap()[0].as_text().unwrap().text.clone())
+}
+
+#[tokio::test]
+async fn test_stdio_to_http() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+    let bridge = TokioChildProcess::new(Command::new(BRIDGE).configure(|command| {
+        command.args(["stdio", &url, "-H", "X-Test: yes"]);
+    }))?;
+    let client = sampling_client().serve(bridge).await?;
+    assert_eq!(ask(&client).await?, "yes hi");
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
+
+/// Start `rmcp-bridge http` with `args` in front of a bridge to `url`, returning its endpoint.
+fn bridge_http(url: &str, args: &[&str]) -> anyhow::Result<(tokio::process::Child, String)> {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let bind = format!("127.0.0.1:{port}");
+    // the stdio server is itself a bridge to the http server
+    let bridge = Command::new(BRIDGE)
+        .args(["http", "--bind", &bind])
+        .args(args)
+        .args(["--", BRIDGE, "stdio", url])
+        .kill_on_drop(true)
+        .spawn()?;
+    Ok((bridge, format!("http://{bind}/mcp")))
+}
+
+/// Connect `client` to the bridge, which may not listen yet.
+async fn connect_bridge<S: rmcp::Service<rmcp::RoleClient> + Clone>(
+    client: S,
+    url: &str,
+) -> anyhow::Result<rmcp::service::RunningService<rmcp::RoleClient, S>> {
+    let mut attempts = 0;
+    loop {
+        match client
+            .clone()
+            .serve(StreamableHttpClientTransport::from_uri(url.to_owned()))
+            .await
+        {
+            Ok(client) => return Ok(client),
+            Err(_) if attempts < 50 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_http_to_stdio() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+    let (mut bridge, bridge_url) = bridge_http(&url, &[])?;
+    let client = connect_bridge(sampling_client(), &bridge_url).await?;
+    assert_eq!(ask(&client).await?, "none hi");
+    client.cancel().await?;
+    bridge.kill().await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stateless_http_to_stdio() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+    let (mut bridge, bridge_url) = bridge_http(&url, &["--stateless"])?;
+    // every request starts its own server
+    let client = connect_bridge(MockClient::new(), &bridge_url).await?;
+    let tools = client.list_all_tools().await?;
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].name, "ask");
+    client.cancel().await?;
+    bridge.kill().await?;
+    ct.cancel();
+    Ok(())
+}
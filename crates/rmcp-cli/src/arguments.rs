@@ -0,0 +1,112 @@
+//! Arguments of the tool calls and prompts, given as a JSON object or `key=value` pairs.
+use anyhow::{Context, Result};
+use rmcp::model::JsonObject;
+use serde_json::Value;
+
+/// Whether the arguments are `key=value` pairs, rather than a JSON object.
+pub fn are_pairs(arguments: &[String]) -> bool {
+    !matches!(arguments, [only] if only.trim_start().starts_with('{'))
+}
+
+/// The arguments of a tool call.
+///
+/// The values of `key=value` pairs are parsed as JSON, unless the input schema declares a string
+/// property, or they are not valid JSON.
+pub fn tool_arguments(
+    arguments: &[String],
+    schema: Option<&JsonObject>,
+) -> Result<Option<JsonObject>> {
+    parse(arguments, |key, value| {
+        let expects_string = schema
+            .and_then(|schema| schema.get("properties")?.get(key)?.get("type"))
+            .is_some_and(|kind| kind == "string");
+        if expects_string {
+            Value::String(value.to_owned())
+        } else {
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_owned()))
+        }
+    })
+}
+
+/// The arguments of a prompt, whose values are strings.
+pub fn prompt_arguments(arguments: &[String]) -> Result<Option<JsonObject>> {
+    parse(arguments, |_, value| Value::String(value.to_owned()))
+}
+
+fn parse(arguments: &[String], value: impl Fn(&str, &str) -> Value) -> Result<Option<JsonObject>> {
+    if arguments.is_empty() {
+        return Ok(None);
+    }
+    if !are_pairs(arguments) {
+        return serde_json::from_str(&arguments[0])
+            .map(Some)
+            .context("the arguments are not a JSON object");
+    }
+    arguments
+        .iter()
+        .map(|argument| {
+            let (key, raw) = argument
+                .split_once('=')
+                .with_context(|| format!("expected `key=value`, got `{argument}`"))?;
+            Ok((key.to_owned(), value(key, raw)))
+        })
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn strings(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_tool_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" }, "count": { "type": "integer" } }
+        });
+        let schema = schema.as_object();
+        assert_eq!(tool_arguments(&[], schema).unwrap(), None);
+        assert_eq!(
+            Value::Object(
+                tool_arguments(
+                    &strings(&["name=42", "count=3", "tags=[\"a\"]", "x=a b"]),
+                    schema
+                )
+                .unwrap()
+                .unwrap()
+            ),
+            json!({ "name": "42", "count": 3, "tags": ["a"], "x": "a b" })
+        );
+        assert_eq!(
+            Value::Object(
+                tool_arguments(&strings(&[r#"{"count": 1}"#]), schema)
+                    .unwrap()
+                    .unwrap()
+            ),
+            json!({ "count": 1 })
+        );
+        assert!(tool_arguments(&strings(&["count"]), schema).is_err());
+        assert!(tool_arguments(&strings(&["{not json"]), schema).is_err());
+    }
+
+    #[test]
+    fn test_prompt_arguments() {
+        assert_eq!(
+            Value::Object(
+                prompt_arguments(&strings(&["n=1", "s=a=b"]))
+                    .unwrap()
+                    .unwrap()
+            ),
+            json!({ "n": "1", "s": "a=b" })
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! The commands run against the server, from the command line or the interactive shell.
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use rmcp::{RoleClient, model::*, service::Peer};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{arguments, output};
+
+#[derive(Debug, Subcommand)]
+pub enum Action {
+    /// Print the server info and capabilities
+    Info,
+    /// List the tools
+    Tools,
+    /// List the prompts
+    Prompts,
+    /// List the resources
+    Resources,
+    /// List the resource templates
+    Templates,
+    /// Call a tool
+    Call {
+        /// Name of the tool
+        tool: String,
+        /// A JSON object, or `key=value` pairs whose values are parsed as JSON unless the tool
+        /// expects a string
+        arguments: Vec<String>,
+    },
+    /// Get a prompt
+    Prompt {
+        /// Name of the prompt
+        name: String,
+        /// A JSON object, or `key=value` pairs
+        arguments: Vec<String>,
+    },
+    /// Read a resource
+    Read { uri: String },
+    /// Subscribe to the updates of a resource
+    Subscribe { uri: String },
+    /// Unsubscribe from the updates of a resource
+    Unsubscribe { uri: String },
+    /// Set the minimum level of the log messages sent by the server
+    Level {
+        #[arg(value_parser = parse_level)]
+        level: LoggingLevel,
+    },
+    /// Ping the server
+    Ping,
+}
+
+pub const LEVELS: [&str; 8] = [
+    "debug",
+    "info",
+    "notice",
+    "warning",
+    "error",
+    "critical",
+    "alert",
+    "emergency",
+];
+
+pub fn parse_level(level: &str) -> Result<LoggingLevel, String> {
+    serde_json::from_value(Value::String(level.to_lowercase()))
+        .map_err(|_| format!("expected one of {}", LEVELS.join(", ")))
+}
+
+/// A tool call whose result is an error, with the printed result.
+#[derive(Debug)]
+pub struct ToolFailed(pub String);
+
+impl std::fmt::Display for ToolFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the tool returned an error")
+    }
+}
+
+impl std::error::Error for ToolFailed {}
+
+/// Print an error on stderr, and the result of a failed tool call on stdout.
+pub fn report(error: &anyhow::Error) {
+    if let Some(ToolFailed(output)) = error.downcast_ref() {
+        print!("{output}");
+    }
+    eprintln!("error: {error:#}");
+}
+
+fn render<T: Serialize>(json: bool, value: &T, human: impl FnOnce(&T) -> String) -> String {
+    if json {
+        output::json(value)
+    } else {
+        human(value)
+    }
+}
+
+impl Action {
+    /// Run the action, and return its printed result.
+    pub async fn run(self, peer: &Peer<RoleClient>, json: bool) -> Result<String> {
+        let output = match self {
+            Action::Info => {
+                let info = peer.peer_info().context("the session is not initialized")?;
+                render(json, info, output::server_info)
+            }
+            Action::Tools => render(json, &peer.list_all_tools().await?, |tools| {
+                output::tools(tools)
+            }),
+            Action::Prompts => render(json, &peer.list_all_prompts().await?, |prompts| {
+                output::prompts(prompts)
+            }),
+            Action::Resources => render(json, &peer.list_all_resources().await?, |resources| {
+                output::resources(resources)
+            }),
+            Action::Templates => render(
+                json,
+                &peer.list_all_resource_templates().await?,
+                |templates| output::templates(templates),
+            ),
+            Action::Call { tool, arguments } => {
+                let schema = if !arguments.is_empty() && arguments::are_pairs(&arguments) {
+                    peer.list_all_tools()
+                        .await?
+                        .into_iter()
+                        .find(|listed| listed.name == tool)
+                        .map(|listed| listed.input_schema)
+                } else {
+                    None
+                };
+                let arguments = arguments::tool_arguments(&arguments, schema.as_deref())?;
+                let result = peer
+                    .call_tool(CallToolRequestParam {
+                        name: tool.into(),
+                        arguments,
+                    })
+                    .await?;
+                let output = render(json, &result, output::call_result);
+                if result.is_error == Some(true) {
+                    return Err(ToolFailed(output).into());
+                }
+                output
+            }
+            Action::Prompt { name, arguments } => {
+                let result = peer
+                    .get_prompt(GetPromptRequestParam {
+                        name,
+                        arguments: arguments::prompt_arguments(&arguments)?,
+                    })
+                    .await?;
+                render(json, &result, output::prompt_result)
+            }
+            Action::Read { uri } => {
+                let result = peer.read_resource(ReadResourceRequestParam { uri }).await?;
+                render(json, &result, output::read_result)
+            }
+            Action::Subscribe { uri } => {
+                peer.subscribe(SubscribeRequestParam { uri }).await?;
+                String::new()
+            }
+            Action::Unsubscribe { uri } => {
+                peer.unsubscribe(UnsubscribeRequestParam { uri }).await?;
+                String::new()
+            }
+            Action::Level { level } => {
+                peer.set_level(SetLevelRequestParam { level }).await?;
+                String::new()
+            }
+            Action::Ping => {
+                peer.ping().await?;
+                String::new()
+            }
+        };
+        Ok(output)
+    }
+}
@@ -0,0 +1,101 @@
+//! Connection to the inspected server.
+use anyhow::{Context, Result};
+use clap::Args;
+use reqwest::header::{HeaderName, HeaderValue};
+use rmcp::{
+    RoleClient, ServiceExt,
+    service::RunningService,
+    transport::{
+        ConfigureCommandExt, SseClientTransport, StreamableHttpClientTransport, TokioChildProcess,
+        auth::AuthClient,
+        common::http_header::parse_header,
+        sse_client::{SseClient, SseClientConfig},
+        streamable_http_client::{StreamableHttpClient, StreamableHttpClientTransportConfig},
+    },
+};
+use tokio::process::Command;
+
+use crate::{inspector::Inspector, oauth::OAuthArgs};
+
+pub type Client = RunningService<RoleClient, Inspector>;
+
+#[derive(Debug, Args)]
+pub struct Target {
+    /// URL of a streamable HTTP server, or the command line of a stdio server
+    #[arg(value_name = "URL|COMMAND")]
+    pub target: String,
+    /// Connect with the legacy SSE transport, the URL being the SSE endpoint
+    #[arg(long)]
+    pub sse: bool,
+    /// Header sent with every request, like `Authorization: Bearer <token>`
+    #[arg(short = 'H', long = "header", value_name = "NAME: VALUE", value_parser = parse_header)]
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    #[command(flatten)]
+    pub oauth: OAuthArgs,
+}
+
+impl Target {
+    fn url(&self) -> Option<&str> {
+        (self.target.starts_with("http://") || self.target.starts_with("https://"))
+            .then_some(self.target.as_str())
+    }
+
+    /// Connect to the server, and initialize the session.
+    pub async fn connect(&self, inspector: Inspector) -> Result<Client> {
+        match self.url() {
+            Some(url) => self.connect_http(url, inspector).await,
+            None => {
+                anyhow::ensure!(
+                    !self.sse && self.headers.is_empty() && !self.oauth.oauth,
+                    "`--sse`, `--header` and `--oauth` only apply to URLs"
+                );
+                let words = shlex::split(&self.target)
+                    .with_context(|| format!("invalid command line `{}`", self.target))?;
+                let (program, args) = words.split_first().context("missing the server command")?;
+                let transport =
+                    TokioChildProcess::new(Command::new(program).configure(|command| {
+                        command.args(args);
+                    }))
+                    .with_context(|| format!("failed to start `{program}`"))?;
+                Ok(inspector.serve(transport).await?)
+            }
+        }
+    }
+
+    async fn connect_http(&self, url: &str, inspector: Inspector) -> Result<Client> {
+        let client = reqwest::Client::builder()
+            .default_headers(self.headers.iter().cloned().collect())
+            .build()
+            .context("failed to build the http client")?;
+        if self.oauth.oauth {
+            let manager = self.oauth.authorize(url, client.clone()).await?;
+            serve_http(AuthClient::new(client, manager), url, self.sse, inspector).await
+        } else {
+            serve_http(client, url, self.sse, inspector).await
+        }
+    }
+}
+
+async fn serve_http<C>(client: C, url: &str, sse: bool, inspector: Inspector) -> Result<Client>
+where
+    C: StreamableHttpClient + SseClient + Sync,
+{
+    if sse {
+        let transport = SseClientTransport::start_with_client(
+            client,
+            SseClientConfig {
+                sse_endpoint: url.to_owned().into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .context("failed to connect to the SSE endpoint")?;
+        Ok(inspector.serve(transport).await?)
+    } else {
+        let transport = StreamableHttpClientTransport::with_client(
+            client,
+            StreamableHttpClientTransportConfig::with_uri(url.to_owned()),
+        );
+        Ok(inspector.serve(transport).await?)
+    }
+}
@@ -0,0 +1,100 @@
+//! The client handler, forwarding the notifications of the server.
+use rmcp::{
+    ClientHandler,
+    model::*,
+    service::{NotificationContext, RoleClient},
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// A notification of the server.
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub method: &'static str,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+impl Event {
+    /// Whether the tools, prompts or resources of the server changed.
+    pub fn is_list_changed(&self) -> bool {
+        self.method.ends_with("/list_changed")
+    }
+}
+
+/// Sends the notifications of the server to a channel, they are dropped once it is closed.
+#[derive(Debug, Clone)]
+pub struct Inspector {
+    events: mpsc::UnboundedSender<Event>,
+}
+
+impl Inspector {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Event>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        (Self { events }, receiver)
+    }
+
+    fn send<M: ConstString>(&self, params: impl Serialize) {
+        let _ = self.events.send(Event {
+            method: M::VALUE,
+            params: serde_json::to_value(params).unwrap_or_default(),
+        });
+    }
+}
+
+impl ClientHandler for Inspector {
+    async fn on_cancelled(
+        &self,
+        params: CancelledNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.send::<CancelledNotificationMethod>(params);
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.send::<ProgressNotificationMethod>(params);
+    }
+
+    async fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.send::<LoggingMessageNotificationMethod>(params);
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        self.send::<ResourceUpdatedNotificationMethod>(params);
+    }
+
+    async fn on_resource_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.send::<ResourceListChangedNotificationMethod>(Value::Null);
+    }
+
+    async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.send::<ToolListChangedNotificationMethod>(Value::Null);
+    }
+
+    async fn on_prompt_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        self.send::<PromptListChangedNotificationMethod>(Value::Null);
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            client_info: Implementation {
+                name: env!("CARGO_PKG_NAME").to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+            },
+            ..Default::default()
+        }
+    }
+}
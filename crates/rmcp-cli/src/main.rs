@@ -0,0 +1,122 @@
+//! Inspect MCP servers from the command line.
+//!
+//! `rmcp <TARGET> <COMMAND>` connects to a server, runs the command and disconnects. The target
+//! is the URL of a streamable HTTP (or, with `--sse`, SSE) server, or the command line of a
+//! stdio server. Without a command, an interactive shell is started.
+//!
+//! Results are printed on stdout, as JSON with `--json`. Logs go to stderr, filtered by
+//! `RUST_LOG`.
+use std::{process::ExitCode, time::Duration};
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use rmcp::model::{LoggingLevel, SetLevelRequestParam, SubscribeRequestParam};
+use tokio::sync::mpsc;
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    commands::{Action, parse_level},
+    connect::{Client, Target},
+    inspector::{Event, Inspector},
+};
+
+mod arguments;
+mod commands;
+mod connect;
+mod inspector;
+mod oauth;
+mod output;
+mod repl;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "rmcp",
+    version,
+    about = "Inspect MCP servers from the command line"
+)]
+struct Cli {
+    #[command(flatten)]
+    target: Target,
+    /// Print the results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(flatten)]
+    Action(Action),
+    /// Print the notifications and log messages of the server until interrupted
+    Tail {
+        /// Ask the server for the log messages of this level and above
+        #[arg(long, value_parser = parse_level)]
+        level: Option<LoggingLevel>,
+        /// Subscribe to the updates of a resource
+        #[arg(long = "subscribe", value_name = "URI")]
+        subscriptions: Vec<String>,
+    },
+    /// Start the interactive shell, the default
+    Repl,
+}
+
+async fn tail(
+    client: &Client,
+    mut events: mpsc::UnboundedReceiver<Event>,
+    level: Option<LoggingLevel>,
+    subscriptions: Vec<String>,
+    json: bool,
+) -> Result<()> {
+    let peer = client.peer();
+    if let Some(level) = level {
+        peer.set_level(SetLevelRequestParam { level }).await?;
+    }
+    for uri in subscriptions {
+        peer.subscribe(SubscribeRequestParam { uri }).await?;
+    }
+    let mut check = tokio::time::interval(Duration::from_millis(500));
+    loop {
+        tokio::select! {
+            Some(event) = events.recv() => println!("{}", output::event(&event, json)),
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+            _ = check.tick() => if peer.is_transport_closed() {
+                anyhow::bail!("the server closed the connection");
+            },
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let (inspector, events) = Inspector::new();
+    let client = cli.target.connect(inspector).await?;
+    let result = match cli.command.unwrap_or(Command::Repl) {
+        Command::Action(action) => action
+            .run(client.peer(), cli.json)
+            .await
+            .map(|output| print!("{output}")),
+        Command::Tail {
+            level,
+            subscriptions,
+        } => tail(&client, events, level, subscriptions, cli.json).await,
+        Command::Repl => repl::run(&client, events, cli.json).await,
+    };
+    client.cancel().await?;
+    result
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "warn".into()))
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .init();
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            commands::report(&error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Interactive OAuth authorization of the remote server.
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use clap::Args;
+use rmcp::transport::auth::{AuthorizationManager, AuthorizationSession, CallbackServer};
+
+#[derive(Debug, Args)]
+pub struct OAuthArgs {
+    /// Authorize with OAuth before connecting, the authorization URL is printed on stderr
+    #[arg(long)]
+    pub oauth: bool,
+    /// Scope requested by the authorization
+    #[arg(long = "scope", requires = "oauth")]
+    pub scopes: Vec<String>,
+    /// Local port receiving the authorization code
+    #[arg(long, default_value_t = 8080, requires = "oauth")]
+    pub callback_port: u16,
+}
+
+impl OAuthArgs {
+    /// Run the authorization code flow, the browser is redirected to a local callback.
+    pub async fn authorize(
+        &self,
+        url: &str,
+        client: reqwest::Client,
+    ) -> Result<AuthorizationManager> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], self.callback_port));
+        let callback = CallbackServer::bind(addr)
+            .await
+            .with_context(|| format!("failed to listen on {addr} for the oauth callback"))?;
+
+        let mut manager = AuthorizationManager::new(url).await?;
+        manager.with_client(client)?;
+        let metadata = manager
+            .discover_metadata()
+            .await
+            .context("failed to discover the authorization server")?;
+        manager.set_metadata(metadata);
+        let scopes = self.scopes.iter().map(String::as_str).collect::<Vec<_>>();
+        let session = AuthorizationSession::new(manager, &scopes, callback.redirect_uri())
+            .await
+            .context("failed to start the authorization")?;
+        eprintln!(
+            "Open this URL to authorize the connection:\n\n{}\n",
+            session.get_authorization_url()
+        );
+        callback.authorize(&session).await?;
+        Ok(session.auth_manager)
+    }
+}
@@ -0,0 +1,244 @@
+//! Human readable output of the results.
+use std::fmt::Write;
+
+use rmcp::model::*;
+use serde_json::Value;
+
+use crate::inspector::Event;
+
+/// Two columns, the first one padded to its widest cell.
+fn table(rows: impl IntoIterator<Item = (String, String)>) -> String {
+    let rows = rows.into_iter().collect::<Vec<_>>();
+    let width = rows.iter().map(|(first, _)| first.len()).max().unwrap_or(0);
+    let mut table = String::new();
+    for (first, second) in rows {
+        if second.is_empty() {
+            writeln!(table, "{first}").unwrap();
+        } else {
+            writeln!(table, "{first:width$}  {second}").unwrap();
+        }
+    }
+    table
+}
+
+fn first_line(text: Option<&str>) -> String {
+    text.and_then(|text| text.lines().next())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// `name(required: type, optional?: type)`, from the input schema of the tool.
+fn tool_signature(tool: &Tool) -> String {
+    let required = tool
+        .input_schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let parameters = tool
+        .input_schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .map(|(name, schema)| {
+            let optional = if required.contains(&name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            match schema.get("type").and_then(Value::as_str) {
+                Some(kind) => format!("{name}{optional}: {kind}"),
+                None => format!("{name}{optional}"),
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("{}({})", tool.name, parameters.join(", "))
+}
+
+pub fn tools(tools: &[Tool]) -> String {
+    table(tools.iter().map(|tool| {
+        (
+            tool_signature(tool),
+            first_line(tool.description.as_deref()),
+        )
+    }))
+}
+
+pub fn prompts(prompts: &[Prompt]) -> String {
+    table(prompts.iter().map(|prompt| {
+        let arguments = prompt
+            .arguments
+            .iter()
+            .flatten()
+            .map(|argument| match argument.required {
+                Some(true) => argument.name.clone(),
+                _ => format!("{}?", argument.name),
+            })
+            .collect::<Vec<_>>();
+        (
+            format!("{}({})", prompt.name, arguments.join(", ")),
+            first_line(prompt.description.as_deref()),
+        )
+    }))
+}
+
+pub fn resources(resources: &[Resource]) -> String {
+    table(
+        resources
+            .iter()
+            .map(|resource| (resource.uri.clone(), resource.name.clone())),
+    )
+}
+
+pub fn templates(templates: &[ResourceTemplate]) -> String {
+    table(
+        templates
+            .iter()
+            .map(|template| (template.uri_template.clone(), template.name.clone())),
+    )
+}
+
+/// The approximate size of base64 data, once decoded.
+fn decoded_len(data: &str) -> usize {
+    data.trim_end_matches('=').len() * 3 / 4
+}
+
+fn resource_contents(contents: &ResourceContents) -> String {
+    match contents {
+        ResourceContents::TextResourceContents { text, .. } => text.clone(),
+        ResourceContents::BlobResourceContents {
+            uri,
+            mime_type,
+            blob,
+        } => format!(
+            "[blob {uri} ({}), {} bytes]",
+            mime_type.as_deref().unwrap_or("unknown type"),
+            decoded_len(blob)
+        ),
+    }
+}
+
+fn content(content: &RawContent) -> String {
+    match content {
+        RawContent::Text(text) => text.text.clone(),
+        RawContent::Image(image) => format!(
+            "[image ({}), {} bytes]",
+            image.mime_type,
+            decoded_len(&image.data)
+        ),
+        RawContent::Audio(audio) => format!(
+            "[audio ({}), {} bytes]",
+            audio.mime_type,
+            decoded_len(&audio.data)
+        ),
+        RawContent::Resource(resource) => resource_contents(&resource.resource),
+    }
+}
+
+fn lines(items: impl IntoIterator<Item = String>) -> String {
+    items.into_iter().fold(String::new(), |mut output, item| {
+        writeln!(output, "{item}").unwrap();
+        output
+    })
+}
+
+/// The content of the result, or its structured content when it has none.
+pub fn call_result(result: &CallToolResult) -> String {
+    match (&result.content, &result.structured_content) {
+        (Some(contents), _) if !contents.is_empty() => {
+            lines(contents.iter().map(|item| content(item)))
+        }
+        (_, Some(structured)) => json(structured),
+        _ => String::new(),
+    }
+}
+
+pub fn read_result(result: &ReadResourceResult) -> String {
+    lines(result.contents.iter().map(resource_contents))
+}
+
+pub fn prompt_result(result: &GetPromptResult) -> String {
+    lines(result.messages.iter().map(|message| {
+        let role = match message.role {
+            PromptMessageRole::User => "user",
+            PromptMessageRole::Assistant => "assistant",
+        };
+        let text = match &message.content {
+            PromptMessageContent::Text { text } => text.clone(),
+            PromptMessageContent::Image { image } => content(&RawContent::Image(image.raw.clone())),
+            PromptMessageContent::Resource { resource } => resource_contents(&resource.resource),
+        };
+        format!("{role}: {text}")
+    }))
+}
+
+pub fn server_info(info: &ServerInfo) -> String {
+    let capabilities = &info.capabilities;
+    let supported = [
+        ("tools", capabilities.tools.is_some()),
+        ("prompts", capabilities.prompts.is_some()),
+        ("resources", capabilities.resources.is_some()),
+        ("logging", capabilities.logging.is_some()),
+        ("completions", capabilities.completions.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, supported)| supported.then_some(name))
+    .collect::<Vec<_>>();
+    let mut output = lines([
+        format!("{} {}", info.server_info.name, info.server_info.version),
+        format!("protocol: {}", info.protocol_version),
+        format!("capabilities: {}", supported.join(", ")),
+    ]);
+    if let Some(instructions) = &info.instructions {
+        writeln!(output, "\n{instructions}").unwrap();
+    }
+    output
+}
+
+/// A line describing the notification.
+pub fn event(event: &Event, json: bool) -> String {
+    if json {
+        return serde_json::to_string(event).unwrap_or_default();
+    }
+    let params = &event.params;
+    match event.method {
+        LoggingMessageNotificationMethod::VALUE => {
+            let level = params["level"].as_str().unwrap_or_default();
+            let data = match &params["data"] {
+                Value::String(data) => data.clone(),
+                data => data.to_string(),
+            };
+            match params["logger"].as_str() {
+                Some(logger) => format!("[{level}] {logger}: {data}"),
+                None => format!("[{level}] {data}"),
+            }
+        }
+        ProgressNotificationMethod::VALUE => {
+            let mut progress = format!(
+                "progress {}: {}",
+                params["progressToken"], params["progress"]
+            );
+            if let Some(total) = params["total"].as_u64() {
+                write!(progress, "/{total}").unwrap();
+            }
+            if let Some(message) = params["message"].as_str() {
+                write!(progress, " {message}").unwrap();
+            }
+            progress
+        }
+        method if params.is_null() => method.to_owned(),
+        method => format!("{method} {params}"),
+    }
+}
+
+pub fn json(value: &impl serde::Serialize) -> String {
+    let mut json = serde_json::to_string_pretty(value).unwrap_or_default();
+    json.push('\n');
+    json
+}
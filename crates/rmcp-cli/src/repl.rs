@@ -0,0 +1,353 @@
+//! The interactive shell.
+//!
+//! The names of the commands, tools, prompts and resources are completed from the listings of
+//! the server, refreshed when it announces they changed. The values of the prompt arguments and
+//! of the resource template variables are completed by the server, with `completion/complete`.
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use clap::{CommandFactory, Parser};
+use rmcp::{RoleClient, model::*, service::Peer};
+use rustyline::{
+    Context, Editor, ExternalPrinter, Helper,
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::DefaultHistory,
+    validate::Validator,
+};
+use tokio::{runtime::Handle, sync::mpsc};
+
+use crate::{
+    commands::{Action, LEVELS, report},
+    connect::Client,
+    inspector::Event,
+    output,
+};
+
+/// How long the completion waits for the server.
+const COMPLETE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Parser)]
+#[command(name = "", no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    action: Action,
+}
+
+/// The listings of the server.
+#[derive(Debug, Default)]
+struct Catalogue {
+    tools: Vec<Tool>,
+    prompts: Vec<Prompt>,
+    resources: Vec<Resource>,
+    templates: Vec<ResourceTemplate>,
+}
+
+impl Catalogue {
+    /// List what the server declares, a failed listing is left empty.
+    async fn list(peer: &Peer<RoleClient>) -> Self {
+        let capabilities = peer
+            .peer_info()
+            .map(|info| info.capabilities.clone())
+            .unwrap_or_default();
+        let mut catalogue = Self::default();
+        if capabilities.tools.is_some() {
+            catalogue.tools = peer.list_all_tools().await.unwrap_or_default();
+        }
+        if capabilities.prompts.is_some() {
+            catalogue.prompts = peer.list_all_prompts().await.unwrap_or_default();
+        }
+        if capabilities.resources.is_some() {
+            catalogue.resources = peer.list_all_resources().await.unwrap_or_default();
+            catalogue.templates = peer.list_all_resource_templates().await.unwrap_or_default();
+        }
+        catalogue
+    }
+}
+
+struct Completion {
+    peer: Peer<RoleClient>,
+    catalogue: Arc<Mutex<Catalogue>>,
+    runtime: Handle,
+}
+
+impl Completion {
+    /// The values the server suggests for an argument.
+    fn complete(&self, reference: Reference, name: &str, value: &str) -> Vec<String> {
+        let request = self.peer.complete(CompleteRequestParam {
+            r#ref: reference,
+            argument: ArgumentInfo {
+                name: name.to_owned(),
+                value: value.to_owned(),
+            },
+        });
+        match self
+            .runtime
+            .block_on(tokio::time::timeout(COMPLETE_TIMEOUT, request))
+        {
+            Ok(Ok(result)) => result.completion.values,
+            _ => Vec::new(),
+        }
+    }
+
+    fn candidates(&self, words: &[&str], word: &str) -> Vec<String> {
+        let catalogue = self.catalogue.lock().expect("catalogue lock poisoned");
+        match words {
+            [] => Line::command()
+                .get_subcommands()
+                .map(|command| command.get_name().to_owned())
+                .chain(["exit".to_owned()])
+                .collect(),
+            ["call"] => names(catalogue.tools.iter().map(|tool| tool.name.as_ref())),
+            ["prompt"] => names(catalogue.prompts.iter().map(|prompt| prompt.name.as_str())),
+            ["level"] => names(LEVELS),
+            ["read" | "subscribe" | "unsubscribe"] => {
+                let mut candidates = names(
+                    catalogue
+                        .resources
+                        .iter()
+                        .map(|resource| resource.uri.as_str()),
+                );
+                let templates = catalogue
+                    .templates
+                    .iter()
+                    .map(|template| template.uri_template.clone())
+                    .collect::<Vec<_>>();
+                drop(catalogue);
+                for template in templates {
+                    match template_variable(&template, word) {
+                        Some((typed, variable, value)) => candidates.extend(
+                            self.complete(
+                                Reference::Resource(ResourceReference {
+                                    uri: template.clone(),
+                                }),
+                                variable,
+                                value,
+                            )
+                            .into_iter()
+                            .map(|value| format!("{typed}{value}")),
+                        ),
+                        // the text before the first variable
+                        None => candidates.extend(template.split('{').next().map(str::to_owned)),
+                    }
+                }
+                candidates
+            }
+            ["call", tool, ..] => catalogue
+                .tools
+                .iter()
+                .find(|listed| listed.name == *tool)
+                .and_then(|listed| listed.input_schema.get("properties")?.as_object())
+                .into_iter()
+                .flatten()
+                .map(|(name, _)| format!("{name}="))
+                .collect(),
+            ["prompt", prompt, ..] => match word.split_once('=') {
+                Some((name, value)) => {
+                    drop(catalogue);
+                    self.complete(
+                        Reference::Prompt(PromptReference {
+                            name: prompt.to_string(),
+                        }),
+                        name,
+                        value,
+                    )
+                    .into_iter()
+                    .map(|value| format!("{name}={value}"))
+                    .collect()
+                }
+                None => catalogue
+                    .prompts
+                    .iter()
+                    .find(|listed| listed.name == *prompt)
+                    .and_then(|listed| listed.arguments.as_ref())
+                    .into_iter()
+                    .flatten()
+                    .map(|argument| format!("{}=", argument.name))
+                    .collect(),
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    names.into_iter().map(str::to_owned).collect()
+}
+
+/// The variable of the URI template being typed in `word`: the text typed before it, its name
+/// and its partial value.
+fn template_variable<'a>(template: &'a str, word: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+    let mut template = template;
+    let mut typed = 0;
+    loop {
+        let open = template.find('{')?;
+        typed += word[typed..]
+            .starts_with(&template[..open])
+            .then_some(open)?;
+        let close = open + template[open..].find('}')?;
+        let variable =
+            template[open + 1..close].trim_start_matches(['+', '#', '.', '/', ';', '?', '&']);
+        template = &template[close + 1..];
+        let literal = &template[..template.find('{').unwrap_or(template.len())];
+        match word[typed..].find(literal).filter(|_| !literal.is_empty()) {
+            // the value is complete, continue with the next variable
+            Some(end) => typed += end,
+            None => return Some((&word[..typed], variable, &word[typed..])),
+        }
+    }
+}
+
+impl Completer for Completion {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |space| space + 1);
+        let (words, word) = (
+            line[..start].split_whitespace().collect::<Vec<_>>(),
+            &line[start..],
+        );
+        let candidates = self
+            .candidates(&words, word)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+/// Run the shell until the end of the input, or until the server closes the connection.
+pub async fn run(
+    client: &Client,
+    mut events: mpsc::UnboundedReceiver<Event>,
+    json: bool,
+) -> Result<()> {
+    let peer = client.peer().clone();
+    let catalogue = Arc::new(Mutex::new(Catalogue::list(&peer).await));
+    let mut editor = Editor::<Completion, DefaultHistory>::new()?;
+    editor.set_helper(Some(Completion {
+        peer: peer.clone(),
+        catalogue: catalogue.clone(),
+        runtime: Handle::current(),
+    }));
+
+    // the notifications are printed above the prompt
+    let mut print: Box<dyn FnMut(String) + Send> = match editor.create_external_printer() {
+        Ok(mut printer) => Box::new(move |line| {
+            let _ = printer.print(line);
+        }),
+        Err(_) => Box::new(|line| println!("{line}")),
+    };
+    let printer = {
+        let peer = peer.clone();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if event.is_list_changed() {
+                    let listed = Catalogue::list(&peer).await;
+                    *catalogue.lock().expect("catalogue lock poisoned") = listed;
+                }
+                print(output::event(&event, json));
+            }
+        })
+    };
+
+    let prompt = match peer.peer_info() {
+        Some(info) => format!("{}> ", info.server_info.name),
+        None => "> ".to_owned(),
+    };
+    let runtime = Handle::current();
+    let result =
+        tokio::task::spawn_blocking(move || shell(editor, &prompt, &peer, json, runtime)).await?;
+    printer.abort();
+    result
+}
+
+fn shell(
+    mut editor: Editor<Completion, DefaultHistory>,
+    prompt: &str,
+    peer: &Peer<RoleClient>,
+    json: bool,
+    runtime: Handle,
+) -> Result<()> {
+    loop {
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        if matches!(line, "exit" | "quit") {
+            return Ok(());
+        }
+        let Some(words) = shlex::split(line) else {
+            eprintln!("error: unbalanced quotes");
+            continue;
+        };
+        match Line::try_parse_from(words) {
+            Ok(Line { action }) => match runtime.block_on(action.run(peer, json)) {
+                Ok(output) => print!("{output}"),
+                Err(error) => report(&error),
+            },
+            Err(error) => {
+                let _ = error.print();
+            }
+        }
+        if peer.is_transport_closed() {
+            anyhow::bail!("the server closed the connection");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_variable() {
+        let template = "repo://{owner}/{name}/blob{+path}";
+        assert_eq!(template_variable(template, "repo:"), None);
+        assert_eq!(
+            template_variable(template, "repo://rust"),
+            Some(("repo://", "owner", "rust"))
+        );
+        assert_eq!(
+            template_variable(template, "repo://rust-lang/r"),
+            Some(("repo://rust-lang/", "name", "r"))
+        );
+        assert_eq!(
+            template_variable(template, "repo://rust-lang/rust/blob/src"),
+            Some(("repo://rust-lang/rust/blob", "path", "/src"))
+        );
+        assert_eq!(template_variable("file:///{path}", "https://"), None);
+    }
+}
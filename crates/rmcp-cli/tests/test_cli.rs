@@ -0,0 +1,273 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use rmcp::{
+    ErrorData,
+    model::*,
+    testing::MockServer,
+    transport::streamable_http_server::{
+        StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+    },
+};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    process::Command,
+};
+use tokio_util::sync::CancellationToken;
+
+const RMCP: &str = env!("CARGO_BIN_EXE_rmcp");
+
+fn echo_tool() -> Tool {
+    let schema = json!({
+        "type": "object",
+        "properties": { "text": { "type": "string" }, "times": { "type": "integer" } },
+        "required": ["text"]
+    });
+    Tool::new(
+        "echo",
+        "Repeat a text",
+        Arc::new(schema.as_object().unwrap().clone()),
+    )
+}
+
+fn server() -> MockServer {
+    MockServer::new()
+        .with_info(ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_completions()
+                .enable_prompts()
+                .enable_resources()
+                .enable_tools()
+                .build(),
+            server_info: Implementation {
+                name: "test-server".into(),
+                version: "1.0.0".into(),
+            },
+            ..Default::default()
+        })
+        .tool(echo_tool(), |arguments, _| async move {
+            let text = arguments["text"].as_str().unwrap_or_default();
+            let times = arguments.get("times").and_then(Value::as_u64).unwrap_or(1);
+            Ok(CallToolResult::success(vec![Content::text(
+                text.repeat(times as usize),
+            )]))
+        })
+        .tool(
+            Tool::new("fail", "Always fail", Arc::new(JsonObject::default())),
+            |_, _| async { Ok(CallToolResult::error(vec![Content::text("broken")])) },
+        )
+        .on_request(|request, context| async move {
+            let result = match request {
+                ClientRequest::ListPromptsRequest(_) => {
+                    ServerResult::ListPromptsResult(ListPromptsResult {
+                        prompts: vec![Prompt::new(
+                            "greet",
+                            Some("Greet someone"),
+                            Some(vec![PromptArgument {
+                                name: "name".into(),
+                                description: None,
+                                required: Some(true),
+                            }]),
+                        )],
+                        next_cursor: None,
+                    })
+                }
+                ClientRequest::GetPromptRequest(request) => {
+                    let name = request.params.arguments.unwrap_or_default()["name"].clone();
+                    ServerResult::GetPromptResult(GetPromptResult {
+                        description: None,
+                        messages: vec![PromptMessage::new_text(
+                            PromptMessageRole::User,
+                            format!("Hello {}", name.as_str().unwrap_or_default()),
+                        )],
+                    })
+                }
+                ClientRequest::ListResourcesRequest(_) => {
+                    ServerResult::ListResourcesResult(ListResourcesResult {
+                        resources: vec![RawResource::new("memo://notes", "notes").no_annotation()],
+                        next_cursor: None,
+                    })
+                }
+                ClientRequest::ListResourceTemplatesRequest(_) => {
+                    ServerResult::ListResourceTemplatesResult(ListResourceTemplatesResult {
+                        resource_templates: vec![
+                            RawResourceTemplate {
+                                uri_template: "memo://{name}".into(),
+                                name: "memo".into(),
+                                description: None,
+                                mime_type: None,
+                            }
+                            .no_annotation(),
+                        ],
+                        next_cursor: None,
+                    })
+                }
+                ClientRequest::ReadResourceRequest(request) => {
+                    ServerResult::ReadResourceResult(ReadResourceResult {
+                        contents: vec![ResourceContents::text(
+                            format!("contents of {}", request.params.uri),
+                            request.params.uri,
+                        )],
+                    })
+                }
+                ClientRequest::SetLevelRequest(_) => {
+                    // log until the client disconnects
+                    let peer = context.peer.clone();
+                    tokio::spawn(async move {
+                        while peer
+                            .notify_logging_message(LoggingMessageNotificationParam {
+                                level: LoggingLevel::Info,
+                                logger: Some("test".into()),
+                                data: json!("working"),
+                            })
+                            .await
+                            .is_ok()
+                        {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                        }
+                    });
+                    ServerResult::empty(())
+                }
+                _ => {
+                    return Err(ErrorData::new(
+                        ErrorCode::METHOD_NOT_FOUND,
+                        "unsupported",
+                        None,
+                    ));
+                }
+            };
+            Ok(result)
+        })
+}
+
+async fn serve_http() -> anyhow::Result<(String, CancellationToken)> {
+    let service = StreamableHttpService::new(
+        || Ok(server()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig::default(),
+    );
+    let router = axum::Router::new().nest_service("/mcp", service);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let ct = CancellationToken::new();
+    let shutdown = ct.clone();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+    });
+    Ok((format!("http://{addr}/mcp"), ct))
+}
+
+/// Run the command line, and return whether it succeeded with its stdout.
+async fn rmcp(args: &[&str]) -> anyhow::Result<(bool, String)> {
+    let output = Command::new(RMCP).args(args).output().await?;
+    Ok((output.status.success(), String::from_utf8(output.stdout)?))
+}
+
+#[tokio::test]
+async fn test_commands() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+
+    let (ok, tools) = rmcp(&[&url, "tools"]).await?;
+    assert!(ok);
+    assert_eq!(
+        tools,
+        "echo(text: string, times?: integer)  Repeat a text\nfail()                               Always fail\n"
+    );
+    // a string parameter keeps a value which is valid JSON
+    assert_eq!(
+        rmcp(&[&url, "call", "echo", "text=42", "times=2"]).await?,
+        (true, "4242\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "call", "echo", r#"{"text": "hi"}"#]).await?,
+        (true, "hi\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "call", "fail"]).await?,
+        (false, "broken\n".to_owned())
+    );
+    let (ok, result) = rmcp(&[&url, "--json", "call", "echo", "text=hi"]).await?;
+    assert!(ok);
+    assert_eq!(
+        serde_json::from_str::<Value>(&result)?["content"][0]["text"],
+        "hi"
+    );
+
+    assert_eq!(
+        rmcp(&[&url, "prompts"]).await?,
+        (true, "greet(name)  Greet someone\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "prompt", "greet", "name=Ada"]).await?,
+        (true, "user: Hello Ada\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "resources"]).await?,
+        (true, "memo://notes  notes\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "templates"]).await?,
+        (true, "memo://{name}  memo\n".to_owned())
+    );
+    assert_eq!(
+        rmcp(&[&url, "read", "memo://notes"]).await?,
+        (true, "contents of memo://notes\n".to_owned())
+    );
+    let (ok, info) = rmcp(&[&url, "info"]).await?;
+    assert!(ok);
+    assert!(info.starts_with("test-server 1.0.0\n"));
+    assert!(!rmcp(&[&url, "call", "echo", "text"]).await?.0);
+
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+    let mut tail = Command::new(RMCP)
+        .args([&url, "--json", "tail", "--level", "info"])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut lines = BufReader::new(tail.stdout.take().unwrap()).lines();
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+        .await??
+        .unwrap();
+    assert_eq!(
+        serde_json::from_str::<Value>(&line)?,
+        json!({
+            "method": "notifications/message",
+            "params": { "level": "info", "logger": "test", "data": "working" }
+        })
+    );
+    tail.kill().await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repl() -> anyhow::Result<()> {
+    let (url, ct) = serve_http().await?;
+    let mut repl = Command::new(RMCP)
+        .arg(&url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = repl.stdin.take().unwrap();
+    stdin
+        .write_all(b"call echo text='a b'\nread memo://notes\nexit\n")
+        .await?;
+    drop(stdin);
+    let output = tokio::time::timeout(Duration::from_secs(5), repl.wait_with_output()).await??;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("a b\n"), "{stdout}");
+    assert!(stdout.contains("contents of memo://notes\n"), "{stdout}");
+    ct.cancel();
+    Ok(())
+}
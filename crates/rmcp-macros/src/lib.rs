@@ -18,6 +18,46 @@ mod tool_router;
 /// | `description`     | `String`                   | A description of the tool. The document of this function will be used. |
 /// | `input_schema`    | `Expr`                     | A JSON Schema object defining the expected parameters for the tool. If not provide, if will use the json schema of its argument with type `Parameters<T>` |
 /// | `annotations`     | `ToolAnnotationsAttribute` | Additional tool information. Defaults to `None`. |
+/// | `guard`           | `Expr`                     | A `ToolGuard` the requests must pass to list and call the tool, carried by the route of the generated `{name}_tool_route` function. |
+/// | `timeout`         | `String`                   | How long a call may run, like `"500ms"`, `"30s"`, `"5m"` or `"1h"`, carried by the route of the generated `{name}_tool_route` function. The call is dropped on expiry, and its `CancellationToken` is cancelled. |
+///
+/// ## Plain arguments
+///
+/// Instead of a `Parameters<T>` struct, the function can take the tool arguments one by one. Every
+/// argument which is not an extractor (`Peer`, `RequestContext`, `CancellationToken`, `Extension`,
+/// `Meta`, ...) becomes a property of the input schema, required unless it is an `Option`.
+/// Extractors are recognized by their imported name or their full `rmcp::` path, an argument
+/// marked with `#[arg]` is always a property, even if its type has the name of an extractor.
+/// The arguments are deserialized, so they can't be references.
+///
+/// The property descriptions come from `#[arg(description = "...")]`, or from the `# Arguments`
+/// section of the function documentation, which is left out of the tool description.
+///
+/// A `{name}_tool_call` function deserializing the arguments is generated next to the tool, and is
+/// the one routed by `#[tool_router]`.
+///
+/// ```rust,ignore
+/// /// Add two numbers.
+/// ///
+/// /// # Arguments
+/// ///
+/// /// * `a` - The first number
+/// /// * `b` - The second number
+/// #[tool]
+/// async fn add(&self, a: i64, b: i64, #[arg(description = "Printed before the sum")] label: Option<String>, peer: Peer<RoleServer>) -> String {
+///     // handling tool request
+/// }
+/// ```
+///
+/// ## Routes
+///
+/// A tool with a `guard` or a `timeout` also gets a `{name}_tool_route` function, returning its
+/// `ToolRoute` with them. `#[tool_router]` routes it, and a router built by hand must use it too:
+/// a route built from `{name}_tool_attr` has neither.
+///
+/// ```rust,ignore
+/// let router = ToolRouter::new().with_route(Self::restart_tool_route());
+/// ```
 ///
 /// ## Example
 ///
@@ -147,9 +187,9 @@ pub fn tool_router(attr: TokenStream, input: TokenStream) -> TokenStream {
 ///     async fn list_tools(
 ///         &self,
 ///         _request: Option<PaginatedRequestParam>,
-///         _context: RequestContext<RoleServer>,
+///         context: RequestContext<RoleServer>,
 ///     ) -> Result<ListToolsResult, rmcp::ErrorData> {
-///         let items = self.tool_router.list_all();
+///         let items = self.tool_router.list_visible(&context);
 ///         Ok(ListToolsResult::with_all_items(items))
 ///     }
 /// }
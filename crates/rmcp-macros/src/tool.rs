@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use darling::{FromMeta, ast::NestedMeta};
 use proc_macro2::TokenStream;
 use quote::{ToTokens, format_ident, quote};
-use syn::{Expr, Ident, ImplItemFn, ReturnType};
+use syn::{Expr, FnArg, Ident, ImplItemFn, Pat, PatType, ReturnType, Signature, Type};
 #[derive(FromMeta, Default, Debug)]
 #[darling(default)]
 pub struct ToolAttribute {
@@ -14,6 +16,12 @@ pub struct ToolAttribute {
     pub output_schema: Option<Expr>,
     /// Optional additional tool information.
     pub annotations: Option<ToolAnnotationsAttribute>,
+    /// A guard the requests must pass to list and call the tool, carried by the route of
+    /// `{name}_tool_route`
+    pub guard: Option<Expr>,
+    /// How long a call may run, like `"500ms"`, `"30s"`, `"5m"` or `"1h"`, carried by the route of
+    /// `{name}_tool_route`
+    pub timeout: Option<syn::LitStr>,
 }
 
 pub struct ResolvedToolAttribute {
@@ -95,6 +103,292 @@ pub struct ToolAnnotationsAttribute {
     pub open_world_hint: Option<bool>,
 }
 
+#[derive(FromMeta, Debug, Default)]
+#[darling(default)]
+pub struct ArgAttribute {
+    /// A description of the argument, overriding the one from the `# Arguments` section of the
+    /// function documentation.
+    pub description: Option<String>,
+}
+
+/// The types extracted from the tool call context rather than deserialized from the arguments.
+const EXTRACTORS: &[&str] = &[
+    "Parameters",
+    "Peer",
+    "RequestContext",
+    "CancellationToken",
+    "ToolName",
+    "JsonObject",
+    "Extensions",
+    "Extension",
+    "Meta",
+    "RequestId",
+];
+
+/// The crates whose paths name the extractors in full, like `rmcp::model::Meta`.
+const EXTRACTOR_CRATES: &[&str] = &["rmcp", "tokio_util"];
+
+/// Whether the argument is extracted from the tool call context rather than deserialized from
+/// the tool arguments.
+///
+/// An extractor is named by an imported ident of [`EXTRACTORS`], or by a path starting with one
+/// of [`EXTRACTOR_CRATES`]. `#[arg]` marks a type with the same name as a plain argument.
+fn is_extractor(pat_type: &PatType) -> bool {
+    if pat_type
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("arg"))
+    {
+        return false;
+    }
+    match &*pat_type.ty {
+        // the service of a function tool
+        Type::Reference(_) => true,
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let segments = &type_path.path.segments;
+            let Some(last) = segments.last() else {
+                return false;
+            };
+            EXTRACTORS.iter().any(|name| last.ident == name)
+                && (segments.len() == 1 && type_path.path.leading_colon.is_none()
+                    || EXTRACTOR_CRATES
+                        .iter()
+                        .any(|name| segments[0].ident == name))
+        }
+        _ => false,
+    }
+}
+
+/// Whether some arguments of the function are deserialized from the tool arguments, in which
+/// case `#[tool]` generates a `{name}_tool_call` handler.
+pub fn has_plain_arguments(sig: &Signature) -> bool {
+    sig.inputs
+        .iter()
+        .any(|input| matches!(input, FnArg::Typed(pat_type) if !is_extractor(pat_type)))
+}
+
+struct PlainArgument {
+    ident: Ident,
+    ty: Type,
+    description: Option<String>,
+}
+
+/// Take the `#[arg]` attributes off the function, and collect its plain arguments.
+fn take_plain_arguments(fn_item: &mut ImplItemFn) -> syn::Result<Vec<PlainArgument>> {
+    let mut arguments = vec![];
+    let has_receiver = fn_item.sig.receiver().is_some();
+    for (index, input) in fn_item.sig.inputs.iter_mut().enumerate() {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        // only the first argument of a function tool may borrow, it is the service
+        if matches!(&*pat_type.ty, Type::Reference(_)) && (has_receiver || index > 0) {
+            return Err(syn::Error::new_spanned(
+                &pat_type.ty,
+                "tool arguments are deserialized from the tool call, take an owned type like \
+                 `String` instead of a reference",
+            ));
+        }
+        let extractor = is_extractor(pat_type);
+        let mut attribute = None;
+        let mut error = None;
+        pat_type.attrs.retain(|attr| {
+            if !attr.path().is_ident("arg") {
+                return true;
+            }
+            let parsed = match &attr.meta {
+                syn::Meta::Path(_) => Ok(ArgAttribute::default()),
+                meta => ArgAttribute::from_meta(meta).map_err(syn::Error::from),
+            };
+            match parsed {
+                Ok(parsed) => attribute = Some(parsed),
+                Err(e) => error = Some(e),
+            }
+            false
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        if extractor {
+            continue;
+        }
+        let Pat::Ident(pat_ident) = &*pat_type.pat else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "tool arguments must be bound to an identifier",
+            ));
+        };
+        arguments.push(PlainArgument {
+            ident: pat_ident.ident.clone(),
+            ty: (*pat_type.ty).clone(),
+            description: attribute.and_then(|attribute| attribute.description),
+        });
+    }
+    Ok(arguments)
+}
+
+/// Split the `# Arguments` section off the documentation, it lists the arguments like
+/// ``* `name` - description``.
+fn split_arguments_section(docs: &str) -> (Option<String>, HashMap<String, String>) {
+    let mut description = vec![];
+    let mut arguments = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut in_section = false;
+    for line in docs.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            in_section = trimmed.trim_start_matches('#').trim() == "Arguments";
+            current = None;
+            if in_section {
+                continue;
+            }
+        }
+        if !in_section {
+            description.push(line);
+            continue;
+        }
+        let item = trimmed
+            .strip_prefix("* ")
+            .or_else(|| trimmed.strip_prefix("- "))
+            .and_then(|item| item.strip_prefix('`'))
+            .and_then(|item| item.split_once('`'));
+        if let Some((name, text)) = item {
+            let text = text.trim_start().trim_start_matches(['-', ':']).trim();
+            arguments.insert(name.to_owned(), text.to_owned());
+            current = Some(name.to_owned());
+        } else if let Some(text) = current.as_ref().and_then(|name| arguments.get_mut(name)) {
+            if !trimmed.is_empty() {
+                text.push(' ');
+                text.push_str(trimmed);
+            }
+        }
+    }
+    let description = description.join("\n").trim().to_owned();
+    ((!description.is_empty()).then_some(description), arguments)
+}
+
+fn arguments_struct_ident(fn_ident: &Ident) -> Ident {
+    let name = fn_ident
+        .to_string()
+        .trim_start_matches("r#")
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    format_ident!("{}Arguments", name)
+}
+
+/// The input schema of the plain arguments, from a struct local to the expression.
+fn plain_arguments_schema(struct_ident: &Ident, arguments: &[PlainArgument]) -> syn::Result<Expr> {
+    let fields = arguments.iter().map(|argument| {
+        let PlainArgument {
+            ident,
+            ty,
+            description,
+        } = argument;
+        let description = description
+            .as_ref()
+            .map(|description| quote! { #[schemars(description = #description)] });
+        quote! {
+            #description
+            #ident: #ty
+        }
+    });
+    syn::parse2::<Expr>(quote! {
+        {
+            #[derive(rmcp::schemars::JsonSchema)]
+            #[schemars(crate = "rmcp::schemars")]
+            #[allow(dead_code)]
+            struct #struct_ident {
+                #(#fields,)*
+            }
+            rmcp::handler::server::tool::cached_schema_for_type::<#struct_ident>()
+        }
+    })
+}
+
+/// A handler deserializing the plain arguments before calling the tool function.
+fn plain_arguments_handler(
+    fn_item: &ImplItemFn,
+    struct_ident: &Ident,
+    arguments: &[PlainArgument],
+    is_async: bool,
+) -> syn::Result<ImplItemFn> {
+    let fn_ident = &fn_item.sig.ident;
+    let handler_ident = format_ident!("{}_tool_call", fn_ident);
+    let vis = &fn_item.vis;
+    let mut inputs = vec![];
+    let mut call_args = vec![];
+    for (index, input) in fn_item.sig.inputs.iter().enumerate() {
+        let FnArg::Typed(pat_type) = input else {
+            continue;
+        };
+        // the `#[arg]` attributes are already taken off, so the plain arguments are told apart
+        // by name
+        match &*pat_type.pat {
+            Pat::Ident(pat_ident)
+                if arguments
+                    .iter()
+                    .any(|argument| argument.ident == pat_ident.ident) =>
+            {
+                let ident = &pat_ident.ident;
+                call_args.push(quote! { #ident });
+            }
+            _ => {
+                let ident = format_ident!("__rmcp_extractor_{}", index);
+                let ty = &pat_type.ty;
+                inputs.push(quote! { #ident: #ty });
+                call_args.push(quote! { #ident });
+            }
+        }
+    }
+    let (receiver, callee, lt) = if fn_item.sig.receiver().is_some() {
+        (quote! { &self, }, quote! { self.#fn_ident }, quote! { '_ })
+    } else {
+        (quote! {}, quote! { Self::#fn_ident }, quote! { 'static })
+    };
+    let await_call = is_async.then(|| quote! { .await });
+    let idents = arguments.iter().map(|argument| &argument.ident);
+    let fields = arguments
+        .iter()
+        .map(|PlainArgument { ident, ty, .. }| quote! { #ident: #ty });
+    syn::parse2::<ImplItemFn>(quote! {
+        #vis fn #handler_ident(
+            #receiver
+            __rmcp_arguments: rmcp::model::JsonObject,
+            #(#inputs,)*
+        ) -> std::pin::Pin<Box<dyn Future<Output = Result<rmcp::model::CallToolResult, rmcp::ErrorData>> + Send + #lt>> {
+            #[derive(rmcp::serde::Deserialize)]
+            #[serde(crate = "rmcp::serde")]
+            #[allow(dead_code)]
+            struct #struct_ident {
+                #(#fields,)*
+            }
+            let #struct_ident { #(#idents,)* } = match rmcp::serde_json::from_value(
+                rmcp::serde_json::Value::Object(__rmcp_arguments),
+            ) {
+                Ok(arguments) => arguments,
+                Err(error) => {
+                    return Box::pin(std::future::ready(Err(rmcp::ErrorData::invalid_params(
+                        format!("failed to deserialize parameters: {error}"),
+                        None,
+                    ))));
+                }
+            };
+            Box::pin(async move {
+                rmcp::handler::server::tool::IntoCallToolResult::into_call_tool_result(
+                    #callee(#(#call_args),*)#await_call,
+                )
+            })
+        }
+    })
+}
+
 fn none_expr() -> Expr {
     syn::parse2::<Expr>(quote! { None }).unwrap()
 }
@@ -187,19 +481,126 @@ fn extract_doc_line(existing_docs: Option<String>, attr: &syn::Attribute) -> Opt
     }
 }
 
+/// The attribute of a function marked with `#[tool]`.
+pub fn tool_attribute(fn_item: &ImplItemFn) -> syn::Result<ToolAttribute> {
+    let Some(attr) = fn_item.attrs.iter().find(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "tool")
+    }) else {
+        return Ok(Default::default());
+    };
+    let syn::Meta::List(list) = &attr.meta else {
+        return Ok(Default::default());
+    };
+    let attr_args = NestedMeta::parse_meta_list(list.tokens.clone())?;
+    Ok(ToolAttribute::from_list(&attr_args)?)
+}
+
+/// Parse a duration like `"500ms"`, `"30s"`, `"5m"` or `"1h"` into milliseconds.
+pub fn parse_timeout(timeout: &syn::LitStr) -> syn::Result<u64> {
+    let value = timeout.value();
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let multiplier = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => 0,
+    };
+    match amount.parse::<u64>() {
+        Ok(amount) if multiplier != 0 => amount
+            .checked_mul(multiplier)
+            .ok_or_else(|| syn::Error::new(timeout.span(), "the timeout is too long")),
+        _ => Err(syn::Error::new(
+            timeout.span(),
+            "expected a duration like \"500ms\", \"30s\", \"5m\" or \"1h\"",
+        )),
+    }
+}
+
+/// Whether `#[tool]` generates a `{name}_tool_route` function, for the options which can't be
+/// carried by the tool attribute.
+pub fn has_route_fn(attribute: &ToolAttribute) -> bool {
+    attribute.guard.is_some() || attribute.timeout.is_some()
+}
+
+/// The `{name}_tool_route` function, the route of the tool with its guard and timeout.
+fn route_fn(
+    fn_ident: &Ident,
+    handler: &Ident,
+    attribute: &ToolAttribute,
+) -> syn::Result<Option<ImplItemFn>> {
+    if !has_route_fn(attribute) {
+        return Ok(None);
+    }
+    let route_fn_ident = format_ident!("{fn_ident}_tool_route");
+    let tool_attr_fn_ident = format_ident!("{fn_ident}_tool_attr");
+    let guard = attribute
+        .guard
+        .as_ref()
+        .map(|guard| quote! { .with_guard(#guard) });
+    let timeout = attribute
+        .timeout
+        .as_ref()
+        .map(parse_timeout)
+        .transpose()?
+        .map(|millis| quote! { .with_timeout(std::time::Duration::from_millis(#millis)) });
+    syn::parse2::<ImplItemFn>(quote! {
+        pub fn #route_fn_ident() -> rmcp::handler::server::router::tool::ToolRoute<Self> {
+            rmcp::handler::server::router::tool::ToolRoute::new(
+                Self::#tool_attr_fn_ident(),
+                Self::#handler,
+            )
+            #guard
+            #timeout
+        }
+    })
+    .map(Some)
+}
+
 pub fn tool(attr: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
-    let attribute = if attr.is_empty() {
+    let mut attribute = if attr.is_empty() {
         Default::default()
     } else {
         let attr_args = NestedMeta::parse_meta_list(attr)?;
         ToolAttribute::from_list(&attr_args)?
     };
+    let route_options = ToolAttribute {
+        guard: attribute.guard.take(),
+        timeout: attribute.timeout.take(),
+        ..Default::default()
+    };
     let mut fn_item = syn::parse2::<ImplItemFn>(input.clone())?;
+    let mut plain_arguments = take_plain_arguments(&mut fn_item)?;
     let fn_ident = &fn_item.sig.ident;
 
     let tool_attr_fn_ident = format_ident!("{}_tool_attr", fn_ident);
+    let struct_ident = arguments_struct_ident(fn_ident);
+    let mut description = attribute
+        .description
+        .or_else(|| fn_item.attrs.iter().fold(None, extract_doc_line));
+    if !plain_arguments.is_empty() {
+        if let Some(docs) = description.take() {
+            let (docs, mut documented) = split_arguments_section(&docs);
+            description = docs;
+            for argument in &mut plain_arguments {
+                if argument.description.is_none() {
+                    argument.description =
+                        documented.remove(argument.ident.to_string().trim_start_matches("r#"));
+                }
+            }
+        }
+    }
     let input_schema_expr = if let Some(input_schema) = attribute.input_schema {
         input_schema
+    } else if !plain_arguments.is_empty() {
+        plain_arguments_schema(&struct_ident, &plain_arguments)?
     } else {
         // try to find some parameters wrapper in the function
         let params_ty = fn_item.sig.inputs.iter().find_map(|input| {
@@ -270,16 +671,31 @@ pub fn tool(attr: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
 
     let resolved_tool_attr = ResolvedToolAttribute {
         name: attribute.name.unwrap_or_else(|| fn_ident.to_string()),
-        description: attribute
-            .description
-            .or_else(|| fn_item.attrs.iter().fold(None, extract_doc_line)),
+        description,
         input_schema: input_schema_expr,
         output_schema: output_schema_expr,
         annotations: annotations_expr,
     };
     let tool_attr_fn = resolved_tool_attr.into_fn(tool_attr_fn_ident)?;
+    let is_async = fn_item.sig.asyncness.is_some();
+    let handler = if plain_arguments.is_empty() {
+        fn_ident.clone()
+    } else {
+        format_ident!("{fn_ident}_tool_call")
+    };
+    let route_fn = route_fn(fn_ident, &handler, &route_options)?;
+    let tool_call_fn = if plain_arguments.is_empty() {
+        None
+    } else {
+        Some(plain_arguments_handler(
+            &fn_item,
+            &struct_ident,
+            &plain_arguments,
+            is_async,
+        )?)
+    };
     // modify the the input function
-    if fn_item.sig.asyncness.is_some() {
+    if is_async {
         // 1. remove asyncness from sig
         // 2. make return type: `std::pin::Pin<Box<dyn Future<Output = #ReturnType> + Send + '_>>`
         // 3. make body: { Box::pin(async move { #body }) }
@@ -313,6 +729,8 @@ pub fn tool(attr: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
     }
     Ok(quote! {
         #tool_attr_fn
+        #tool_call_fn
+        #route_fn
         #fn_item
     })
 }
@@ -374,4 +792,80 @@ mod test {
         assert!(result_str.contains("Explicit description has priority"));
         Ok(())
     }
+
+    #[test]
+    fn test_plain_arguments() -> syn::Result<()> {
+        let input = quote! {
+            /// Add two numbers.
+            ///
+            /// # Arguments
+            ///
+            /// * `a` - The first number
+            async fn add(&self, a: i64, #[arg(description = "The second number")] b: i64, peer: Peer<RoleServer>) -> String {
+                (a + b).to_string()
+            }
+        };
+        let result = tool(quote! {}, input)?.to_string();
+        assert!(result.contains("fn add_tool_call"));
+        assert!(result.contains("\"The first number\""));
+        assert!(result.contains("\"The second number\""));
+        assert!(result.contains("\"Add two numbers.\""));
+        assert!(!result.contains("#[arg"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extractor_detection() -> syn::Result<()> {
+        // `#[arg]` and a path outside of rmcp make a plain argument of a type named `Meta`
+        let input = quote! {
+            async fn tag(&self, #[arg] meta: Meta, other: units::Meta, request: rmcp::model::Meta) {}
+        };
+        let result = tool(quote! {}, input)?.to_string();
+        assert!(result.contains("meta : Meta"));
+        assert!(result.contains("other : units :: Meta"));
+        assert!(result.contains("__rmcp_extractor_3 : rmcp :: model :: Meta"));
+
+        let input = quote! {
+            async fn greet(&self, name: &str) {}
+        };
+        assert!(tool(quote! {}, input).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_guard_route() -> syn::Result<()> {
+        let input = quote! {
+            async fn restart(&self) -> String {
+                "restarted".into()
+            }
+        };
+        let result = tool(quote! { guard = admin }, input.clone())?.to_string();
+        assert!(result.contains("pub fn restart_tool_route"));
+        assert!(result.contains("with_guard (admin)"));
+        let result = tool(quote! { timeout = "30s" }, input.clone())?.to_string();
+        assert!(result.contains("pub fn restart_tool_route"));
+        assert!(result.contains("from_millis (30000u64)"));
+        let result = tool(quote! {}, input)?.to_string();
+        assert!(!result.contains("restart_tool_route"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        let parse = |value: &str| {
+            parse_timeout(&syn::LitStr::new(value, proc_macro2::Span::call_site())).ok()
+        };
+        assert_eq!(parse("500ms"), Some(500));
+        assert_eq!(parse("30s"), Some(30_000));
+        assert_eq!(parse("5m"), Some(300_000));
+        assert_eq!(parse("1h"), Some(3_600_000));
+        assert_eq!(parse("30"), None);
+        assert_eq!(parse("s"), None);
+        assert_eq!(parse("1.5s"), None);
+        assert_eq!(parse("18446744073709551615h"), None);
+        let input = quote! {
+            async fn slow(&self) {}
+        };
+        assert!(tool(quote! { timeout = "soon" }, input).is_err());
+    }
 }
@@ -38,9 +38,9 @@ pub fn tool_handler(attr: TokenStream, input: TokenStream) -> syn::Result<TokenS
         async fn list_tools(
             &self,
             _request: Option<rmcp::model::PaginatedRequestParam>,
-            _context: rmcp::service::RequestContext<rmcp::RoleServer>,
+            context: rmcp::service::RequestContext<rmcp::RoleServer>,
         ) -> Result<rmcp::model::ListToolsResult, rmcp::ErrorData> {
-            Ok(rmcp::model::ListToolsResult::with_all_items(#router.list_all()))
+            Ok(rmcp::model::ListToolsResult::with_all_items(#router.list_visible(&context)))
         }
     };
     let tool_call_fn = syn::parse2::<ImplItem>(tool_call_fn)?;
@@ -46,17 +46,31 @@ pub fn tool_router(attr: TokenStream, input: TokenStream) -> syn::Result<TokenSt
                             .last()
                             .is_some_and(|seg| seg.ident == "tool")
                     })
-                    .then_some(&fn_item.sig.ident)
+                    .then_some(fn_item)
             } else {
                 None
             }
         })
         .collect();
     let mut routers = vec![];
-    for handler in tool_attr_fns {
+    for fn_item in tool_attr_fns {
+        let sig = &fn_item.sig;
+        let handler = &sig.ident;
         let tool_attr_fn_ident = format_ident!("{handler}_tool_attr");
+        let handler = if crate::tool::has_plain_arguments(sig) {
+            format_ident!("{handler}_tool_call")
+        } else {
+            handler.clone()
+        };
+        let attribute = crate::tool::tool_attribute(fn_item)?;
+        let route = if crate::tool::has_route_fn(&attribute) {
+            let route_fn_ident = format_ident!("{}_tool_route", sig.ident);
+            quote! { Self::#route_fn_ident() }
+        } else {
+            quote! { (Self::#tool_attr_fn_ident(), Self::#handler) }
+        };
         routers.push(quote! {
-            .with_route((Self::#tool_attr_fn_ident(), Self::#handler))
+            .with_route(#route)
         })
     }
     let router_fn = syn::parse2::<ImplItem>(quote! {
@@ -0,0 +1,936 @@
+//! A server aggregating several upstream servers behind a single endpoint.
+//!
+//! A [`Gateway`] connects as a client to each [`Upstream`] when its downstream client
+//! initializes, and serves the union of their tools, prompts and resources. Tool and prompt
+//! names are [namespaced](Namespacing) by the prefix of their upstream, resource URIs are kept
+//! as they are. Names still exposed by several upstreams are resolved by the [`Collision`] rule.
+//!
+//! Calls are routed to the upstream which owns the tool, prompt or resource, along with their
+//! progress notifications and cancellation. Sampling and roots requests of the upstreams are
+//! relayed to the downstream client, and their `list_changed` notifications are propagated.
+//!
+//! Every served instance has its own upstream connections, so serving a clone per downstream
+//! session keeps sessions apart. An instance serving requests without an initialization, like
+//! the stateless mode of the streamable HTTP server, connects them on its first request.
+//!
+//! ```rust,ignore
+//! let gateway = Gateway::new()
+//!     .upstream(Upstream::new("git", || async {
+//!         TokioChildProcess::new(Command::new("mcp-server-git"))
+//!     }))
+//!     .upstream(Upstream::new("search", || async {
+//!         Ok::<_, Infallible>(StreamableHttpClientTransport::from_uri("http://search/mcp"))
+//!     }));
+//! gateway.serve(stdio()).await?.waiting().await?;
+//! ```
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    FutureExt,
+    future::{BoxFuture, join_all},
+};
+
+use crate::{
+    ErrorData,
+    model::*,
+    service::{
+        AtomicU32ProgressTokenProvider, ClientInitializeError, NotificationContext,
+        PeerRequestOptions, ProgressTokenProvider, RequestContext, RoleClient, RoleServer,
+        RunningService, Service, ServiceError, ServiceExt,
+    },
+    transport::IntoTransport,
+};
+
+pub const DEFAULT_SEPARATOR: &str = "__";
+
+/// How the names of tools and prompts are qualified by their upstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Namespacing {
+    /// names are exposed as they are
+    None,
+    /// every name is prefixed by the prefix of its upstream and the separator
+    Always { separator: Cow<'static, str> },
+    /// only the names exposed by several upstreams are prefixed
+    OnCollision { separator: Cow<'static, str> },
+}
+
+impl Default for Namespacing {
+    fn default() -> Self {
+        Namespacing::Always {
+            separator: DEFAULT_SEPARATOR.into(),
+        }
+    }
+}
+
+/// Which upstream wins when a name is still exposed by several of them after namespacing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collision {
+    /// the upstream added first
+    #[default]
+    KeepFirst,
+    /// the upstream added last
+    KeepLast,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UpstreamError {
+    #[error("failed to create the transport: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    #[error("failed to initialize: {0}")]
+    Initialize(#[from] ClientInitializeError),
+}
+
+type Connect = Arc<
+    dyn Fn(
+            UpstreamClient,
+        )
+            -> BoxFuture<'static, Result<RunningService<RoleClient, UpstreamClient>, UpstreamError>>
+        + Send
+        + Sync,
+>;
+
+/// An upstream server, connected by a new transport for every downstream client.
+#[derive(Clone)]
+pub struct Upstream {
+    name: Arc<str>,
+    prefix: Arc<str>,
+    connect: Connect,
+}
+
+impl std::fmt::Debug for Upstream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Upstream")
+            .field("name", &self.name)
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+impl Upstream {
+    /// The name is also the prefix of the upstream, unless [`Upstream::prefix`] is set.
+    pub fn new<F, Fut, T, TE, E, A>(name: impl Into<Arc<str>>, transport: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, TE>> + Send + 'static,
+        TE: Into<Box<dyn std::error::Error + Send + Sync>>,
+        T: IntoTransport<RoleClient, E, A> + Send + 'static,
+        E: std::error::Error + Send + Sync + 'static,
+        A: 'static,
+    {
+        let name = name.into();
+        Self {
+            prefix: name.clone(),
+            name,
+            connect: Arc::new(move |client| {
+                let transport = transport();
+                async move {
+                    let transport = transport
+                        .await
+                        .map_err(|e| UpstreamError::Transport(e.into()))?;
+                    Ok(client.serve(transport).await?)
+                }
+                .boxed()
+            }),
+        }
+    }
+
+    pub fn prefix(mut self, prefix: impl Into<Arc<str>>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The client side of the gateway, relaying the requests and notifications of an upstream to
+/// the downstream client.
+struct UpstreamClient {
+    info: ClientInfo,
+    downstream: crate::Peer<RoleServer>,
+    /// the progress tokens of the forwarded requests, to the tokens of the downstream client
+    progress: Arc<Mutex<HashMap<ProgressToken, ProgressToken>>>,
+}
+
+impl crate::ClientHandler for UpstreamClient {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, ErrorData> {
+        self.downstream
+            .create_message(params)
+            .await
+            .map_err(downstream_error)
+    }
+
+    async fn list_roots(
+        &self,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, ErrorData> {
+        self.downstream.list_roots().await.map_err(downstream_error)
+    }
+
+    async fn on_progress(
+        &self,
+        mut params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let token = self
+            .progress
+            .lock()
+            .expect("lock poisoned")
+            .get(&params.progress_token)
+            .cloned();
+        if let Some(token) = token {
+            params.progress_token = token;
+            let _ = self.downstream.notify_progress(params).await;
+        }
+    }
+
+    async fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.downstream.notify_logging_message(params).await;
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.downstream.notify_resource_updated(params).await;
+    }
+
+    async fn on_resource_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        let _ = self.downstream.notify_resource_list_changed().await;
+    }
+
+    async fn on_tool_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        let _ = self.downstream.notify_tool_list_changed().await;
+    }
+
+    async fn on_prompt_list_changed(&self, _context: NotificationContext<RoleClient>) {
+        let _ = self.downstream.notify_prompt_list_changed().await;
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+}
+
+fn downstream_error(error: ServiceError) -> ErrorData {
+    match error {
+        ServiceError::McpError(error) => error,
+        error => ErrorData::internal_error(format!("downstream client: {error}"), None),
+    }
+}
+
+struct Connection {
+    upstream: Upstream,
+    service: RunningService<RoleClient, UpstreamClient>,
+    progress: Arc<Mutex<HashMap<ProgressToken, ProgressToken>>>,
+    /// the progress tokens of the forwarded requests, mapped before they are sent
+    progress_tokens: AtomicU32ProgressTokenProvider,
+}
+
+impl Connection {
+    fn capabilities(&self) -> Option<&ServerCapabilities> {
+        self.service.peer_info().map(|info| &info.capabilities)
+    }
+
+    fn upstream_error(&self, error: ServiceError) -> ErrorData {
+        match error {
+            ServiceError::McpError(error) => error,
+            error => {
+                ErrorData::internal_error(format!("upstream {}: {error}", self.upstream.name), None)
+            }
+        }
+    }
+}
+
+/// Where a tool, prompt or resource is served.
+#[derive(Debug, Clone)]
+struct Route {
+    upstream: usize,
+    name: String,
+}
+
+#[derive(Default)]
+struct Routes {
+    tools: HashMap<String, Route>,
+    prompts: HashMap<String, Route>,
+    resources: HashMap<String, Route>,
+    resource_templates: HashMap<String, Route>,
+}
+
+/// The key an item is routed by, its name or its URI.
+trait Routed {
+    /// resource URIs are never namespaced
+    const NAMESPACED: bool = true;
+    fn key(&self) -> &str;
+    fn rename(&mut self, name: String);
+}
+
+impl Routed for Tool {
+    fn key(&self) -> &str {
+        &self.name
+    }
+    fn rename(&mut self, name: String) {
+        self.name = name.into();
+    }
+}
+
+impl Routed for Prompt {
+    fn key(&self) -> &str {
+        &self.name
+    }
+    fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+}
+
+impl Routed for Resource {
+    const NAMESPACED: bool = false;
+    fn key(&self) -> &str {
+        &self.raw.uri
+    }
+    fn rename(&mut self, _name: String) {}
+}
+
+impl Routed for ResourceTemplate {
+    const NAMESPACED: bool = false;
+    fn key(&self) -> &str {
+        &self.raw.uri_template
+    }
+    fn rename(&mut self, _name: String) {}
+}
+
+/// A server serving the tools, prompts and resources of several upstream servers, see the
+/// [module documentation](self).
+#[derive(Default)]
+pub struct Gateway {
+    upstreams: Vec<Upstream>,
+    namespacing: Namespacing,
+    collision: Collision,
+    info: Option<Implementation>,
+    connections: tokio::sync::OnceCell<Vec<Connection>>,
+    routes: Mutex<Routes>,
+}
+
+/// Clones the configuration only, the clone connects to the upstreams on its own.
+impl Clone for Gateway {
+    fn clone(&self) -> Self {
+        Self {
+            upstreams: self.upstreams.clone(),
+            namespacing: self.namespacing.clone(),
+            collision: self.collision,
+            info: self.info.clone(),
+            connections: tokio::sync::OnceCell::new(),
+            routes: Mutex::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Gateway {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Gateway")
+            .field("upstreams", &self.upstreams)
+            .field("namespacing", &self.namespacing)
+            .field("collision", &self.collision)
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl Gateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn upstream(mut self, upstream: Upstream) -> Self {
+        self.upstreams.push(upstream);
+        self
+    }
+
+    pub fn namespacing(mut self, namespacing: Namespacing) -> Self {
+        self.namespacing = namespacing;
+        self
+    }
+
+    pub fn on_collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// The implementation reported to the downstream client.
+    pub fn with_info(mut self, info: Implementation) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    /// The names of the connected upstreams, the ones which failed to connect are left out.
+    pub fn connected_upstreams(&self) -> Vec<&str> {
+        self.connections()
+            .iter()
+            .map(|connection| connection.upstream.name())
+            .collect()
+    }
+
+    fn connections(&self) -> &[Connection] {
+        self.connections
+            .get()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Connect every upstream once, the ones which fail are logged and left out.
+    ///
+    /// The initialization fails when none of them could be connected.
+    async fn connect(&self, info: ClientInfo, downstream: crate::Peer<RoleServer>) {
+        self.connections
+            .get_or_init(|| self.connect_upstreams(info, downstream))
+            .await;
+    }
+
+    async fn connect_upstreams(
+        &self,
+        info: ClientInfo,
+        downstream: crate::Peer<RoleServer>,
+    ) -> Vec<Connection> {
+        let connections = join_all(self.upstreams.iter().map(|upstream| {
+            let progress = Arc::<Mutex<HashMap<_, _>>>::default();
+            let client = UpstreamClient {
+                info: info.clone(),
+                downstream: downstream.clone(),
+                progress: progress.clone(),
+            };
+            let connect = (upstream.connect)(client);
+            async move {
+                match connect.await {
+                    Ok(service) => Some(Connection {
+                        upstream: upstream.clone(),
+                        service,
+                        progress,
+                        progress_tokens: Default::default(),
+                    }),
+                    Err(e) => {
+                        tracing::warn!(upstream = %upstream.name, "failed to connect upstream: {e}");
+                        None
+                    }
+                }
+            }
+        }))
+        .await;
+        connections.into_iter().flatten().collect()
+    }
+
+    fn merged_info(&self) -> ServerInfo {
+        let mut capabilities = ServerCapabilities::default();
+        let mut instructions = Vec::new();
+        for connection in self.connections() {
+            let Some(info) = connection.service.peer_info() else {
+                continue;
+            };
+            let upstream = &info.capabilities;
+            if upstream.tools.is_some() {
+                capabilities.tools = Some(ToolsCapability {
+                    list_changed: Some(true),
+                });
+            }
+            if upstream.prompts.is_some() {
+                capabilities.prompts = Some(PromptsCapability {
+                    list_changed: Some(true),
+                });
+            }
+            if let Some(resources) = &upstream.resources {
+                let merged = capabilities.resources.get_or_insert(ResourcesCapability {
+                    subscribe: None,
+                    list_changed: Some(true),
+                });
+                if resources.subscribe == Some(true) {
+                    merged.subscribe = Some(true);
+                }
+            }
+            if upstream.logging.is_some() {
+                capabilities.logging = Some(JsonObject::default());
+            }
+            if upstream.completions.is_some() {
+                capabilities.completions = Some(JsonObject::default());
+            }
+            if let Some(text) = &info.instructions {
+                instructions.push(format!("{}: {text}", connection.upstream.name));
+            }
+        }
+        ServerInfo {
+            protocol_version: ProtocolVersion::default(),
+            capabilities,
+            server_info: self.info.clone().unwrap_or_default(),
+            instructions: (!instructions.is_empty()).then(|| instructions.join("\n")),
+        }
+    }
+
+    /// Merge the lists of the upstreams, returns the exposed items and their routes.
+    fn expose<T: Routed>(&self, lists: Vec<(usize, Vec<T>)>) -> (Vec<T>, HashMap<String, Route>) {
+        let mut counts = HashMap::<String, usize>::new();
+        for (_, items) in &lists {
+            for item in items {
+                *counts.entry(item.key().to_owned()).or_default() += 1;
+            }
+        }
+        let connections = self.connections();
+        let mut exposed = Vec::<T>::new();
+        let mut routes = HashMap::<String, (Route, usize)>::new();
+        for (upstream, items) in lists {
+            let prefix = &connections[upstream].upstream.prefix;
+            for mut item in items {
+                let original = item.key().to_owned();
+                let name = match &self.namespacing {
+                    _ if !T::NAMESPACED => original.clone(),
+                    Namespacing::Always { separator } => format!("{prefix}{separator}{original}"),
+                    Namespacing::OnCollision { separator } if counts[&original] > 1 => {
+                        format!("{prefix}{separator}{original}")
+                    }
+                    _ => original.clone(),
+                };
+                let route = Route {
+                    upstream,
+                    name: original,
+                };
+                match routes.get(&name) {
+                    Some((_, position)) => {
+                        let position = *position;
+                        tracing::warn!(name, upstream = %connections[upstream].upstream.name, "name collision");
+                        if self.collision == Collision::KeepLast {
+                            item.rename(name.clone());
+                            exposed[position] = item;
+                            routes.insert(name, (route, position));
+                        }
+                    }
+                    None => {
+                        item.rename(name.clone());
+                        routes.insert(name, (route, exposed.len()));
+                        exposed.push(item);
+                    }
+                }
+            }
+        }
+        let routes = routes
+            .into_iter()
+            .map(|(name, (route, _))| (name, route))
+            .collect();
+        (exposed, routes)
+    }
+
+    /// Query every upstream having a capability, the failures are logged and left out.
+    async fn list<'a, T, Fut>(
+        &'a self,
+        has_capability: impl Fn(&ServerCapabilities) -> bool,
+        list: impl Fn(&'a Connection) -> Fut,
+    ) -> Vec<(usize, Vec<T>)>
+    where
+        Fut: Future<Output = Result<Vec<T>, ServiceError>>,
+    {
+        let lists = self
+            .connections()
+            .iter()
+            .enumerate()
+            .filter(|(_, connection)| connection.capabilities().is_some_and(&has_capability))
+            .map(|(index, connection)| list(connection).map(move |result| (index, result)));
+        join_all(lists)
+            .await
+            .into_iter()
+            .filter_map(|(index, result)| match result {
+                Ok(items) => Some((index, items)),
+                Err(e) => {
+                    tracing::warn!(upstream = %self.connections()[index].upstream.name, "failed to list: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    async fn list_tools(&self) -> Vec<Tool> {
+        let lists = self
+            .list(
+                |capabilities| capabilities.tools.is_some(),
+                |connection| connection.service.list_all_tools(),
+            )
+            .await;
+        let (tools, routes) = self.expose(lists);
+        self.routes.lock().expect("lock poisoned").tools = routes;
+        tools
+    }
+
+    async fn list_prompts(&self) -> Vec<Prompt> {
+        let lists = self
+            .list(
+                |capabilities| capabilities.prompts.is_some(),
+                |connection| connection.service.list_all_prompts(),
+            )
+            .await;
+        let (prompts, routes) = self.expose(lists);
+        self.routes.lock().expect("lock poisoned").prompts = routes;
+        prompts
+    }
+
+    async fn list_resources(&self) -> Vec<Resource> {
+        let lists = self
+            .list(
+                |capabilities| capabilities.resources.is_some(),
+                |connection| connection.service.list_all_resources(),
+            )
+            .await;
+        let (resources, routes) = self.expose(lists);
+        self.routes.lock().expect("lock poisoned").resources = routes;
+        resources
+    }
+
+    async fn list_resource_templates(&self) -> Vec<ResourceTemplate> {
+        let lists = self
+            .list(
+                |capabilities| capabilities.resources.is_some(),
+                |connection| connection.service.list_all_resource_templates(),
+            )
+            .await;
+        let (templates, routes) = self.expose(lists);
+        self.routes
+            .lock()
+            .expect("lock poisoned")
+            .resource_templates = routes;
+        templates
+    }
+
+    async fn route_tool(&self, name: &str) -> Result<Route, ErrorData> {
+        let route = self
+            .routes
+            .lock()
+            .expect("lock poisoned")
+            .tools
+            .get(name)
+            .cloned();
+        let route = match route {
+            Some(route) => Some(route),
+            None => {
+                self.list_tools().await;
+                self.routes
+                    .lock()
+                    .expect("lock poisoned")
+                    .tools
+                    .get(name)
+                    .cloned()
+            }
+        };
+        route.ok_or_else(|| ErrorData::invalid_params(format!("tool {name} not found"), None))
+    }
+
+    async fn route_prompt(&self, name: &str) -> Result<Route, ErrorData> {
+        let route = self
+            .routes
+            .lock()
+            .expect("lock poisoned")
+            .prompts
+            .get(name)
+            .cloned();
+        let route = match route {
+            Some(route) => Some(route),
+            None => {
+                self.list_prompts().await;
+                self.routes
+                    .lock()
+                    .expect("lock poisoned")
+                    .prompts
+                    .get(name)
+                    .cloned()
+            }
+        };
+        route.ok_or_else(|| ErrorData::invalid_params(format!("prompt {name} not found"), None))
+    }
+
+    /// Route a resource by its URI, or else by the template with the longest literal prefix
+    /// matching it.
+    async fn route_resource(&self, uri: &str) -> Result<usize, ErrorData> {
+        if let Some(upstream) = self.find_resource(uri) {
+            return Ok(upstream);
+        }
+        self.list_resources().await;
+        self.list_resource_templates().await;
+        self.find_resource(uri)
+            .ok_or_else(|| ErrorData::resource_not_found(format!("resource {uri} not found"), None))
+    }
+
+    fn find_resource(&self, uri: &str) -> Option<usize> {
+        let routes = self.routes.lock().expect("lock poisoned");
+        if let Some(route) = routes.resources.get(uri) {
+            return Some(route.upstream);
+        }
+        routes
+            .resource_templates
+            .iter()
+            .filter_map(|(template, route)| {
+                let prefix = template.split('{').next().unwrap_or_default();
+                uri.starts_with(prefix)
+                    .then_some((prefix.len(), route.upstream))
+            })
+            .max_by_key(|(length, upstream)| match self.collision {
+                Collision::KeepFirst => (*length, usize::MAX - upstream),
+                Collision::KeepLast => (*length, *upstream),
+            })
+            .map(|(_, upstream)| upstream)
+    }
+
+    /// Send a request to an upstream, relaying its progress and cancellation.
+    ///
+    /// The progress token is mapped before the request is sent, so no progress notification
+    /// is missed. A request cancelled by the downstream client fails as
+    /// [`ErrorCode::REQUEST_CANCELLED`].
+    async fn forward(
+        &self,
+        upstream: usize,
+        request: ClientRequest,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<ServerResult, ErrorData> {
+        let connection = &self.connections()[upstream];
+        let mut options = PeerRequestOptions::no_options();
+        let progress_token = context.meta.get_progress_token().map(|token| {
+            let progress_token = connection.progress_tokens.next_progress_token();
+            connection
+                .progress
+                .lock()
+                .expect("lock poisoned")
+                .insert(progress_token.clone(), token);
+            let mut meta = Meta::new();
+            meta.set_progress_token(progress_token.clone());
+            options.meta = Some(meta);
+            progress_token
+        });
+        let result = async {
+            let handle = connection
+                .service
+                .send_request_with_option(request, options)
+                .await
+                .map_err(|e| connection.upstream_error(e))?;
+            let id = handle.id.clone();
+            tokio::select! {
+                result = handle.await_response() => result.map_err(|e| connection.upstream_error(e)),
+                _ = context.ct.cancelled() => {
+                    let _ = connection
+                        .service
+                        .notify_cancelled(CancelledNotificationParam {
+                            request_id: id,
+                            reason: Some("cancelled by the downstream client".to_owned()),
+                        })
+                        .await;
+                    Err(ErrorData::request_cancelled("request cancelled by the client", None))
+                }
+            }
+        }
+        .await;
+        if let Some(progress_token) = progress_token {
+            connection
+                .progress
+                .lock()
+                .expect("lock poisoned")
+                .remove(&progress_token);
+        }
+        result
+    }
+
+    /// Send a request to every upstream having a capability.
+    ///
+    /// The failures are reported together, by upstream name, in a single error which keeps
+    /// their code when they share one.
+    async fn broadcast(
+        &self,
+        has_capability: impl Fn(&ServerCapabilities) -> bool,
+        request: ClientRequest,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<ServerResult, ErrorData> {
+        let results = join_all(
+            self.connections()
+                .iter()
+                .enumerate()
+                .filter(|(_, connection)| connection.capabilities().is_some_and(&has_capability))
+                .map(|(index, connection)| {
+                    self.forward(index, request.clone(), context)
+                        .map(move |result| (connection.upstream.name(), result))
+                }),
+        )
+        .await;
+        let failures = results
+            .into_iter()
+            .filter_map(|(name, result)| result.err().map(|error| (name, error)))
+            .collect::<Vec<_>>();
+        let Some((_, first)) = failures.first() else {
+            return Ok(ServerResult::empty(()));
+        };
+        let code = if failures.iter().all(|(_, error)| error.code == first.code) {
+            first.code
+        } else {
+            ErrorCode::INTERNAL_ERROR
+        };
+        let message = failures
+            .iter()
+            .map(|(name, error)| format!("{name}: {}", error.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let data = failures
+            .iter()
+            .map(|(name, error)| {
+                (
+                    name.to_string(),
+                    serde_json::to_value(error).unwrap_or_default(),
+                )
+            })
+            .collect::<JsonObject>();
+        Err(ErrorData::new(
+            code,
+            format!("upstream servers failed: {message}"),
+            Some(serde_json::Value::Object(data)),
+        ))
+    }
+}
+
+impl Service<RoleServer> for Gateway {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, ErrorData> {
+        if !matches!(request, ClientRequest::InitializeRequest(_)) {
+            // served without an initialization
+            let info = context.peer.peer_info().cloned().unwrap_or_default();
+            self.connect(info, context.peer.clone()).await;
+        }
+        match request {
+            ClientRequest::InitializeRequest(request) => {
+                if context.peer.peer_info().is_none() {
+                    context.peer.set_peer_info(request.params.clone());
+                }
+                self.connect(request.params, context.peer.clone()).await;
+                if self.connections().is_empty() && !self.upstreams.is_empty() {
+                    return Err(ErrorData::internal_error(
+                        "failed to connect any upstream server",
+                        None,
+                    ));
+                }
+                Ok(ServerResult::InitializeResult(self.get_info()))
+            }
+            ClientRequest::PingRequest(_) => Ok(ServerResult::empty(())),
+            ClientRequest::ListToolsRequest(_) => {
+                Ok(ServerResult::ListToolsResult(ListToolsResult {
+                    tools: self.list_tools().await,
+                    next_cursor: None,
+                }))
+            }
+            ClientRequest::ListPromptsRequest(_) => {
+                Ok(ServerResult::ListPromptsResult(ListPromptsResult {
+                    prompts: self.list_prompts().await,
+                    next_cursor: None,
+                }))
+            }
+            ClientRequest::ListResourcesRequest(_) => {
+                Ok(ServerResult::ListResourcesResult(ListResourcesResult {
+                    resources: self.list_resources().await,
+                    next_cursor: None,
+                }))
+            }
+            ClientRequest::ListResourceTemplatesRequest(_) => Ok(
+                ServerResult::ListResourceTemplatesResult(ListResourceTemplatesResult {
+                    resource_templates: self.list_resource_templates().await,
+                    next_cursor: None,
+                }),
+            ),
+            ClientRequest::CallToolRequest(mut request) => {
+                let route = self.route_tool(&request.params.name).await?;
+                request.params.name = route.name.into();
+                self.forward(
+                    route.upstream,
+                    ClientRequest::CallToolRequest(request),
+                    &context,
+                )
+                .await
+            }
+            ClientRequest::GetPromptRequest(mut request) => {
+                let route = self.route_prompt(&request.params.name).await?;
+                request.params.name = route.name;
+                self.forward(
+                    route.upstream,
+                    ClientRequest::GetPromptRequest(request),
+                    &context,
+                )
+                .await
+            }
+            ClientRequest::ReadResourceRequest(request) => {
+                let upstream = self.route_resource(&request.params.uri).await?;
+                self.forward(
+                    upstream,
+                    ClientRequest::ReadResourceRequest(request),
+                    &context,
+                )
+                .await
+            }
+            ClientRequest::SubscribeRequest(request) => {
+                let upstream = self.route_resource(&request.params.uri).await?;
+                self.forward(upstream, ClientRequest::SubscribeRequest(request), &context)
+                    .await
+            }
+            ClientRequest::UnsubscribeRequest(request) => {
+                let upstream = self.route_resource(&request.params.uri).await?;
+                self.forward(
+                    upstream,
+                    ClientRequest::UnsubscribeRequest(request),
+                    &context,
+                )
+                .await
+            }
+            ClientRequest::CompleteRequest(mut request) => {
+                let upstream = match &mut request.params.r#ref {
+                    Reference::Prompt(prompt) => {
+                        let route = self.route_prompt(&prompt.name).await?;
+                        prompt.name = route.name;
+                        route.upstream
+                    }
+                    Reference::Resource(resource) => self.route_resource(&resource.uri).await?,
+                };
+                self.forward(upstream, ClientRequest::CompleteRequest(request), &context)
+                    .await
+            }
+            ClientRequest::SetLevelRequest(request) => {
+                self.broadcast(
+                    |capabilities| capabilities.logging.is_some(),
+                    ClientRequest::SetLevelRequest(request),
+                    &context,
+                )
+                .await
+            }
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        _context: NotificationContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        if let ClientNotification::RootsListChangedNotification(_) = notification {
+            join_all(
+                self.connections()
+                    .iter()
+                    .map(|connection| connection.service.notify_roots_list_changed()),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.merged_info()
+    }
+}
@@ -1,4 +1,6 @@
 pub mod progress;
+pub mod roots;
+pub mod sampling;
 use crate::{
     error::ErrorData as McpError,
     model::*,
@@ -0,0 +1,211 @@
+//! Roots shared with the servers, announced when they change.
+//!
+//! A [`RootsManager`] holds the roots of a client. The `file://` roots whose directory doesn't
+//! exist are left out of its listing, and every change of the listing, whether the roots were
+//! edited or a directory appeared or disappeared, is announced to the servers with a
+//! `notifications/roots/list_changed` notification.
+//!
+//! ```rust,ignore
+//! let roots = RootsManager::new().with_root(Root::from_path("/home/me/project"));
+//! let client = roots.clone().serve(transport).await?;
+//! roots.attach(client.peer().clone());
+//! let _watcher = roots.watch(Duration::from_secs(1));
+//!
+//! roots.add(Root::from_path("/home/me/other").with_name("other"));
+//! ```
+//!
+//! Clients implementing [`ClientHandler`] themselves can answer `list_roots` with
+//! [`RootsManager::list_roots`].
+use std::{
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use futures::future::join_all;
+
+use super::ClientHandler;
+use crate::{
+    error::ErrorData as McpError,
+    model::*,
+    service::{Peer, RequestContext, RoleClient},
+};
+
+fn lock<T>(guard: std::sync::LockResult<T>) -> T {
+    guard.expect("roots lock poisoned")
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    roots: Mutex<Vec<Root>>,
+    /// The listing at the last announcement.
+    announced: Mutex<Vec<Root>>,
+    peers: Mutex<Vec<Peer<RoleClient>>>,
+}
+
+/// The roots of a client, see the [module documentation](self).
+///
+/// The clones of a manager share its roots.
+#[derive(Debug, Clone, Default)]
+pub struct RootsManager {
+    info: ClientInfo,
+    inner: Arc<Inner>,
+}
+
+impl RootsManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The info sent to the server, the roots capability is always declared.
+    pub fn with_info(mut self, info: ClientInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    pub fn with_root(self, root: Root) -> Self {
+        self.add(root);
+        self
+    }
+
+    /// Add a root, or replace the one with the same URI. Returns whether the root is new.
+    pub fn add(&self, root: Root) -> bool {
+        let added = {
+            let mut roots = lock(self.inner.roots.lock());
+            match roots.iter_mut().find(|existing| existing.uri == root.uri) {
+                Some(existing) => {
+                    *existing = root;
+                    false
+                }
+                None => {
+                    roots.push(root);
+                    true
+                }
+            }
+        };
+        self.changed();
+        added
+    }
+
+    /// Remove the root with this URI. Returns whether it was there.
+    pub fn remove(&self, uri: &str) -> bool {
+        let removed = {
+            let mut roots = lock(self.inner.roots.lock());
+            let len = roots.len();
+            roots.retain(|root| root.uri != uri);
+            roots.len() != len
+        };
+        self.changed();
+        removed
+    }
+
+    /// Replace all the roots.
+    pub fn set(&self, roots: Vec<Root>) {
+        *lock(self.inner.roots.lock()) = roots;
+        self.changed();
+    }
+
+    /// The roots, including the `file://` roots whose directory doesn't exist.
+    pub fn roots(&self) -> Vec<Root> {
+        lock(self.inner.roots.lock()).clone()
+    }
+
+    /// The roots listed to the servers.
+    pub fn list_roots(&self) -> ListRootsResult {
+        ListRootsResult {
+            roots: listing(&self.inner),
+        }
+    }
+
+    /// Announce the changes to `peer` until its transport is closed.
+    ///
+    /// The peers listing the roots are attached too.
+    pub fn attach(&self, peer: Peer<RoleClient>) {
+        let mut peers = lock(self.inner.peers.lock());
+        peers.retain(|attached| !attached.is_transport_closed());
+        if !peers.iter().any(|attached| attached.is_same_peer(&peer)) {
+            peers.push(peer);
+        }
+    }
+
+    /// Check the directories of the roots every `interval`, and announce the ones which appeared
+    /// or disappeared.
+    ///
+    /// The watcher stops when it is aborted, or once every clone of the manager is dropped.
+    pub fn watch(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let inner = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Some(inner) = Weak::upgrade(&inner) else {
+                    return;
+                };
+                if let Some(peers) = take_change(&inner) {
+                    announce(peers).await;
+                }
+            }
+        })
+    }
+
+    fn changed(&self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            // without a runtime, the change is announced by the watcher, or listed by the
+            // peers attached later
+            return;
+        };
+        if let Some(peers) = take_change(&self.inner) {
+            runtime.spawn(announce(peers));
+        }
+    }
+}
+
+/// The configured roots, without the missing directories.
+fn listing(inner: &Inner) -> Vec<Root> {
+    lock(inner.roots.lock())
+        .iter()
+        .filter(|root| root.path().is_none_or(|path| path.is_dir()))
+        .cloned()
+        .collect()
+}
+
+/// The peers to notify if the listing changed since the last announcement.
+fn take_change(inner: &Inner) -> Option<Vec<Peer<RoleClient>>> {
+    let listing = listing(inner);
+    let mut announced = lock(inner.announced.lock());
+    if *announced == listing {
+        return None;
+    }
+    *announced = listing;
+    let mut peers = lock(inner.peers.lock());
+    peers.retain(|attached| !attached.is_transport_closed());
+    Some(peers.clone())
+}
+
+async fn announce(peers: Vec<Peer<RoleClient>>) {
+    join_all(
+        peers
+            .iter()
+            .map(|peer| async { peer.notify_roots_list_changed().await }),
+    )
+    .await;
+}
+
+impl ClientHandler for RootsManager {
+    async fn list_roots(
+        &self,
+        context: RequestContext<RoleClient>,
+    ) -> Result<ListRootsResult, McpError> {
+        self.attach(context.peer);
+        Ok(self.list_roots())
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        let mut info = self.info.clone();
+        info.capabilities
+            .roots
+            .get_or_insert_with(Default::default)
+            .list_changed = Some(true);
+        info
+    }
+}
@@ -0,0 +1,405 @@
+//! Answer the sampling requests of servers with configured LLM providers.
+//!
+//! A [`SamplingHandler`] is a [`ClientHandler`] routing `sampling/createMessage` requests to the
+//! [`SamplingProvider`] whose model matches the request's model hints, the first configured
+//! model otherwise. Before a request reaches the provider, its `includeContext` is lowered to the
+//! allowed [`ContextInclusion`], its `maxTokens` is capped, and the tokens are reserved in the
+//! token budget of the handler. A [`SamplingApproval`] lets the user review, edit or reject the
+//! request and the response.
+//!
+//! ```rust,ignore
+//! let client = SamplingHandler::new()
+//!     .with_model("claude-3-5-sonnet", anthropic)
+//!     .with_model("gpt-4o", openai)
+//!     .with_approval(ConsoleApproval)
+//!     .with_max_tokens(1024)
+//!     .with_token_budget(100_000)
+//!     .serve(transport)
+//!     .await?;
+//! ```
+//!
+//! Clients implementing [`ClientHandler`] themselves can answer with
+//! [`SamplingHandler::sample`] from their own `create_message`.
+use std::sync::{Arc, Mutex};
+
+use futures::{TryFutureExt, future::BoxFuture};
+
+use super::ClientHandler;
+use crate::{
+    error::ErrorData as McpError,
+    model::*,
+    service::{RequestContext, RoleClient},
+};
+
+/// Generates the messages of sampling requests, usually by calling an LLM.
+pub trait SamplingProvider: Send + Sync + 'static {
+    fn create_message(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Result<CreateMessageResult, McpError>>;
+
+    /// Generate a message, with the tokens it used when the provider knows them.
+    ///
+    /// The token budget of a [`SamplingHandler`] is settled against the usage, and spends the
+    /// whole `maxTokens` of the request when it is unknown, as it is by default.
+    fn create_message_with_usage(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Result<(CreateMessageResult, Option<u64>), McpError>> {
+        Box::pin(self.create_message(request).map_ok(|result| (result, None)))
+    }
+}
+
+/// Lets the user review the sampling requests and their responses.
+///
+/// Returning `None` rejects the request, which fails with a
+/// [`USER_REJECTED`](ErrorCode::USER_REJECTED) error.
+pub trait SamplingApproval: Send + Sync + 'static {
+    /// Review, and possibly edit, a request before it is sent to the provider.
+    fn review_request(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Option<CreateMessageRequestParam>> {
+        Box::pin(std::future::ready(Some(request)))
+    }
+
+    /// Review, and possibly edit, the response of the provider before it is sent to the server.
+    fn review_result<'a>(
+        &'a self,
+        request: &'a CreateMessageRequestParam,
+        result: CreateMessageResult,
+    ) -> BoxFuture<'a, Option<CreateMessageResult>> {
+        let _ = request;
+        Box::pin(std::future::ready(Some(result)))
+    }
+}
+
+#[derive(Clone)]
+struct Backend {
+    model: String,
+    provider: Arc<dyn SamplingProvider>,
+}
+
+/// A [`ClientHandler`] answering sampling requests with [`SamplingProvider`]s.
+///
+/// The clones of a handler share its token budget.
+#[derive(Clone)]
+pub struct SamplingHandler {
+    info: ClientInfo,
+    backends: Vec<Backend>,
+    approval: Option<Arc<dyn SamplingApproval>>,
+    include_context: ContextInclusion,
+    max_tokens: Option<u32>,
+    budget: Option<Arc<Mutex<u64>>>,
+}
+
+impl std::fmt::Debug for SamplingHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SamplingHandler")
+            .field("info", &self.info)
+            .field(
+                "models",
+                &self.backends.iter().map(|b| &b.model).collect::<Vec<_>>(),
+            )
+            .field("approval", &self.approval.is_some())
+            .field("include_context", &self.include_context)
+            .field("max_tokens", &self.max_tokens)
+            .field("remaining_budget", &self.remaining_budget())
+            .finish()
+    }
+}
+
+impl Default for SamplingHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamplingHandler {
+    /// A handler without models, which doesn't include any context.
+    pub fn new() -> Self {
+        Self {
+            info: ClientInfo::default(),
+            backends: Vec::new(),
+            approval: None,
+            include_context: ContextInclusion::None,
+            max_tokens: None,
+            budget: None,
+        }
+    }
+
+    /// The info sent to the server, the sampling capability is always declared.
+    pub fn with_info(mut self, info: ClientInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Serve the requests hinting at `model`. The first model also serves the requests without
+    /// matching hints.
+    pub fn with_model(mut self, model: impl Into<String>, provider: impl SamplingProvider) -> Self {
+        self.backends.push(Backend {
+            model: model.into(),
+            provider: Arc::new(provider),
+        });
+        self
+    }
+
+    pub fn with_approval(mut self, approval: impl SamplingApproval) -> Self {
+        self.approval = Some(Arc::new(approval));
+        self
+    }
+
+    /// The most context a request may include, requests asking for more are lowered to it.
+    pub fn with_context_inclusion(mut self, include_context: ContextInclusion) -> Self {
+        self.include_context = include_context;
+        self
+    }
+
+    /// Cap the `maxTokens` of every request.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// The tokens all the requests may use together. Each request reserves its `maxTokens` in
+    /// the budget, and requests fail once it is exhausted.
+    ///
+    /// A request gives back the tokens it didn't use when its provider reports its usage, and all
+    /// of them when it fails or its result is rejected.
+    pub fn with_token_budget(mut self, tokens: u64) -> Self {
+        self.budget = Some(Arc::new(Mutex::new(tokens)));
+        self
+    }
+
+    /// The tokens left in the budget, if there is one.
+    pub fn remaining_budget(&self) -> Option<u64> {
+        self.budget
+            .as_ref()
+            .map(|budget| *budget.lock().expect("budget lock poisoned"))
+    }
+
+    /// The model serving a request with these preferences.
+    ///
+    /// The hints are tried in order, a hint matches the models whose name contains it, ignoring
+    /// case.
+    pub fn select_model(&self, preferences: Option<&ModelPreferences>) -> Option<&str> {
+        self.select(preferences)
+            .map(|backend| backend.model.as_str())
+    }
+
+    fn select(&self, preferences: Option<&ModelPreferences>) -> Option<&Backend> {
+        preferences
+            .and_then(|preferences| preferences.hints.as_deref())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|hint| hint.name.as_deref())
+            .find_map(|hint| {
+                let hint = hint.to_lowercase();
+                self.backends
+                    .iter()
+                    .find(|backend| backend.model.to_lowercase().contains(&hint))
+            })
+            .or_else(|| self.backends.first())
+    }
+
+    /// Apply the context and token limits to a request.
+    fn constrain(&self, mut request: CreateMessageRequestParam) -> CreateMessageRequestParam {
+        if let Some(include_context) = &request.include_context {
+            if context_rank(include_context) > context_rank(&self.include_context) {
+                request.include_context = Some(self.include_context.clone());
+            }
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            request.max_tokens = request.max_tokens.min(max_tokens);
+        }
+        request
+    }
+
+    /// Reserve the tokens of a request in the budget, lowering its `maxTokens` to what is left.
+    fn reserve(&self, request: &mut CreateMessageRequestParam) -> Result<Reservation, McpError> {
+        let Some(budget) = &self.budget else {
+            return Ok(Reservation {
+                budget: None,
+                tokens: 0,
+            });
+        };
+        let mut remaining = budget.lock().expect("budget lock poisoned");
+        if *remaining == 0 {
+            return Err(McpError::invalid_request(
+                "the sampling token budget is exhausted",
+                None,
+            ));
+        }
+        request.max_tokens = request
+            .max_tokens
+            .min(u32::try_from(*remaining).unwrap_or(u32::MAX));
+        *remaining -= u64::from(request.max_tokens);
+        Ok(Reservation {
+            budget: Some(budget.clone()),
+            tokens: u64::from(request.max_tokens),
+        })
+    }
+
+    /// Answer a sampling request.
+    pub async fn sample(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> Result<CreateMessageResult, McpError> {
+        let mut request = self.constrain(request);
+        if let Some(approval) = &self.approval {
+            request = approval
+                .review_request(request)
+                .await
+                .map(|request| self.constrain(request))
+                .ok_or_else(rejected)?;
+        }
+        let backend = self
+            .select(request.model_preferences.as_ref())
+            .ok_or_else(|| McpError::internal_error("no sampling model is configured", None))?;
+        // given back when the request fails, or is dropped
+        let reservation = self.reserve(&mut request)?;
+        let (result, used_tokens) = backend
+            .provider
+            .create_message_with_usage(request.clone())
+            .await?;
+        let result = match &self.approval {
+            Some(approval) => approval
+                .review_result(&request, result)
+                .await
+                .ok_or_else(rejected)?,
+            None => result,
+        };
+        reservation.spend(used_tokens);
+        Ok(result)
+    }
+}
+
+/// Tokens reserved in the budget for a request, the unspent ones are given back on drop.
+struct Reservation {
+    budget: Option<Arc<Mutex<u64>>>,
+    tokens: u64,
+}
+
+impl Reservation {
+    /// Spend `used` tokens of the reservation, all of them when the usage is unknown.
+    fn spend(mut self, used: Option<u64>) {
+        self.tokens -= used.map_or(self.tokens, |used| used.min(self.tokens));
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            *budget.lock().expect("budget lock poisoned") += self.tokens;
+        }
+    }
+}
+
+fn rejected() -> McpError {
+    McpError::new(
+        ErrorCode::USER_REJECTED,
+        "User rejected sampling request",
+        None,
+    )
+}
+
+fn context_rank(include_context: &ContextInclusion) -> u8 {
+    match include_context {
+        ContextInclusion::None => 0,
+        ContextInclusion::ThisServer => 1,
+        ContextInclusion::AllServers => 2,
+    }
+}
+
+impl ClientHandler for SamplingHandler {
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> Result<CreateMessageResult, McpError> {
+        self.sample(params).await
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        let mut info = self.info.clone();
+        info.capabilities
+            .sampling
+            .get_or_insert_with(Default::default);
+        info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    impl SamplingProvider for Echo {
+        fn create_message(
+            &self,
+            _request: CreateMessageRequestParam,
+        ) -> BoxFuture<'_, Result<CreateMessageResult, McpError>> {
+            Box::pin(std::future::ready(Err(McpError::internal_error(
+                "echo is unavailable",
+                None,
+            ))))
+        }
+    }
+
+    fn preferences(hints: &[&str]) -> ModelPreferences {
+        ModelPreferences {
+            hints: Some(
+                hints
+                    .iter()
+                    .map(|name| ModelHint {
+                        name: Some(name.to_string()),
+                    })
+                    .collect(),
+            ),
+            cost_priority: None,
+            speed_priority: None,
+            intelligence_priority: None,
+        }
+    }
+
+    #[test]
+    fn test_select_model() {
+        let handler = SamplingHandler::new()
+            .with_model("claude-3-5-sonnet", Echo)
+            .with_model("gpt-4o", Echo);
+        assert_eq!(handler.select_model(None), Some("claude-3-5-sonnet"));
+        assert_eq!(
+            handler.select_model(Some(&preferences(&["GPT"]))),
+            Some("gpt-4o")
+        );
+        assert_eq!(
+            handler.select_model(Some(&preferences(&["gemini", "claude"]))),
+            Some("claude-3-5-sonnet")
+        );
+        assert_eq!(
+            handler.select_model(Some(&preferences(&["gemini"]))),
+            Some("claude-3-5-sonnet")
+        );
+        assert_eq!(SamplingHandler::new().select_model(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_failed_requests_give_back_their_tokens() {
+        let handler = SamplingHandler::new()
+            .with_model("echo", Echo)
+            .with_token_budget(100);
+        let request = CreateMessageRequestParam {
+            messages: Vec::new(),
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 60,
+            stop_sequences: None,
+            metadata: None,
+        };
+        assert!(handler.sample(request).await.is_err());
+        assert_eq!(handler.remaining_budget(), Some(100));
+    }
+}
@@ -4,9 +4,16 @@ use crate::{
     service::{NotificationContext, RequestContext, RoleServer, Service, ServiceRole},
 };
 
+pub mod guard;
+pub mod registry;
 mod resource;
+pub mod roots;
 pub mod router;
+pub mod schema_profile;
 pub mod tool;
+#[cfg(feature = "schema-validation")]
+#[cfg_attr(docsrs, doc(cfg(feature = "schema-validation")))]
+pub mod validation;
 pub mod wrapper;
 impl<H: ServerHandler> Service<RoleServer> for H {
     async fn handle_request(
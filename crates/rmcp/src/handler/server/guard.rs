@@ -0,0 +1,87 @@
+//! Guards deciding which tools a request may list and call.
+//!
+//! A guard is given the [`RequestContext`] of the `tools/list` or `tools/call` request, with the
+//! extensions attached by the transport, such as the
+//! [`AuthClaims`](crate::transport::auth_server::AuthClaims) of the bearer auth layer. The tools
+//! a request isn't allowed to call are left out of its listing, and calling them fails with a
+//! [`FORBIDDEN`](crate::model::ErrorCode::FORBIDDEN) error.
+//!
+//! ```rust,ignore
+//! #[tool(description = "Drop the database", guard = require_scope("admin"))]
+//! async fn drop_database(&self) -> String { .. }
+//!
+//! // or for every tool of a router
+//! Self::tool_router().with_guard(require_scope("admin"))
+//! ```
+use crate::service::{RequestContext, RoleServer};
+
+pub trait ToolGuard: Send + Sync + 'static {
+    /// Whether the request may list and call the tool.
+    fn allows(&self, context: &RequestContext<RoleServer>) -> bool;
+}
+
+impl std::fmt::Debug for dyn ToolGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ToolGuard")
+    }
+}
+
+impl<F> ToolGuard for F
+where
+    F: Fn(&RequestContext<RoleServer>) -> bool + Send + Sync + 'static,
+{
+    fn allows(&self, context: &RequestContext<RoleServer>) -> bool {
+        (self)(context)
+    }
+}
+
+/// Allow the requests whose access token was granted `scope`.
+#[cfg(feature = "auth-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth-server")))]
+pub fn require_scope(scope: impl Into<String>) -> impl ToolGuard {
+    let scope = scope.into();
+    move |context: &RequestContext<RoleServer>| {
+        context
+            .extensions
+            .get::<crate::transport::auth_server::AuthClaims>()
+            .is_some_and(|claims| claims.has_scope(&scope))
+    }
+}
+
+#[cfg(all(test, feature = "auth-server"))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        model::{Extensions, Meta, RequestId},
+        service::{AtomicU32RequestIdProvider, Peer},
+        transport::auth_server::AuthClaims,
+    };
+
+    fn context(scopes: Option<&[&str]>) -> RequestContext<RoleServer> {
+        let (peer, _) = Peer::new(Arc::new(AtomicU32RequestIdProvider::default()), None);
+        let mut extensions = Extensions::new();
+        if let Some(scopes) = scopes {
+            extensions.insert(AuthClaims {
+                scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+                ..Default::default()
+            });
+        }
+        RequestContext {
+            ct: Default::default(),
+            id: RequestId::Number(0),
+            meta: Meta::default(),
+            extensions,
+            peer,
+        }
+    }
+
+    #[test]
+    fn test_require_scope() {
+        let guard = require_scope("admin");
+        assert!(guard.allows(&context(Some(&["read", "admin"]))));
+        assert!(!guard.allows(&context(Some(&["read"]))));
+        assert!(!guard.allows(&context(None)));
+    }
+}
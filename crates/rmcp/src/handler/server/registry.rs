@@ -0,0 +1,494 @@
+//! Tools, prompts and resources which can change while the server is running.
+//!
+//! A [`Registry`] is shared by every session of a server: clone it into the service created for
+//! each session, and [attach](Registry::attach) the peer of the session once it's initialized.
+//! Every change is announced to the attached peers with a `list_changed` notification, changes
+//! made within the [debounce](Registry::with_debounce) delay are announced once. The
+//! announcements are sent from the tokio runtime of the change: the changes made outside of a
+//! runtime are only announced with the next change made within one.
+//!
+//! The registry serves its tools like a [`ToolRouter`], so it can be used with
+//! `#[tool_handler(router = self.registry)]`.
+//!
+//! ```rust,ignore
+//! #[derive(Clone)]
+//! struct Server {
+//!     registry: Registry<Self>,
+//! }
+//!
+//! #[tool_handler(router = self.registry)]
+//! impl ServerHandler for Server {
+//!     async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+//!         self.registry.attach(context.peer);
+//!     }
+//! }
+//!
+//! let registry = Registry::new().with_tools(Server::tool_router());
+//! let service = StreamableHttpService::new(
+//!     move || Ok(Server { registry: registry.clone() }),
+//!     Default::default(),
+//!     Default::default(),
+//! );
+//! registry.remove_tool("install");
+//! ```
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::future::{BoxFuture, join_all};
+
+use super::{
+    router::tool::{IntoToolRoute, ToolRoute, ToolRouter},
+    tool::ToolCallContext,
+};
+use crate::{
+    ErrorData,
+    model::{
+        CallToolResult, GetPromptRequestParam, GetPromptResult, JsonObject, Prompt,
+        ReadResourceRequestParam, ReadResourceResult, Resource, Tool,
+    },
+    service::{Peer, RequestContext, RoleServer},
+};
+
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+type DynGetPromptHandler<S> = dyn for<'a> Fn(
+        &'a S,
+        Option<JsonObject>,
+        RequestContext<RoleServer>,
+    ) -> BoxFuture<'a, Result<GetPromptResult, ErrorData>>
+    + Send
+    + Sync;
+
+type DynReadResourceHandler<S> = dyn for<'a> Fn(
+        &'a S,
+        RequestContext<RoleServer>,
+    ) -> BoxFuture<'a, Result<ReadResourceResult, ErrorData>>
+    + Send
+    + Sync;
+
+/// A prompt, and how to get it from its arguments.
+pub struct PromptRoute<S> {
+    pub get: Arc<DynGetPromptHandler<S>>,
+    pub attr: Prompt,
+}
+
+impl<S> PromptRoute<S> {
+    pub fn new<F>(attr: Prompt, get: F) -> Self
+    where
+        F: for<'a> Fn(
+                &'a S,
+                Option<JsonObject>,
+                RequestContext<RoleServer>,
+            ) -> BoxFuture<'a, Result<GetPromptResult, ErrorData>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            get: Arc::new(get),
+            attr,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.attr.name
+    }
+}
+
+impl<S> Clone for PromptRoute<S> {
+    fn clone(&self) -> Self {
+        Self {
+            get: self.get.clone(),
+            attr: self.attr.clone(),
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for PromptRoute<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PromptRoute")
+            .field("name", &self.attr.name)
+            .finish()
+    }
+}
+
+/// A resource, and how to read it.
+pub struct ResourceRoute<S> {
+    pub read: Arc<DynReadResourceHandler<S>>,
+    pub attr: Resource,
+}
+
+impl<S> ResourceRoute<S> {
+    pub fn new<F>(attr: Resource, read: F) -> Self
+    where
+        F: for<'a> Fn(
+                &'a S,
+                RequestContext<RoleServer>,
+            ) -> BoxFuture<'a, Result<ReadResourceResult, ErrorData>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            read: Arc::new(read),
+            attr,
+        }
+    }
+    pub fn uri(&self) -> &str {
+        &self.attr.uri
+    }
+}
+
+impl<S> Clone for ResourceRoute<S> {
+    fn clone(&self) -> Self {
+        Self {
+            read: self.read.clone(),
+            attr: self.attr.clone(),
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for ResourceRoute<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResourceRoute")
+            .field("uri", &self.attr.uri)
+            .finish()
+    }
+}
+
+/// The lists changed since the last announcement.
+#[derive(Debug, Default)]
+struct Changes {
+    tools: bool,
+    prompts: bool,
+    resources: bool,
+    scheduled: bool,
+}
+
+struct Inner<S> {
+    tools: RwLock<Arc<ToolRouter<S>>>,
+    tools_added: AtomicBool,
+    prompts: RwLock<BTreeMap<String, PromptRoute<S>>>,
+    resources: RwLock<BTreeMap<String, ResourceRoute<S>>>,
+    peers: Mutex<Vec<Peer<RoleServer>>>,
+    changes: Mutex<Changes>,
+    debounce: Duration,
+}
+
+/// Tools, prompts and resources shared by the sessions of a server, announcing their changes to
+/// every attached peer.
+pub struct Registry<S> {
+    inner: Arc<Inner<S>>,
+}
+
+impl<S> Clone for Registry<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S> std::fmt::Debug for Registry<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry")
+            .field("tools", &lock(self.inner.tools.read()).map.keys())
+            .field("prompts", &lock(self.inner.prompts.read()).keys())
+            .field("resources", &lock(self.inner.resources.read()).keys())
+            .field("debounce", &self.inner.debounce)
+            .finish()
+    }
+}
+
+impl<S: Send + Sync + 'static> Default for Registry<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lock<T>(guard: std::sync::LockResult<T>) -> T {
+    guard.expect("registry lock poisoned")
+}
+
+impl<S> Registry<S>
+where
+    S: Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::with_debounce(DEFAULT_DEBOUNCE)
+    }
+
+    /// Changes made within `debounce` of each other are announced by a single notification.
+    pub fn with_debounce(debounce: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                tools: Default::default(),
+                tools_added: AtomicBool::new(false),
+                prompts: Default::default(),
+                resources: Default::default(),
+                peers: Default::default(),
+                changes: Default::default(),
+                debounce,
+            }),
+        }
+    }
+
+    pub fn with_tools(self, tools: ToolRouter<S>) -> Self {
+        self.add_tools(tools);
+        self
+    }
+
+    pub fn with_prompt(self, prompt: PromptRoute<S>) -> Self {
+        self.add_prompt(prompt);
+        self
+    }
+
+    pub fn with_resource(self, resource: ResourceRoute<S>) -> Self {
+        self.add_resource(resource);
+        self
+    }
+
+    /// Announce the changes to `peer` until its transport is closed.
+    pub fn attach(&self, peer: Peer<RoleServer>) {
+        let mut peers = lock(self.inner.peers.lock());
+        peers.retain(|attached| !attached.is_transport_closed());
+        if !peers.iter().any(|attached| attached.is_same_peer(&peer)) {
+            peers.push(peer);
+        }
+    }
+
+    /// The number of attached peers whose transport is still open.
+    pub fn peer_count(&self) -> usize {
+        let mut peers = lock(self.inner.peers.lock());
+        peers.retain(|attached| !attached.is_transport_closed());
+        peers.len()
+    }
+
+    fn read_tools(&self) -> Arc<ToolRouter<S>> {
+        lock(self.inner.tools.read()).clone()
+    }
+
+    /// Update the tools under the write lock, the change is announced unless `update` returns
+    /// `None`.
+    fn update_tools<T>(
+        &self,
+        update: impl FnOnce(&mut Arc<ToolRouter<S>>) -> Option<T>,
+    ) -> Option<T> {
+        let output = update(&mut *lock(self.inner.tools.write()));
+        if output.is_some() {
+            self.changed(|changes| changes.tools = true);
+        }
+        output
+    }
+
+    /// Add a tool, unless there is already a tool with the same name.
+    pub fn add_tool<R, A>(&self, route: R) -> bool
+    where
+        R: IntoToolRoute<S, A>,
+    {
+        let route = route.into_tool_route();
+        self.update_tools(|tools| {
+            (!tools.has_route(route.name())).then(|| Arc::make_mut(tools).add_route(route))
+        })
+        .is_some()
+    }
+
+    /// Add a tool, returning the tool it replaced.
+    pub fn replace_tool<R, A>(&self, route: R) -> Option<ToolRoute<S>>
+    where
+        R: IntoToolRoute<S, A>,
+    {
+        let route = route.into_tool_route();
+        self.update_tools(|tools| {
+            Some(
+                Arc::make_mut(tools)
+                    .map
+                    .insert(route.attr.name.clone(), route),
+            )
+        })
+        .flatten()
+    }
+
+    /// Add every tool of the router, replacing the tools with the same names.
+    ///
+    /// The first router added also sets the schema profiles and the validation of the registry.
+    pub fn add_tools(&self, mut tools: ToolRouter<S>) {
+        self.update_tools(|router| {
+            let router = Arc::make_mut(router);
+            if !self.inner.tools_added.swap(true, Ordering::Relaxed) {
+                router.schema_profiles = std::mem::take(&mut tools.schema_profiles);
+                #[cfg(feature = "schema-validation")]
+                {
+                    router.input_validation = tools.input_validation.take();
+                    router.output_validation = tools.output_validation.take();
+                }
+            }
+            router.merge(tools);
+            Some(())
+        });
+    }
+
+    pub fn remove_tool(&self, name: &str) -> Option<ToolRoute<S>> {
+        self.update_tools(|tools| {
+            if !tools.has_route(name) {
+                return None;
+            }
+            Arc::make_mut(tools).map.remove(name)
+        })
+    }
+
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.read_tools().has_route(name)
+    }
+
+    /// Call a tool, like [`ToolRouter::call`].
+    pub async fn call(&self, context: ToolCallContext<'_, S>) -> Result<CallToolResult, ErrorData> {
+        self.read_tools().call(context).await
+    }
+
+    pub fn list_all(&self) -> Vec<Tool> {
+        self.read_tools().list_all()
+    }
+
+    /// The tools the request is allowed to call, like [`ToolRouter::list_visible`].
+    pub fn list_visible(&self, context: &RequestContext<RoleServer>) -> Vec<Tool> {
+        self.read_tools().list_visible(context)
+    }
+
+    /// Add a prompt, unless there is already a prompt with the same name.
+    pub fn add_prompt(&self, route: PromptRoute<S>) -> bool {
+        let mut prompts = lock(self.inner.prompts.write());
+        if prompts.contains_key(route.name()) {
+            return false;
+        }
+        prompts.insert(route.name().to_owned(), route);
+        drop(prompts);
+        self.changed(|changes| changes.prompts = true);
+        true
+    }
+
+    /// Add a prompt, returning the prompt it replaced.
+    pub fn replace_prompt(&self, route: PromptRoute<S>) -> Option<PromptRoute<S>> {
+        let replaced = lock(self.inner.prompts.write()).insert(route.name().to_owned(), route);
+        self.changed(|changes| changes.prompts = true);
+        replaced
+    }
+
+    pub fn remove_prompt(&self, name: &str) -> Option<PromptRoute<S>> {
+        let removed = lock(self.inner.prompts.write()).remove(name)?;
+        self.changed(|changes| changes.prompts = true);
+        Some(removed)
+    }
+
+    pub fn list_prompts(&self) -> Vec<Prompt> {
+        lock(self.inner.prompts.read())
+            .values()
+            .map(|route| route.attr.clone())
+            .collect()
+    }
+
+    pub async fn get_prompt(
+        &self,
+        service: &S,
+        GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let route = lock(self.inner.prompts.read())
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| ErrorData::invalid_params(format!("prompt {name} not found"), None))?;
+        (route.get)(service, arguments, context).await
+    }
+
+    /// Add a resource, unless there is already a resource with the same URI.
+    pub fn add_resource(&self, route: ResourceRoute<S>) -> bool {
+        let mut resources = lock(self.inner.resources.write());
+        if resources.contains_key(route.uri()) {
+            return false;
+        }
+        resources.insert(route.uri().to_owned(), route);
+        drop(resources);
+        self.changed(|changes| changes.resources = true);
+        true
+    }
+
+    /// Add a resource, returning the resource it replaced.
+    pub fn replace_resource(&self, route: ResourceRoute<S>) -> Option<ResourceRoute<S>> {
+        let replaced = lock(self.inner.resources.write()).insert(route.uri().to_owned(), route);
+        self.changed(|changes| changes.resources = true);
+        replaced
+    }
+
+    pub fn remove_resource(&self, uri: &str) -> Option<ResourceRoute<S>> {
+        let removed = lock(self.inner.resources.write()).remove(uri)?;
+        self.changed(|changes| changes.resources = true);
+        Some(removed)
+    }
+
+    pub fn list_resources(&self) -> Vec<Resource> {
+        lock(self.inner.resources.read())
+            .values()
+            .map(|route| route.attr.clone())
+            .collect()
+    }
+
+    pub async fn read_resource(
+        &self,
+        service: &S,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        let route = lock(self.inner.resources.read())
+            .get(&uri)
+            .cloned()
+            .ok_or_else(|| {
+                ErrorData::resource_not_found(
+                    format!("resource {uri} not found"),
+                    Some(serde_json::json!({ "uri": uri })),
+                )
+            })?;
+        (route.read)(service, context).await
+    }
+
+    /// Record a change, and schedule its announcement unless one is already scheduled.
+    ///
+    /// Outside of a tokio runtime the change is only recorded, and announced with the next one.
+    fn changed(&self, record: impl FnOnce(&mut Changes)) {
+        if self.peer_count() == 0 {
+            // the peers attached later list everything anyway
+            return;
+        }
+        let mut changes = lock(self.inner.changes.lock());
+        record(&mut changes);
+        if changes.scheduled {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        changes.scheduled = true;
+        let inner = self.inner.clone();
+        runtime.spawn(async move {
+            tokio::time::sleep(inner.debounce).await;
+            let changes = std::mem::take(&mut *lock(inner.changes.lock()));
+            let peers = lock(inner.peers.lock()).clone();
+            join_all(peers.iter().map(|peer| async {
+                if changes.tools {
+                    let _ = peer.notify_tool_list_changed().await;
+                }
+                if changes.prompts {
+                    let _ = peer.notify_prompt_list_changed().await;
+                }
+                if changes.resources {
+                    let _ = peer.notify_resource_list_changed().await;
+                }
+            }))
+            .await;
+        });
+    }
+}
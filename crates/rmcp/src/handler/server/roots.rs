@@ -0,0 +1,196 @@
+//! The roots of the clients, and a guard keeping the tools inside them.
+//!
+//! A [`RootsCache`] lists the roots of each client once, until the client announces they
+//! changed:
+//!
+//! ```rust,ignore
+//! impl ServerHandler for FileServer {
+//!     async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+//!         self.roots.invalidate(&context.peer);
+//!     }
+//! }
+//!
+//! #[tool(description = "Read a file")]
+//! async fn read_file(&self, path: String, peer: Peer<RoleServer>) -> Result<String, ErrorData> {
+//!     let path = self.roots.guard(&peer).await?.check(&path)?;
+//!     tokio::fs::read_to_string(path).await.map_err(|e| ErrorData::internal_error(e.to_string(), None))
+//! }
+//! ```
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    error::ErrorData as McpError,
+    model::Root,
+    service::{Peer, RoleServer, ServiceError},
+};
+
+fn lock<T>(guard: std::sync::LockResult<T>) -> T {
+    guard.expect("roots lock poisoned")
+}
+
+/// The cached roots of each client.
+type Entries = Vec<(Peer<RoleServer>, Arc<[Root]>)>;
+
+/// The roots of each client, listed on first use.
+///
+/// The clones of a cache share its entries.
+#[derive(Debug, Clone, Default)]
+pub struct RootsCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl RootsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The roots of the client, listed unless they are cached.
+    ///
+    /// Clients without the roots capability have no roots.
+    pub async fn roots(&self, peer: &Peer<RoleServer>) -> Result<Arc<[Root]>, ServiceError> {
+        let cached = lock(self.entries.lock())
+            .iter()
+            .find(|(cached, _)| cached.is_same_peer(peer))
+            .map(|(_, roots)| roots.clone());
+        match cached {
+            Some(roots) => Ok(roots),
+            None => self.refresh(peer).await,
+        }
+    }
+
+    /// List the roots of the client again.
+    pub async fn refresh(&self, peer: &Peer<RoleServer>) -> Result<Arc<[Root]>, ServiceError> {
+        let supported = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        let roots: Arc<[Root]> = if supported {
+            peer.list_roots().await?.roots.into()
+        } else {
+            Arc::new([])
+        };
+        let mut entries = lock(self.entries.lock());
+        entries.retain(|(cached, _)| !cached.is_transport_closed() && !cached.is_same_peer(peer));
+        entries.push((peer.clone(), roots.clone()));
+        Ok(roots)
+    }
+
+    /// Forget the roots of the client, they are listed again on next use.
+    pub fn invalidate(&self, peer: &Peer<RoleServer>) {
+        lock(self.entries.lock()).retain(|(cached, _)| !cached.is_same_peer(peer));
+    }
+
+    /// A guard for the `file://` roots of the client.
+    pub async fn guard(&self, peer: &Peer<RoleServer>) -> Result<RootsGuard, ServiceError> {
+        Ok(RootsGuard::new(self.roots(peer).await?.iter()))
+    }
+}
+
+/// Checks the paths are inside the directories of `file://` roots.
+///
+/// Paths are resolved before they are checked: `..` components are applied, and the symbolic
+/// links of the existing part of the path are followed, so neither can escape a root.
+#[derive(Debug, Clone, Default)]
+pub struct RootsGuard {
+    directories: Vec<PathBuf>,
+}
+
+impl RootsGuard {
+    pub fn new<'a>(roots: impl IntoIterator<Item = &'a Root>) -> Self {
+        Self {
+            directories: roots
+                .into_iter()
+                .filter_map(Root::path)
+                .filter_map(|path| resolve(&path))
+                .collect(),
+        }
+    }
+
+    /// The resolved directories of the roots.
+    pub fn directories(&self) -> &[PathBuf] {
+        &self.directories
+    }
+
+    /// Resolve an absolute path or a `file://` URI, and check it is inside a root.
+    pub fn check(&self, path: &str) -> Result<PathBuf, McpError> {
+        let requested = if path.starts_with("file:") {
+            Root::new(path).path().ok_or_else(|| {
+                McpError::invalid_params(format!("{path} is not a local file URI"), None)
+            })?
+        } else {
+            PathBuf::from(path)
+        };
+        let resolved = resolve(&requested).ok_or_else(|| {
+            McpError::invalid_params(format!("{path} is not an absolute path"), None)
+        })?;
+        if self
+            .directories
+            .iter()
+            .any(|directory| resolved.starts_with(directory))
+        {
+            Ok(resolved)
+        } else {
+            Err(McpError::forbidden(
+                format!("{path} is outside the allowed roots"),
+                Some(serde_json::json!({ "path": path })),
+            ))
+        }
+    }
+}
+
+/// Apply the `..` components of an absolute path, and follow the symbolic links of its longest
+/// existing ancestor.
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    let mut missing = Vec::new();
+    let mut existing = normalized.as_path();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(canonical, |path, name| path.join(name)),
+            );
+        }
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roots_guard() {
+        let guard = RootsGuard::new(&[
+            Root::new("file:///nonexistent/project"),
+            Root::new("https://example.com"),
+        ]);
+        assert_eq!(guard.directories(), [PathBuf::from("/nonexistent/project")]);
+        assert_eq!(
+            guard.check("/nonexistent/project/src/../lib.rs").unwrap(),
+            PathBuf::from("/nonexistent/project/lib.rs")
+        );
+        assert!(guard.check("file:///nonexistent/project/a%20b").is_ok());
+        assert!(guard.check("/nonexistent/project/../secret").is_err());
+        assert!(guard.check("/nonexistent/projects").is_err());
+        assert!(guard.check("relative/path").is_err());
+        assert!(RootsGuard::default().check("/").is_err());
+    }
+}
@@ -80,7 +80,7 @@ where
                 }
             }
             ClientRequest::ListToolsRequest(_) => {
-                let tools = self.tool_router.list_all();
+                let tools = self.tool_router.list_visible(&context);
                 Ok(ServerResult::ListToolsResult(ListToolsResult {
                     tools,
                     next_cursor: None,
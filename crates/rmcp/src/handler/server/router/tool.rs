@@ -1,20 +1,31 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 use futures::{FutureExt, future::BoxFuture};
 use schemars::JsonSchema;
 
+#[cfg(feature = "schema-validation")]
+use crate::handler::server::validation::SchemaValidators;
 use crate::{
-    handler::server::tool::{
-        CallToolHandler, DynCallToolHandler, ToolCallContext, schema_for_type,
-        validate_against_schema,
+    handler::server::{
+        guard::ToolGuard,
+        schema_profile::{SchemaProfile, SchemaProfiles},
+        tool::{
+            CallToolHandler, DynCallToolHandler, ToolCallContext, schema_for_type,
+            validate_against_schema,
+        },
     },
-    model::{CallToolResult, Tool, ToolAnnotations},
+    model::{CallToolResult, Content, Tool, ToolAnnotations},
+    service::{RequestContext, RoleServer},
 };
 
 pub struct ToolRoute<S> {
     #[allow(clippy::type_complexity)]
     pub call: Arc<DynCallToolHandler<S>>,
     pub attr: crate::model::Tool,
+    /// The requests must pass every guard to list and call the tool.
+    pub guards: Vec<Arc<dyn ToolGuard>>,
+    /// How long a call may run, instead of the default timeout of the router.
+    pub timeout: Option<Duration>,
 }
 
 impl<S> std::fmt::Debug for ToolRoute<S> {
@@ -23,6 +34,8 @@ impl<S> std::fmt::Debug for ToolRoute<S> {
             .field("name", &self.attr.name)
             .field("description", &self.attr.description)
             .field("input_schema", &self.attr.input_schema)
+            .field("guards", &self.guards.len())
+            .field("timeout", &self.timeout)
             .finish()
     }
 }
@@ -32,6 +45,8 @@ impl<S> Clone for ToolRoute<S> {
         Self {
             call: self.call.clone(),
             attr: self.attr.clone(),
+            guards: self.guards.clone(),
+            timeout: self.timeout,
         }
     }
 }
@@ -47,6 +62,8 @@ impl<S: Send + Sync + 'static> ToolRoute<S> {
                 context.invoke(call).boxed()
             }),
             attr: attr.into(),
+            guards: Vec::new(),
+            timeout: None,
         }
     }
     pub fn new_dyn<C>(attr: impl Into<Tool>, call: C) -> Self
@@ -61,11 +78,26 @@ impl<S: Send + Sync + 'static> ToolRoute<S> {
         Self {
             call: Arc::new(call),
             attr: attr.into(),
+            guards: Vec::new(),
+            timeout: None,
         }
     }
     pub fn name(&self) -> &str {
         &self.attr.name
     }
+    /// Only let the requests allowed by `guard` list and call the tool.
+    pub fn with_guard(mut self, guard: impl ToolGuard) -> Self {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+    pub fn allows(&self, context: &RequestContext<RoleServer>) -> bool {
+        self.guards.iter().all(|guard| guard.allows(context))
+    }
+    /// Stop the calls running longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 pub trait IntoToolRoute<S, A> {
@@ -173,6 +205,26 @@ pub struct ToolRouter<S> {
     pub map: std::collections::HashMap<Cow<'static, str>, ToolRoute<S>>,
 
     pub transparent_when_not_found: bool,
+
+    /// Guards applied to every tool of the router, in addition to their own.
+    pub guards: Vec<Arc<dyn ToolGuard>>,
+
+    /// How long a call may run, unless its tool has its own timeout.
+    pub default_timeout: Option<Duration>,
+
+    /// How the schemas are adapted for each client in [`ToolRouter::list_visible`].
+    pub schema_profiles: SchemaProfiles,
+
+    /// Validate the arguments of the calls against the input schema of their tool.
+    #[cfg(feature = "schema-validation")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "schema-validation")))]
+    pub input_validation: Option<Arc<SchemaValidators>>,
+
+    /// Validate the structured content of the results against the output schema of their tool,
+    /// enabled by default in debug builds.
+    #[cfg(feature = "schema-validation")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "schema-validation")))]
+    pub output_validation: Option<Arc<SchemaValidators>>,
 }
 
 impl<S> Default for ToolRouter<S> {
@@ -180,6 +232,13 @@ impl<S> Default for ToolRouter<S> {
         Self {
             map: std::collections::HashMap::new(),
             transparent_when_not_found: false,
+            guards: Vec::new(),
+            default_timeout: None,
+            schema_profiles: SchemaProfiles::default(),
+            #[cfg(feature = "schema-validation")]
+            input_validation: None,
+            #[cfg(feature = "schema-validation")]
+            output_validation: cfg!(debug_assertions).then(Default::default),
         }
     }
 }
@@ -188,6 +247,13 @@ impl<S> Clone for ToolRouter<S> {
         Self {
             map: self.map.clone(),
             transparent_when_not_found: self.transparent_when_not_found,
+            guards: self.guards.clone(),
+            default_timeout: self.default_timeout,
+            schema_profiles: self.schema_profiles.clone(),
+            #[cfg(feature = "schema-validation")]
+            input_validation: self.input_validation.clone(),
+            #[cfg(feature = "schema-validation")]
+            output_validation: self.output_validation.clone(),
         }
     }
 }
@@ -206,10 +272,62 @@ where
     S: Send + Sync + 'static,
 {
     pub fn new() -> Self {
-        Self {
-            map: std::collections::HashMap::new(),
-            transparent_when_not_found: false,
+        Self::default()
+    }
+
+    /// Validate the arguments of every call against the input schema of its tool before
+    /// dispatching it, rejecting them with an `invalid_params` error listing every violation.
+    ///
+    /// The validators are compiled once per schema, and shared by the clones of the router.
+    #[cfg(feature = "schema-validation")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "schema-validation")))]
+    pub fn with_input_validation(mut self) -> Self {
+        self.input_validation = Some(Arc::new(SchemaValidators::new()));
+        self
+    }
+
+    /// Validate the structured content of every successful result against the output schema of
+    /// its tool, failing the call with an `internal_error` listing every violation.
+    ///
+    /// This is the default in debug builds, use it to keep the validation in release builds.
+    #[cfg(feature = "schema-validation")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "schema-validation")))]
+    pub fn with_output_validation(mut self) -> Self {
+        if self.output_validation.is_none() {
+            self.output_validation = Some(Arc::new(SchemaValidators::new()));
         }
+        self
+    }
+    /// Only let the requests allowed by `guard` list and call the tools of the router.
+    pub fn with_guard(mut self, guard: impl ToolGuard) -> Self {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+    /// Stop the calls running longer than `timeout`, unless their tool has its own timeout.
+    ///
+    /// A stopped call is dropped and fails with an error result. The
+    /// [`CancellationToken`](tokio_util::sync::CancellationToken) of the call is cancelled, so the
+    /// work it spawned can stop too.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+    /// Adapt the listed schemas with `profile`, for the clients without a profile of their own.
+    pub fn with_schema_profile(mut self, profile: SchemaProfile) -> Self {
+        self.schema_profiles.default = profile;
+        self
+    }
+    /// Adapt the listed schemas with `profile` for the clients whose name, sent on initialize,
+    /// starts with `client_name`.
+    pub fn with_client_schema_profile(
+        mut self,
+        client_name: impl Into<String>,
+        profile: SchemaProfile,
+    ) -> Self {
+        self.schema_profiles
+            .clients
+            .push((client_name.into(), profile));
+        self
     }
     pub fn with_route<R, A>(mut self, route: R) -> Self
     where
@@ -223,8 +341,11 @@ where
         self.map.insert(item.attr.name.clone(), item);
     }
 
+    /// Add the routes of `other`, which keep the guards and default timeout of `other`.
     pub fn merge(&mut self, other: ToolRouter<S>) {
-        for item in other.map.into_values() {
+        for mut item in other.map.into_values() {
+            item.guards.extend(other.guards.iter().cloned());
+            item.timeout = item.timeout.or(other.default_timeout);
             self.add_route(item);
         }
     }
@@ -237,34 +358,89 @@ where
     }
     pub async fn call(
         &self,
-        context: ToolCallContext<'_, S>,
+        mut context: ToolCallContext<'_, S>,
     ) -> Result<CallToolResult, crate::ErrorData> {
         let item = self
             .map
             .get(context.name())
             .ok_or_else(|| crate::ErrorData::invalid_params("tool not found", None))?;
+        if !self.allows(item, &context.request_context) {
+            return Err(crate::ErrorData::forbidden(
+                format!("access to tool {} is forbidden", item.name()),
+                Some(serde_json::json!({ "tool": item.name() })),
+            ));
+        }
 
-        let result = (item.call)(context).await?;
+        #[cfg(feature = "schema-validation")]
+        if let Some(validators) = &self.input_validation {
+            // a call without arguments is validated as an empty object, but keeps none
+            let provided = context.arguments.is_some();
+            let arguments = serde_json::Value::Object(context.arguments.take().unwrap_or_default());
+            validators.validate_arguments(&item.attr.input_schema, &arguments)?;
+            if let (true, serde_json::Value::Object(arguments)) = (provided, arguments) {
+                context.arguments = Some(arguments);
+            }
+        }
+
+        let result = match item.timeout.or(self.default_timeout) {
+            Some(timeout) => {
+                // cancel the token seen by the tool on expiry, not the whole request
+                let ct = context.request_context.ct.child_token();
+                context.request_context.ct = ct.clone();
+                match tokio::time::timeout(timeout, (item.call)(context)).await {
+                    Ok(result) => result?,
+                    Err(_elapsed) => {
+                        ct.cancel();
+                        return Ok(CallToolResult::error(vec![Content::text(format!(
+                            "tool {} timed out after {timeout:?}",
+                            item.name()
+                        ))]));
+                    }
+                }
+            }
+            None => (item.call)(context).await?,
+        };
 
-        // Validate structured content against output schema if present
-        if let Some(ref output_schema) = item.attr.output_schema {
-            // When output_schema is defined, structured_content is required
-            if result.structured_content.is_none() {
-                return Err(crate::ErrorData::invalid_params(
-                    "Tool with output_schema must return structured_content",
+        // Tool errors are reported as content, only successful results must match the schema
+        if let (Some(output_schema), false) =
+            (&item.attr.output_schema, result.is_error == Some(true))
+        {
+            let Some(structured_content) = &result.structured_content else {
+                return Err(crate::ErrorData::internal_error(
+                    "tool with output_schema must return structured_content",
                     None,
                 ));
+            };
+            validate_against_schema(structured_content, output_schema)
+                .map_err(|error| crate::ErrorData::internal_error(error.message, error.data))?;
+            #[cfg(feature = "schema-validation")]
+            if let Some(validators) = &self.output_validation {
+                validators.validate_output(output_schema, structured_content)?;
             }
-            // Validate the structured content against the schema
-            validate_against_schema(result.structured_content.as_ref().unwrap(), output_schema)?;
         }
 
-        Ok(result)
+        Ok(result.with_text_mirror())
     }
 
     pub fn list_all(&self) -> Vec<crate::model::Tool> {
         self.map.values().map(|item| item.attr.clone()).collect()
     }
+
+    /// The tools the request is allowed to call, with the schemas adapted to the client.
+    pub fn list_visible(&self, context: &RequestContext<RoleServer>) -> Vec<crate::model::Tool> {
+        let profile = self
+            .schema_profiles
+            .for_client(context.peer.peer_info().map(|info| &info.client_info));
+        self.map
+            .values()
+            .filter(|item| self.allows(item, context))
+            .map(|item| profile.apply_to_tool(item.attr.clone()))
+            .collect()
+    }
+
+    fn allows(&self, item: &ToolRoute<S>, context: &RequestContext<RoleServer>) -> bool {
+        self.guards.iter().all(|guard| guard.allows(context)) && item.allows(context)
+    }
 }
 
 impl<S> std::ops::Add<ToolRouter<S>> for ToolRouter<S>
@@ -0,0 +1,443 @@
+//! Adapt the schemas of the tools to the JSON Schema dialect a client supports.
+//!
+//! Some clients, or the models behind them, reject parts of JSON Schema: references, `oneOf`,
+//! `format`, or nullable types. A [`SchemaProfile`] rewrites the input and output schemas listed
+//! by a [`ToolRouter`](crate::handler::server::router::tool::ToolRouter), and [`SchemaProfiles`]
+//! chooses the profile from the `clientInfo` sent by the client on initialize.
+//!
+//! ```rust,ignore
+//! Self::tool_router()
+//!     .with_client_schema_profile("gemini", SchemaProfile::portable())
+//! ```
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::model::{Implementation, JsonObject, Tool};
+
+pub const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Keywords whose value is a map of schemas.
+const SCHEMA_MAPS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "$defs",
+    "definitions",
+    "dependentSchemas",
+];
+/// Keywords whose value is a list of schemas.
+const SCHEMA_LISTS: &[&str] = &["allOf", "anyOf", "oneOf", "prefixItems"];
+/// Keywords whose value is a schema, or a list of schemas for `items` before draft 2020-12.
+const SCHEMAS: &[&str] = &[
+    "items",
+    "additionalItems",
+    "additionalProperties",
+    "not",
+    "if",
+    "then",
+    "else",
+    "contains",
+    "propertyNames",
+    "unevaluatedItems",
+    "unevaluatedProperties",
+];
+
+/// How schemas are rewritten for a client, the default profile leaves them as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaProfile {
+    /// Replace every `$ref` by the schema it refers to, recursive references are kept.
+    pub inline_refs: bool,
+    /// Rewrite draft 2020-12 keywords, like `$defs` and `prefixItems`, to draft-07.
+    pub draft_07: bool,
+    /// Make the nullable properties optional, with the type of their value.
+    pub flatten_nullable: bool,
+    /// Remove these keywords, `oneOf` is replaced by `anyOf` instead.
+    pub strip_keywords: Vec<Cow<'static, str>>,
+}
+
+impl SchemaProfile {
+    /// Every adaptation, for the clients accepting the least of JSON Schema.
+    pub fn portable() -> Self {
+        Self {
+            inline_refs: true,
+            draft_07: true,
+            flatten_nullable: true,
+            strip_keywords: vec!["format".into(), "oneOf".into()],
+        }
+    }
+    pub fn inline_refs(mut self, inline_refs: bool) -> Self {
+        self.inline_refs = inline_refs;
+        self
+    }
+    pub fn draft_07(mut self, draft_07: bool) -> Self {
+        self.draft_07 = draft_07;
+        self
+    }
+    pub fn flatten_nullable(mut self, flatten_nullable: bool) -> Self {
+        self.flatten_nullable = flatten_nullable;
+        self
+    }
+    pub fn strip_keyword(mut self, keyword: impl Into<Cow<'static, str>>) -> Self {
+        self.strip_keywords.push(keyword.into());
+        self
+    }
+
+    /// Whether the profile leaves schemas as they are.
+    pub fn is_identity(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn apply(&self, schema: &JsonObject) -> JsonObject {
+        let mut schema = schema.clone();
+        if self.draft_07 {
+            downgrade(&mut schema);
+        }
+        if self.inline_refs {
+            inline_refs(&mut schema);
+        }
+        if self.flatten_nullable {
+            walk(&mut schema, &mut flatten_nullable_properties);
+        }
+        if !self.strip_keywords.is_empty() {
+            walk(&mut schema, &mut |object| {
+                for keyword in &self.strip_keywords {
+                    if let (Some(one_of), "oneOf") =
+                        (object.remove(keyword.as_ref()), keyword.as_ref())
+                    {
+                        object.insert("anyOf".into(), one_of);
+                    }
+                }
+            });
+        }
+        schema
+    }
+
+    /// Apply the profile to the input and output schemas of the tool.
+    pub fn apply_to_tool(&self, mut tool: Tool) -> Tool {
+        if self.is_identity() {
+            return tool;
+        }
+        tool.input_schema = self.apply(&tool.input_schema).into();
+        tool.output_schema = tool.output_schema.map(|schema| self.apply(&schema).into());
+        tool
+    }
+}
+
+/// The profile of each client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaProfiles {
+    /// The profile of the clients without a profile of their own.
+    pub default: SchemaProfile,
+    /// Profiles of the clients whose name starts with the prefix, ignoring case, the first
+    /// matching prefix wins.
+    pub clients: Vec<(String, SchemaProfile)>,
+}
+
+impl SchemaProfiles {
+    pub fn for_client(&self, client: Option<&Implementation>) -> &SchemaProfile {
+        client
+            .and_then(|client| {
+                let name = client.name.to_lowercase();
+                self.clients
+                    .iter()
+                    .find(|(prefix, _)| name.starts_with(&prefix.to_lowercase()))
+            })
+            .map_or(&self.default, |(_, profile)| profile)
+    }
+}
+
+/// Call `f` on the schema, then on each of its subschemas.
+fn walk(schema: &mut JsonObject, f: &mut impl FnMut(&mut JsonObject)) {
+    f(schema);
+    for subschema in subschemas(schema) {
+        if let Value::Object(subschema) = subschema {
+            walk(subschema, f);
+        }
+    }
+}
+
+fn subschemas(schema: &mut JsonObject) -> Vec<&mut Value> {
+    let mut subschemas = vec![];
+    for (keyword, value) in schema.iter_mut() {
+        let keyword = keyword.as_str();
+        if SCHEMA_MAPS.contains(&keyword) {
+            if let Value::Object(map) = value {
+                subschemas.extend(map.values_mut());
+            }
+        } else if SCHEMA_LISTS.contains(&keyword) || (keyword == "items" && value.is_array()) {
+            if let Value::Array(list) = value {
+                subschemas.extend(list.iter_mut());
+            }
+        } else if SCHEMAS.contains(&keyword) {
+            subschemas.push(value);
+        }
+    }
+    subschemas
+}
+
+fn downgrade(schema: &mut JsonObject) {
+    if schema.contains_key("$schema") {
+        schema.insert("$schema".into(), DRAFT_07.into());
+    }
+    if let Some(defs) = schema.remove("$defs") {
+        let definitions = schema
+            .entry("definitions")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let (Value::Object(definitions), Value::Object(defs)) = (definitions, defs) {
+            definitions.extend(defs);
+        }
+    }
+    walk(schema, &mut |object| {
+        if let Some(Value::String(reference)) = object.get_mut("$ref") {
+            if let Some(name) = reference.strip_prefix("#/$defs/") {
+                *reference = format!("#/definitions/{name}");
+            }
+        }
+        if let Some(prefix_items) = object.remove("prefixItems") {
+            if let Some(items) = object.remove("items") {
+                object.insert("additionalItems".into(), items);
+            }
+            object.insert("items".into(), prefix_items);
+        }
+        if let Some(dependent_required) = object.remove("dependentRequired") {
+            object.insert("dependencies".into(), dependent_required);
+        }
+    });
+}
+
+fn inline_refs(schema: &mut JsonObject) {
+    let mut definitions = JsonObject::new();
+    for keyword in ["$defs", "definitions"] {
+        if let Some(Value::Object(defs)) = schema.remove(keyword) {
+            definitions.insert(keyword.into(), Value::Object(defs));
+        }
+    }
+    let root = schema.clone();
+    let mut recursive = false;
+    for subschema in subschemas(schema) {
+        inline_value(subschema, &root, &definitions, &mut vec![], &mut recursive);
+    }
+    // the recursive references still need their definitions
+    if recursive {
+        schema.extend(definitions);
+    }
+}
+
+fn resolve(reference: &str, root: &JsonObject, definitions: &JsonObject) -> Option<JsonObject> {
+    if reference == "#" {
+        return Some(root.clone());
+    }
+    let pointer = reference.strip_prefix('#')?;
+    let mut path = pointer.trim_start_matches('/').splitn(2, '/');
+    let container = definitions.get(path.next()?)?;
+    match container.pointer(&format!("/{}", path.next()?))? {
+        Value::Object(target) => Some(target.clone()),
+        _ => None,
+    }
+}
+
+fn inline_value(
+    value: &mut Value,
+    root: &JsonObject,
+    definitions: &JsonObject,
+    expanding: &mut Vec<String>,
+    recursive: &mut bool,
+) {
+    let Value::Object(object) = value else {
+        return;
+    };
+    let mut pushed = false;
+    if let Some(Value::String(reference)) = object.get("$ref").cloned() {
+        if expanding.contains(&reference) {
+            *recursive = true;
+            return;
+        }
+        if let Some(mut target) = resolve(&reference, root, definitions) {
+            object.remove("$ref");
+            target.extend(std::mem::take(object));
+            *object = target;
+            expanding.push(reference);
+            pushed = true;
+        }
+    }
+    for subschema in subschemas(object) {
+        inline_value(subschema, root, definitions, expanding, recursive);
+    }
+    if pushed {
+        expanding.pop();
+    }
+}
+
+fn is_null_schema(schema: &Value) -> bool {
+    match schema {
+        Value::Object(object) => {
+            object.get("type") == Some(&Value::from("null"))
+                || object.get("const") == Some(&Value::Null)
+        }
+        _ => false,
+    }
+}
+
+/// Remove `null` from the values of the schema, returning whether it accepted it.
+fn remove_null(schema: &mut JsonObject) -> bool {
+    let mut nullable = schema.remove("nullable") == Some(Value::Bool(true));
+    if let Some(Value::Array(types)) = schema.get_mut("type") {
+        let len = types.len();
+        types.retain(|ty| ty != "null");
+        nullable |= types.len() != len;
+        if let [single] = types.as_slice() {
+            let single = single.clone();
+            schema.insert("type".into(), single);
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        let Some(Value::Array(variants)) = schema.get_mut(keyword) else {
+            continue;
+        };
+        let len = variants.len();
+        variants.retain(|variant| !is_null_schema(variant));
+        if variants.len() == len {
+            continue;
+        }
+        nullable = true;
+        if let [Value::Object(single)] = variants.as_slice() {
+            let single = single.clone();
+            schema.remove(keyword);
+            for (key, value) in single {
+                schema.entry(key).or_insert(value);
+            }
+        }
+    }
+    nullable
+}
+
+fn flatten_nullable_properties(schema: &mut JsonObject) {
+    let Some(Value::Object(properties)) = schema.get_mut("properties") else {
+        return;
+    };
+    let mut nullable = vec![];
+    for (name, property) in properties.iter_mut() {
+        if let Value::Object(property) = property {
+            if remove_null(property) {
+                nullable.push(name.clone());
+            }
+        }
+    }
+    if let Some(Value::Array(required)) = schema.get_mut("required") {
+        required.retain(|name| !nullable.iter().any(|nullable| name == nullable.as_str()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::object;
+
+    fn apply(profile: SchemaProfile, schema: Value) -> Value {
+        Value::Object(profile.apply(&object(schema)))
+    }
+
+    #[test]
+    fn test_inline_refs() {
+        let schema = json!({
+            "$defs": {
+                "Inner": { "type": "object", "properties": { "x": { "type": "integer" } } },
+                "Node": { "type": "object", "properties": { "next": { "$ref": "#/$defs/Node" } } }
+            },
+            "properties": {
+                "inner": { "$ref": "#/$defs/Inner", "description": "The inner value" },
+                "list": { "items": { "$ref": "#/$defs/Inner" } }
+            }
+        });
+        let inlined = apply(SchemaProfile::default().inline_refs(true), schema.clone());
+        let inner = json!({ "type": "object", "properties": { "x": { "type": "integer" } } });
+        assert_eq!(inlined["properties"]["list"]["items"], inner);
+        assert_eq!(
+            inlined["properties"]["inner"]["description"],
+            "The inner value"
+        );
+        assert!(inlined.get("$defs").is_none());
+
+        let mut recursive = schema;
+        recursive["properties"]["node"] = json!({ "$ref": "#/$defs/Node" });
+        let inlined = apply(SchemaProfile::default().inline_refs(true), recursive);
+        assert_eq!(
+            inlined["properties"]["node"]["properties"]["next"],
+            json!({ "$ref": "#/$defs/Node" })
+        );
+        assert!(inlined["$defs"]["Node"].is_object());
+    }
+
+    #[test]
+    fn test_draft_07() {
+        let schema = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "$defs": { "Inner": { "type": "string" } },
+            "properties": {
+                "inner": { "$ref": "#/$defs/Inner" },
+                "pair": { "prefixItems": [{ "type": "integer" }, { "type": "string" }], "items": false }
+            }
+        });
+        let downgraded = apply(SchemaProfile::default().draft_07(true), schema);
+        assert_eq!(downgraded["$schema"], DRAFT_07);
+        assert_eq!(downgraded["definitions"]["Inner"]["type"], "string");
+        assert_eq!(
+            downgraded["properties"]["inner"]["$ref"],
+            "#/definitions/Inner"
+        );
+        assert_eq!(
+            downgraded["properties"]["pair"],
+            json!({ "items": [{ "type": "integer" }, { "type": "string" }], "additionalItems": false })
+        );
+    }
+
+    #[test]
+    fn test_flatten_nullable_and_strip() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "a": { "type": "string", "nullable": true, "format": "email" },
+                "b": { "anyOf": [{ "type": "integer" }, { "const": null, "nullable": true }] },
+                "c": { "type": ["string", "null"] },
+                "d": { "oneOf": [{ "type": "integer" }, { "type": "string" }] },
+                "format": { "type": "string" }
+            },
+            "required": ["a", "b", "c", "d", "format"]
+        });
+        let flattened = apply(SchemaProfile::portable(), schema);
+        assert_eq!(
+            flattened,
+            json!({
+                "type": "object",
+                "properties": {
+                    "a": { "type": "string" },
+                    "b": { "type": "integer" },
+                    "c": { "type": "string" },
+                    "d": { "anyOf": [{ "type": "integer" }, { "type": "string" }] },
+                    "format": { "type": "string" }
+                },
+                "required": ["d", "format"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_for_client() {
+        let profiles = SchemaProfiles {
+            default: SchemaProfile::default(),
+            clients: vec![("Gemini".into(), SchemaProfile::portable())],
+        };
+        let client = |name: &str| Implementation {
+            name: name.into(),
+            version: "1".into(),
+        };
+        assert_eq!(
+            profiles.for_client(Some(&client("gemini-cli"))),
+            &SchemaProfile::portable()
+        );
+        assert!(profiles.for_client(Some(&client("other"))).is_identity());
+        assert!(profiles.for_client(None).is_identity());
+    }
+}
@@ -9,8 +9,10 @@
 //! Tools can return structured JSON data using the [`Json`] wrapper type.
 //! When using `Json<T>`, the framework will:
 //! - Automatically generate a JSON schema for the output type
-//! - Validate the output against the schema
-//! - Return the data in the `structured_content` field of [`CallToolResult`]
+//! - Validate the output against the schema, fully with the `schema-validation` feature in debug
+//!   builds or with [`ToolRouter::with_output_validation`](crate::handler::server::router::tool::ToolRouter)
+//! - Return the data in the `structured_content` field of [`CallToolResult`], and its serialized
+//!   text in `content` for the clients which don't read structured content
 //!
 //! # Example
 //!
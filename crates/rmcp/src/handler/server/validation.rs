@@ -0,0 +1,176 @@
+//! JSON Schema validation of the tool arguments.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, Weak},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::model::JsonObject;
+
+type CompiledSchema = Arc<Result<jsonschema::Validator, String>>;
+
+/// A value which doesn't conform to a schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+    /// The JSON pointer of the value in the validated document.
+    pub pointer: String,
+    pub message: String,
+}
+
+/// Validators compiled from the schemas they validate against, compiled once per schema.
+///
+/// The schemas are identified by their `Arc`. The cache only holds weak references to them, the
+/// validators of the dropped schemas, e.g. of a replaced tool, are evicted on the next compilation.
+#[derive(Default)]
+pub struct SchemaValidators {
+    cache: RwLock<HashMap<usize, (Weak<JsonObject>, CompiledSchema)>>,
+}
+
+impl std::fmt::Debug for SchemaValidators {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let compiled = self.cache.read().map(|cache| cache.len()).unwrap_or(0);
+        f.debug_struct("SchemaValidators")
+            .field("compiled", &compiled)
+            .finish()
+    }
+}
+
+impl SchemaValidators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn compiled(&self, schema: &Arc<JsonObject>) -> CompiledSchema {
+        let key = Arc::as_ptr(schema) as usize;
+        // an entry whose schema is still alive at the same address is the schema itself
+        if let Some((_, compiled)) = self
+            .cache
+            .read()
+            .expect("validator cache lock poisoned")
+            .get(&key)
+            .filter(|(cached, _)| cached.strong_count() > 0)
+        {
+            return compiled.clone();
+        }
+        let compiled = Arc::new(
+            jsonschema::options()
+                .should_validate_formats(true)
+                .build(&Value::Object(schema.as_ref().clone()))
+                .map_err(|e| e.to_string()),
+        );
+        let mut cache = self.cache.write().expect("validator cache lock poisoned");
+        cache.retain(|_, (cached, _)| cached.strong_count() > 0);
+        cache.insert(key, (Arc::downgrade(schema), compiled.clone()));
+        compiled
+    }
+
+    /// Every violation of the schema by the value, an empty list when the value is valid.
+    pub fn violations(
+        &self,
+        schema: &Arc<JsonObject>,
+        value: &Value,
+    ) -> Result<Vec<SchemaViolation>, crate::ErrorData> {
+        match self.compiled(schema).as_ref() {
+            Ok(validator) => Ok(validator
+                .iter_errors(value)
+                .map(|error| SchemaViolation {
+                    pointer: error.instance_path.to_string(),
+                    message: error.to_string(),
+                })
+                .collect()),
+            Err(error) => Err(crate::ErrorData::internal_error(
+                format!("invalid schema: {error}"),
+                None,
+            )),
+        }
+    }
+
+    /// Validate the arguments of a tool call, the `invalid_params` error lists every violation.
+    pub fn validate_arguments(
+        &self,
+        schema: &Arc<JsonObject>,
+        arguments: &Value,
+    ) -> Result<(), crate::ErrorData> {
+        let violations = self.violations(schema, arguments)?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(crate::ErrorData::invalid_params(
+            format!("invalid arguments: {}", describe(&violations)),
+            Some(serde_json::json!({ "violations": violations })),
+        ))
+    }
+
+    /// Validate the structured content of a tool result, the `internal_error` lists every violation.
+    pub fn validate_output(
+        &self,
+        schema: &Arc<JsonObject>,
+        structured_content: &Value,
+    ) -> Result<(), crate::ErrorData> {
+        let violations = self.violations(schema, structured_content)?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(crate::ErrorData::internal_error(
+            format!(
+                "structured content doesn't match the output schema: {}",
+                describe(&violations)
+            ),
+            Some(serde_json::json!({ "violations": violations })),
+        ))
+    }
+}
+
+fn describe(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| {
+            let pointer = if violation.pointer.is_empty() {
+                "/"
+            } else {
+                &violation.pointer
+            };
+            format!("{pointer}: {}", violation.message)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dropped_schemas_are_evicted() {
+        let validators = SchemaValidators::new();
+        let schema = Arc::new(crate::model::object(
+            serde_json::json!({ "type": "object" }),
+        ));
+        assert_eq!(
+            validators.violations(&schema, &Value::Null).unwrap().len(),
+            1
+        );
+        assert!(
+            validators
+                .violations(&schema, &serde_json::json!({}))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(validators.cache.read().unwrap().len(), 1);
+
+        drop(schema);
+        let replaced = Arc::new(crate::model::object(
+            serde_json::json!({ "type": "string" }),
+        ));
+        assert!(
+            validators
+                .violations(&replaced, &Value::Null)
+                .unwrap()
+                .len()
+                == 1
+        );
+        assert_eq!(validators.cache.read().unwrap().len(), 1);
+    }
+}
@@ -161,7 +161,16 @@ pub use service::{RoleClient, serve_client};
 #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
 pub use service::{RoleServer, serve_server};
 
+#[cfg(feature = "gateway")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gateway")))]
+pub mod gateway;
 pub mod handler;
+#[cfg(feature = "client-pool")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client-pool")))]
+pub mod pool;
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
 pub mod transport;
 
 // re-export
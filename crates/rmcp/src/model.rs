@@ -432,6 +432,18 @@ impl ErrorCode {
     pub const INVALID_PARAMS: Self = Self(-32602);
     pub const INTERNAL_ERROR: Self = Self(-32603);
     pub const PARSE_ERROR: Self = Self(-32700);
+    /// Not from the specification: the connection which would have carried the response was
+    /// lost, the request may be sent again.
+    pub const RESPONSE_LOST: Self = Self(-32099);
+    /// Not from the specification: the request isn't allowed to use the tool, prompt or
+    /// resource.
+    pub const FORBIDDEN: Self = Self(-32098);
+    /// The user rejected the request, like a sampling request, as in the examples of the
+    /// specification.
+    pub const USER_REJECTED: Self = Self(-1);
+    /// Not from the specification: the request was cancelled by the client before it
+    /// completed, as `RequestCancelled` of the language server protocol.
+    pub const REQUEST_CANCELLED: Self = Self(-32800);
 }
 
 /// Error information for JSON-RPC error responses.
@@ -483,6 +495,12 @@ impl ErrorData {
     pub fn internal_error(message: impl Into<Cow<'static, str>>, data: Option<Value>) -> Self {
         Self::new(ErrorCode::INTERNAL_ERROR, message, data)
     }
+    pub fn forbidden(message: impl Into<Cow<'static, str>>, data: Option<Value>) -> Self {
+        Self::new(ErrorCode::FORBIDDEN, message, data)
+    }
+    pub fn request_cancelled(message: impl Into<Cow<'static, str>>, data: Option<Value>) -> Self {
+        Self::new(ErrorCode::REQUEST_CANCELLED, message, data)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -1160,6 +1178,92 @@ pub struct Root {
     pub name: Option<String>,
 }
 
+impl Root {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            name: None,
+        }
+    }
+
+    /// The `file://` root of a directory, a relative path is relative to the current directory.
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Self {
+        let path = path.as_ref();
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        Self::new(file_uri(&path))
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The directory of a `file://` root.
+    pub fn path(&self) -> Option<std::path::PathBuf> {
+        file_path(&self.uri)
+    }
+}
+
+/// The `file://` URI of an absolute path.
+pub(crate) fn file_uri(path: &std::path::Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // a windows drive
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+/// The path of a `file://` URI on the local host.
+pub(crate) fn file_path(uri: &str) -> Option<std::path::PathBuf> {
+    let rest = uri
+        .strip_prefix("file://")
+        .or_else(|| uri.strip_prefix("FILE://"))?;
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        // a remote host
+        return None;
+    }
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    if cfg!(windows) {
+        // file:///C:/dir
+        let drive = path.strip_prefix('/').unwrap_or(&path);
+        if drive.as_bytes().get(1) == Some(&b':') {
+            return Some(drive.into());
+        }
+    }
+    Some(path.into())
+}
+
 const_string!(ListRootsRequestMethod = "roots/list");
 pub type ListRootsRequest = RequestNoParam<ListRootsRequestMethod>;
 
@@ -1182,7 +1286,9 @@ pub type RootsListChangedNotification = NotificationNoParam<RootsListChangedNoti
 /// Contains the content returned by the tool execution and an optional
 /// flag indicating whether the operation resulted in an error.
 ///
-/// Note: `content` and `structured_content` are mutually exclusive - exactly one must be provided.
+/// Note: at least one of `content` and `structured_content` must be provided. A structured result
+/// should also carry its serialized text in `content` for the clients which don't read it, the
+/// tool router adds it when it's missing.
 #[derive(Debug, Serialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -1262,17 +1368,26 @@ impl CallToolResult {
         }
     }
 
-    /// Validate that content and structured_content are mutually exclusive
+    /// Validate that either content or structured_content is provided
     pub fn validate(&self) -> Result<(), &'static str> {
         match (&self.content, &self.structured_content) {
-            (Some(_), Some(_)) => Err("content and structured_content are mutually exclusive"),
             (None, None) => Err("either content or structured_content must be provided"),
             _ => Ok(()),
         }
     }
+
+    /// Add the serialized structured content as text content, when there is no content yet.
+    ///
+    /// Clients which don't support structured content read the text instead.
+    pub fn with_text_mirror(mut self) -> Self {
+        if let (None, Some(structured_content)) = (&self.content, &self.structured_content) {
+            self.content = Some(vec![Content::text(structured_content.to_string())]);
+        }
+        self
+    }
 }
 
-// Custom deserialize implementation to validate mutual exclusivity
+// Custom deserialize implementation to validate that some content is provided
 impl<'de> Deserialize<'de> for CallToolResult {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -1296,7 +1411,6 @@ impl<'de> Deserialize<'de> for CallToolResult {
             is_error: helper.is_error,
         };
 
-        // Validate mutual exclusivity
         result.validate().map_err(serde::de::Error::custom)?;
 
         Ok(result)
@@ -1504,6 +1618,27 @@ mod tests {
 
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_root_file_uri() {
+        let root = Root::from_path("/home/me/my project/ü");
+        assert_eq!(root.uri, "file:///home/me/my%20project/%C3%BC");
+        assert_eq!(
+            root.path(),
+            Some(std::path::PathBuf::from("/home/me/my project/ü"))
+        );
+        assert_eq!(
+            Root::new("file://localhost/srv").path(),
+            Some("/srv".into())
+        );
+        assert_eq!(Root::new("file://server/share").path(), None);
+        assert_eq!(Root::new("https://example.com").path(), None);
+
+        let root = Root::from_path("project");
+        let expected = std::env::current_dir().unwrap().join("project");
+        assert_eq!(root.path(), Some(expected));
+    }
+
     #[test]
     fn test_notification_serde() {
         let raw = json!( {
@@ -0,0 +1,737 @@
+//! A client connected to several servers, configured by an `mcpServers` file.
+//!
+//! A [`ClientPool`] starts and owns one client per server, and serves the union of their tools
+//! as a single catalogue. Tool names are prefixed by the name of their server and a
+//! [separator](DEFAULT_SEPARATOR), and calls are routed to the server owning the tool.
+//! [`ClientPool::check_health`] pings the servers and restarts the ones which failed, the servers
+//! stopped by [`ClientPool::shutdown`] stay stopped.
+//!
+//! The servers are described by the `mcpServers` config used by most MCP clients:
+//!
+//! ```json
+//! {
+//!   "mcpServers": {
+//!     "git": { "command": "uvx", "args": ["mcp-server-git"], "env": { "LOG": "debug" } },
+//!     "search": { "url": "https://search.example.com/mcp", "headers": { "Authorization": "Bearer token" } },
+//!     "legacy": { "type": "sse", "url": "https://legacy.example.com/sse" }
+//!   }
+//! }
+//! ```
+//!
+//! A server with a `command` is started as a child process and spoken to over stdio, which needs
+//! the `transport-child-process` feature. A server with a `url` is reached over streamable HTTP,
+//! or SSE when its `type` is `sse`, which need the `reqwest` feature and the
+//! `transport-streamable-http-client` or `transport-sse-client` feature.
+//!
+//! ```rust,ignore
+//! let pool = ClientPool::from_config(&PoolConfig::from_path("mcp.json")?);
+//! pool.start().await;
+//! let _health = pool.watch(Duration::from_secs(30), Duration::from_secs(5));
+//!
+//! let tools = pool.list_tools().await;
+//! let result = pool
+//!     .call_tool(CallToolRequestParam {
+//!         name: "git__status".into(),
+//!         arguments: None,
+//!     })
+//!     .await?;
+//! ```
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::{
+    FutureExt,
+    future::{BoxFuture, join_all},
+};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    ClientHandler,
+    model::*,
+    service::{ClientInitializeError, Peer, RoleClient, RunningService, ServiceError, ServiceExt},
+    transport::IntoTransport,
+};
+
+pub const DEFAULT_SEPARATOR: &str = "__";
+
+/// The servers of an `mcpServers` config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PoolConfig {
+    #[serde(rename = "mcpServers", default)]
+    pub servers: BTreeMap<String, ServerConfig>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid config: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+impl PoolConfig {
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+}
+
+/// How to reach a server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "RawServerConfig")]
+pub enum ServerConfig {
+    /// a child process spoken to over stdio
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+    },
+    StreamableHttp {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+    Sse {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+}
+
+/// A server entry as it is written, the transport is guessed from its fields unless its `type`
+/// is given, at the top level or in a `transport` object.
+#[derive(Deserialize)]
+struct RawServerConfig {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    cwd: Option<PathBuf>,
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    transport: Option<RawTransport>,
+}
+
+#[derive(Deserialize)]
+struct RawTransport {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+impl TryFrom<RawServerConfig> for ServerConfig {
+    type Error = String;
+
+    fn try_from(mut raw: RawServerConfig) -> Result<Self, Self::Error> {
+        if let Some(transport) = raw.transport {
+            raw.kind = transport.kind.or(raw.kind);
+            raw.url = transport.url.or(raw.url);
+            raw.headers.extend(transport.headers);
+        }
+        let kind = match raw.kind {
+            Some(kind) => kind.to_ascii_lowercase().replace(['-', '_'], ""),
+            None if raw.command.is_some() => "stdio".to_owned(),
+            None if raw.url.is_some() => "http".to_owned(),
+            None => return Err("a server needs a command or a url".to_owned()),
+        };
+        let url = || {
+            raw.url
+                .clone()
+                .ok_or(format!("a {kind} server needs a url"))
+        };
+        match kind.as_str() {
+            "stdio" => Ok(ServerConfig::Stdio {
+                command: raw.command.ok_or("a stdio server needs a command")?,
+                args: raw.args,
+                env: raw.env,
+                cwd: raw.cwd,
+            }),
+            "http" | "streamablehttp" => Ok(ServerConfig::StreamableHttp {
+                url: url()?,
+                headers: raw.headers,
+            }),
+            "sse" => Ok(ServerConfig::Sse {
+                url: url()?,
+                headers: raw.headers,
+            }),
+            _ => Err(format!("unknown transport type {kind}")),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PoolError {
+    #[error("server {0} is not in the pool")]
+    UnknownServer(String),
+    #[error("tool {0} doesn't belong to any server of the pool")]
+    UnknownTool(String),
+    #[error("server {name} is not running: {status:?}")]
+    NotRunning { name: String, status: ServerStatus },
+    #[error("the {0} feature is needed for this transport")]
+    TransportDisabled(&'static str),
+    #[error("failed to create the transport: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    #[error("failed to initialize: {0}")]
+    Initialize(#[from] ClientInitializeError),
+    #[error(transparent)]
+    Service(#[from] ServiceError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerStatus {
+    Stopped,
+    Running,
+    /// the server failed to start, or stopped answering
+    Failed(String),
+}
+
+type Connect<H> = Arc<
+    dyn Fn(H) -> BoxFuture<'static, Result<RunningService<RoleClient, H>, PoolError>> + Send + Sync,
+>;
+
+enum State<H: ClientHandler> {
+    Stopped,
+    Running(RunningService<RoleClient, H>),
+    Failed(String),
+}
+
+struct Server<H: ClientHandler> {
+    name: String,
+    handler: H,
+    connect: Connect<H>,
+    state: Mutex<State<H>>,
+    /// Counts the restarts and shutdowns, changed with the state lock held.
+    generation: AtomicU64,
+}
+
+impl<H: ClientHandler + Clone> Server<H> {
+    /// Stop the running client, and start a new one.
+    async fn restart(&self) -> Result<(), PoolError> {
+        self.restart_from(None).await
+    }
+
+    /// Restart the server, unless it was restarted or shut down since the `seen` generation.
+    ///
+    /// The new client is connected without holding the state lock, and is dropped if the server
+    /// is restarted or shut down meanwhile.
+    async fn restart_from(&self, seen: Option<u64>) -> Result<(), PoolError> {
+        let (generation, stopped) = {
+            let mut state = self.state.lock().await;
+            let current = self.generation.load(Ordering::Acquire);
+            if seen.is_some_and(|seen| seen != current) {
+                return Ok(());
+            }
+            self.generation.store(current + 1, Ordering::Release);
+            (current + 1, std::mem::replace(&mut *state, State::Stopped))
+        };
+        if let State::Running(service) = stopped {
+            let _ = service.cancel().await;
+        }
+        let connected = (self.connect)(self.handler.clone()).await;
+        let mut state = self.state.lock().await;
+        if self.generation.load(Ordering::Acquire) != generation {
+            drop(state);
+            let service = connected?;
+            let _ = service.cancel().await;
+            return Ok(());
+        }
+        match connected {
+            Ok(service) => {
+                *state = State::Running(service);
+                Ok(())
+            }
+            Err(e) => {
+                tracing::warn!(server = %self.name, "failed to start server: {e}");
+                *state = State::Failed(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop the running client, until the server is started again.
+    async fn shutdown(&self) {
+        let stopped = {
+            let mut state = self.state.lock().await;
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            std::mem::replace(&mut *state, State::Stopped)
+        };
+        if let State::Running(service) = stopped {
+            let _ = service.cancel().await;
+        }
+    }
+
+    async fn peer(&self) -> Result<Peer<RoleClient>, PoolError> {
+        match &*self.state.lock().await {
+            State::Running(service) => Ok(service.peer().clone()),
+            state => Err(PoolError::NotRunning {
+                name: self.name.clone(),
+                status: status(state),
+            }),
+        }
+    }
+
+    /// Ping the server, and restart it unless it answers within `timeout`. A failed server is
+    /// restarted, a stopped one is left alone.
+    async fn check_health(&self, timeout: Duration) -> ServerStatus {
+        let (generation, peer) = {
+            let state = self.state.lock().await;
+            let peer = match (&*state, status(&state)) {
+                (_, ServerStatus::Stopped) => return ServerStatus::Stopped,
+                (State::Running(service), ServerStatus::Running) => Some(service.peer().clone()),
+                _ => None,
+            };
+            (self.generation.load(Ordering::Acquire), peer)
+        };
+        let healthy = match peer {
+            Some(peer) => match tokio::time::timeout(timeout, peer.ping()).await {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    tracing::warn!(server = %self.name, "ping failed: {e}");
+                    false
+                }
+                Err(_) => {
+                    tracing::warn!(server = %self.name, "ping timed out after {timeout:?}");
+                    false
+                }
+            },
+            None => false,
+        };
+        if !healthy {
+            let _ = self.restart_from(Some(generation)).await;
+        }
+        status(&*self.state.lock().await)
+    }
+}
+
+fn status<H: ClientHandler>(state: &State<H>) -> ServerStatus {
+    match state {
+        State::Stopped => ServerStatus::Stopped,
+        State::Running(service) if service.is_transport_closed() => {
+            ServerStatus::Failed("the transport is closed".to_owned())
+        }
+        State::Running(_) => ServerStatus::Running,
+        State::Failed(error) => ServerStatus::Failed(error.clone()),
+    }
+}
+
+/// Clients of several servers, see the [module documentation](self).
+pub struct ClientPool<H: ClientHandler + Clone = ()> {
+    handler: H,
+    separator: Cow<'static, str>,
+    servers: Vec<Arc<Server<H>>>,
+}
+
+impl<H: ClientHandler + Clone> std::fmt::Debug for ClientPool<H> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("separator", &self.separator)
+            .field("servers", &self.names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for ClientPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientPool {
+    pub fn new() -> Self {
+        Self::with_handler(())
+    }
+
+    pub fn from_config(config: &PoolConfig) -> Self {
+        Self::new().with_config(config)
+    }
+}
+
+impl<H: ClientHandler + Clone> ClientPool<H> {
+    /// A pool whose clients handle the requests and notifications of their server with `handler`.
+    pub fn with_handler(handler: H) -> Self {
+        Self {
+            handler,
+            separator: DEFAULT_SEPARATOR.into(),
+            servers: Vec::new(),
+        }
+    }
+
+    pub fn with_separator(mut self, separator: impl Into<Cow<'static, str>>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Add the servers of a config.
+    pub fn with_config(self, config: &PoolConfig) -> Self {
+        config.servers.iter().fold(self, |pool, (name, server)| {
+            pool.with_server_config(name.clone(), server.clone())
+        })
+    }
+
+    pub fn with_server_config(self, name: impl Into<String>, config: ServerConfig) -> Self {
+        let config = Arc::new(config);
+        self.with_connect(
+            name.into(),
+            Arc::new(move |handler| {
+                let config = config.clone();
+                async move { connect(&config, handler).await }.boxed()
+            }),
+        )
+    }
+
+    /// Add a server reached by a new transport every time it is started.
+    pub fn with_server<F, Fut, T, TE, E, A>(self, name: impl Into<String>, transport: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, TE>> + Send + 'static,
+        TE: Into<Box<dyn std::error::Error + Send + Sync>>,
+        T: IntoTransport<RoleClient, E, A> + Send + 'static,
+        E: std::error::Error + Send + Sync + 'static,
+        A: 'static,
+    {
+        self.with_connect(
+            name.into(),
+            Arc::new(move |handler| {
+                let transport = transport();
+                async move {
+                    let transport = transport
+                        .await
+                        .map_err(|e| PoolError::Transport(e.into()))?;
+                    Ok(handler.serve(transport).await?)
+                }
+                .boxed()
+            }),
+        )
+    }
+
+    fn with_connect(mut self, name: String, connect: Connect<H>) -> Self {
+        self.servers.retain(|server| server.name != name);
+        self.servers.push(Arc::new(Server {
+            name,
+            handler: self.handler.clone(),
+            connect,
+            state: Mutex::new(State::Stopped),
+            generation: AtomicU64::new(0),
+        }));
+        self
+    }
+
+    /// The names of the servers.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.servers.iter().map(|server| server.name.as_str())
+    }
+
+    fn server(&self, name: &str) -> Result<&Server<H>, PoolError> {
+        self.servers
+            .iter()
+            .find(|server| server.name == name)
+            .map(Arc::as_ref)
+            .ok_or_else(|| PoolError::UnknownServer(name.to_owned()))
+    }
+
+    /// Start the servers which are not running. The ones which fail to start are logged, and
+    /// reported by [`ClientPool::status`].
+    pub async fn start(&self) {
+        join_all(self.servers.iter().map(|server| async move {
+            if status(&*server.state.lock().await) != ServerStatus::Running {
+                let _ = server.restart().await;
+            }
+        }))
+        .await;
+    }
+
+    /// Stop the running servers.
+    pub async fn shutdown(&self) {
+        join_all(self.servers.iter().map(|server| server.shutdown())).await;
+    }
+
+    pub async fn restart(&self, name: &str) -> Result<(), PoolError> {
+        self.server(name)?.restart().await
+    }
+
+    pub async fn status(&self) -> Vec<(String, ServerStatus)> {
+        join_all(self.servers.iter().map(|server| async move {
+            (server.name.clone(), status(&*server.state.lock().await))
+        }))
+        .await
+    }
+
+    /// The client of a running server.
+    pub async fn peer(&self, name: &str) -> Result<Peer<RoleClient>, PoolError> {
+        self.server(name)?.peer().await
+    }
+
+    /// Ping every server, and restart the ones which don't answer within `timeout`, or failed.
+    /// The servers which were [shut down](ClientPool::shutdown) are not restarted.
+    pub async fn check_health(&self, timeout: Duration) -> Vec<(String, ServerStatus)> {
+        join_all(
+            self.servers.iter().map(|server| async move {
+                (server.name.clone(), server.check_health(timeout).await)
+            }),
+        )
+        .await
+    }
+
+    /// [Check the health](ClientPool::check_health) of the servers every `interval`.
+    ///
+    /// The servers which were [shut down](ClientPool::shutdown) are not restarted.
+    ///
+    /// The task stops when it is aborted, or once the pool is dropped.
+    pub fn watch(&self, interval: Duration, timeout: Duration) -> tokio::task::JoinHandle<()> {
+        let servers: Vec<Weak<Server<H>>> = self.servers.iter().map(Arc::downgrade).collect();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // the first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let servers: Vec<_> = servers.iter().filter_map(Weak::upgrade).collect();
+                if servers.is_empty() {
+                    return;
+                }
+                join_all(servers.iter().map(|server| server.check_health(timeout))).await;
+            }
+        })
+    }
+
+    /// The tools of every running server, named `{server}{separator}{tool}`.
+    ///
+    /// The servers failing to list their tools are logged and left out.
+    pub async fn list_tools(&self) -> Vec<Tool> {
+        let tools = join_all(self.servers.iter().map(|server| async move {
+            let peer = server.peer().await.ok()?;
+            match peer.list_all_tools().await {
+                Ok(tools) => Some((server, tools)),
+                Err(e) => {
+                    tracing::warn!(server = %server.name, "failed to list tools: {e}");
+                    None
+                }
+            }
+        }))
+        .await;
+        tools
+            .into_iter()
+            .flatten()
+            .flat_map(|(server, tools)| {
+                tools.into_iter().map(move |mut tool| {
+                    tool.name = format!("{}{}{}", server.name, self.separator, tool.name).into();
+                    tool
+                })
+            })
+            .collect()
+    }
+
+    /// Call a tool of the [catalogue](ClientPool::list_tools) on its server.
+    pub async fn call_tool(
+        &self,
+        mut params: CallToolRequestParam,
+    ) -> Result<CallToolResult, PoolError> {
+        let (server, tool) = self
+            .route(&params.name)
+            .ok_or_else(|| PoolError::UnknownTool(params.name.to_string()))?;
+        params.name = tool.to_owned().into();
+        Ok(server.peer().await?.call_tool(params).await?)
+    }
+
+    /// The server of a namespaced tool, the longest matching server name wins.
+    fn route<'a>(&self, name: &'a str) -> Option<(&Server<H>, &'a str)> {
+        self.servers
+            .iter()
+            .filter_map(|server| {
+                let tool = name
+                    .strip_prefix(server.name.as_str())?
+                    .strip_prefix(self.separator.as_ref())?;
+                Some((server.as_ref(), tool))
+            })
+            .max_by_key(|(server, _)| server.name.len())
+    }
+}
+
+async fn connect<H: ClientHandler>(
+    config: &ServerConfig,
+    handler: H,
+) -> Result<RunningService<RoleClient, H>, PoolError> {
+    match config {
+        ServerConfig::Stdio {
+            command,
+            args,
+            env,
+            cwd,
+        } => {
+            #[cfg(feature = "transport-child-process")]
+            {
+                use crate::transport::{ConfigureCommandExt, TokioChildProcess};
+                let command = tokio::process::Command::new(command).configure(|cmd| {
+                    cmd.args(args).envs(env);
+                    if let Some(cwd) = cwd {
+                        cmd.current_dir(cwd);
+                    }
+                });
+                let transport =
+                    TokioChildProcess::new(command).map_err(|e| PoolError::Transport(e.into()))?;
+                Ok(handler.serve(transport).await?)
+            }
+            #[cfg(not(feature = "transport-child-process"))]
+            {
+                let _ = (command, args, env, cwd, handler);
+                Err(PoolError::TransportDisabled("transport-child-process"))
+            }
+        }
+        ServerConfig::StreamableHttp { url, headers } => {
+            #[cfg(all(feature = "transport-streamable-http-client", feature = "__reqwest"))]
+            {
+                use crate::transport::{
+                    StreamableHttpClientTransport,
+                    streamable_http_client::StreamableHttpClientTransportConfig,
+                };
+                let transport = StreamableHttpClientTransport::with_client(
+                    http_client(headers)?,
+                    StreamableHttpClientTransportConfig {
+                        uri: url.as_str().into(),
+                        ..Default::default()
+                    },
+                );
+                Ok(handler.serve(transport).await?)
+            }
+            #[cfg(not(all(feature = "transport-streamable-http-client", feature = "__reqwest")))]
+            {
+                let _ = (url, headers, handler);
+                Err(PoolError::TransportDisabled(
+                    "transport-streamable-http-client and reqwest",
+                ))
+            }
+        }
+        ServerConfig::Sse { url, headers } => {
+            #[cfg(all(feature = "transport-sse-client", feature = "__reqwest"))]
+            {
+                use crate::transport::{SseClientTransport, sse_client::SseClientConfig};
+                let transport = SseClientTransport::start_with_client(
+                    http_client(headers)?,
+                    SseClientConfig {
+                        sse_endpoint: url.as_str().into(),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .map_err(|e| PoolError::Transport(e.into()))?;
+                Ok(handler.serve(transport).await?)
+            }
+            #[cfg(not(all(feature = "transport-sse-client", feature = "__reqwest")))]
+            {
+                let _ = (url, headers, handler);
+                Err(PoolError::TransportDisabled(
+                    "transport-sse-client and reqwest",
+                ))
+            }
+        }
+    }
+}
+
+/// An HTTP client sending `headers` with every request.
+#[cfg(all(
+    feature = "__reqwest",
+    any(
+        feature = "transport-streamable-http-client",
+        feature = "transport-sse-client"
+    )
+))]
+fn http_client(headers: &HashMap<String, String>) -> Result<reqwest::Client, PoolError> {
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::try_from(name).map_err(|e| PoolError::Transport(e.into()))?;
+        let value = HeaderValue::try_from(value).map_err(|e| PoolError::Transport(e.into()))?;
+        map.insert(name, value);
+    }
+    reqwest::Client::builder()
+        .default_headers(map)
+        .build()
+        .map_err(|e| PoolError::Transport(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_config() {
+        let config = PoolConfig::from_json(
+            r#"{
+                "mcpServers": {
+                    "git": { "command": "uvx", "args": ["mcp-server-git"], "env": { "LOG": "debug" } },
+                    "search": { "url": "https://search/mcp", "headers": { "Authorization": "Bearer t" } },
+                    "legacy": { "type": "sse", "url": "https://legacy/sse" },
+                    "react": {
+                        "command": "http",
+                        "transport": { "type": "sse", "url": "http://localhost:8080/mcp" }
+                    },
+                    "http": { "type": "streamable-http", "url": "https://http/mcp" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.servers["git"],
+            ServerConfig::Stdio {
+                command: "uvx".into(),
+                args: vec!["mcp-server-git".into()],
+                env: [("LOG".into(), "debug".into())].into(),
+                cwd: None,
+            }
+        );
+        assert_eq!(
+            config.servers["search"],
+            ServerConfig::StreamableHttp {
+                url: "https://search/mcp".into(),
+                headers: [("Authorization".into(), "Bearer t".into())].into(),
+            }
+        );
+        assert!(matches!(config.servers["legacy"], ServerConfig::Sse { .. }));
+        assert_eq!(
+            config.servers["react"],
+            ServerConfig::Sse {
+                url: "http://localhost:8080/mcp".into(),
+                headers: HashMap::new(),
+            }
+        );
+        assert!(matches!(
+            config.servers["http"],
+            ServerConfig::StreamableHttp { .. }
+        ));
+
+        assert!(PoolConfig::from_json(r#"{ "mcpServers": { "x": {} } }"#).is_err());
+        assert!(
+            PoolConfig::from_json(
+                r#"{ "mcpServers": { "x": { "type": "ws", "url": "ws://x" } } }"#
+            )
+            .is_err()
+        );
+        assert!(PoolConfig::from_json("{}").unwrap().servers.is_empty());
+    }
+}
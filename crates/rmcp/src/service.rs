@@ -428,6 +428,11 @@ impl<R: ServiceRole> Peer<R> {
     pub fn is_transport_closed(&self) -> bool {
         self.tx.is_closed()
     }
+
+    /// Whether both peers send to the same connection.
+    pub(crate) fn is_same_peer(&self, other: &Self) -> bool {
+        self.tx.same_channel(&other.tx)
+    }
 }
 
 #[derive(Debug)]
@@ -480,10 +485,13 @@ impl RunningServiceCancellationToken {
 }
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum QuitReason {
     Cancelled,
     Closed,
     JoinError(tokio::task::JoinError),
+    /// The process on the other end of the transport exited.
+    ProcessExited(std::process::ExitStatus),
 }
 
 /// Request execution context
@@ -616,7 +624,7 @@ where
                         } else {
                             // input stream closed
                             tracing::info!("input stream terminated");
-                            break QuitReason::Closed
+                            break transport.quit_reason().unwrap_or(QuitReason::Closed)
                         }
                     }
                     m = peer_rx.recv(), if !peer_rx.is_closed() => {
@@ -11,7 +11,7 @@ use crate::{
         GetPromptRequest, GetPromptRequestParam, GetPromptResult, InitializeRequest,
         InitializedNotification, JsonRpcResponse, ListPromptsRequest, ListPromptsResult,
         ListResourceTemplatesRequest, ListResourceTemplatesResult, ListResourcesRequest,
-        ListResourcesResult, ListToolsRequest, ListToolsResult, PaginatedRequestParam,
+        ListResourcesResult, ListToolsRequest, ListToolsResult, PaginatedRequestParam, PingRequest,
         ProgressNotification, ProgressNotificationParam, ReadResourceRequest,
         ReadResourceRequestParam, ReadResourceResult, RequestId, RootsListChangedNotification,
         ServerInfo, ServerJsonRpcMessage, ServerNotification, ServerRequest, ServerResult,
@@ -224,6 +224,20 @@ macro_rules! method {
             }
         }
     };
+    (peer_req $method:ident $Req:ident()) => {
+        pub async fn $method(&self) -> Result<(), ServiceError> {
+            let result = self
+                .send_request(ClientRequest::$Req($Req {
+                    method: Default::default(),
+                    extensions: Default::default(),
+                }))
+                .await?;
+            match result {
+                ServerResult::EmptyResult(_) => Ok(()),
+                _ => Err(ServiceError::UnexpectedResponse),
+            }
+        }
+    };
     (peer_req $method:ident $Req:ident($Param: ident) => $Resp: ident ) => {
         pub async fn $method(&self, params: $Param) -> Result<$Resp, ServiceError> {
             let result = self
@@ -294,6 +308,7 @@ macro_rules! method {
 }
 
 impl Peer<RoleClient> {
+    method!(peer_req ping PingRequest());
     method!(peer_req complete CompleteRequest(CompleteRequestParam) => CompleteResult);
     method!(peer_req set_level SetLevelRequest(SetLevelRequestParam));
     method!(peer_req get_prompt GetPromptRequest(GetPromptRequestParam) => GetPromptResult);
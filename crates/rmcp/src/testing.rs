@@ -0,0 +1,547 @@
+//! Helpers to test services without any real I/O.
+//!
+//! [`connect`] serves a server and a [`MockClient`] over an in-memory
+//! [`pair`](crate::transport::memory::pair) of transports. The mock client records the
+//! notifications it receives, so a test can wait for them, and [`MockServer`] is a server built
+//! from closures, for testing clients.
+//!
+//! Each side numbers its requests from `0` in the order they are sent, so request ids are the
+//! same on every run.
+//!
+//! ```rust
+//! # use std::time::Duration;
+//! # use rmcp::{ServerHandler, model::*, service::{RequestContext, RoleServer}, testing};
+//! #[derive(Clone)]
+//! struct Server;
+//!
+//! impl ServerHandler for Server {
+//!     async fn call_tool(
+//!         &self,
+//!         _request: CallToolRequestParam,
+//!         context: RequestContext<RoleServer>,
+//!     ) -> Result<CallToolResult, ErrorData> {
+//!         let _ = context.peer.notify_tool_list_changed().await;
+//!         Ok(CallToolResult::success(vec![]))
+//!     }
+//! }
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> anyhow::Result<()> {
+//! let connection = testing::connect(Server).await?;
+//! connection
+//!     .client
+//!     .call_tool(CallToolRequestParam {
+//!         name: "install".into(),
+//!         arguments: None,
+//!     })
+//!     .await?;
+//! connection
+//!     .mock_client()
+//!     .expect_tool_list_changed(Duration::from_millis(100))
+//!     .await?;
+//! connection.close().await;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{TryFutureExt, future::BoxFuture};
+use tokio::sync::Notify;
+
+use crate::{
+    ErrorData,
+    handler::client::sampling::SamplingProvider,
+    model::*,
+    service::{
+        ClientInitializeError, NotificationContext, RequestContext, RoleClient, RoleServer,
+        RunningService, ServerInitializeError, Service, ServiceExt, ServiceRole,
+    },
+    transport::memory,
+};
+
+/// Handles a request which has no default response.
+type RequestFn<R> = Arc<
+    dyn Fn(
+            <R as ServiceRole>::PeerReq,
+            RequestContext<R>,
+        ) -> BoxFuture<'static, Result<<R as ServiceRole>::Resp, ErrorData>>
+        + Send
+        + Sync,
+>;
+
+type ToolFn = Arc<
+    dyn Fn(
+            JsonObject,
+            RequestContext<RoleServer>,
+        ) -> BoxFuture<'static, Result<CallToolResult, ErrorData>>
+        + Send
+        + Sync,
+>;
+
+fn method_not_found(request: &impl serde::Serialize) -> ErrorData {
+    let method = serde_json::to_value(request)
+        .ok()
+        .and_then(|request| request.get("method")?.as_str().map(ToOwned::to_owned))
+        .unwrap_or_default();
+    ErrorData::new(ErrorCode::METHOD_NOT_FOUND, method, None)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConnectError {
+    #[error("client failed to initialize: {0}")]
+    Client(#[from] ClientInitializeError),
+    #[error("server failed to initialize: {0}")]
+    Server(#[from] ServerInitializeError),
+}
+
+/// No matching notification was received in time.
+#[derive(Debug, thiserror::Error)]
+#[error("no matching notification within {within:?}, pending notifications: {pending:?}")]
+pub struct NotificationTimeout {
+    pub within: Duration,
+    pub pending: Vec<ServerNotification>,
+}
+
+/// A client and a server connected in memory.
+pub struct TestConnection<S: Service<RoleServer>> {
+    pub client: RunningService<RoleClient, MockClient>,
+    pub server: RunningService<RoleServer, S>,
+}
+
+impl<S: Service<RoleServer>> TestConnection<S> {
+    pub fn mock_client(&self) -> &MockClient {
+        self.client.service()
+    }
+
+    /// Stop both sides.
+    pub async fn close(self) {
+        let _ = self.client.cancel().await;
+        let _ = self.server.cancel().await;
+    }
+}
+
+/// Serve `server` and connect a default [`MockClient`] to it.
+pub async fn connect<S: Service<RoleServer>>(server: S) -> Result<TestConnection<S>, ConnectError> {
+    connect_with(MockClient::new(), server).await
+}
+
+pub async fn connect_with<S: Service<RoleServer>>(
+    client: MockClient,
+    server: S,
+) -> Result<TestConnection<S>, ConnectError> {
+    let (client_transport, server_transport) = memory::pair();
+    let (server, client) = tokio::join!(
+        server.serve(server_transport),
+        client.serve(client_transport)
+    );
+    Ok(TestConnection {
+        client: client?,
+        server: server?,
+    })
+}
+
+#[derive(Default)]
+struct NotificationLog {
+    pending: Mutex<Vec<ServerNotification>>,
+    received: Notify,
+}
+
+/// A client which records the notifications sent by the server.
+///
+/// It answers pings and root listings, other requests are handled by
+/// [`MockClient::on_request`].
+#[derive(Clone, Default)]
+pub struct MockClient {
+    info: ClientInfo,
+    roots: Vec<Root>,
+    on_request: Option<RequestFn<RoleClient>>,
+    log: Arc<NotificationLog>,
+}
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_info(mut self, info: ClientInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Answer `roots/list` with these roots.
+    pub fn with_roots(mut self, roots: Vec<Root>) -> Self {
+        self.roots = roots;
+        self
+    }
+
+    /// Handle the requests which aren't answered by default, like `sampling/createMessage`.
+    pub fn on_request<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(ServerRequest, RequestContext<RoleClient>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ClientResult, ErrorData>> + Send + 'static,
+    {
+        self.on_request = Some(Arc::new(move |request, context| {
+            Box::pin(handler(request, context))
+        }));
+        self
+    }
+
+    /// The received notifications which weren't matched by an expectation yet.
+    pub fn notifications(&self) -> Vec<ServerNotification> {
+        self.log.pending.lock().expect("lock poisoned").clone()
+    }
+
+    /// Take the received notifications which weren't matched by an expectation yet.
+    pub fn take_notifications(&self) -> Vec<ServerNotification> {
+        std::mem::take(&mut *self.log.pending.lock().expect("lock poisoned"))
+    }
+
+    /// Wait for a notification for which `matches` returns `Some`, received before or within
+    /// `within`. The matched notification is consumed.
+    pub async fn expect_notification<T>(
+        &self,
+        within: Duration,
+        mut matches: impl FnMut(&ServerNotification) -> Option<T>,
+    ) -> Result<T, NotificationTimeout> {
+        let deadline = tokio::time::Instant::now() + within;
+        loop {
+            let received = self.log.received.notified();
+            tokio::pin!(received);
+            received.as_mut().enable();
+            {
+                let mut pending = self.log.pending.lock().expect("lock poisoned");
+                let matched = pending
+                    .iter()
+                    .enumerate()
+                    .find_map(|(index, notification)| Some((index, matches(notification)?)));
+                if let Some((index, value)) = matched {
+                    pending.remove(index);
+                    return Ok(value);
+                }
+            }
+            if tokio::time::timeout_at(deadline, received).await.is_err() {
+                return Err(NotificationTimeout {
+                    within,
+                    pending: self.notifications(),
+                });
+            }
+        }
+    }
+
+    pub async fn expect_tool_list_changed(
+        &self,
+        within: Duration,
+    ) -> Result<(), NotificationTimeout> {
+        self.expect_notification(within, |notification| {
+            matches!(
+                notification,
+                ServerNotification::ToolListChangedNotification(_)
+            )
+            .then_some(())
+        })
+        .await
+    }
+
+    pub async fn expect_prompt_list_changed(
+        &self,
+        within: Duration,
+    ) -> Result<(), NotificationTimeout> {
+        self.expect_notification(within, |notification| {
+            matches!(
+                notification,
+                ServerNotification::PromptListChangedNotification(_)
+            )
+            .then_some(())
+        })
+        .await
+    }
+
+    pub async fn expect_resource_list_changed(
+        &self,
+        within: Duration,
+    ) -> Result<(), NotificationTimeout> {
+        self.expect_notification(within, |notification| {
+            matches!(
+                notification,
+                ServerNotification::ResourceListChangedNotification(_)
+            )
+            .then_some(())
+        })
+        .await
+    }
+
+    pub async fn expect_resource_updated(
+        &self,
+        uri: &str,
+        within: Duration,
+    ) -> Result<(), NotificationTimeout> {
+        self.expect_notification(within, |notification| match notification {
+            ServerNotification::ResourceUpdatedNotification(n) if n.params.uri == uri => Some(()),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn expect_progress(
+        &self,
+        within: Duration,
+    ) -> Result<ProgressNotificationParam, NotificationTimeout> {
+        self.expect_notification(within, |notification| match notification {
+            ServerNotification::ProgressNotification(n) => Some(n.params.clone()),
+            _ => None,
+        })
+        .await
+    }
+
+    pub async fn expect_logging_message(
+        &self,
+        within: Duration,
+    ) -> Result<LoggingMessageNotificationParam, NotificationTimeout> {
+        self.expect_notification(within, |notification| match notification {
+            ServerNotification::LoggingMessageNotification(n) => Some(n.params.clone()),
+            _ => None,
+        })
+        .await
+    }
+}
+
+impl Service<RoleClient> for MockClient {
+    async fn handle_request(
+        &self,
+        request: ServerRequest,
+        context: RequestContext<RoleClient>,
+    ) -> Result<ClientResult, ErrorData> {
+        match request {
+            ServerRequest::PingRequest(_) => Ok(ClientResult::empty(())),
+            ServerRequest::ListRootsRequest(_) => {
+                Ok(ClientResult::ListRootsResult(ListRootsResult {
+                    roots: self.roots.clone(),
+                }))
+            }
+            request => match &self.on_request {
+                Some(handler) => handler(request, context).await,
+                None => Err(method_not_found(&request)),
+            },
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ServerNotification,
+        _context: NotificationContext<RoleClient>,
+    ) -> Result<(), ErrorData> {
+        self.log
+            .pending
+            .lock()
+            .expect("lock poisoned")
+            .push(notification);
+        self.log.received.notify_waiters();
+        Ok(())
+    }
+
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+}
+
+/// A server built from closures, which records the requests and notifications it receives.
+///
+/// It answers initialization, pings and the tool requests for the tools added with
+/// [`MockServer::tool`], other requests are handled by [`MockServer::on_request`].
+#[derive(Clone, Default)]
+pub struct MockServer {
+    info: ServerInfo,
+    tools: Vec<(Tool, ToolFn)>,
+    on_request: Option<RequestFn<RoleServer>>,
+    requests: Arc<Mutex<Vec<(RequestId, ClientRequest)>>>,
+    notifications: Arc<Mutex<Vec<ClientNotification>>>,
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_info(mut self, info: ServerInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Add a tool, called with the arguments of the request.
+    pub fn tool<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(JsonObject, RequestContext<RoleServer>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CallToolResult, ErrorData>> + Send + 'static,
+    {
+        self.tools.push((
+            tool,
+            Arc::new(move |arguments, context| Box::pin(handler(arguments, context))),
+        ));
+        self
+    }
+
+    /// Handle the requests which aren't answered by default.
+    pub fn on_request<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(ClientRequest, RequestContext<RoleServer>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ServerResult, ErrorData>> + Send + 'static,
+    {
+        self.on_request = Some(Arc::new(move |request, context| {
+            Box::pin(handler(request, context))
+        }));
+        self
+    }
+
+    /// The received requests with their ids, initialization included.
+    pub fn requests(&self) -> Vec<(RequestId, ClientRequest)> {
+        self.requests.lock().expect("lock poisoned").clone()
+    }
+
+    pub fn notifications(&self) -> Vec<ClientNotification> {
+        self.notifications.lock().expect("lock poisoned").clone()
+    }
+}
+
+impl Service<RoleServer> for MockServer {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, ErrorData> {
+        self.requests
+            .lock()
+            .expect("lock poisoned")
+            .push((context.id.clone(), request.clone()));
+        match request {
+            ClientRequest::InitializeRequest(request) => {
+                if context.peer.peer_info().is_none() {
+                    context.peer.set_peer_info(request.params);
+                }
+                Ok(ServerResult::InitializeResult(self.get_info()))
+            }
+            ClientRequest::PingRequest(_) => Ok(ServerResult::empty(())),
+            ClientRequest::ListToolsRequest(_) if !self.tools.is_empty() => {
+                Ok(ServerResult::ListToolsResult(ListToolsResult {
+                    tools: self.tools.iter().map(|(tool, _)| tool.clone()).collect(),
+                    next_cursor: None,
+                }))
+            }
+            ClientRequest::CallToolRequest(request) if !self.tools.is_empty() => {
+                let Some((_, handler)) = self
+                    .tools
+                    .iter()
+                    .find(|(tool, _)| tool.name == request.params.name)
+                else {
+                    return Err(ErrorData::invalid_params(
+                        format!("tool {} not found", request.params.name),
+                        None,
+                    ));
+                };
+                handler(request.params.arguments.unwrap_or_default(), context)
+                    .await
+                    .map(ServerResult::CallToolResult)
+            }
+            request => match &self.on_request {
+                Some(handler) => handler(request, context).await,
+                None => Err(method_not_found(&request)),
+            },
+        }
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        _context: NotificationContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.notifications
+            .lock()
+            .expect("lock poisoned")
+            .push(notification);
+        Ok(())
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let mut info = self.info.clone();
+        if !self.tools.is_empty() && info.capabilities.tools.is_none() {
+            info.capabilities.tools = Some(Default::default());
+        }
+        info
+    }
+}
+
+/// A [`SamplingProvider`] answering every request with the same text, or with the text of its
+/// last message, and recording the requests it receives.
+#[derive(Debug, Clone)]
+pub struct FakeSamplingProvider {
+    model: String,
+    reply: Option<String>,
+    usage: Option<u64>,
+    requests: Arc<Mutex<Vec<CreateMessageRequestParam>>>,
+}
+
+impl FakeSamplingProvider {
+    /// A provider echoing the last message of the requests as `model`.
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            reply: None,
+            usage: None,
+            requests: Default::default(),
+        }
+    }
+
+    pub fn with_reply(mut self, reply: impl Into<String>) -> Self {
+        self.reply = Some(reply.into());
+        self
+    }
+
+    /// Report `tokens` as the usage of every message.
+    pub fn with_usage(mut self, tokens: u64) -> Self {
+        self.usage = Some(tokens);
+        self
+    }
+
+    /// The requests received so far, as they were sent to the provider.
+    pub fn requests(&self) -> Vec<CreateMessageRequestParam> {
+        self.requests.lock().expect("lock poisoned").clone()
+    }
+}
+
+impl SamplingProvider for FakeSamplingProvider {
+    fn create_message(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Result<CreateMessageResult, ErrorData>> {
+        let reply = self.reply.clone().unwrap_or_else(|| {
+            request
+                .messages
+                .last()
+                .and_then(|message| message.content.as_text())
+                .map(|text| text.text.clone())
+                .unwrap_or_default()
+        });
+        self.requests.lock().expect("lock poisoned").push(request);
+        Box::pin(std::future::ready(Ok(CreateMessageResult {
+            model: self.model.clone(),
+            stop_reason: Some(CreateMessageResult::STOP_REASON_END_TURN.to_string()),
+            message: SamplingMessage {
+                role: Role::Assistant,
+                content: Content::text(reply),
+            },
+        })))
+    }
+
+    fn create_message_with_usage(
+        &self,
+        request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Result<(CreateMessageResult, Option<u64>), ErrorData>> {
+        let usage = self.usage;
+        Box::pin(
+            self.create_message(request)
+                .map_ok(move |result| (result, usage)),
+        )
+    }
+}
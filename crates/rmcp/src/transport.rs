@@ -7,6 +7,7 @@
 //! | transport         | client                                                    | server                                                |
 //! |:-:                |:-:                                                        |:-:                                                    |
 //! | std IO            | [`child_process::TokioChildProcess`]                      | [`io::stdio`]                                         |
+//! | WASI std IO       |                                                           | [`wasi::stdio`]                                       |
 //! | streamable http   | [`streamable_http_client::StreamableHttpClientTransport`] | [`streamable_http_server::StreamableHttpService`]     |
 //! | sse               | [`sse_client::SseClientTransport`]                        | [`sse_server::SseServer`]                             |
 //!
@@ -66,7 +67,7 @@
 
 use std::{borrow::Cow, sync::Arc};
 
-use crate::service::{RxJsonRpcMessage, ServiceRole, TxJsonRpcMessage};
+use crate::service::{QuitReason, RxJsonRpcMessage, ServiceRole, TxJsonRpcMessage};
 
 pub mod sink_stream;
 
@@ -84,10 +85,34 @@ pub use worker::WorkerTransport;
 #[cfg(feature = "transport-child-process")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-child-process")))]
 pub mod child_process;
+#[cfg(all(feature = "transport-child-process", feature = "client"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(all(feature = "transport-child-process", feature = "client")))
+)]
+pub use child_process::SupervisedChildProcess;
 #[cfg(feature = "transport-child-process")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-child-process")))]
 pub use child_process::{ConfigureCommandExt, TokioChildProcess};
 
+#[cfg(feature = "transport-listener")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-listener")))]
+pub mod listener;
+#[cfg(feature = "transport-listener")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-listener")))]
+pub use listener::ListenerServer;
+
+#[cfg(feature = "transport-memory")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-memory")))]
+pub mod memory;
+
+#[cfg(feature = "transport-record")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-record")))]
+pub mod record;
+#[cfg(feature = "transport-record")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-record")))]
+pub use record::{RecordingTransport, ReplayTransport};
+
 #[cfg(feature = "transport-io")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-io")))]
 pub mod io;
@@ -95,6 +120,10 @@ pub mod io;
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-io")))]
 pub use io::stdio;
 
+#[cfg(feature = "transport-wasi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-wasi")))]
+pub mod wasi;
+
 #[cfg(feature = "transport-sse-client")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-sse-client")))]
 pub mod sse_client;
@@ -116,6 +145,13 @@ pub mod auth;
 #[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
 pub use auth::{AuthError, AuthorizationManager, AuthorizationSession, AuthorizedHttpClient};
 
+#[cfg(feature = "auth-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth-server")))]
+pub mod auth_server;
+#[cfg(feature = "auth-server")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth-server")))]
+pub use auth_server::{AuthClaims, BearerAuthConfig, BearerAuthLayer, JwtValidator};
+
 // #[cfg(feature = "transport-ws")]
 // #[cfg_attr(docsrs, doc(cfg(feature = "transport-ws")))]
 // pub mod ws;
@@ -129,12 +165,22 @@ pub use streamable_http_server::tower::{StreamableHttpServerConfig, StreamableHt
 #[cfg(feature = "transport-streamable-http-client")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-streamable-http-client")))]
 pub mod streamable_http_client;
+#[cfg(feature = "__hyper")]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "transport-streamable-http-client-hyper",
+        feature = "transport-sse-client-hyper"
+    )))
+)]
+pub use common::hyper::HyperClient;
 #[cfg(feature = "transport-streamable-http-client")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-streamable-http-client")))]
 pub use streamable_http_client::StreamableHttpClientTransport;
 
 /// Common use codes
 pub mod common;
+pub use common::limits::PayloadLimits;
 
 pub trait Transport<R>: Send
 where
@@ -159,6 +205,13 @@ where
 
     /// Close the transport
     fn close(&mut self) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Why the transport stopped, asked once [`Transport::receive`] returned `None`.
+    ///
+    /// Defaults to [`QuitReason::Closed`] when `None` is returned.
+    fn quit_reason(&mut self) -> Option<QuitReason> {
+        None
+    }
 }
 
 pub trait IntoTransport<R, E, A>: Send + 'static
@@ -5,7 +5,7 @@ use futures::{SinkExt, StreamExt};
 use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     sync::Mutex,
 };
 use tokio_util::{
@@ -13,7 +13,10 @@ use tokio_util::{
     codec::{Decoder, Encoder, FramedRead, FramedWrite},
 };
 
-use super::{IntoTransport, Transport};
+use super::{
+    IntoTransport, Transport,
+    common::limits::{PayloadError, PayloadLimits, is_response},
+};
 use crate::service::{RxJsonRpcMessage, ServiceRole, TxJsonRpcMessage};
 
 pub enum TransportAdapterAsyncRW {}
@@ -43,7 +46,7 @@ where
 }
 
 pub struct AsyncRwTransport<Role: ServiceRole, R: AsyncRead, W: AsyncWrite> {
-    read: FramedRead<R, JsonRpcMessageCodec<RxJsonRpcMessage<Role>>>,
+    read: FramedRead<R, ReceiveCodec<RxJsonRpcMessage<Role>>>,
     write: Arc<Mutex<FramedWrite<W, JsonRpcMessageCodec<TxJsonRpcMessage<Role>>>>>,
 }
 
@@ -52,10 +55,25 @@ where
     R: Send + AsyncRead + Unpin,
     W: Send + AsyncWrite + Unpin + 'static,
 {
+    /// A server rejects the messages over the default [`PayloadLimits`], a client reads the
+    /// responses of its server whatever their size.
     pub fn new(read: R, write: W) -> Self {
+        let limits = if Role::IS_CLIENT {
+            PayloadLimits::unlimited()
+        } else {
+            PayloadLimits::default()
+        };
+        Self::with_limits(read, write, limits)
+    }
+
+    /// Received messages over the limits, or malformed, are answered with a JSON-RPC error
+    /// response and skipped.
+    pub fn with_limits(read: R, write: W, limits: PayloadLimits) -> Self {
         let read = FramedRead::new(
             read,
-            JsonRpcMessageCodec::<RxJsonRpcMessage<Role>>::default(),
+            ReceiveCodec(JsonRpcMessageCodec::<RxJsonRpcMessage<Role>>::with_limits(
+                limits,
+            )),
         );
         let write = Arc::new(Mutex::new(FramedWrite::new(
             write,
@@ -107,15 +125,31 @@ where
         }
     }
 
-    fn receive(&mut self) -> impl Future<Output = Option<RxJsonRpcMessage<Role>>> {
-        let next = self.read.next();
-        async {
-            next.await.and_then(|e| {
-                e.inspect_err(|e| {
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<Role>> {
+        loop {
+            match self.read.next().await? {
+                Ok(Received::Message(message)) => return Some(message),
+                Ok(Received::Rejected(Some(response))) => {
+                    let mut line = serde_json::to_vec(&response).expect("valid json");
+                    line.push(b'\n');
+                    let mut write = self.write.lock().await;
+                    let write = write.get_mut();
+                    if let Err(e) = async {
+                        write.write_all(&line).await?;
+                        write.flush().await
+                    }
+                    .await
+                    {
+                        tracing::error!("Error writing to stream: {}", e);
+                        return None;
+                    }
+                }
+                Ok(Received::Rejected(None)) => {}
+                Err(e) => {
                     tracing::error!("Error reading from stream: {}", e);
-                })
-                .ok()
-            })
+                    return None;
+                }
+            }
         }
     }
 
@@ -128,7 +162,7 @@ where
 pub struct JsonRpcMessageCodec<T> {
     _marker: PhantomData<fn() -> T>,
     next_index: usize,
-    max_length: usize,
+    limits: PayloadLimits,
     is_discarding: bool,
 }
 
@@ -139,24 +173,30 @@ impl<T> Default for JsonRpcMessageCodec<T> {
 }
 
 impl<T> JsonRpcMessageCodec<T> {
+    /// A codec without limits.
     pub fn new() -> Self {
+        Self::with_limits(PayloadLimits::unlimited())
+    }
+
+    pub fn new_with_max_length(max_length: usize) -> Self {
+        Self::with_limits(PayloadLimits::unlimited().max_message_bytes(max_length))
+    }
+
+    pub fn with_limits(limits: PayloadLimits) -> Self {
         Self {
             _marker: PhantomData,
             next_index: 0,
-            max_length: usize::MAX,
+            limits,
             is_discarding: false,
         }
     }
 
-    pub fn new_with_max_length(max_length: usize) -> Self {
-        Self {
-            max_length,
-            ..Self::new()
-        }
+    pub fn max_length(&self) -> usize {
+        self.limits.max_message_bytes
     }
 
-    pub fn max_length(&self) -> usize {
-        self.max_length
+    pub fn limits(&self) -> &PayloadLimits {
+        &self.limits
     }
 }
 
@@ -189,7 +229,7 @@ fn is_standard_notification(method: &str) -> bool {
 fn try_parse_with_compatibility<T: serde::de::DeserializeOwned>(
     line: &[u8],
     context: &str,
-) -> Result<Option<T>, JsonRpcMessageCodecError> {
+) -> Result<Option<T>, PayloadError> {
     if let Ok(line_str) = std::str::from_utf8(line) {
         match serde_json::from_slice(line) {
             Ok(item) => Ok(Some(item)),
@@ -220,13 +260,11 @@ fn try_parse_with_compatibility<T: serde::de::DeserializeOwned>(
                     line_str,
                     e
                 );
-                Err(JsonRpcMessageCodecError::Serde(e))
+                Err(e.into())
             }
         }
     } else {
-        serde_json::from_slice(line)
-            .map(Some)
-            .map_err(JsonRpcMessageCodecError::Serde)
+        Ok(Some(serde_json::from_slice(line)?))
     }
 }
 
@@ -236,14 +274,29 @@ pub enum JsonRpcMessageCodecError {
     MaxLineLengthExceeded,
     #[error("serde error {0}")]
     Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Payload(PayloadError),
     #[error("io error {0}")]
     Io(#[from] std::io::Error),
 }
 
+impl From<PayloadError> for JsonRpcMessageCodecError {
+    fn from(value: PayloadError) -> Self {
+        match value {
+            PayloadError::TooLarge { .. } => JsonRpcMessageCodecError::MaxLineLengthExceeded,
+            PayloadError::Parse(e) | PayloadError::InvalidMessage(e) => {
+                JsonRpcMessageCodecError::Serde(e)
+            }
+            other => JsonRpcMessageCodecError::Payload(other),
+        }
+    }
+}
+
 impl From<JsonRpcMessageCodecError> for std::io::Error {
     fn from(value: JsonRpcMessageCodecError) -> Self {
         match value {
-            JsonRpcMessageCodecError::MaxLineLengthExceeded => {
+            JsonRpcMessageCodecError::MaxLineLengthExceeded
+            | JsonRpcMessageCodecError::Payload(_) => {
                 std::io::Error::new(std::io::ErrorKind::InvalidData, value)
             }
             JsonRpcMessageCodecError::Serde(e) => e.into(),
@@ -252,19 +305,20 @@ impl From<JsonRpcMessageCodecError> for std::io::Error {
     }
 }
 
-impl<T: DeserializeOwned> Decoder for JsonRpcMessageCodec<T> {
-    type Item = T;
-
-    type Error = JsonRpcMessageCodecError;
-
-    fn decode(
+impl<T: DeserializeOwned> JsonRpcMessageCodec<T> {
+    /// Split the next line off the buffer, without its line ending.
+    ///
+    /// A line over the size limit is reported once, then discarded up to its end.
+    fn decode_line(
         &mut self,
         buf: &mut BytesMut,
-    ) -> Result<Option<Self::Item>, JsonRpcMessageCodecError> {
+        eof: bool,
+    ) -> Result<Option<BytesMut>, PayloadError> {
+        let max_length = self.limits.max_message_bytes;
         loop {
             // Determine how far into the buffer we'll search for a newline. If
             // there's no max_length set, we'll read to the end of the buffer.
-            let read_to = std::cmp::min(self.max_length.saturating_add(1), buf.len());
+            let read_to = std::cmp::min(max_length.saturating_add(1), buf.len());
 
             let newline_offset = buf[self.next_index..read_to]
                 .iter()
@@ -293,23 +347,21 @@ impl<T: DeserializeOwned> Decoder for JsonRpcMessageCodec<T> {
                     // Found a line!
                     let newline_index = offset + self.next_index;
                     self.next_index = 0;
-                    let line = buf.split_to(newline_index + 1);
-                    let line = &line[..line.len() - 1];
-                    let line = without_carriage_return(line);
-
-                    // Use compatibility handling function
-                    let item = match try_parse_with_compatibility(line, "decode")? {
-                        Some(item) => item,
-                        None => return Ok(None), // Skip non-standard message
-                    };
-                    return Ok(Some(item));
+                    let mut line = buf.split_to(newline_index + 1);
+                    line.truncate(line.len() - 1);
+                    return Ok(Some(line));
                 }
-                (false, None) if buf.len() > self.max_length => {
+                (false, None) if buf.len() > max_length => {
                     // Reached the maximum length without finding a
                     // newline, return an error and start discarding on the
                     // next call.
                     self.is_discarding = true;
-                    return Err(JsonRpcMessageCodecError::MaxLineLengthExceeded);
+                    return Err(PayloadError::TooLarge { limit: max_length });
+                }
+                (false, None) if eof => {
+                    // No terminating newline - return remaining data, if any
+                    self.next_index = 0;
+                    return Ok((!buf.is_empty()).then(|| buf.split_to(buf.len())));
                 }
                 (false, None) => {
                     // We didn't find a line or reach the length limit, so the next
@@ -321,27 +373,99 @@ impl<T: DeserializeOwned> Decoder for JsonRpcMessageCodec<T> {
         }
     }
 
+    /// Parse a line, blank lines and non-standard notifications are skipped.
+    fn parse_line(&self, line: &[u8], context: &str) -> Result<Option<T>, PayloadError> {
+        let line = without_carriage_return(line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        self.limits.check(line)?;
+        try_parse_with_compatibility(line, context)
+    }
+
+    fn decode_message(
+        &mut self,
+        buf: &mut BytesMut,
+        eof: bool,
+    ) -> Result<Option<T>, JsonRpcMessageCodecError> {
+        let context = if eof { "decode_eof" } else { "decode" };
+        while let Some(line) = self.decode_line(buf, eof)? {
+            if let Some(item) = self.parse_line(&line, context)? {
+                return Ok(Some(item));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for JsonRpcMessageCodec<T> {
+    type Item = T;
+
+    type Error = JsonRpcMessageCodecError;
+
+    fn decode(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<Self::Item>, JsonRpcMessageCodecError> {
+        self.decode_message(buf, false)
+    }
+
     fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<T>, JsonRpcMessageCodecError> {
-        Ok(match self.decode(buf)? {
-            Some(frame) => Some(frame),
-            None => {
-                self.next_index = 0;
-                // No terminating newline - return remaining data, if any
-                if buf.is_empty() || buf == &b"\r"[..] {
-                    None
-                } else {
-                    let line = buf.split_to(buf.len());
-                    let line = without_carriage_return(&line);
+        self.decode_message(buf, true)
+    }
+}
+
+/// A message read by [`AsyncRwTransport`], or the error response rejecting it.
+enum Received<T> {
+    Message(T),
+    /// `None` when the rejected payload was a response, which is not answered.
+    Rejected(Option<serde_json::Value>),
+}
 
-                    // Use compatibility handling function
-                    let item = match try_parse_with_compatibility(line, "decode_eof")? {
-                        Some(item) => item,
-                        None => return Ok(None), // Skip non-standard message
-                    };
-                    Some(item)
+/// Frames the received messages, rejecting the invalid ones instead of ending the stream.
+struct ReceiveCodec<T>(JsonRpcMessageCodec<T>);
+
+impl<T: DeserializeOwned> ReceiveCodec<T> {
+    fn decode_received(
+        &mut self,
+        buf: &mut BytesMut,
+        eof: bool,
+    ) -> Result<Option<Received<T>>, JsonRpcMessageCodecError> {
+        let context = if eof { "decode_eof" } else { "decode" };
+        loop {
+            let line = match self.0.decode_line(buf, eof) {
+                Ok(Some(line)) => line,
+                Ok(None) => return Ok(None),
+                Err(error) => {
+                    tracing::warn!("Rejected message: {error}");
+                    return Ok(Some(Received::Rejected(Some(error.error_response(&[])))));
+                }
+            };
+            match self.0.parse_line(&line, context) {
+                Ok(Some(item)) => return Ok(Some(Received::Message(item))),
+                Ok(None) => {}
+                Err(error) => {
+                    tracing::warn!("Rejected message: {error}");
+                    let line = without_carriage_return(&line);
+                    let response = (!is_response(line)).then(|| error.error_response(line));
+                    return Ok(Some(Received::Rejected(response)));
                 }
             }
-        })
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for ReceiveCodec<T> {
+    type Item = Received<T>;
+
+    type Error = JsonRpcMessageCodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Received<T>>, Self::Error> {
+        self.decode_received(buf, false)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Received<T>>, Self::Error> {
+        self.decode_received(buf, true)
     }
 }
 
@@ -1,20 +1,35 @@
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, SystemTime},
 };
 
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EmptyExtraTokenFields,
-    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, Scope, StandardTokenResponse,
-    TokenResponse, TokenUrl,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, DeviceAuthorizationUrl,
+    EmptyExtraTokenFields, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, RefreshToken, Scope,
+    StandardTokenResponse, TokenResponse, TokenUrl,
     basic::{BasicClient, BasicTokenType},
 };
-use reqwest::{Client as HttpClient, IntoUrl, StatusCode, Url, header::AUTHORIZATION};
-use serde::{Deserialize, Serialize};
+use reqwest::{
+    Client as HttpClient, IntoUrl, StatusCode, Url,
+    header::{ACCEPT, AUTHORIZATION, WWW_AUTHENTICATE},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use thiserror::Error;
-use tokio::sync::{Mutex, RwLock};
-use tracing::{debug, error};
+use tokio::sync::{Mutex, RwLock, broadcast};
+use tracing::{debug, error, warn};
+
+mod callback;
+mod credential_store;
+pub use callback::{AuthorizationCallback, CALLBACK_PATH, CallbackServer};
+#[cfg(feature = "auth-file-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth-file-store")))]
+pub use credential_store::EncryptedFileCredentialStore;
+pub use credential_store::{CredentialStore, InMemoryCredentialStore, StoredCredentials};
+
+pub use super::common::protected_resource::{
+    PROTECTED_RESOURCE_METADATA_PATH, ProtectedResourceMetadata,
+};
 
 /// sse client with oauth2 authorization
 #[derive(Clone)]
@@ -47,6 +62,12 @@ impl<C> AuthClient<C> {
         let auth_manager = self.auth_manager.clone();
         async move { auth_manager.lock().await.get_access_token().await }
     }
+
+    /// called when the server rejected our access token, returns a fresh token to retry with
+    pub fn handle_unauthorized(&self) -> impl Future<Output = Result<String, AuthError>> + Send {
+        let auth_manager = self.auth_manager.clone();
+        async move { auth_manager.lock().await.handle_unauthorized().await }
+    }
 }
 
 /// Auth error
@@ -93,6 +114,24 @@ pub enum AuthError {
 
     #[error("Registration failed: {0}")]
     RegistrationFailed(String),
+
+    #[error("Credential store error: {0}")]
+    CredentialStoreError(String),
+}
+
+/// events emitted by the [`AuthorizationManager`] when its credentials change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthEvent {
+    /// new tokens were obtained from the authorization server
+    Authorized,
+    /// credentials were restored from the credential store
+    Restored,
+    /// the access token was refreshed, `refresh_token_rotated` is true if the
+    /// authorization server issued a new refresh token
+    Refreshed { refresh_token_rotated: bool },
+    /// the credentials were rejected by the authorization server and have been discarded,
+    /// the user must authorize again
+    Revoked { reason: String },
 }
 
 /// oauth2 metadata
@@ -100,6 +139,8 @@ pub enum AuthError {
 pub struct AuthorizationMetadata {
     pub authorization_endpoint: String,
     pub token_endpoint: String,
+    /// empty if the server does not support dynamic client registration
+    #[serde(default)]
     pub registration_endpoint: String,
     pub issuer: Option<String>,
     pub jwks_uri: Option<String>,
@@ -109,6 +150,15 @@ pub struct AuthorizationMetadata {
     pub additional_fields: HashMap<String, serde_json::Value>,
 }
 
+impl AuthorizationMetadata {
+    /// the RFC 8628 device authorization endpoint, if the server supports the device flow
+    pub fn device_authorization_endpoint(&self) -> Option<&str> {
+        self.additional_fields
+            .get("device_authorization_endpoint")
+            .and_then(|v| v.as_str())
+    }
+}
+
 /// oauth2 client config
 #[derive(Debug, Clone)]
 pub struct OAuthClientConfig {
@@ -121,6 +171,8 @@ pub struct OAuthClientConfig {
 // add type aliases for oauth2 types
 type OAuthErrorResponse = oauth2::StandardErrorResponse<oauth2::basic::BasicErrorResponseType>;
 pub type OAuthTokenResponse = StandardTokenResponse<EmptyExtraTokenFields, BasicTokenType>;
+/// RFC 8628 device authorization response, shown to the user to complete the device flow
+pub type DeviceAuthorizationResponse = oauth2::StandardDeviceAuthorizationResponse;
 type OAuthTokenIntrospection =
     oauth2::StandardTokenIntrospectionResponse<EmptyExtraTokenFields, BasicTokenType>;
 type OAuthRevocableToken = oauth2::StandardRevocableToken;
@@ -146,10 +198,19 @@ pub struct AuthorizationManager {
     oauth_client: Option<OAuthClient>,
     credentials: RwLock<Option<OAuthTokenResponse>>,
     pkce_verifier: RwLock<Option<PkceCodeVerifier>>,
-    expires_at: RwLock<Option<Instant>>,
+    expires_at: RwLock<Option<SystemTime>>,
     base_url: Url,
+    credential_store: Option<Arc<dyn CredentialStore>>,
+    refresh_threshold: Duration,
+    events: broadcast::Sender<AuthEvent>,
+    /// scopes of the client credentials grant, used to request a new token on expiry
+    client_credentials_scopes: RwLock<Option<Vec<String>>>,
+    resource_metadata: RwLock<Option<ProtectedResourceMetadata>>,
 }
 
+/// refresh the access token when it expires within this duration
+pub const DEFAULT_REFRESH_THRESHOLD: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientRegistrationRequest {
     pub client_name: String,
@@ -187,6 +248,11 @@ impl AuthorizationManager {
             pkce_verifier: RwLock::new(None),
             expires_at: RwLock::new(None),
             base_url,
+            credential_store: None,
+            refresh_threshold: DEFAULT_REFRESH_THRESHOLD,
+            events: broadcast::channel(16).0,
+            client_credentials_scopes: RwLock::new(None),
+            resource_metadata: RwLock::new(None),
         };
 
         Ok(manager)
@@ -197,44 +263,274 @@ impl AuthorizationManager {
         Ok(())
     }
 
+    /// set the authorization server metadata, usually obtained from [`Self::discover_metadata`]
+    pub fn set_metadata(&mut self, metadata: AuthorizationMetadata) {
+        self.metadata = Some(metadata);
+    }
+
+    /// persist credentials in the given store, keyed by the base url
+    pub fn with_credential_store(&mut self, store: impl CredentialStore) {
+        self.credential_store = Some(Arc::new(store));
+    }
+
+    /// refresh the access token once it expires within `threshold`
+    pub fn with_refresh_threshold(&mut self, threshold: Duration) {
+        self.refresh_threshold = threshold;
+    }
+
+    /// subscribe to credential changes, such as refreshes and revocations
+    pub fn subscribe(&self) -> broadcast::Receiver<AuthEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: AuthEvent) {
+        // no receiver is not an error
+        let _ = self.events.send(event);
+    }
+
+    fn store_key(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    /// restore credentials from the credential store
+    ///
+    /// Returns `Ok(false)` if there is no store configured or nothing stored for this server.
+    pub async fn load_credentials(&mut self) -> Result<bool, AuthError> {
+        let Some(store) = self.credential_store.clone() else {
+            return Ok(false);
+        };
+        let Some(stored) = store.load(self.store_key()).await? else {
+            return Ok(false);
+        };
+        if self.metadata.is_none() {
+            self.metadata = Some(self.discover_metadata().await?);
+        }
+        self.configure_client(OAuthClientConfig {
+            client_id: stored.client_id.clone(),
+            client_secret: stored.client_secret.clone(),
+            scopes: vec![],
+            redirect_uri: stored
+                .redirect_uri
+                .clone()
+                .unwrap_or_else(|| self.base_url.to_string()),
+        })?;
+        *self.expires_at.write().await = stored.expires_at_system_time();
+        let has_token = stored.token_response.is_some();
+        *self.credentials.write().await = stored.token_response;
+        if has_token {
+            self.emit(AuthEvent::Restored);
+        }
+        Ok(has_token)
+    }
+
+    /// write the current credentials to the credential store
+    async fn save_credentials(&self) -> Result<(), AuthError> {
+        let Some(store) = self.credential_store.as_ref() else {
+            return Ok(());
+        };
+        let Some(oauth_client) = self.oauth_client.as_ref() else {
+            return Ok(());
+        };
+        let mut stored = store
+            .load(self.store_key())
+            .await?
+            .filter(|stored| stored.client_id == oauth_client.client_id().as_str())
+            .unwrap_or_else(|| StoredCredentials::new(oauth_client.client_id().as_str()));
+        stored.token_response = self.credentials.read().await.clone();
+        stored.set_expires_at(*self.expires_at.read().await);
+        store.save(self.store_key(), stored).await
+    }
+
+    /// discard the current tokens, both in memory and in the credential store
+    ///
+    /// The client registration is kept in the store, so it can be reused to authorize again.
+    pub async fn clear_credentials(&self) -> Result<(), AuthError> {
+        *self.credentials.write().await = None;
+        *self.expires_at.write().await = None;
+        if let Some(store) = self.credential_store.as_ref() {
+            if let Some(mut stored) = store.load(self.store_key()).await? {
+                stored.token_response = None;
+                stored.expires_at = None;
+                store.save(self.store_key(), stored).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn revoke(&self, reason: String) {
+        warn!("credentials revoked: {}", reason);
+        if let Err(e) = self.clear_credentials().await {
+            error!("failed to clear revoked credentials: {}", e);
+        }
+        self.emit(AuthEvent::Revoked { reason });
+    }
+
     /// discover oauth2 metadata
+    ///
+    /// Follows the discovery chain of the MCP authorization spec:
+    /// 1. the protected resource metadata of the server ([RFC 9728](https://datatracker.ietf.org/doc/html/rfc9728)),
+    ///    which names the authorization server,
+    /// 2. the authorization server metadata ([RFC 8414](https://datatracker.ietf.org/doc/html/rfc8414)),
+    /// 3. the OpenID Connect discovery document.
+    ///
+    /// The server is first requested without credentials, the `resource_metadata` parameter of
+    /// the `WWW-Authenticate` header of its `401 Unauthorized` response is tried before the
+    /// well-known urls.
+    ///
+    /// Well-known urls are path aware. A candidate which can't be fetched or parsed is skipped.
+    /// If the server publishes no protected resource metadata, the server itself is treated as
+    /// the authorization server, and if no metadata is found at all the default endpoints on its
+    /// origin are used.
     pub async fn discover_metadata(&self) -> Result<AuthorizationMetadata, AuthError> {
-        // according to the specification, the metadata should be located at "/.well-known/oauth-authorization-server"
-        let mut discovery_url = self.base_url.clone();
-        discovery_url.set_path("/.well-known/oauth-authorization-server");
-        debug!("discovery url: {:?}", discovery_url);
+        let resource_metadata_url = self.probe_resource_metadata_url().await;
+        self.discover_metadata_with_resource_metadata(resource_metadata_url)
+            .await
+    }
+
+    /// the `resource_metadata` url of the challenge of the server to an unauthenticated request
+    async fn probe_resource_metadata_url(&self) -> Option<Url> {
         let response = self
             .http_client
-            .get(discovery_url)
-            .header("MCP-Protocol-Version", "2024-11-05")
+            .get(self.base_url.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
             .send()
+            .await
+            .inspect_err(|e| debug!("failed to probe {}: {}", self.base_url, e))
+            .ok()?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return None;
+        }
+        let challenge = response.headers().get(WWW_AUTHENTICATE)?.to_str().ok()?;
+        let url = resource_metadata_url_from_challenge(challenge)?;
+        Url::parse(&url)
+            .inspect_err(|e| debug!("invalid resource_metadata url {}: {}", url, e))
+            .ok()
+    }
+
+    /// discover oauth2 metadata, starting from the `resource_metadata` parameter of the
+    /// `WWW-Authenticate` header of a `401 Unauthorized` response if it has one
+    pub async fn discover_metadata_from_challenge(
+        &self,
+        www_authenticate: &str,
+    ) -> Result<AuthorizationMetadata, AuthError> {
+        let resource_metadata_url = resource_metadata_url_from_challenge(www_authenticate)
+            .map(|url| Url::parse(&url))
+            .transpose()?;
+        self.discover_metadata_with_resource_metadata(resource_metadata_url)
+            .await
+    }
+
+    async fn discover_metadata_with_resource_metadata(
+        &self,
+        resource_metadata_url: Option<Url>,
+    ) -> Result<AuthorizationMetadata, AuthError> {
+        let resource_metadata = self
+            .discover_protected_resource_metadata(resource_metadata_url)
             .await?;
+        // without protected resource metadata, the server is its own authorization server
+        let issuer = match resource_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.authorization_servers.first())
+        {
+            Some(issuer) => Url::parse(issuer)?,
+            None => {
+                let mut issuer = self.base_url.clone();
+                issuer.set_path("");
+                issuer
+            }
+        };
+        *self.resource_metadata.write().await = resource_metadata;
 
-        if response.status() == StatusCode::OK {
-            let metadata = response
-                .json::<AuthorizationMetadata>()
+        for discovery_url in authorization_server_metadata_urls(&issuer) {
+            if let Some(metadata) = self
+                .fetch_metadata::<AuthorizationMetadata>(discovery_url)
                 .await
-                .map_err(|e| {
-                    AuthError::MetadataError(format!("Failed to parse metadata: {}", e))
-                })?;
-            debug!("metadata: {:?}", metadata);
-            Ok(metadata)
-        } else {
-            // fallback to default endpoints
-            let mut auth_base = self.base_url.clone();
-            // discard the path part, only keep scheme, host, port
-            auth_base.set_path("");
+            {
+                debug!("metadata: {:?}", metadata);
+                return Ok(metadata);
+            }
+        }
+
+        // fallback to default endpoints
+        let mut auth_base = issuer;
+        // discard the path part, only keep scheme, host, port
+        auth_base.set_path("");
+        let auth_base = auth_base.as_str().trim_end_matches('/');
+
+        Ok(AuthorizationMetadata {
+            authorization_endpoint: format!("{}/authorize", auth_base),
+            token_endpoint: format!("{}/token", auth_base),
+            registration_endpoint: format!("{}/register", auth_base),
+            issuer: None,
+            jwks_uri: None,
+            scopes_supported: None,
+            additional_fields: HashMap::new(),
+        })
+    }
+
+    /// fetch the protected resource metadata of the server, from `resource_metadata_url` if
+    /// given, then from the path aware and the root well-known url
+    pub async fn discover_protected_resource_metadata(
+        &self,
+        resource_metadata_url: Option<Url>,
+    ) -> Result<Option<ProtectedResourceMetadata>, AuthError> {
+        let path_aware = well_known_url(&self.base_url, "oauth-protected-resource");
+        let mut root = self.base_url.clone();
+        root.set_path(PROTECTED_RESOURCE_METADATA_PATH);
+        root.set_query(None);
+        root.set_fragment(None);
+        let mut candidates = Vec::new();
+        for url in resource_metadata_url.into_iter().chain([path_aware, root]) {
+            if !candidates.contains(&url) {
+                candidates.push(url);
+            }
+        }
+        for url in candidates {
+            if let Some(metadata) = self.fetch_metadata::<ProtectedResourceMetadata>(url).await {
+                debug!("protected resource metadata: {:?}", metadata);
+                return Ok(Some(metadata));
+            }
+        }
+        Ok(None)
+    }
+
+    /// fetch a discovery candidate, `None` if it can't be fetched or isn't the expected metadata
+    async fn fetch_metadata<T: DeserializeOwned>(&self, url: Url) -> Option<T> {
+        debug!("discovery url: {:?}", url);
+        let response = match self
+            .http_client
+            .get(url.clone())
+            .header("MCP-Protocol-Version", "2024-11-05")
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                debug!("failed to fetch {}: {}", url, e);
+                return None;
+            }
+        };
+        if response.status() != StatusCode::OK {
+            return None;
+        }
+        match response.json::<T>().await {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                debug!("invalid metadata at {}: {}", url, e);
+                None
+            }
+        }
+    }
 
-            Ok(AuthorizationMetadata {
-                authorization_endpoint: format!("{}/authorize", auth_base),
-                token_endpoint: format!("{}/token", auth_base),
-                registration_endpoint: format!("{}/register", auth_base),
-                issuer: None,
-                jwks_uri: None,
-                scopes_supported: None,
-                additional_fields: HashMap::new(),
-            })
+    /// the resource indicator ([RFC 8707](https://datatracker.ietf.org/doc/html/rfc8707)) sent
+    /// in authorization and token requests, so tokens are bound to this server
+    pub async fn resource_indicator(&self) -> String {
+        if let Some(metadata) = self.resource_metadata.read().await.as_ref() {
+            return metadata.resource.clone();
         }
+        let mut resource = self.base_url.clone();
+        resource.set_fragment(None);
+        resource.to_string()
     }
 
     /// get client id and credentials
@@ -292,8 +588,30 @@ impl AuthorizationManager {
             return Err(AuthError::NoAuthorizationSupport);
         }
 
+        // reuse a previous registration for the same redirect uri
+        if let Some(store) = self.credential_store.clone() {
+            if let Some(stored) = store.load(self.store_key()).await? {
+                if stored.redirect_uri.as_deref() == Some(redirect_uri) {
+                    debug!("reuse stored client registration: {}", stored.client_id);
+                    let config = OAuthClientConfig {
+                        client_id: stored.client_id,
+                        client_secret: stored.client_secret,
+                        redirect_uri: redirect_uri.to_string(),
+                        scopes: vec![],
+                    };
+                    self.configure_client(config.clone())?;
+                    return Ok(config);
+                }
+            }
+        }
+
         let metadata = self.metadata.as_ref().unwrap();
         let registration_url = metadata.registration_endpoint.clone();
+        if registration_url.is_empty() {
+            return Err(AuthError::RegistrationFailed(
+                "server does not support dynamic client registration".to_string(),
+            ));
+        }
 
         debug!("registration url: {:?}", registration_url);
         // prepare registration request
@@ -353,6 +671,12 @@ impl AuthorizationManager {
             }
         };
 
+        if reg_response.client_id.is_empty() {
+            return Err(AuthError::RegistrationFailed(
+                "registration response has an empty client_id".to_string(),
+            ));
+        }
+
         let config = OAuthClientConfig {
             client_id: reg_response.client_id,
             client_secret: reg_response.client_secret,
@@ -361,6 +685,14 @@ impl AuthorizationManager {
         };
 
         self.configure_client(config.clone())?;
+        if let Some(store) = self.credential_store.as_ref() {
+            let mut stored = StoredCredentials::new(config.client_id.clone());
+            stored.client_secret = config.client_secret.clone();
+            stored.redirect_uri = Some(config.redirect_uri.clone());
+            if let Err(e) = store.save(self.store_key(), stored).await {
+                error!("failed to persist client registration: {}", e);
+            }
+        }
         Ok(config)
     }
 
@@ -389,7 +721,8 @@ impl AuthorizationManager {
         // build authorization request
         let mut auth_request = oauth_client
             .authorize_url(CsrfToken::new_random)
-            .set_pkce_challenge(pkce_challenge);
+            .set_pkce_challenge(pkce_challenge)
+            .add_extra_param("resource", self.resource_indicator().await);
 
         // add request scopes
         for scope in scopes {
@@ -433,35 +766,164 @@ impl AuthorizationManager {
         let token_result = oauth_client
             .exchange_code(AuthorizationCode::new(code.to_string()))
             .set_pkce_verifier(pkce_verifier)
+            .add_extra_param("resource", self.resource_indicator().await)
             .request_async(&http_client)
             .await
             .map_err(|e| AuthError::TokenExchangeFailed(e.to_string()))?;
 
-        // get expires_in from token response
-        let expires_in = token_result.expires_in();
-        if let Some(expires_in) = expires_in {
-            let expires_at = Instant::now() + expires_in;
-            *self.expires_at.write().await = Some(expires_at);
-        }
         debug!("exchange token result: {:?}", token_result);
         // store credentials
+        self.set_token(&token_result, AuthEvent::Authorized).await;
+
+        Ok(token_result)
+    }
+
+    /// store a token obtained from any grant, persist it and notify subscribers
+    async fn set_token(&self, token_result: &OAuthTokenResponse, event: AuthEvent) {
+        *self.expires_at.write().await = token_result
+            .expires_in()
+            .map(|expires_in| SystemTime::now() + expires_in);
         *self.credentials.write().await = Some(token_result.clone());
+        if let Err(e) = self.save_credentials().await {
+            error!("failed to persist credentials: {}", e);
+        }
+        self.emit(event);
+    }
+
+    /// request an access token with the client credentials grant, for machine to machine use
+    ///
+    /// The client must be configured with a client secret. The token is requested again with the
+    /// same scopes whenever it expires.
+    pub async fn exchange_client_credentials(
+        &self,
+        scopes: &[&str],
+    ) -> Result<OAuthTokenResponse, AuthError> {
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        let token_result = self.request_client_credentials_token(&scopes).await?;
+        *self.client_credentials_scopes.write().await = Some(scopes);
+        self.set_token(&token_result, AuthEvent::Authorized).await;
+        Ok(token_result)
+    }
+
+    async fn request_client_credentials_token(
+        &self,
+        scopes: &[String],
+    ) -> Result<OAuthTokenResponse, AuthError> {
+        let oauth_client = self
+            .oauth_client
+            .as_ref()
+            .ok_or_else(|| AuthError::InternalError("OAuth client not configured".to_string()))?;
+        debug!("request client credentials token, scopes: {:?}", scopes);
+        oauth_client
+            .exchange_client_credentials()
+            .add_scopes(scopes.iter().cloned().map(Scope::new))
+            .add_extra_param("resource", self.resource_indicator().await)
+            .request_async(&self.http_client)
+            .await
+            .map_err(|e| AuthError::TokenExchangeFailed(e.to_string()))
+    }
+
+    /// start the device authorization grant (RFC 8628)
+    ///
+    /// Show the returned verification uri and user code to the user, then call
+    /// [`Self::poll_device_token`] to wait for the authorization to complete.
+    pub async fn request_device_authorization(
+        &self,
+        scopes: &[&str],
+    ) -> Result<DeviceAuthorizationResponse, AuthError> {
+        let oauth_client = self
+            .oauth_client
+            .as_ref()
+            .ok_or_else(|| AuthError::InternalError("OAuth client not configured".to_string()))?;
+        let device_authorization_url = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.device_authorization_endpoint())
+            .ok_or_else(|| {
+                AuthError::MetadataError(
+                    "authorization server does not support the device authorization grant"
+                        .to_string(),
+                )
+            })?;
+        let device_authorization_url =
+            DeviceAuthorizationUrl::new(device_authorization_url.to_string()).map_err(|e| {
+                AuthError::OAuthError(format!("Invalid device authorization URL: {}", e))
+            })?;
+        let details: DeviceAuthorizationResponse = oauth_client
+            .clone()
+            .set_device_authorization_url(device_authorization_url)
+            .exchange_device_code()
+            .add_scopes(scopes.iter().map(|s| Scope::new(s.to_string())))
+            .add_extra_param("resource", self.resource_indicator().await)
+            .request_async(&self.http_client)
+            .await
+            .map_err(|e| AuthError::AuthorizationFailed(e.to_string()))?;
+        debug!(
+            "device authorization: verification uri {}",
+            details.verification_uri().as_str()
+        );
+        Ok(details)
+    }
 
+    /// poll the token endpoint until the user completes the device authorization
+    ///
+    /// Respects the polling interval and `slow_down` responses of the authorization server,
+    /// and gives up once the device code expires or `timeout` elapses.
+    pub async fn poll_device_token(
+        &self,
+        details: &DeviceAuthorizationResponse,
+        timeout: Option<Duration>,
+    ) -> Result<OAuthTokenResponse, AuthError> {
+        let oauth_client = self
+            .oauth_client
+            .as_ref()
+            .ok_or_else(|| AuthError::InternalError("OAuth client not configured".to_string()))?;
+        let token_result = oauth_client
+            .exchange_device_access_token(details)
+            .add_extra_param("resource", self.resource_indicator().await)
+            .request_async(&self.http_client, tokio::time::sleep, timeout)
+            .await
+            .map_err(|e| AuthError::AuthorizationFailed(e.to_string()))?;
+        self.set_token(&token_result, AuthEvent::Authorized).await;
         Ok(token_result)
     }
 
-    /// get access token, if expired, refresh it automatically
+    /// check if the token can be renewed without user interaction
+    async fn can_refresh(&self, creds: &OAuthTokenResponse) -> bool {
+        creds.refresh_token().is_some() || self.client_credentials_scopes.read().await.is_some()
+    }
+
+    /// get access token, if expired or about to expire, refresh it automatically
     pub async fn get_access_token(&self) -> Result<String, AuthError> {
         let credentials = self.credentials.read().await;
 
         if let Some(creds) = credentials.as_ref() {
-            // check if the token is expire
-            if let Some(expires_at) = *self.expires_at.read().await {
-                if expires_at < Instant::now() {
-                    // token expired, try to refresh , release the lock
+            // check if the token is expired or about to expire
+            let expires_at = *self.expires_at.read().await;
+            if let Some(expires_at) = expires_at {
+                let now = SystemTime::now();
+                if expires_at <= now + self.refresh_threshold {
+                    // release the lock before refreshing
+                    let current = creds.access_token().secret().to_string();
+                    let can_refresh = self.can_refresh(creds).await;
                     drop(credentials);
-                    let new_creds = self.refresh_token().await?;
-                    return Ok(new_creds.access_token().secret().to_string());
+                    if !can_refresh && expires_at > now {
+                        // nothing we can do yet, use the token until it expires
+                        return Ok(current);
+                    }
+                    return match self.refresh_token().await {
+                        Ok(new_creds) => Ok(new_creds.access_token().secret().to_string()),
+                        Err(AuthError::TokenRefreshFailed(e)) => {
+                            if expires_at > now && self.credentials.read().await.is_some() {
+                                // the token is still valid, refresh again next time
+                                warn!("proactive token refresh failed: {}", e);
+                                Ok(current)
+                            } else {
+                                Err(AuthError::TokenRefreshFailed(e))
+                            }
+                        }
+                        Err(e) => Err(e),
+                    };
                 }
             }
 
@@ -471,6 +933,34 @@ impl AuthorizationManager {
         }
     }
 
+    /// handle a `401 Unauthorized` response for a request made with the current access token
+    ///
+    /// The token is refreshed once, if the refresh fails the credentials are discarded and
+    /// [`AuthError::AuthorizationRequired`] is returned. The next authorization rediscovers the
+    /// metadata from the challenge of the server, see [`Self::discover_metadata`].
+    pub async fn handle_unauthorized(&self) -> Result<String, AuthError> {
+        let can_refresh = match self.credentials.read().await.as_ref() {
+            Some(creds) => self.can_refresh(creds).await,
+            None => false,
+        };
+        if !can_refresh {
+            self.revoke("access token rejected by server".to_string())
+                .await;
+            return Err(AuthError::AuthorizationRequired);
+        }
+        match self.refresh_token().await {
+            Ok(new_creds) => Ok(new_creds.access_token().secret().to_string()),
+            Err(AuthError::TokenRefreshFailed(e)) => {
+                // refresh_token already reports revocation by the authorization server
+                if self.credentials.read().await.is_some() {
+                    self.revoke(e).await;
+                }
+                Err(AuthError::AuthorizationRequired)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// refresh access token
     pub async fn refresh_token(
         &self,
@@ -487,26 +977,64 @@ impl AuthorizationManager {
             .clone()
             .ok_or_else(|| AuthError::AuthorizationRequired)?;
 
-        let refresh_token = current_credentials.refresh_token().ok_or_else(|| {
-            AuthError::TokenRefreshFailed("No refresh token available".to_string())
-        })?;
+        let Some(refresh_token) = current_credentials.refresh_token() else {
+            // client credentials tokens are renewed by requesting a new one
+            let scopes = self.client_credentials_scopes.read().await.clone();
+            let Some(scopes) = scopes else {
+                return Err(AuthError::TokenRefreshFailed(
+                    "No refresh token available".to_string(),
+                ));
+            };
+            let token_result = self
+                .request_client_credentials_token(&scopes)
+                .await
+                .map_err(|e| AuthError::TokenRefreshFailed(e.to_string()))?;
+            self.set_token(
+                &token_result,
+                AuthEvent::Refreshed {
+                    refresh_token_rotated: false,
+                },
+            )
+            .await;
+            return Ok(token_result);
+        };
         debug!("refresh token: {:?}", refresh_token);
         // refresh token
-        let token_result = oauth_client
+        let mut token_result = match oauth_client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.secret().to_string()))
+            .add_extra_param("resource", self.resource_indicator().await)
             .request_async(&self.http_client)
             .await
-            .map_err(|e| AuthError::TokenRefreshFailed(e.to_string()))?;
+        {
+            Ok(token_result) => token_result,
+            Err(oauth2::RequestTokenError::ServerResponse(response))
+                if *response.error() == oauth2::basic::BasicErrorResponseType::InvalidGrant =>
+            {
+                // the refresh token is no longer valid
+                let reason = response.to_string();
+                self.revoke(reason.clone()).await;
+                return Err(AuthError::TokenRefreshFailed(reason));
+            }
+            Err(e) => return Err(AuthError::TokenRefreshFailed(e.to_string())),
+        };
 
-        // store new credentials
-        *self.credentials.write().await = Some(token_result.clone());
+        // the authorization server may keep the refresh token unchanged and omit it
+        let refresh_token_rotated = match token_result.refresh_token() {
+            Some(new_token) => new_token.secret() != refresh_token.secret(),
+            None => {
+                token_result.set_refresh_token(Some(refresh_token.clone()));
+                false
+            }
+        };
 
-        // get expires_in from token response
-        let expires_in = token_result.expires_in();
-        if let Some(expires_in) = expires_in {
-            let expires_at = Instant::now() + expires_in;
-            *self.expires_at.write().await = Some(expires_at);
-        }
+        // store new credentials
+        self.set_token(
+            &token_result,
+            AuthEvent::Refreshed {
+                refresh_token_rotated,
+            },
+        )
+        .await;
         Ok(token_result)
     }
 
@@ -533,6 +1061,62 @@ impl AuthorizationManager {
     }
 }
 
+/// build a path aware well-known url, e.g. `https://host/.well-known/{name}/path` for `https://host/path`
+fn well_known_url(url: &Url, name: &str) -> Url {
+    let mut discovery_url = url.clone();
+    let path = url.path().trim_end_matches('/');
+    discovery_url.set_path(&format!("/.well-known/{}{}", name, path));
+    discovery_url.set_query(None);
+    discovery_url.set_fragment(None);
+    discovery_url
+}
+
+/// metadata urls to try for an issuer, in order: RFC 8414, OpenID Connect with the well-known
+/// segment inserted, OpenID Connect with the well-known segment appended
+fn authorization_server_metadata_urls(issuer: &Url) -> Vec<Url> {
+    let mut urls = vec![
+        well_known_url(issuer, "oauth-authorization-server"),
+        well_known_url(issuer, "openid-configuration"),
+    ];
+    let path = issuer.path().trim_end_matches('/');
+    if !path.is_empty() {
+        let mut appended = issuer.clone();
+        appended.set_path(&format!("{}/.well-known/openid-configuration", path));
+        appended.set_query(None);
+        appended.set_fragment(None);
+        urls.push(appended);
+    }
+    urls
+}
+
+/// extract the `resource_metadata` parameter from a `WWW-Authenticate` header value
+pub fn resource_metadata_url_from_challenge(www_authenticate: &str) -> Option<String> {
+    let (scheme, params) = www_authenticate.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let mut rest = params.trim();
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=')?;
+        let after_name = after_name.trim_start();
+        let (value, after_value) = if let Some(quoted) = after_name.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            let end = after_name.find(',').unwrap_or(after_name.len());
+            (after_name[..end].trim(), &after_name[end..])
+        };
+        if name.trim().eq_ignore_ascii_case("resource_metadata") {
+            return Some(value.to_string());
+        }
+        rest = after_value
+            .trim_start()
+            .trim_start_matches(',')
+            .trim_start();
+    }
+    None
+}
+
 /// oauth2 authorization session, for guiding user to complete the authorization process
 pub struct AuthorizationSession {
     pub auth_manager: AuthorizationManager,
@@ -623,10 +1207,21 @@ impl AuthorizedHttpClient {
         self.auth_manager.prepare_request(request).await
     }
 
-    /// send get request
+    /// send get request, the token is refreshed and the request retried once on 401
     pub async fn get<U: IntoUrl>(&self, url: U) -> Result<reqwest::Response, AuthError> {
-        let request = self.request(reqwest::Method::GET, url).await?;
+        let url = url.into_url()?;
+        let request = self.request(reqwest::Method::GET, url.clone()).await?;
         let response = request.send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let token = self.auth_manager.handle_unauthorized().await?;
+            let response = self
+                .inner_client
+                .get(url)
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .send()
+                .await?;
+            return self.auth_manager.handle_response(response).await;
+        }
         self.auth_manager.handle_response(response).await
     }
 
@@ -831,6 +1426,26 @@ impl OAuthState {
         }
     }
 
+    /// Restore credentials from the credential store of the manager and move into authorized state
+    /// Returns `Ok(false)` and stays unauthorized if nothing was stored
+    pub async fn restore_credentials(&mut self) -> Result<bool, AuthError> {
+        if let OAuthState::Unauthorized(manager) = self {
+            if !manager.load_credentials().await? {
+                return Ok(false);
+            }
+            let manager = std::mem::replace(
+                manager,
+                AuthorizationManager::new("http://localhost").await?,
+            );
+            *self = OAuthState::Authorized(manager);
+            Ok(true)
+        } else {
+            Err(AuthError::InternalError(
+                "Cannot restore credentials in this state".to_string(),
+            ))
+        }
+    }
+
     pub fn into_authorization_manager(self) -> Option<AuthorizationManager> {
         match self {
             OAuthState::Authorized(manager) => Some(manager),
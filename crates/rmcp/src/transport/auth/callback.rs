@@ -0,0 +1,270 @@
+//! Local redirect target of the authorization code flow
+//!
+//! A [`CallbackServer`] listens on the machine of the user for the redirect of the browser
+//! after the authorization, checks it answers the [`AuthorizationSession`], and exchanges its
+//! authorization code for a token. It serves the command line clients which can't register a
+//! redirect uri of their own.
+use std::io;
+
+use futures::{StreamExt, stream::FuturesUnordered};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+use tracing::debug;
+use url::Url;
+
+use super::{AuthError, AuthorizationSession, OAuthTokenResponse};
+
+/// the path the browser is redirected to
+pub const CALLBACK_PATH: &str = "/callback";
+
+/// the longest request head read from the browser
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// the parameters of the redirect to the callback
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthorizationCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+impl AuthorizationCallback {
+    /// parse the parameters of the query of the redirect
+    pub fn from_query(query: &str) -> Self {
+        let mut callback = Self::default();
+        for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            let field = match name.as_ref() {
+                "code" => &mut callback.code,
+                "state" => &mut callback.state,
+                "error" => &mut callback.error,
+                "error_description" => &mut callback.error_description,
+                _ => continue,
+            };
+            *field = Some(value.into_owned());
+        }
+        callback
+    }
+
+    /// the authorization code, if the callback answers the request with this state
+    pub fn into_code(self, state: &str) -> Result<String, AuthError> {
+        if let Some(error) = self.error {
+            return Err(AuthError::AuthorizationFailed(
+                match self.error_description {
+                    Some(description) => format!("{error}: {description}"),
+                    None => error,
+                },
+            ));
+        }
+        if self.state.as_deref() != Some(state) {
+            return Err(AuthError::AuthorizationFailed(
+                "the state of the callback doesn't match the authorization request".to_string(),
+            ));
+        }
+        self.code.ok_or_else(|| {
+            AuthError::AuthorizationFailed("the callback has no authorization code".to_string())
+        })
+    }
+}
+
+/// receives the redirect of the browser after the authorization
+#[derive(Debug)]
+pub struct CallbackServer {
+    listener: TcpListener,
+    redirect_uri: String,
+}
+
+impl CallbackServer {
+    /// listen on `addr`, use a loopback address to keep the code on the machine
+    pub async fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let redirect_uri = format!("http://{}{CALLBACK_PATH}", listener.local_addr()?);
+        Ok(Self {
+            listener,
+            redirect_uri,
+        })
+    }
+
+    /// the redirect uri to start the [`AuthorizationSession`] with
+    pub fn redirect_uri(&self) -> &str {
+        &self.redirect_uri
+    }
+
+    /// wait for the redirect answering `session`, and exchange its authorization code
+    ///
+    /// an error reported by the authorization server, or a redirect with another state, ends
+    /// the flow
+    pub async fn authorize(
+        &self,
+        session: &AuthorizationSession,
+    ) -> Result<OAuthTokenResponse, AuthError> {
+        let state = authorization_state(session.get_authorization_url())?;
+        let code = self.receive_code(&state).await?;
+        session.handle_callback(&code).await
+    }
+
+    /// answer the requests until the redirect to the callback, browsers may open idle
+    /// connections so they are answered concurrently
+    async fn receive_code(&self, state: &str) -> Result<String, AuthError> {
+        let mut requests = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (stream, peer) = accepted.map_err(|e| {
+                        AuthError::InternalError(format!("failed to accept the callback: {e}"))
+                    })?;
+                    requests.push(async move { (peer, respond(stream, state).await) });
+                }
+                Some((peer, outcome)) = requests.next() => match outcome {
+                    Ok(Some(code)) => return code,
+                    Ok(None) => {}
+                    Err(e) => debug!("failed to answer the request of {peer}: {e}"),
+                },
+            }
+        }
+    }
+}
+
+/// the `state` parameter of the authorization url, sent back to the callback
+fn authorization_state(authorization_url: &str) -> Result<String, AuthError> {
+    Url::parse(authorization_url)?
+        .query_pairs()
+        .find(|(name, _)| name == "state")
+        .map(|(_, state)| state.into_owned())
+        .ok_or_else(|| {
+            AuthError::AuthorizationFailed("the authorization url has no state".to_string())
+        })
+}
+
+/// answer a request, with the outcome of the authorization if it's the callback
+async fn respond(stream: TcpStream, state: &str) -> io::Result<Option<Result<String, AuthError>>> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    // skip the headers
+    let mut head = request_line.len();
+    loop {
+        let mut header = String::new();
+        let read = stream.read_line(&mut header).await?;
+        head += read;
+        if read == 0 || header.trim_end().is_empty() {
+            break;
+        }
+        if head > MAX_REQUEST_HEAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the request head is too long",
+            ));
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (status, body, outcome) = if path != CALLBACK_PATH {
+        ("404 Not Found", "Not found".to_string(), None)
+    } else {
+        let code = AuthorizationCallback::from_query(query).into_code(state);
+        match &code {
+            Ok(_) => (
+                "200 OK",
+                "Authorized, this window can be closed.".to_string(),
+                Some(code),
+            ),
+            Err(e) => ("400 Bad Request", e.to_string(), Some(code)),
+        }
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: text/plain; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let mut stream = stream.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[test]
+    fn test_callback() {
+        let callback = |code: Option<&str>, state: Option<&str>| AuthorizationCallback {
+            code: code.map(str::to_owned),
+            state: state.map(str::to_owned),
+            ..Default::default()
+        };
+        assert_eq!(
+            callback(Some("code"), Some("state"))
+                .into_code("state")
+                .unwrap(),
+            "code"
+        );
+        assert!(
+            callback(Some("code"), Some("forged"))
+                .into_code("state")
+                .is_err()
+        );
+        assert!(callback(Some("code"), None).into_code("state").is_err());
+        assert!(callback(None, Some("state")).into_code("state").is_err());
+
+        let denied = AuthorizationCallback::from_query(
+            "error=access_denied&error_description=the+user+declined",
+        );
+        assert_eq!(
+            denied.into_code("state").unwrap_err().to_string(),
+            "OAuth authorization failed: access_denied: the user declined"
+        );
+    }
+
+    #[test]
+    fn test_authorization_state() {
+        assert_eq!(
+            authorization_state("https://auth.example.com/authorize?client_id=a&state=xyz%3D")
+                .unwrap(),
+            "xyz="
+        );
+        assert!(authorization_state("https://auth.example.com/authorize").is_err());
+    }
+
+    async fn get(server: &CallbackServer, target: &str) -> String {
+        let addr = server.listener.local_addr().unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {target} HTTP/1.1\r\nHost: {addr}\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_callback_server() {
+        let server = CallbackServer::bind("127.0.0.1:0").await.unwrap();
+        assert!(server.redirect_uri().ends_with("/callback"));
+        let browser = async {
+            // an idle connection doesn't hold back the others
+            let _idle = TcpStream::connect(server.listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let not_found = get(&server, "/favicon.ico").await;
+            assert!(not_found.starts_with("HTTP/1.1 404"));
+            let authorized = get(&server, "/callback?code=abc&state=xyz").await;
+            assert!(authorized.starts_with("HTTP/1.1 200"));
+        };
+        let (code, ()) = tokio::join!(server.receive_code("xyz"), browser);
+        assert_eq!(code.unwrap(), "abc");
+
+        let browser = async {
+            let forged = get(&server, "/callback?code=abc&state=forged").await;
+            assert!(forged.starts_with("HTTP/1.1 400"));
+        };
+        let (code, ()) = tokio::join!(server.receive_code("xyz"), browser);
+        assert!(code.is_err());
+    }
+}
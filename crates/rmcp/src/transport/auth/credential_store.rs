@@ -0,0 +1,302 @@
+//! Persistent storage for oauth2 credentials
+//!
+//! A [`CredentialStore`] keeps the client registration and the token response of every
+//! server the client has been authorized against, keyed by the server url. This allows an
+//! [`AuthorizationManager`](super::AuthorizationManager) to restore its session after a
+//! restart instead of sending the user through the browser flow again.
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use super::{AuthError, OAuthTokenResponse};
+
+/// credentials persisted for one server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCredentials {
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// the redirect uri the client was registered with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_response: Option<OAuthTokenResponse>,
+    /// unix timestamp (seconds) at which the access token expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl StoredCredentials {
+    pub fn new(client_id: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            redirect_uri: None,
+            token_response: None,
+            expires_at: None,
+        }
+    }
+
+    pub(crate) fn expires_at_system_time(&self) -> Option<SystemTime> {
+        self.expires_at
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    pub(crate) fn set_expires_at(&mut self, expires_at: Option<SystemTime>) {
+        self.expires_at = expires_at
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+    }
+}
+
+/// storage backend for oauth2 credentials, keyed by server url
+pub trait CredentialStore: Send + Sync + 'static {
+    /// load the credentials stored for `server_url`
+    fn load<'a>(
+        &'a self,
+        server_url: &'a str,
+    ) -> BoxFuture<'a, Result<Option<StoredCredentials>, AuthError>>;
+    /// store the credentials for `server_url`, replacing any previous entry
+    fn save<'a>(
+        &'a self,
+        server_url: &'a str,
+        credentials: StoredCredentials,
+    ) -> BoxFuture<'a, Result<(), AuthError>>;
+    /// remove the credentials stored for `server_url`
+    fn clear<'a>(&'a self, server_url: &'a str) -> BoxFuture<'a, Result<(), AuthError>>;
+}
+
+impl<T: CredentialStore + ?Sized> CredentialStore for std::sync::Arc<T> {
+    fn load<'a>(
+        &'a self,
+        server_url: &'a str,
+    ) -> BoxFuture<'a, Result<Option<StoredCredentials>, AuthError>> {
+        T::load(self, server_url)
+    }
+
+    fn save<'a>(
+        &'a self,
+        server_url: &'a str,
+        credentials: StoredCredentials,
+    ) -> BoxFuture<'a, Result<(), AuthError>> {
+        T::save(self, server_url, credentials)
+    }
+
+    fn clear<'a>(&'a self, server_url: &'a str) -> BoxFuture<'a, Result<(), AuthError>> {
+        T::clear(self, server_url)
+    }
+}
+
+/// credential store that only lives as long as the process
+#[derive(Debug, Default)]
+pub struct InMemoryCredentialStore {
+    entries: RwLock<HashMap<String, StoredCredentials>>,
+}
+
+impl InMemoryCredentialStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CredentialStore for InMemoryCredentialStore {
+    fn load<'a>(
+        &'a self,
+        server_url: &'a str,
+    ) -> BoxFuture<'a, Result<Option<StoredCredentials>, AuthError>> {
+        Box::pin(async move { Ok(self.entries.read().await.get(server_url).cloned()) })
+    }
+
+    fn save<'a>(
+        &'a self,
+        server_url: &'a str,
+        credentials: StoredCredentials,
+    ) -> BoxFuture<'a, Result<(), AuthError>> {
+        Box::pin(async move {
+            self.entries
+                .write()
+                .await
+                .insert(server_url.to_string(), credentials);
+            Ok(())
+        })
+    }
+
+    fn clear<'a>(&'a self, server_url: &'a str) -> BoxFuture<'a, Result<(), AuthError>> {
+        Box::pin(async move {
+            self.entries.write().await.remove(server_url);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "auth-file-store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "auth-file-store")))]
+pub use file::EncryptedFileCredentialStore;
+
+#[cfg(feature = "auth-file-store")]
+mod file {
+    use std::path::{Path, PathBuf};
+
+    use aes_gcm::{
+        Aes256Gcm, KeyInit, Nonce,
+        aead::{Aead, AeadCore, OsRng},
+    };
+    use futures::future::BoxFuture;
+    use tokio::sync::Mutex;
+
+    use super::{AuthError, CredentialStore, StoredCredentials};
+
+    type Entries = std::collections::HashMap<String, StoredCredentials>;
+
+    const NONCE_LEN: usize = 12;
+
+    /// credential store backed by a single AES-256-GCM encrypted file
+    ///
+    /// The file holds the credentials of every server, encrypted with the key given on
+    /// construction. A fresh nonce is used for every write and the file is replaced atomically.
+    pub struct EncryptedFileCredentialStore {
+        path: PathBuf,
+        cipher: Aes256Gcm,
+        lock: Mutex<()>,
+    }
+
+    impl std::fmt::Debug for EncryptedFileCredentialStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("EncryptedFileCredentialStore")
+                .field("path", &self.path)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl EncryptedFileCredentialStore {
+        /// create a store at `path` using a 256 bit key
+        pub fn new(path: impl Into<PathBuf>, key: [u8; 32]) -> Self {
+            Self {
+                path: path.into(),
+                cipher: Aes256Gcm::new(&key.into()),
+                lock: Mutex::new(()),
+            }
+        }
+
+        /// generate a random key suitable for [`EncryptedFileCredentialStore::new`]
+        pub fn generate_key() -> [u8; 32] {
+            Aes256Gcm::generate_key(OsRng).into()
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        async fn read_entries(&self) -> Result<Entries, AuthError> {
+            let data = match tokio::fs::read(&self.path).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Entries::new()),
+                Err(e) => return Err(AuthError::CredentialStoreError(e.to_string())),
+            };
+            if data.len() < NONCE_LEN {
+                return Err(AuthError::CredentialStoreError(
+                    "credential file is truncated".to_string(),
+                ));
+            }
+            let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+            let plaintext = self
+                .cipher
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| {
+                    AuthError::CredentialStoreError("failed to decrypt credential file".to_string())
+                })?;
+            serde_json::from_slice(&plaintext)
+                .map_err(|e| AuthError::CredentialStoreError(e.to_string()))
+        }
+
+        async fn write_entries(&self, entries: &Entries) -> Result<(), AuthError> {
+            let plaintext = serde_json::to_vec(entries)
+                .map_err(|e| AuthError::CredentialStoreError(e.to_string()))?;
+            let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+            let ciphertext = self
+                .cipher
+                .encrypt(&nonce, plaintext.as_slice())
+                .map_err(|_| {
+                    AuthError::CredentialStoreError("failed to encrypt credentials".to_string())
+                })?;
+            let mut data = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+            data.extend_from_slice(&nonce);
+            data.extend_from_slice(&ciphertext);
+
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| AuthError::CredentialStoreError(e.to_string()))?;
+            }
+            let mut tmp = self.path.clone().into_os_string();
+            tmp.push(".tmp");
+            let tmp = PathBuf::from(tmp);
+            write_private(&tmp, &data)
+                .await
+                .map_err(|e| AuthError::CredentialStoreError(e.to_string()))?;
+            tokio::fs::rename(&tmp, &self.path)
+                .await
+                .map_err(|e| AuthError::CredentialStoreError(e.to_string()))
+        }
+    }
+
+    #[cfg(unix)]
+    async fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .await?;
+        file.write_all(data).await?;
+        file.sync_all().await
+    }
+
+    #[cfg(not(unix))]
+    async fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+        tokio::fs::write(path, data).await
+    }
+
+    impl CredentialStore for EncryptedFileCredentialStore {
+        fn load<'a>(
+            &'a self,
+            server_url: &'a str,
+        ) -> BoxFuture<'a, Result<Option<StoredCredentials>, AuthError>> {
+            Box::pin(async move {
+                let _guard = self.lock.lock().await;
+                Ok(self.read_entries().await?.remove(server_url))
+            })
+        }
+
+        fn save<'a>(
+            &'a self,
+            server_url: &'a str,
+            credentials: StoredCredentials,
+        ) -> BoxFuture<'a, Result<(), AuthError>> {
+            Box::pin(async move {
+                let _guard = self.lock.lock().await;
+                let mut entries = self.read_entries().await?;
+                entries.insert(server_url.to_string(), credentials);
+                self.write_entries(&entries).await
+            })
+        }
+
+        fn clear<'a>(&'a self, server_url: &'a str) -> BoxFuture<'a, Result<(), AuthError>> {
+            Box::pin(async move {
+                let _guard = self.lock.lock().await;
+                let mut entries = self.read_entries().await?;
+                if entries.remove(server_url).is_some() {
+                    self.write_entries(&entries).await?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
@@ -0,0 +1,531 @@
+//! OAuth 2.1 resource server support for the HTTP server transports.
+//!
+//! [`BearerAuthLayer`] can wrap a [`StreamableHttpService`](crate::transport::StreamableHttpService)
+//! or the router created by [`SseServer::new`](crate::transport::SseServer::new). It will:
+//!
+//! - serve the protected resource metadata document ([RFC 9728](https://datatracker.ietf.org/doc/html/rfc9728))
+//!   at `/.well-known/oauth-protected-resource`,
+//! - reject requests without a valid bearer token with a `WWW-Authenticate` challenge,
+//! - inject the validated [`AuthClaims`] into the request, so handlers can read them from
+//!   the request [`Extensions`](crate::model::Extensions).
+//!
+//! Tokens are validated by a [`TokenValidator`]. [`JwtValidator`] verifies JWTs against a JWKS
+//! loaded from config or a local file, and can fall back to an introspection hook for opaque tokens.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let validator = JwtValidator::from_jwks_file("jwks.json")?
+//!     .with_issuer("https://auth.example.com")
+//!     .with_audience("https://mcp.example.com/mcp");
+//! let config = BearerAuthConfig::new(ProtectedResourceMetadata::new(
+//!     "https://mcp.example.com/mcp",
+//!     ["https://auth.example.com"],
+//! ));
+//! let service = BearerAuthLayer::new(validator, config).layer(streamable_http_service);
+//! ```
+use std::{convert::Infallible, path::Path, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{HeaderValue, Method, Request, Response, StatusCode, header::WWW_AUTHENTICATE};
+use http_body::Body;
+use http_body_util::{BodyExt, Full, combinators::UnsyncBoxBody};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation,
+    jwk::{Jwk, JwkSet},
+};
+use thiserror::Error;
+
+use crate::model::JsonObject;
+pub use crate::transport::common::protected_resource::{
+    PROTECTED_RESOURCE_METADATA_PATH, ProtectedResourceMetadata,
+};
+
+/// The claims of a validated access token.
+///
+/// The bearer auth layer inserts this into the http request extensions, and the http server
+/// transports forward it into the extensions of the MCP request or notification.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthClaims {
+    pub subject: Option<String>,
+    pub client_id: Option<String>,
+    pub issuer: Option<String>,
+    pub audience: Vec<String>,
+    pub scopes: Vec<String>,
+    /// seconds since unix epoch
+    pub expires_at: Option<u64>,
+    /// all claims of the token, or the introspection response
+    pub claims: JsonObject,
+}
+
+impl AuthClaims {
+    /// Build claims from a JWT payload or an [RFC 7662](https://datatracker.ietf.org/doc/html/rfc7662) introspection response.
+    pub fn from_claims(claims: JsonObject) -> Self {
+        let string_claim = |key: &str| claims.get(key).and_then(|v| v.as_str()).map(String::from);
+        let string_list = |value: Option<&serde_json::Value>| -> Vec<String> {
+            match value {
+                Some(serde_json::Value::String(s)) => {
+                    s.split_whitespace().map(String::from).collect()
+                }
+                Some(serde_json::Value::Array(values)) => values
+                    .iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect(),
+                _ => vec![],
+            }
+        };
+        let audience = match claims.get("aud") {
+            Some(serde_json::Value::String(s)) => vec![s.clone()],
+            other => string_list(other),
+        };
+        Self {
+            subject: string_claim("sub"),
+            client_id: string_claim("client_id").or_else(|| string_claim("azp")),
+            issuer: string_claim("iss"),
+            audience,
+            scopes: string_list(claims.get("scope").or_else(|| claims.get("scp"))),
+            expires_at: claims.get("exp").and_then(|v| v.as_u64()),
+            claims,
+        }
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TokenValidationError {
+    #[error("Missing bearer token")]
+    MissingToken,
+
+    #[error("Invalid token: {0}")]
+    InvalidToken(String),
+
+    #[error("Token expired")]
+    Expired,
+
+    #[error("Insufficient scope, required: {}", .0.join(" "))]
+    InsufficientScope(Vec<String>),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl TokenValidationError {
+    /// The oauth2 error code used in the `WWW-Authenticate` challenge
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            TokenValidationError::MissingToken => None,
+            TokenValidationError::InvalidToken(_) | TokenValidationError::Expired => {
+                Some("invalid_token")
+            }
+            TokenValidationError::InsufficientScope(_) => Some("insufficient_scope"),
+            TokenValidationError::Internal(_) => None,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            TokenValidationError::InsufficientScope(_) => StatusCode::FORBIDDEN,
+            TokenValidationError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// Validate a bearer token and resolve its claims.
+///
+/// It's implemented for [`JwtValidator`], and for any async function `Fn(String) -> Future<Output = Result<AuthClaims, TokenValidationError>>`,
+/// which could be used to call a token introspection endpoint.
+pub trait TokenValidator: Send + Sync + 'static {
+    fn validate(&self, token: String) -> BoxFuture<'_, Result<AuthClaims, TokenValidationError>>;
+}
+
+impl<F, Fut> TokenValidator for F
+where
+    F: Fn(String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<AuthClaims, TokenValidationError>> + Send + 'static,
+{
+    fn validate(&self, token: String) -> BoxFuture<'_, Result<AuthClaims, TokenValidationError>> {
+        Box::pin((self)(token))
+    }
+}
+
+/// Validate JWT access tokens against a JSON Web Key Set
+#[derive(Clone)]
+pub struct JwtValidator {
+    jwks: JwkSet,
+    issuer: Option<String>,
+    audience: Vec<String>,
+    algorithms: Vec<Algorithm>,
+    leeway: Duration,
+    introspection: Option<Arc<dyn TokenValidator>>,
+}
+
+impl std::fmt::Debug for JwtValidator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtValidator")
+            .field("jwks", &self.jwks)
+            .field("issuer", &self.issuer)
+            .field("audience", &self.audience)
+            .field("algorithms", &self.algorithms)
+            .field("leeway", &self.leeway)
+            .field("introspection", &self.introspection.is_some())
+            .finish()
+    }
+}
+
+impl JwtValidator {
+    /// Asymmetric algorithms accepted by default, the `HS*` family must be enabled explicitly
+    pub const DEFAULT_ALGORITHMS: &[Algorithm] = &[
+        Algorithm::RS256,
+        Algorithm::RS384,
+        Algorithm::RS512,
+        Algorithm::PS256,
+        Algorithm::PS384,
+        Algorithm::PS512,
+        Algorithm::ES256,
+        Algorithm::ES384,
+        Algorithm::EdDSA,
+    ];
+
+    pub fn new(jwks: JwkSet) -> Self {
+        Self {
+            jwks,
+            issuer: None,
+            audience: vec![],
+            algorithms: Self::DEFAULT_ALGORITHMS.to_vec(),
+            leeway: Duration::from_secs(60),
+            introspection: None,
+        }
+    }
+
+    /// load the key set from a JWKS json document
+    pub fn from_jwks_json(json: &str) -> Result<Self, TokenValidationError> {
+        let jwks = serde_json::from_str::<JwkSet>(json)
+            .map_err(|e| TokenValidationError::Internal(format!("invalid jwks: {e}")))?;
+        Ok(Self::new(jwks))
+    }
+
+    /// load the key set from a local JWKS file
+    pub fn from_jwks_file(path: impl AsRef<Path>) -> Result<Self, TokenValidationError> {
+        let json = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            TokenValidationError::Internal(format!(
+                "fail to read jwks file {}: {e}",
+                path.as_ref().display()
+            ))
+        })?;
+        Self::from_jwks_json(&json)
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// accept tokens issued for this audience, can be called multiple times
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience.push(audience.into());
+        self
+    }
+
+    pub fn with_algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = algorithms.into_iter().collect();
+        self
+    }
+
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// validate tokens which are not JWTs (opaque tokens) with an introspection hook
+    pub fn with_introspection(mut self, introspection: impl TokenValidator) -> Self {
+        self.introspection = Some(Arc::new(introspection));
+        self
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
+    fn candidate_keys(&self, kid: Option<&str>) -> Vec<&Jwk> {
+        match kid {
+            Some(kid) => self.jwks.find(kid).into_iter().collect(),
+            None => self.jwks.keys.iter().collect(),
+        }
+    }
+
+    /// verify the signature and the registered claims of a JWT
+    pub fn validate_jwt(&self, token: &str) -> Result<AuthClaims, TokenValidationError> {
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| TokenValidationError::InvalidToken(e.to_string()))?;
+        if !self.algorithms.contains(&header.alg) {
+            return Err(TokenValidationError::InvalidToken(format!(
+                "algorithm {:?} is not allowed",
+                header.alg
+            )));
+        }
+        let mut validation = Validation::new(header.alg);
+        validation.leeway = self.leeway.as_secs();
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        if self.audience.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audience);
+        }
+        let keys = self.candidate_keys(header.kid.as_deref());
+        if keys.is_empty() {
+            return Err(TokenValidationError::InvalidToken(
+                "no matching key found in jwks".to_string(),
+            ));
+        }
+        let mut last_error = None;
+        for jwk in keys {
+            let key = match DecodingKey::from_jwk(jwk) {
+                Ok(key) => key,
+                Err(e) => {
+                    tracing::warn!(kid = ?jwk.common.key_id, "skip invalid jwk: {e}");
+                    continue;
+                }
+            };
+            match jsonwebtoken::decode::<JsonObject>(token, &key, &validation) {
+                Ok(data) => return Ok(AuthClaims::from_claims(data.claims)),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) if matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature) => {
+                TokenValidationError::Expired
+            }
+            Some(e) => TokenValidationError::InvalidToken(e.to_string()),
+            None => TokenValidationError::InvalidToken("no usable key in jwks".to_string()),
+        })
+    }
+}
+
+impl TokenValidator for JwtValidator {
+    fn validate(&self, token: String) -> BoxFuture<'_, Result<AuthClaims, TokenValidationError>> {
+        Box::pin(async move {
+            // a JWT always consists of three dot separated segments
+            let looks_like_jwt = token.split('.').count() == 3;
+            match (&self.introspection, looks_like_jwt) {
+                (Some(introspection), false) => introspection.validate(token).await,
+                _ => self.validate_jwt(&token),
+            }
+        })
+    }
+}
+
+/// bearer auth layer config
+#[derive(Debug, Clone)]
+pub struct BearerAuthConfig {
+    /// The metadata document to serve
+    pub metadata: ProtectedResourceMetadata,
+    /// The path to serve the metadata document, default to [`PROTECTED_RESOURCE_METADATA_PATH`]
+    pub metadata_path: String,
+    /// The absolute url of the metadata document, used in the `WWW-Authenticate` challenge.
+    ///
+    /// If not set, it will be derived from the `resource` of metadata.
+    pub metadata_url: Option<String>,
+    /// Scopes every request must carry
+    pub required_scopes: Vec<String>,
+}
+
+impl BearerAuthConfig {
+    pub fn new(metadata: ProtectedResourceMetadata) -> Self {
+        Self {
+            metadata,
+            metadata_path: PROTECTED_RESOURCE_METADATA_PATH.to_string(),
+            metadata_url: None,
+            required_scopes: vec![],
+        }
+    }
+
+    pub fn with_required_scopes(
+        mut self,
+        scopes: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.required_scopes = scopes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The absolute url of the metadata document
+    pub fn resource_metadata_url(&self) -> String {
+        if let Some(url) = &self.metadata_url {
+            return url.clone();
+        }
+        match url::Url::parse(&self.metadata.resource) {
+            Ok(mut url) => {
+                url.set_path(&self.metadata_path);
+                url.set_query(None);
+                url.set_fragment(None);
+                url.to_string()
+            }
+            Err(_) => self.metadata_path.clone(),
+        }
+    }
+
+    fn is_metadata_path(&self, path: &str) -> bool {
+        path == self.metadata_path
+            || path
+                .strip_prefix(self.metadata_path.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// A tower layer that protects a http service with bearer token authorization.
+#[derive(Clone)]
+pub struct BearerAuthLayer {
+    validator: Arc<dyn TokenValidator>,
+    config: Arc<BearerAuthConfig>,
+}
+
+impl BearerAuthLayer {
+    pub fn new(validator: impl TokenValidator, config: BearerAuthConfig) -> Self {
+        Self {
+            validator: Arc::new(validator),
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for BearerAuthLayer {
+    type Service = BearerAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BearerAuthService {
+            inner,
+            validator: self.validator.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BearerAuthService<S> {
+    inner: S,
+    validator: Arc<dyn TokenValidator>,
+    config: Arc<BearerAuthConfig>,
+}
+
+pub type BearerAuthResponse<E> = Response<UnsyncBoxBody<Bytes, E>>;
+
+impl BearerAuthConfig {
+    fn metadata_response<E>(&self) -> BearerAuthResponse<E> {
+        let body = serde_json::to_vec(&self.metadata).expect("valid metadata");
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(
+                http::header::CONTENT_TYPE,
+                super::common::http_header::JSON_MIME_TYPE,
+            )
+            .body(full_body(body))
+            .expect("valid response")
+    }
+
+    fn challenge_response<E>(&self, error: &TokenValidationError) -> BearerAuthResponse<E> {
+        let mut challenge = format!(
+            "Bearer resource_metadata=\"{}\"",
+            self.resource_metadata_url()
+        );
+        if let Some(code) = error.error_code() {
+            challenge.push_str(&format!(
+                ", error=\"{code}\", error_description=\"{}\"",
+                error.to_string().replace('"', "'")
+            ));
+        }
+        if let TokenValidationError::InsufficientScope(scopes) = error {
+            challenge.push_str(&format!(", scope=\"{}\"", scopes.join(" ")));
+        }
+        let mut response = Response::builder()
+            .status(error.status_code())
+            .body(full_body(error.to_string()))
+            .expect("valid response");
+        if let Ok(value) = HeaderValue::from_str(&challenge) {
+            response.headers_mut().insert(WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+}
+
+fn full_body<E>(data: impl Into<Bytes>) -> UnsyncBoxBody<Bytes, E> {
+    Full::new(data.into())
+        .map_err(|never: Infallible| match never {})
+        .boxed_unsync()
+}
+
+fn bearer_token<B>(request: &Request<B>) -> Option<String> {
+    let value = request
+        .headers()
+        .get(http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+impl<S, ReqBody, ResBody> tower_service::Service<Request<ReqBody>> for BearerAuthService<S>
+where
+    S: tower_service::Service<Request<ReqBody>, Response = Response<ResBody>>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Body<Data = Bytes> + Send + 'static,
+{
+    type Response = BearerAuthResponse<ResBody::Error>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        // take the service that was driven to readiness, leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let validator = self.validator.clone();
+        let config = self.config.clone();
+        Box::pin(async move {
+            if request.method() == Method::GET && config.is_metadata_path(request.uri().path()) {
+                return Ok(config.metadata_response());
+            }
+            let Some(token) = bearer_token(&request) else {
+                return Ok(config.challenge_response(&TokenValidationError::MissingToken));
+            };
+            let claims = match validator.validate(token).await {
+                Ok(claims) => claims,
+                Err(error) => {
+                    tracing::debug!(%error, "reject unauthorized request");
+                    return Ok(config.challenge_response(&error));
+                }
+            };
+            let missing_scopes = config
+                .required_scopes
+                .iter()
+                .filter(|scope| !claims.has_scope(scope))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !missing_scopes.is_empty() {
+                return Ok(config
+                    .challenge_response(&TokenValidationError::InsufficientScope(missing_scopes)));
+            }
+            request.extensions_mut().insert(claims);
+            let response = inner.call(request).await?;
+            Ok(response.map(BodyExt::boxed_unsync))
+        })
+    }
+}
@@ -6,9 +6,18 @@ use tokio::{
     process::{ChildStderr, ChildStdin, ChildStdout},
 };
 
-use super::{IntoTransport, Transport};
+use super::{IntoTransport, PayloadLimits, Transport};
 use crate::service::ServiceRole;
 
+#[cfg(feature = "client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client")))]
+mod supervised;
+#[cfg(feature = "client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client")))]
+pub use supervised::{
+    ResourceLimits, RestartPolicy, SupervisedChildProcess, SupervisedChildProcessBuilder,
+};
+
 /// The parts of a child process.
 type ChildProcessParts = (
     Box<dyn TokioChildWrapper>,
@@ -38,6 +47,7 @@ pub struct TokioChildProcess {
     child: ChildWithCleanup,
     child_stdin: ChildStdin,
     child_stdout: ChildStdout,
+    limits: PayloadLimits,
 }
 
 pub struct ChildWithCleanup {
@@ -101,6 +111,7 @@ impl TokioChildProcess {
             child,
             child_stdin,
             child_stdout,
+            ..
         } = self;
         (
             TokioChildProcessOut {
@@ -118,6 +129,7 @@ pub struct TokioChildProcessBuilder {
     stdin: Stdio,
     stdout: Stdio,
     stderr: Stdio,
+    limits: PayloadLimits,
 }
 
 impl TokioChildProcessBuilder {
@@ -127,6 +139,7 @@ impl TokioChildProcessBuilder {
             stdin: Stdio::piped(),
             stdout: Stdio::piped(),
             stderr: Stdio::inherit(),
+            limits: PayloadLimits::unlimited(),
         }
     }
 
@@ -146,6 +159,12 @@ impl TokioChildProcessBuilder {
         self
     }
 
+    /// Reject the messages of the child process over `limits`, which are unlimited by default.
+    pub fn limits(mut self, limits: PayloadLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Spawn the child process. Returns the transport plus an optional captured stderr handle.
     pub fn spawn(mut self) -> std::io::Result<(TokioChildProcess, Option<ChildStderr>)> {
         self.cmd
@@ -160,6 +179,7 @@ impl TokioChildProcessBuilder {
             child: ChildWithCleanup { inner: child },
             child_stdin: stdin,
             child_stdout: stdout,
+            limits: self.limits,
         };
         Ok((proc, stderr_opt))
     }
@@ -167,9 +187,9 @@ impl TokioChildProcessBuilder {
 
 impl<R: ServiceRole> IntoTransport<R, std::io::Error, ()> for TokioChildProcess {
     fn into_transport(self) -> impl Transport<R, Error = std::io::Error> + 'static {
-        IntoTransport::<R, std::io::Error, super::async_rw::TransportAdapterAsyncRW>::into_transport(
-            self.split(),
-        )
+        let limits = self.limits;
+        let (read, write) = self.split();
+        super::async_rw::AsyncRwTransport::with_limits(read, write, limits)
     }
 }
 
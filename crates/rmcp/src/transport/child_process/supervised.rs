@@ -0,0 +1,712 @@
+//! A child process transport which supervises the server process.
+//!
+//! [`SupervisedChildProcess`] forwards the stderr of the server line by line, restarts it with
+//! an exponential backoff when it crashes and kills the whole process group when dropped. The
+//! environment, working directory and resource limits of the server can be restricted, which is
+//! useful when running servers you don't fully trust.
+//!
+//! ```rust,no_run
+//! # use rmcp::{ServiceExt, transport::child_process::{ResourceLimits, SupervisedChildProcess}};
+//! # async fn run() -> anyhow::Result<()> {
+//! let transport = SupervisedChildProcess::builder(tokio::process::Command::new("my-mcp-server"))
+//!     .env_allow(["PATH", "HOME"])
+//!     .current_dir("/tmp")
+//!     .resource_limits(ResourceLimits {
+//!         address_space: Some(1 << 30),
+//!         ..Default::default()
+//!     })
+//!     .on_stderr(|line| eprintln!("[my-mcp-server] {line}"))
+//!     .spawn()?;
+//! let client = ().serve(transport).await?;
+//! // `QuitReason::ProcessExited` once the server exits for good
+//! let quit_reason = client.waiting().await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::HashSet,
+    ffi::{OsStr, OsString},
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::{SinkExt, StreamExt};
+use process_wrap::tokio::{TokioChildWrapper, TokioCommandWrap};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{ChildStderr, ChildStdin, ChildStdout},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::Instant,
+};
+use tokio_util::{
+    codec::{FramedRead, FramedWrite},
+    sync::{CancellationToken, DropGuard},
+};
+
+use super::child_process;
+use crate::{
+    RoleClient,
+    model::{
+        ClientJsonRpcMessage, ClientNotification, ClientRequest, ErrorData, RequestId,
+        ServerJsonRpcMessage,
+    },
+    service::QuitReason,
+    transport::{Transport, async_rw::JsonRpcMessageCodec},
+};
+
+/// How a crashed server process is restarted.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// maximum number of consecutive restarts, `None` for no limit
+    pub max_restarts: Option<usize>,
+    /// delay before the first restart, doubled for every consecutive restart
+    pub initial_backoff: Duration,
+    /// upper bound of the delay between restarts
+    pub max_backoff: Duration,
+    /// a process which stayed up for this long resets the restart count and the backoff
+    pub reset_after: Duration,
+    /// also restart the process when it exits successfully
+    pub restart_on_success: bool,
+}
+
+impl RestartPolicy {
+    /// Never restart the process.
+    pub fn never() -> Self {
+        Self {
+            max_restarts: Some(0),
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, restarts: usize) -> Duration {
+        let factor = 1u32.checked_shl(restarts as u32).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: Some(5),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            reset_after: Duration::from_secs(60),
+            restart_on_success: false,
+        }
+    }
+}
+
+/// Resource limits applied to the server process, only supported on unix.
+///
+/// Every limit is set as both the soft and the hard limit, so the server can't raise it again.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// cpu time in seconds (`RLIMIT_CPU`)
+    pub cpu_time: Option<u64>,
+    /// virtual memory in bytes (`RLIMIT_AS`)
+    pub address_space: Option<u64>,
+    /// number of open file descriptors (`RLIMIT_NOFILE`)
+    pub open_files: Option<u64>,
+    /// size of created files in bytes (`RLIMIT_FSIZE`)
+    pub file_size: Option<u64>,
+    /// number of processes of the user (`RLIMIT_NPROC`)
+    pub processes: Option<u64>,
+}
+
+impl ResourceLimits {
+    fn is_empty(&self) -> bool {
+        self.cpu_time.is_none()
+            && self.address_space.is_none()
+            && self.open_files.is_none()
+            && self.file_size.is_none()
+            && self.processes.is_none()
+    }
+
+    #[cfg(unix)]
+    fn apply(&self) -> std::io::Result<()> {
+        use nix::sys::resource::{Resource, setrlimit};
+        fn set(resource: Resource, limit: Option<u64>) -> std::io::Result<()> {
+            match limit {
+                Some(limit) => setrlimit(resource, limit, limit).map_err(std::io::Error::from),
+                None => Ok(()),
+            }
+        }
+        set(Resource::RLIMIT_CPU, self.cpu_time)?;
+        set(Resource::RLIMIT_NOFILE, self.open_files)?;
+        set(Resource::RLIMIT_FSIZE, self.file_size)?;
+        #[cfg(not(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+        set(Resource::RLIMIT_AS, self.address_space)?;
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        ))]
+        set(Resource::RLIMIT_NPROC, self.processes)?;
+        Ok(())
+    }
+
+    fn check_supported(&self) -> std::io::Result<()> {
+        let unsupported = if cfg!(not(unix)) {
+            !self.is_empty()
+        } else if cfg!(any(
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )) {
+            self.address_space.is_some()
+        } else if cfg!(not(any(target_os = "linux", target_os = "android"))) {
+            self.processes.is_some()
+        } else {
+            false
+        };
+        if unsupported {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "resource limit not supported on this platform",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+enum EnvPolicy {
+    Inherit,
+    Clear,
+    Allow(Vec<OsString>),
+}
+
+type StderrCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Everything needed to spawn the server process again.
+struct ProcessConfig {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    env_policy: EnvPolicy,
+    current_dir: Option<PathBuf>,
+    limits: ResourceLimits,
+    on_stderr: Option<StderrCallback>,
+    restart: RestartPolicy,
+    exit_timeout: Duration,
+}
+
+impl ProcessConfig {
+    fn command(&self) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(&self.program);
+        command.args(&self.args);
+        match &self.env_policy {
+            EnvPolicy::Inherit => {}
+            EnvPolicy::Clear => {
+                command.env_clear();
+            }
+            EnvPolicy::Allow(keys) => {
+                command.env_clear();
+                for key in keys {
+                    if let Some(value) = std::env::var_os(key) {
+                        command.env(key, value);
+                    }
+                }
+            }
+        }
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+        #[cfg(unix)]
+        if !self.limits.is_empty() {
+            let limits = self.limits.clone();
+            // SAFETY: setrlimit is async-signal-safe and the closure doesn't allocate
+            // unless setting a limit fails
+            unsafe {
+                command.pre_exec(move || limits.apply());
+            }
+        }
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        command
+    }
+
+    fn spawn(&self) -> std::io::Result<ServerProcess> {
+        let mut command = TokioCommandWrap::from(self.command());
+        #[cfg(unix)]
+        command.wrap(process_wrap::tokio::ProcessGroup::leader());
+        #[cfg(windows)]
+        command.wrap(process_wrap::tokio::JobObject);
+        let (child, stdout, stdin, stderr) = child_process(command.spawn()?)?;
+        let pid = child.id();
+        let stderr_task =
+            stderr.map(|stderr| tokio::spawn(forward_stderr(stderr, pid, self.on_stderr.clone())));
+        Ok(ServerProcess {
+            child: ProcessGroupGuard { inner: child },
+            stdin: FramedWrite::new(stdin, JsonRpcMessageCodec::default()),
+            stdout: FramedRead::new(stdout, JsonRpcMessageCodec::default()),
+            stderr_task,
+        })
+    }
+}
+
+async fn forward_stderr(stderr: ChildStderr, pid: Option<u32>, on_stderr: Option<StderrCallback>) {
+    let mut lines = BufReader::new(stderr).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => match &on_stderr {
+                Some(callback) => callback(&line),
+                None => tracing::info!(target: "rmcp::child_process::stderr", ?pid, "{line}"),
+            },
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!(?pid, "failed to read stderr of child process: {e}");
+                break;
+            }
+        }
+    }
+}
+
+/// Kills the whole process group of the server when dropped.
+struct ProcessGroupGuard {
+    inner: Box<dyn TokioChildWrapper>,
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        // the group may already be gone if every process in it exited
+        if let Err(e) = self.inner.start_kill() {
+            tracing::debug!("failed to kill child process group: {e}");
+        }
+    }
+}
+
+struct ServerProcess {
+    child: ProcessGroupGuard,
+    stdin: FramedWrite<ChildStdin, JsonRpcMessageCodec<ClientJsonRpcMessage>>,
+    stdout: FramedRead<ChildStdout, JsonRpcMessageCodec<ServerJsonRpcMessage>>,
+    stderr_task: Option<JoinHandle<()>>,
+}
+
+impl ServerProcess {
+    async fn send(&mut self, message: ClientJsonRpcMessage) -> std::io::Result<()> {
+        self.stdin.send(message).await.map_err(Into::into)
+    }
+
+    async fn receive(&mut self) -> Option<ServerJsonRpcMessage> {
+        loop {
+            match self.stdout.next().await? {
+                Ok(message) => return Some(message),
+                Err(e) => tracing::error!("error reading from child process: {e}"),
+            }
+        }
+    }
+
+    /// Wait for the process to exit once its stdout is closed, killing it after `timeout`.
+    async fn exit_status(mut self, timeout: Duration) -> std::io::Result<ExitStatus> {
+        let child = self.child.inner.inner_mut();
+        let status = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => status?,
+            Err(_) => {
+                tracing::warn!("child process closed stdout but didn't exit, killing it");
+                child.start_kill()?;
+                child.wait().await?
+            }
+        };
+        // let the stderr of the process drain before it's restarted
+        if let Some(task) = self.stderr_task.take() {
+            let _ = tokio::time::timeout(timeout, task).await;
+        }
+        Ok(status)
+    }
+}
+
+enum Served {
+    Exited,
+    Cancelled,
+    HandlerClosed,
+}
+
+struct SendRequest {
+    message: ClientJsonRpcMessage,
+    responder: oneshot::Sender<std::io::Result<()>>,
+}
+
+struct Supervisor {
+    config: ProcessConfig,
+    requests: mpsc::Receiver<SendRequest>,
+    messages: mpsc::Sender<ServerJsonRpcMessage>,
+    ct: CancellationToken,
+    /// the handshake of the session, replayed to every restarted process
+    initialize: Option<ClientJsonRpcMessage>,
+    initialized: Option<ClientJsonRpcMessage>,
+    in_flight: HashSet<RequestId>,
+}
+
+impl Supervisor {
+    async fn run(mut self, mut process: ServerProcess) -> Option<ExitStatus> {
+        let mut restarts = 0;
+        loop {
+            let started = Instant::now();
+            match self.serve(&mut process).await {
+                Served::Exited => {}
+                Served::Cancelled | Served::HandlerClosed => return None,
+            }
+            let status = match process.exit_status(self.config.exit_timeout).await {
+                Ok(status) => status,
+                Err(e) => {
+                    tracing::error!("failed to wait for child process: {e}");
+                    return None;
+                }
+            };
+            self.fail_in_flight(status).await;
+
+            let policy = &self.config.restart;
+            if started.elapsed() >= policy.reset_after {
+                restarts = 0;
+            }
+            if (status.success() && !policy.restart_on_success)
+                || policy.max_restarts.is_some_and(|max| restarts >= max)
+            {
+                tracing::info!(%status, "child process exited");
+                return Some(status);
+            }
+            let mut backoff = policy.backoff(restarts);
+            restarts += 1;
+            tracing::warn!(%status, ?backoff, restarts, "child process exited, restarting");
+
+            process = loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = self.ct.cancelled() => return Some(status),
+                }
+                match self.config.spawn() {
+                    Ok(process) => break process,
+                    Err(e) => {
+                        tracing::error!("failed to restart child process: {e}");
+                        if self
+                            .config
+                            .restart
+                            .max_restarts
+                            .is_some_and(|max| restarts >= max)
+                        {
+                            return Some(status);
+                        }
+                        backoff = self.config.restart.backoff(restarts);
+                        restarts += 1;
+                    }
+                }
+            };
+            match self.replay_handshake(&mut process).await {
+                Some(Served::Exited) => continue,
+                Some(Served::Cancelled | Served::HandlerClosed) => return Some(status),
+                None => {}
+            }
+        }
+    }
+
+    /// Forward messages between the service and the process until the process closes its stdout.
+    async fn serve(&mut self, process: &mut ServerProcess) -> Served {
+        loop {
+            tokio::select! {
+                request = self.requests.recv() => {
+                    let Some(SendRequest { message, responder }) = request else {
+                        return Served::HandlerClosed;
+                    };
+                    self.track_outgoing(&message);
+                    let _ = responder.send(process.send(message).await);
+                }
+                message = process.receive() => {
+                    let Some(message) = message else {
+                        return Served::Exited;
+                    };
+                    if let Some(id) = response_id(&message) {
+                        self.in_flight.remove(id);
+                    }
+                    if self.messages.send(message).await.is_err() {
+                        return Served::HandlerClosed;
+                    }
+                }
+                _ = self.ct.cancelled() => return Served::Cancelled,
+            }
+        }
+    }
+
+    /// Initialize a restarted process with the handshake of the session, its response is dropped
+    /// since the service already is initialized.
+    async fn replay_handshake(&mut self, process: &mut ServerProcess) -> Option<Served> {
+        let initialize = self.initialize.clone()?;
+        let id = request_id(&initialize).cloned();
+        if let Err(e) = process.send(initialize).await {
+            tracing::warn!("failed to replay initialize request: {e}");
+        }
+        loop {
+            tokio::select! {
+                message = process.receive() => {
+                    let Some(message) = message else {
+                        return Some(Served::Exited);
+                    };
+                    if response_id(&message) == id.as_ref() {
+                        break;
+                    }
+                    if self.messages.send(message).await.is_err() {
+                        return Some(Served::HandlerClosed);
+                    }
+                }
+                _ = self.ct.cancelled() => return Some(Served::Cancelled),
+            }
+        }
+        if let Some(initialized) = self.initialized.clone() {
+            if let Err(e) = process.send(initialized).await {
+                tracing::warn!("failed to replay initialized notification: {e}");
+            }
+        }
+        None
+    }
+
+    fn track_outgoing(&mut self, message: &ClientJsonRpcMessage) {
+        match message {
+            ClientJsonRpcMessage::Request(request) => {
+                if matches!(request.request, ClientRequest::InitializeRequest(_)) {
+                    self.initialize = Some(message.clone());
+                } else {
+                    self.in_flight.insert(request.id.clone());
+                }
+            }
+            ClientJsonRpcMessage::Notification(notification)
+                if matches!(
+                    notification.notification,
+                    ClientNotification::InitializedNotification(_)
+                ) =>
+            {
+                self.initialized = Some(message.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Requests sent to a crashed process will never be answered.
+    async fn fail_in_flight(&mut self, status: ExitStatus) {
+        for id in std::mem::take(&mut self.in_flight) {
+            let error =
+                ErrorData::internal_error(format!("server process exited with {status}"), None);
+            if self
+                .messages
+                .send(ServerJsonRpcMessage::error(error, id))
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+fn request_id(message: &ClientJsonRpcMessage) -> Option<&RequestId> {
+    match message {
+        ClientJsonRpcMessage::Request(request) => Some(&request.id),
+        _ => None,
+    }
+}
+
+fn response_id(message: &ServerJsonRpcMessage) -> Option<&RequestId> {
+    match message {
+        ServerJsonRpcMessage::Response(response) => Some(&response.id),
+        ServerJsonRpcMessage::Error(error) => Some(&error.id),
+        _ => None,
+    }
+}
+
+/// A client transport to a server child process which is restarted when it crashes.
+///
+/// Once the process exits for good, the service quits with [`QuitReason::ProcessExited`].
+pub struct SupervisedChildProcess {
+    requests: mpsc::Sender<SendRequest>,
+    messages: mpsc::Receiver<ServerJsonRpcMessage>,
+    supervisor: Option<JoinHandle<Option<ExitStatus>>>,
+    exit_status: Option<ExitStatus>,
+    ct: CancellationToken,
+    _drop_guard: DropGuard,
+}
+
+impl SupervisedChildProcess {
+    pub fn builder(command: impl Into<tokio::process::Command>) -> SupervisedChildProcessBuilder {
+        SupervisedChildProcessBuilder::new(command.into())
+    }
+
+    /// Spawn with the default configuration.
+    pub fn new(command: impl Into<tokio::process::Command>) -> std::io::Result<Self> {
+        Self::builder(command).spawn()
+    }
+
+    /// The exit status of the process, once it exited for good.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status
+    }
+
+    async fn finish(&mut self) {
+        if let Some(supervisor) = self.supervisor.take() {
+            match supervisor.await {
+                Ok(status) => self.exit_status = status,
+                Err(e) => tracing::error!("child process supervisor failed: {e}"),
+            }
+        }
+    }
+}
+
+impl Transport<RoleClient> for SupervisedChildProcess {
+    type Error = std::io::Error;
+
+    fn send(
+        &mut self,
+        item: ClientJsonRpcMessage,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        let requests = self.requests.clone();
+        async move {
+            let closed =
+                || std::io::Error::new(std::io::ErrorKind::BrokenPipe, "child process exited");
+            let (responder, receiver) = oneshot::channel();
+            requests
+                .send(SendRequest {
+                    message: item,
+                    responder,
+                })
+                .await
+                .map_err(|_| closed())?;
+            receiver.await.map_err(|_| closed())?
+        }
+    }
+
+    async fn receive(&mut self) -> Option<ServerJsonRpcMessage> {
+        let message = self.messages.recv().await;
+        if message.is_none() {
+            self.finish().await;
+        }
+        message
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.ct.cancel();
+        self.finish().await;
+        Ok(())
+    }
+
+    fn quit_reason(&mut self) -> Option<QuitReason> {
+        self.exit_status.map(QuitReason::ProcessExited)
+    }
+}
+
+/// Builder for [`SupervisedChildProcess`].
+pub struct SupervisedChildProcessBuilder {
+    config: ProcessConfig,
+}
+
+impl SupervisedChildProcessBuilder {
+    fn new(command: tokio::process::Command) -> Self {
+        let command = command.as_std();
+        Self {
+            config: ProcessConfig {
+                program: command.get_program().to_owned(),
+                args: command.get_args().map(ToOwned::to_owned).collect(),
+                envs: command
+                    .get_envs()
+                    .map(|(key, value)| (key.to_owned(), value.map(ToOwned::to_owned)))
+                    .collect(),
+                env_policy: EnvPolicy::Inherit,
+                current_dir: command.get_current_dir().map(ToOwned::to_owned),
+                limits: ResourceLimits::default(),
+                on_stderr: None,
+                restart: RestartPolicy::default(),
+                exit_timeout: Duration::from_secs(5),
+            },
+        }
+    }
+
+    /// Don't inherit any environment variable, only the ones set explicitly.
+    pub fn env_clear(mut self) -> Self {
+        self.config.env_policy = EnvPolicy::Clear;
+        self
+    }
+
+    /// Only inherit the given environment variables.
+    pub fn env_allow<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<OsStr>,
+    {
+        self.config.env_policy =
+            EnvPolicy::Allow(keys.into_iter().map(|k| k.as_ref().to_owned()).collect());
+        self
+    }
+
+    /// Set an environment variable, regardless of the inherited ones.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.config
+            .envs
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.current_dir = Some(dir.into());
+        self
+    }
+
+    pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.config.limits = limits;
+        self
+    }
+
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.config.restart = policy;
+        self
+    }
+
+    /// Handle every line the process writes to stderr, instead of logging it with `tracing`.
+    pub fn on_stderr(mut self, callback: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.config.on_stderr = Some(Arc::new(callback));
+        self
+    }
+
+    /// How long to wait for the process to exit after it closed its stdout, before killing it.
+    pub fn exit_timeout(mut self, timeout: Duration) -> Self {
+        self.config.exit_timeout = timeout;
+        self
+    }
+
+    /// Spawn the process and start supervising it.
+    pub fn spawn(self) -> std::io::Result<SupervisedChildProcess> {
+        self.config.limits.check_supported()?;
+        let process = self.config.spawn()?;
+        let (requests_tx, requests_rx) = mpsc::channel(16);
+        let (messages_tx, messages_rx) = mpsc::channel(16);
+        let ct = CancellationToken::new();
+        let supervisor = Supervisor {
+            config: self.config,
+            requests: requests_rx,
+            messages: messages_tx,
+            ct: ct.clone(),
+            initialize: None,
+            initialized: None,
+            in_flight: HashSet::new(),
+        };
+        Ok(SupervisedChildProcess {
+            requests: requests_tx,
+            messages: messages_rx,
+            supervisor: Some(tokio::spawn(supervisor.run(process))),
+            exit_status: None,
+            ct: ct.clone(),
+            _drop_guard: ct.drop_guard(),
+        })
+    }
+}
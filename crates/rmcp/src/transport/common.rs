@@ -6,10 +6,22 @@ pub mod server_side_http;
 
 pub mod http_header;
 
+pub mod limits;
+
 #[cfg(feature = "__reqwest")]
 #[cfg_attr(docsrs, doc(cfg(feature = "reqwest")))]
 mod reqwest;
 
+#[cfg(feature = "__hyper")]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        feature = "transport-streamable-http-client-hyper",
+        feature = "transport-sse-client-hyper"
+    )))
+)]
+pub mod hyper;
+
 #[cfg(feature = "client-side-sse")]
 #[cfg_attr(docsrs, doc(cfg(feature = "client-side-sse")))]
 pub mod client_side_sse;
@@ -17,3 +29,7 @@ pub mod client_side_sse;
 #[cfg(feature = "auth")]
 #[cfg_attr(docsrs, doc(cfg(feature = "auth")))]
 pub mod auth;
+
+#[cfg(any(feature = "auth", feature = "auth-server"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "auth", feature = "auth-server"))))]
+pub mod protected_resource;
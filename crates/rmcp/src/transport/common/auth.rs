@@ -5,3 +5,27 @@ mod streamable_http_client;
 #[cfg(feature = "transport-sse-client")]
 #[cfg_attr(docsrs, doc(cfg(feature = "transport-sse-client")))]
 mod sse_client;
+
+/// check if an http client error was caused by a `401 Unauthorized` response
+#[cfg(any(
+    feature = "transport-streamable-http-client",
+    feature = "transport-sse-client"
+))]
+fn is_unauthorized(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+            if error.status() == Some(reqwest::StatusCode::UNAUTHORIZED) {
+                return true;
+            }
+        }
+        #[cfg(feature = "__hyper")]
+        if let Some(error) = error.downcast_ref::<super::hyper::HyperClientError>() {
+            if error.status() == Some(http::StatusCode::UNAUTHORIZED) {
+                return true;
+            }
+        }
+        current = error.source();
+    }
+    false
+}
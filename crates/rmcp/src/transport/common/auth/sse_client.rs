@@ -4,6 +4,15 @@ use crate::transport::{
     auth::AuthClient,
     sse_client::{SseClient, SseTransportError},
 };
+
+/// check if the server rejected the access token we attached to the request
+fn should_retry<E>(provided_token: bool, error: &SseTransportError<E>) -> bool
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    !provided_token && matches!(error, SseTransportError::Client(e) if super::is_unauthorized(e))
+}
+
 impl<C> SseClient for AuthClient<C>
 where
     C: SseClient,
@@ -14,32 +23,56 @@ where
         &self,
         uri: Uri,
         message: crate::model::ClientJsonRpcMessage,
-        mut auth_token: Option<String>,
+        auth_token: Option<String>,
     ) -> Result<(), SseTransportError<Self::Error>> {
-        if auth_token.is_none() {
-            auth_token = Some(self.get_access_token().await?);
-        }
-        self.http_client
-            .post_message(uri, message, auth_token)
+        let provided_token = auth_token.is_some();
+        let token = match auth_token {
+            Some(token) => token,
+            None => self.get_access_token().await?,
+        };
+        match self
+            .http_client
+            .post_message(uri.clone(), message.clone(), Some(token))
             .await
-            .map_err(SseTransportError::Client)
+        {
+            Err(e) if should_retry(provided_token, &e) => {
+                let token = self.handle_unauthorized().await?;
+                self.http_client
+                    .post_message(uri, message, Some(token))
+                    .await
+                    .map_err(SseTransportError::Client)
+            }
+            result => result.map_err(SseTransportError::Client),
+        }
     }
 
     async fn get_stream(
         &self,
         uri: Uri,
         last_event_id: Option<String>,
-        mut auth_token: Option<String>,
+        auth_token: Option<String>,
     ) -> Result<
         crate::transport::common::client_side_sse::BoxedSseResponse,
         SseTransportError<Self::Error>,
     > {
-        if auth_token.is_none() {
-            auth_token = Some(self.get_access_token().await?);
-        }
-        self.http_client
-            .get_stream(uri, last_event_id, auth_token)
+        let provided_token = auth_token.is_some();
+        let token = match auth_token {
+            Some(token) => token,
+            None => self.get_access_token().await?,
+        };
+        match self
+            .http_client
+            .get_stream(uri.clone(), last_event_id.clone(), Some(token))
             .await
-            .map_err(SseTransportError::Client)
+        {
+            Err(e) if should_retry(provided_token, &e) => {
+                let token = self.handle_unauthorized().await?;
+                self.http_client
+                    .get_stream(uri, last_event_id, Some(token))
+                    .await
+                    .map_err(SseTransportError::Client)
+            }
+            result => result.map_err(SseTransportError::Client),
+        }
     }
 }
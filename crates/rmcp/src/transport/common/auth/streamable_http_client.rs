@@ -2,6 +2,27 @@ use crate::transport::{
     auth::AuthClient,
     streamable_http_client::{StreamableHttpClient, StreamableHttpError},
 };
+
+/// check if the server rejected the access token we attached to the request
+fn should_retry<E>(provided_token: bool, error: &StreamableHttpError<E>) -> bool
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    !provided_token && matches!(error, StreamableHttpError::Client(e) if super::is_unauthorized(e))
+}
+
+/// keep the errors the worker acts on visible through the wrapper
+fn lift_error<E>(error: StreamableHttpError<E>) -> StreamableHttpError<StreamableHttpError<E>>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    match error {
+        StreamableHttpError::SessionExpired => StreamableHttpError::SessionExpired,
+        StreamableHttpError::SeverDoesNotSupportSse => StreamableHttpError::SeverDoesNotSupportSse,
+        error => StreamableHttpError::Client(error),
+    }
+}
+
 impl<C> StreamableHttpClient for AuthClient<C>
 where
     C: StreamableHttpClient + Send + Sync,
@@ -12,16 +33,28 @@ where
         &self,
         uri: std::sync::Arc<str>,
         session_id: std::sync::Arc<str>,
-        mut auth_token: Option<String>,
+        auth_token: Option<String>,
     ) -> Result<(), crate::transport::streamable_http_client::StreamableHttpError<Self::Error>>
     {
-        if auth_token.is_none() {
-            auth_token = Some(self.get_access_token().await?);
-        }
-        self.http_client
-            .delete_session(uri, session_id, auth_token)
+        let provided_token = auth_token.is_some();
+        let token = match auth_token {
+            Some(token) => token,
+            None => self.get_access_token().await?,
+        };
+        match self
+            .http_client
+            .delete_session(uri.clone(), session_id.clone(), Some(token))
             .await
-            .map_err(StreamableHttpError::Client)
+        {
+            Err(e) if should_retry(provided_token, &e) => {
+                let token = self.handle_unauthorized().await?;
+                self.http_client
+                    .delete_session(uri, session_id, Some(token))
+                    .await
+                    .map_err(lift_error)
+            }
+            result => result.map_err(lift_error),
+        }
     }
 
     async fn get_stream(
@@ -29,18 +62,35 @@ where
         uri: std::sync::Arc<str>,
         session_id: std::sync::Arc<str>,
         last_event_id: Option<String>,
-        mut auth_token: Option<String>,
+        auth_token: Option<String>,
     ) -> Result<
         futures::stream::BoxStream<'static, Result<sse_stream::Sse, sse_stream::Error>>,
         crate::transport::streamable_http_client::StreamableHttpError<Self::Error>,
     > {
-        if auth_token.is_none() {
-            auth_token = Some(self.get_access_token().await?);
-        }
-        self.http_client
-            .get_stream(uri, session_id, last_event_id, auth_token)
+        let provided_token = auth_token.is_some();
+        let token = match auth_token {
+            Some(token) => token,
+            None => self.get_access_token().await?,
+        };
+        match self
+            .http_client
+            .get_stream(
+                uri.clone(),
+                session_id.clone(),
+                last_event_id.clone(),
+                Some(token),
+            )
             .await
-            .map_err(StreamableHttpError::Client)
+        {
+            Err(e) if should_retry(provided_token, &e) => {
+                let token = self.handle_unauthorized().await?;
+                self.http_client
+                    .get_stream(uri, session_id, last_event_id, Some(token))
+                    .await
+                    .map_err(lift_error)
+            }
+            result => result.map_err(lift_error),
+        }
     }
 
     async fn post_message(
@@ -48,17 +98,34 @@ where
         uri: std::sync::Arc<str>,
         message: crate::model::ClientJsonRpcMessage,
         session_id: Option<std::sync::Arc<str>>,
-        mut auth_token: Option<String>,
+        auth_token: Option<String>,
     ) -> Result<
         crate::transport::streamable_http_client::StreamableHttpPostResponse,
         StreamableHttpError<Self::Error>,
     > {
-        if auth_token.is_none() {
-            auth_token = Some(self.get_access_token().await?);
-        }
-        self.http_client
-            .post_message(uri, message, session_id, auth_token)
+        let provided_token = auth_token.is_some();
+        let token = match auth_token {
+            Some(token) => token,
+            None => self.get_access_token().await?,
+        };
+        match self
+            .http_client
+            .post_message(
+                uri.clone(),
+                message.clone(),
+                session_id.clone(),
+                Some(token),
+            )
             .await
-            .map_err(StreamableHttpError::Client)
+        {
+            Err(e) if should_retry(provided_token, &e) => {
+                let token = self.handle_unauthorized().await?;
+                self.http_client
+                    .post_message(uri, message, session_id, Some(token))
+                    .await
+                    .map_err(lift_error)
+            }
+            result => result.map_err(lift_error),
+        }
     }
 }
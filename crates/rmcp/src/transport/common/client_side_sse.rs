@@ -111,6 +111,9 @@ pin_project_lite::pin_project! {
         retry_policy: Arc<dyn SseRetryPolicy>,
         last_event_id: Option<String>,
         server_retry_interval: Option<Duration>,
+        resume_on_end: bool,
+        // resumptions since the last message
+        resumptions: usize,
         connector: R,
         #[pin]
         state: SseAutoReconnectStreamState<R::Future>,
@@ -127,10 +130,19 @@ impl<R: SseStreamReconnect> SseAutoReconnectStream<R> {
             retry_policy,
             last_event_id: None,
             server_retry_interval: None,
+            resume_on_end: false,
+            resumptions: 0,
             connector,
             state: SseAutoReconnectStreamState::Connected { stream },
         }
     }
+
+    /// Also resume from the last event id when the server ends the stream, as a streamable
+    /// HTTP server may do at any time.
+    pub fn resume_on_end(mut self) -> Self {
+        self.resume_on_end = true;
+        self
+    }
 }
 
 impl<E: std::error::Error + Send> SseAutoReconnectStream<NeverReconnect<E>> {
@@ -140,6 +152,8 @@ impl<E: std::error::Error + Send> SseAutoReconnectStream<NeverReconnect<E>> {
             retry_policy: Arc::new(NeverRetry),
             last_event_id: None,
             server_retry_interval: None,
+            resume_on_end: false,
+            resumptions: 0,
             connector: NeverReconnect {
                 error: Some(error_when_reconnect),
             },
@@ -200,6 +214,7 @@ where
                                     return self.poll_next(cx);
                                 }
                                 Ok(message) => {
+                                    *this.resumptions = 0;
                                     return Poll::Ready(Some(Ok(message)));
                                 }
                             };
@@ -218,8 +233,32 @@ where
                         }
                     }
                     None => {
-                        tracing::debug!("sse stream terminated");
-                        return Poll::Ready(None);
+                        let Some(interval) = this
+                            .last_event_id
+                            .as_ref()
+                            .filter(|_| *this.resume_on_end)
+                            .and_then(|_| this.retry_policy.retry(*this.resumptions))
+                        else {
+                            tracing::debug!("sse stream terminated");
+                            return Poll::Ready(None);
+                        };
+                        tracing::debug!("sse stream ended, resuming");
+                        *this.resumptions += 1;
+                        // the first resumption is immediate, the next ones follow the policy
+                        if *this.resumptions == 1 {
+                            let retrying = this
+                                .connector
+                                .retry_connection(this.last_event_id.as_deref());
+                            SseAutoReconnectStreamState::Retrying {
+                                retry_times: 0,
+                                retrying,
+                            }
+                        } else {
+                            SseAutoReconnectStreamState::WaitingNextRetry {
+                                sleep: tokio::time::sleep(interval),
+                                retry_times: 0,
+                            }
+                        }
                     }
                 }
             }
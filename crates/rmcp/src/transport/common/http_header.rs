@@ -2,3 +2,33 @@ pub const HEADER_SESSION_ID: &str = "Mcp-Session-Id";
 pub const HEADER_LAST_EVENT_ID: &str = "Last-Event-Id";
 pub const EVENT_STREAM_MIME_TYPE: &str = "text/event-stream";
 pub const JSON_MIME_TYPE: &str = "application/json";
+
+/// Parse a `NAME: VALUE` header, as given on a command line.
+#[cfg(feature = "__reqwest")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reqwest")))]
+pub fn parse_header(
+    header: &str,
+) -> Result<(reqwest::header::HeaderName, reqwest::header::HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("expected `NAME: VALUE`, got `{header}`"))?;
+    let name = reqwest::header::HeaderName::try_from(name.trim())
+        .map_err(|e| format!("invalid header name: {e}"))?;
+    let value = reqwest::header::HeaderValue::try_from(value.trim())
+        .map_err(|e| format!("invalid header value: {e}"))?;
+    Ok((name, value))
+}
+
+#[cfg(all(test, feature = "__reqwest"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        let (name, value) = parse_header("Authorization: Bearer a:b").unwrap();
+        assert_eq!(name, "authorization");
+        assert_eq!(value, "Bearer a:b");
+        assert!(parse_header("Authorization").is_err());
+        assert!(parse_header("bad name: value").is_err());
+    }
+}
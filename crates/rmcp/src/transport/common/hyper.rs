@@ -0,0 +1,176 @@
+//! An HTTP client on top of hyper, for builds which can't afford reqwest.
+//!
+//! [`HyperClient`] talks plain HTTP by default. TLS is provided by the connector, e.g. an
+//! `HttpsConnector` from `hyper-rustls` or `hyper-tls`, and [`UnixConnector`] reaches a server
+//! listening on a Unix socket.
+//!
+//! ```rust,ignore
+//! let connector = hyper_rustls::HttpsConnectorBuilder::new()
+//!     .with_webpki_roots()
+//!     .https_or_http()
+//!     .enable_http1()
+//!     .build();
+//! let transport = StreamableHttpClientTransport::with_client(
+//!     HyperClient::with_connector(connector),
+//!     StreamableHttpClientTransportConfig::with_uri("https://example.com/mcp"),
+//! );
+//! ```
+use bytes::Bytes;
+use http::{HeaderValue, Method, Request, Response, StatusCode, Uri, header::AUTHORIZATION};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper_util::{
+    client::legacy::{
+        Client,
+        connect::{Connect, HttpConnector},
+    },
+    rt::TokioExecutor,
+};
+
+#[cfg(feature = "transport-streamable-http-client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-streamable-http-client")))]
+mod streamable_http_client;
+
+#[cfg(feature = "transport-sse-client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transport-sse-client")))]
+mod sse_client;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HyperClientError {
+    #[error("request failed: {0}")]
+    Request(#[from] hyper_util::client::legacy::Error),
+    #[error("failed to read response body: {0}")]
+    Body(#[from] hyper::Error),
+    #[error("invalid request: {0}")]
+    InvalidRequest(#[from] http::Error),
+    #[error("failed to serialize message: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("unexpected status {0}")]
+    Status(StatusCode),
+}
+
+impl HyperClientError {
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            HyperClientError::Status(status) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// An HTTP/1 client, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct HyperClient<C = HttpConnector> {
+    client: Client<C, Full<Bytes>>,
+}
+
+impl HyperClient {
+    /// A client without TLS.
+    pub fn new() -> Self {
+        Self::with_connector(HttpConnector::new())
+    }
+}
+
+impl Default for HyperClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> HyperClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    pub fn with_connector(connector: C) -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        }
+    }
+
+    pub fn from_client(client: Client<C, Full<Bytes>>) -> Self {
+        Self { client }
+    }
+
+    async fn send(
+        &self,
+        request: http::request::Builder,
+        auth_token: Option<String>,
+        body: Bytes,
+    ) -> Result<Response<Incoming>, HyperClientError> {
+        let mut request = request;
+        if let Some(auth_token) = auth_token {
+            let value =
+                HeaderValue::try_from(format!("Bearer {auth_token}")).map_err(http::Error::from)?;
+            request = request.header(AUTHORIZATION, value);
+        }
+        Ok(self.client.request(request.body(Full::new(body))?).await?)
+    }
+}
+
+fn request(
+    method: Method,
+    uri: impl TryInto<Uri, Error: Into<http::Error>>,
+) -> http::request::Builder {
+    Request::builder().method(method).uri(uri)
+}
+
+fn error_for_status(response: Response<Incoming>) -> Result<Response<Incoming>, HyperClientError> {
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        Err(HyperClientError::Status(status))
+    } else {
+        Ok(response)
+    }
+}
+
+async fn read_body(response: Response<Incoming>) -> Result<Bytes, HyperClientError> {
+    Ok(response.into_body().collect().await?.to_bytes())
+}
+
+/// Connects every request to a Unix socket, whatever the host of its URI.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+#[derive(Debug, Clone)]
+pub struct UnixConnector {
+    path: std::sync::Arc<std::path::Path>,
+}
+
+#[cfg(unix)]
+impl UnixConnector {
+    pub fn new(path: impl AsRef<std::path::Path>) -> Self {
+        Self {
+            path: path.as_ref().into(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl tower_service::Service<Uri> for UnixConnector {
+    type Response = hyper_util::rt::TokioIo<tokio::net::UnixStream>;
+    type Error = std::io::Error;
+    type Future = futures::future::BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _uri: Uri) -> Self::Future {
+        let path = self.path.clone();
+        Box::pin(async move {
+            let stream = tokio::net::UnixStream::connect(&*path).await?;
+            Ok(hyper_util::rt::TokioIo::new(stream))
+        })
+    }
+}
+
+#[cfg(unix)]
+impl HyperClient<UnixConnector> {
+    /// A client for a server listening on a Unix socket, URIs only carry the path, like
+    /// `http://localhost/mcp`.
+    pub fn unix(path: impl AsRef<std::path::Path>) -> Self {
+        Self::with_connector(UnixConnector::new(path))
+    }
+}
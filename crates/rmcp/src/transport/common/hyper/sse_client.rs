@@ -0,0 +1,68 @@
+use bytes::Bytes;
+use futures::StreamExt;
+use http::{
+    Method, Uri,
+    header::{ACCEPT, CONTENT_TYPE},
+};
+use hyper_util::client::legacy::connect::Connect;
+use sse_stream::SseStream;
+
+use super::{HyperClient, HyperClientError, error_for_status, request};
+use crate::transport::{
+    common::{
+        client_side_sse::BoxedSseResponse,
+        http_header::{EVENT_STREAM_MIME_TYPE, HEADER_LAST_EVENT_ID, JSON_MIME_TYPE},
+    },
+    sse_client::{SseClient, SseTransportError},
+};
+
+impl From<HyperClientError> for SseTransportError<HyperClientError> {
+    fn from(e: HyperClientError) -> Self {
+        SseTransportError::Client(e)
+    }
+}
+
+impl<C> SseClient for HyperClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    type Error = HyperClientError;
+
+    async fn post_message(
+        &self,
+        uri: Uri,
+        message: crate::model::ClientJsonRpcMessage,
+        auth_token: Option<String>,
+    ) -> Result<(), SseTransportError<Self::Error>> {
+        let body = serde_json::to_vec(&message).map_err(HyperClientError::from)?;
+        let request_builder = request(Method::POST, uri).header(CONTENT_TYPE, JSON_MIME_TYPE);
+        let response = self.send(request_builder, auth_token, body.into()).await?;
+        error_for_status(response)?;
+        Ok(())
+    }
+
+    async fn get_stream(
+        &self,
+        uri: Uri,
+        last_event_id: Option<String>,
+        auth_token: Option<String>,
+    ) -> Result<BoxedSseResponse, SseTransportError<Self::Error>> {
+        let mut request_builder = request(Method::GET, uri).header(ACCEPT, EVENT_STREAM_MIME_TYPE);
+        if let Some(last_event_id) = last_event_id {
+            request_builder = request_builder.header(HEADER_LAST_EVENT_ID, last_event_id);
+        }
+        let response = self.send(request_builder, auth_token, Bytes::new()).await?;
+        let response = error_for_status(response)?;
+        match response.headers().get(CONTENT_TYPE) {
+            Some(ct) => {
+                if !ct.as_bytes().starts_with(EVENT_STREAM_MIME_TYPE.as_bytes()) {
+                    return Err(SseTransportError::UnexpectedContentType(Some(ct.clone())));
+                }
+            }
+            None => {
+                return Err(SseTransportError::UnexpectedContentType(None));
+            }
+        }
+        Ok(SseStream::new(response.into_body()).boxed())
+    }
+}
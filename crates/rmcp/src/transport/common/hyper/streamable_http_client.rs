@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::{StreamExt, stream::BoxStream};
+use http::{
+    Method, StatusCode,
+    header::{ACCEPT, CONTENT_TYPE},
+};
+use hyper_util::client::legacy::connect::Connect;
+use sse_stream::{Sse, SseStream};
+
+use super::{HyperClient, HyperClientError, error_for_status, read_body, request};
+use crate::{
+    model::{ClientJsonRpcMessage, ServerJsonRpcMessage},
+    transport::{
+        common::http_header::{
+            EVENT_STREAM_MIME_TYPE, HEADER_LAST_EVENT_ID, HEADER_SESSION_ID, JSON_MIME_TYPE,
+        },
+        streamable_http_client::*,
+    },
+};
+
+impl From<HyperClientError> for StreamableHttpError<HyperClientError> {
+    fn from(e: HyperClientError) -> Self {
+        StreamableHttpError::Client(e)
+    }
+}
+
+impl<C> StreamableHttpClient for HyperClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    type Error = HyperClientError;
+
+    async fn get_stream(
+        &self,
+        uri: Arc<str>,
+        session_id: Arc<str>,
+        last_event_id: Option<String>,
+        auth_token: Option<String>,
+    ) -> Result<BoxStream<'static, Result<Sse, SseError>>, StreamableHttpError<Self::Error>> {
+        let mut request_builder = request(Method::GET, uri.as_ref())
+            .header(ACCEPT, EVENT_STREAM_MIME_TYPE)
+            .header(HEADER_SESSION_ID, session_id.as_ref());
+        if let Some(last_event_id) = last_event_id {
+            request_builder = request_builder.header(HEADER_LAST_EVENT_ID, last_event_id);
+        }
+        let response = self.send(request_builder, auth_token, Bytes::new()).await?;
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Err(StreamableHttpError::SeverDoesNotSupportSse);
+        }
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(StreamableHttpError::SessionExpired);
+        }
+        let response = error_for_status(response)?;
+        match response.headers().get(CONTENT_TYPE) {
+            Some(ct) => {
+                if !ct.as_bytes().starts_with(EVENT_STREAM_MIME_TYPE.as_bytes()) {
+                    return Err(StreamableHttpError::UnexpectedContentType(Some(
+                        String::from_utf8_lossy(ct.as_bytes()).to_string(),
+                    )));
+                }
+            }
+            None => {
+                return Err(StreamableHttpError::UnexpectedContentType(None));
+            }
+        }
+        Ok(SseStream::new(response.into_body()).boxed())
+    }
+
+    async fn delete_session(
+        &self,
+        uri: Arc<str>,
+        session: Arc<str>,
+        auth_token: Option<String>,
+    ) -> Result<(), StreamableHttpError<Self::Error>> {
+        let request_builder =
+            request(Method::DELETE, uri.as_ref()).header(HEADER_SESSION_ID, session.as_ref());
+        let response = self.send(request_builder, auth_token, Bytes::new()).await?;
+
+        // if method no allowed
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            tracing::debug!("this server doesn't support deleting session");
+            return Ok(());
+        }
+        error_for_status(response)?;
+        Ok(())
+    }
+
+    async fn post_message(
+        &self,
+        uri: Arc<str>,
+        message: ClientJsonRpcMessage,
+        session_id: Option<Arc<str>>,
+        auth_token: Option<String>,
+    ) -> Result<StreamableHttpPostResponse, StreamableHttpError<Self::Error>> {
+        let mut request_builder = request(Method::POST, uri.as_ref())
+            .header(ACCEPT, [EVENT_STREAM_MIME_TYPE, JSON_MIME_TYPE].join(", "))
+            .header(CONTENT_TYPE, JSON_MIME_TYPE);
+        let has_session = session_id.is_some();
+        if let Some(session_id) = session_id {
+            request_builder = request_builder.header(HEADER_SESSION_ID, session_id.as_ref());
+        }
+        let body = serde_json::to_vec(&message).map_err(HyperClientError::from)?;
+        let response = self.send(request_builder, auth_token, body.into()).await?;
+        if has_session && response.status() == StatusCode::NOT_FOUND {
+            return Err(StreamableHttpError::SessionExpired);
+        }
+        let response = error_for_status(response)?;
+        if response.status() == StatusCode::ACCEPTED {
+            return Ok(StreamableHttpPostResponse::Accepted);
+        }
+        let content_type = response.headers().get(CONTENT_TYPE).cloned();
+        let session_id = response
+            .headers()
+            .get(HEADER_SESSION_ID)
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        match content_type {
+            Some(ct) if ct.as_bytes().starts_with(EVENT_STREAM_MIME_TYPE.as_bytes()) => {
+                let event_stream = SseStream::new(response.into_body()).boxed();
+                Ok(StreamableHttpPostResponse::Sse(event_stream, session_id))
+            }
+            Some(ct) if ct.as_bytes().starts_with(JSON_MIME_TYPE.as_bytes()) => {
+                let body = read_body(response).await?;
+                let message: ServerJsonRpcMessage = serde_json::from_slice(&body)?;
+                Ok(StreamableHttpPostResponse::Json(message, session_id))
+            }
+            _ => {
+                // unexpected content type
+                tracing::error!("unexpected content type: {:?}", content_type);
+                Err(StreamableHttpError::UnexpectedContentType(
+                    content_type.map(|ct| String::from_utf8_lossy(ct.as_bytes()).to_string()),
+                ))
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Limits on the messages a transport accepts.
+//!
+//! The limits are checked on the raw payload before it is deserialized, so an oversized or
+//! deeply nested message costs a single pass over its bytes.
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::model::{ErrorCode, ErrorData};
+
+/// The default request body limit of axum.
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 2 * 1024 * 1024;
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 64;
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Limits on the messages received by a transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadLimits {
+    /// Maximum size of a message, or of a request body, in bytes.
+    pub max_message_bytes: usize,
+    /// Maximum number of messages in a batch.
+    pub max_batch_size: usize,
+    /// Maximum nesting depth of arrays and objects.
+    pub max_depth: usize,
+}
+
+impl Default for PayloadLimits {
+    fn default() -> Self {
+        Self {
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PayloadError {
+    #[error("message exceeds the limit of {limit} bytes")]
+    TooLarge { limit: usize },
+    #[error("batch exceeds the limit of {limit} messages")]
+    BatchTooLarge { limit: usize },
+    #[error("nesting depth exceeds the limit of {limit}")]
+    TooDeep { limit: usize },
+    #[error("parse error: {0}")]
+    Parse(serde_json::Error),
+    #[error("invalid message: {0}")]
+    InvalidMessage(serde_json::Error),
+}
+
+impl From<serde_json::Error> for PayloadError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_data() {
+            PayloadError::InvalidMessage(error)
+        } else {
+            PayloadError::Parse(error)
+        }
+    }
+}
+
+impl PayloadError {
+    /// `-32700` when the payload isn't JSON, `-32600` otherwise.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            PayloadError::Parse(_) => ErrorCode::PARSE_ERROR,
+            _ => ErrorCode::INVALID_REQUEST,
+        }
+    }
+
+    pub fn error_data(&self) -> ErrorData {
+        ErrorData::new(self.code(), self.to_string(), None)
+    }
+
+    /// The JSON-RPC error response rejecting `payload`.
+    ///
+    /// It has the id of the payload when one can be read, and a `null` id otherwise.
+    pub fn error_response(&self, payload: &[u8]) -> Value {
+        let id = match self {
+            PayloadError::InvalidMessage(_) => serde_json::from_slice::<Value>(payload)
+                .ok()
+                .and_then(|value| value.get("id").cloned())
+                .filter(|id| id.is_string() || id.is_number())
+                .unwrap_or(Value::Null),
+            _ => Value::Null,
+        };
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": self.error_data(),
+        })
+    }
+}
+
+/// Whether `payload` is a JSON-RPC response, which must not be answered even when rejected.
+pub(crate) fn is_response(payload: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(payload).is_ok_and(|value| {
+        value.get("method").is_none()
+            && (value.get("result").is_some() || value.get("error").is_some())
+    })
+}
+
+impl PayloadLimits {
+    /// No limit besides the recursion limit of `serde_json`.
+    pub const fn unlimited() -> Self {
+        Self {
+            max_message_bytes: usize::MAX,
+            max_batch_size: usize::MAX,
+            max_depth: usize::MAX,
+        }
+    }
+
+    pub fn max_message_bytes(mut self, max_message_bytes: usize) -> Self {
+        self.max_message_bytes = max_message_bytes;
+        self
+    }
+
+    pub fn max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Check the size, batch size and nesting depth of a payload without deserializing it.
+    pub fn check(&self, payload: &[u8]) -> Result<(), PayloadError> {
+        if payload.len() > self.max_message_bytes {
+            return Err(PayloadError::TooLarge {
+                limit: self.max_message_bytes,
+            });
+        }
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut is_batch = false;
+        let mut batch_size = 1usize;
+        for &byte in payload {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'[' | b'{' => {
+                    if depth == 0 {
+                        is_batch = byte == b'[';
+                    }
+                    depth += 1;
+                    if depth > self.max_depth {
+                        return Err(PayloadError::TooDeep {
+                            limit: self.max_depth,
+                        });
+                    }
+                }
+                b']' | b'}' => depth = depth.saturating_sub(1),
+                b',' if depth == 1 && is_batch => {
+                    batch_size += 1;
+                    if batch_size > self.max_batch_size {
+                        return Err(PayloadError::BatchTooLarge {
+                            limit: self.max_batch_size,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a payload, then deserialize it.
+    pub fn parse<T: DeserializeOwned>(&self, payload: &[u8]) -> Result<T, PayloadError> {
+        self.check(payload)?;
+        Ok(serde_json::from_slice(payload)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limits = PayloadLimits::default()
+            .max_message_bytes(64)
+            .max_batch_size(2)
+            .max_depth(3);
+        assert!(limits.check(br#"{"a":[{"b":"[[[[,,,"}]}"#).is_ok());
+        assert!(limits.check(br#"[{"a":[1,2,3]},{}]"#).is_ok());
+        assert!(matches!(
+            limits.check(br#"{"a":[{"b":[]}]}"#),
+            Err(PayloadError::TooDeep { limit: 3 })
+        ));
+        assert!(matches!(
+            limits.check(b"[{},{},{}]"),
+            Err(PayloadError::BatchTooLarge { limit: 2 })
+        ));
+        assert!(matches!(
+            limits.check(&[b' '; 65]),
+            Err(PayloadError::TooLarge { limit: 64 })
+        ));
+    }
+
+    #[test]
+    fn test_error_response() {
+        let limits = PayloadLimits::default();
+        let payload = br#"{"jsonrpc":"2.0","id":7,"method":1}"#;
+        let error = limits.parse::<crate::model::ClientJsonRpcMessage>(payload);
+        let error = error.expect_err("method must be a string");
+        assert_eq!(error.code(), ErrorCode::INVALID_REQUEST);
+        assert_eq!(error.error_response(payload)["id"], 7);
+
+        let payload = b"{\"jsonrpc\":";
+        let error = limits
+            .parse::<crate::model::ClientJsonRpcMessage>(payload)
+            .expect_err("truncated");
+        assert_eq!(error.code(), ErrorCode::PARSE_ERROR);
+        assert_eq!(error.error_response(payload)["id"], Value::Null);
+        assert_eq!(error.error_response(payload)["error"]["code"], -32700);
+    }
+}
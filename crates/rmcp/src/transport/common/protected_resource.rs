@@ -0,0 +1,53 @@
+//! Protected resource metadata ([RFC 9728](https://datatracker.ietf.org/doc/html/rfc9728)),
+//! served by resource servers and used by clients to discover the authorization server.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+pub const PROTECTED_RESOURCE_METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+/// Protected resource metadata, see [RFC 9728](https://datatracker.ietf.org/doc/html/rfc9728#section-2)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectedResourceMetadata {
+    pub resource: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authorization_servers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes_supported: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearer_methods_supported: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_documentation: Option<String>,
+    // allow additional fields
+    #[serde(flatten)]
+    pub additional_fields: HashMap<String, serde_json::Value>,
+}
+
+impl ProtectedResourceMetadata {
+    pub fn new(
+        resource: impl Into<String>,
+        authorization_servers: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            resource: resource.into(),
+            authorization_servers: authorization_servers.into_iter().map(Into::into).collect(),
+            jwks_uri: None,
+            scopes_supported: None,
+            bearer_methods_supported: Some(vec!["header".to_string()]),
+            resource_name: None,
+            resource_documentation: None,
+            additional_fields: HashMap::new(),
+        }
+    }
+    pub fn with_scopes_supported(
+        mut self,
+        scopes: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.scopes_supported = Some(scopes.into_iter().map(Into::into).collect());
+        self
+    }
+}
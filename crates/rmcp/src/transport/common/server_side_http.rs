@@ -1,13 +1,16 @@
 #![allow(dead_code)]
 use std::{convert::Infallible, fmt::Display, sync::Arc, time::Duration};
 
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use http::Response;
 use http_body::Body;
 use http_body_util::{BodyExt, Empty, Full, combinators::BoxBody};
 use sse_stream::{KeepAlive, Sse, SseBody};
 
-use super::http_header::EVENT_STREAM_MIME_TYPE;
+use super::{
+    http_header::{EVENT_STREAM_MIME_TYPE, JSON_MIME_TYPE},
+    limits::{PayloadError, PayloadLimits},
+};
 use crate::model::{ClientJsonRpcMessage, ServerJsonRpcMessage};
 
 pub type SessionId = Arc<str>;
@@ -104,6 +107,25 @@ pub(crate) const fn internal_error_response<E: Display>(
     }
 }
 
+/// Attach the http request parts to the message, so the handlers can access them with extensions.
+///
+/// The [`AuthClaims`](crate::transport::auth_server::AuthClaims) validated by the bearer auth layer
+/// are also attached if present.
+pub(crate) fn insert_request_parts(
+    message: &mut ClientJsonRpcMessage,
+    parts: http::request::Parts,
+) {
+    #[cfg(feature = "auth-server")]
+    if let Some(claims) = parts
+        .extensions
+        .get::<crate::transport::auth_server::AuthClaims>()
+        .cloned()
+    {
+        message.insert_extension(claims);
+    }
+    message.insert_extension(parts);
+}
+
 pub(crate) fn unexpected_message_response(expect: &str) -> Response<BoxBody<Bytes, Infallible>> {
     Response::builder()
         .status(http::StatusCode::UNPROCESSABLE_ENTITY)
@@ -111,35 +133,50 @@ pub(crate) fn unexpected_message_response(expect: &str) -> Response<BoxBody<Byte
         .expect("valid response")
 }
 
+/// Reject a payload with a JSON-RPC error response, `413` when it is too large and `400` otherwise.
+pub(crate) fn payload_error_response(error: &PayloadError, payload: &[u8]) -> BoxResponse {
+    tracing::warn!("Rejected request body: {error}");
+    let status = match error {
+        PayloadError::TooLarge { .. } => http::StatusCode::PAYLOAD_TOO_LARGE,
+        _ => http::StatusCode::BAD_REQUEST,
+    };
+    let body = serde_json::to_vec(&error.error_response(payload)).expect("valid json");
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, JSON_MIME_TYPE)
+        .body(Full::new(Bytes::from(body)).boxed())
+        .expect("valid response")
+}
+
+/// Read a request body up to the size limit, then parse it as a message.
 pub(crate) async fn expect_json<B>(
     body: B,
+    limits: &PayloadLimits,
 ) -> Result<ClientJsonRpcMessage, Response<BoxBody<Bytes, Infallible>>>
 where
     B: Body + Send + 'static,
     B::Error: Display,
 {
-    match body.collect().await {
-        Ok(bytes) => {
-            match serde_json::from_reader::<_, ClientJsonRpcMessage>(bytes.aggregate().reader()) {
-                Ok(message) => Ok(message),
-                Err(e) => {
-                    let response = Response::builder()
-                        .status(http::StatusCode::UNSUPPORTED_MEDIA_TYPE)
-                        .body(
-                            Full::new(Bytes::from(format!("fail to deserialize request body {e}")))
-                                .boxed(),
-                        )
-                        .expect("valid response");
-                    Err(response)
-                }
-            }
-        }
-        Err(e) => {
-            let response = Response::builder()
+    let mut body = std::pin::pin!(body);
+    let mut payload = BytesMut::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| {
+            Response::builder()
                 .status(http::StatusCode::INTERNAL_SERVER_ERROR)
                 .body(Full::new(Bytes::from(format!("Failed to read request body: {e}"))).boxed())
-                .expect("valid response");
-            Err(response)
+                .expect("valid response")
+        })?;
+        if let Ok(mut data) = frame.into_data() {
+            if payload.len() + data.remaining() > limits.max_message_bytes {
+                let error = PayloadError::TooLarge {
+                    limit: limits.max_message_bytes,
+                };
+                return Err(payload_error_response(&error, &[]));
+            }
+            payload.put(&mut data);
         }
     }
+    limits
+        .parse(&payload)
+        .map_err(|error| payload_error_response(&error, &payload))
 }
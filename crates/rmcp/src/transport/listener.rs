@@ -0,0 +1,274 @@
+//! Serve every connection accepted by a listener.
+//!
+//! [`ListenerServer`] accepts connections from a [`Listener`], like a [`TcpListener`] or a
+//! [`UnixListener`](tokio::net::UnixListener), and serves each of them with a new service over
+//! an [`AsyncRwTransport`].
+//!
+//! Every request and notification received over a connection carries its [`ConnectionInfo`] in
+//! its extensions, so a handler can tell who it's talking to or drop the connection.
+//!
+//! ```rust,no_run
+//! # use rmcp::{ServerHandler, transport::listener::ListenerServer};
+//! # #[derive(Clone)] struct Counter;
+//! # impl ServerHandler for Counter {}
+//! # async fn run() -> std::io::Result<()> {
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:8001").await?;
+//! let ct = ListenerServer::new(listener)
+//!     .max_connections(64)
+//!     .with_service(|| Counter);
+//!
+//! tokio::signal::ctrl_c().await?;
+//! ct.cancel();
+//! # Ok(())
+//! # }
+//! ```
+use std::{fmt::Debug, io, sync::Arc, time::Duration};
+
+use futures::{Stream, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf},
+    net::TcpListener,
+    sync::Semaphore,
+    task::JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use super::{Transport, async_rw::AsyncRwTransport};
+use crate::{
+    RoleServer, Service,
+    service::{RxJsonRpcMessage, ServiceExt, TxJsonRpcMessage},
+};
+
+/// Delay before accepting again after the listener failed, e.g. when running out of file descriptors.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A source of incoming connections.
+pub trait Listener: Send + 'static {
+    type Io: AsyncRead + AsyncWrite + Send + 'static;
+    type Addr: Clone + Debug + Send + Sync + 'static;
+    /// Accept the next connection, `None` once no more connections will come.
+    fn accept(&mut self)
+    -> impl Future<Output = io::Result<Option<(Self::Io, Self::Addr)>>> + Send;
+}
+
+impl Listener for TcpListener {
+    type Io = tokio::net::TcpStream;
+    type Addr = std::net::SocketAddr;
+    async fn accept(&mut self) -> io::Result<Option<(Self::Io, Self::Addr)>> {
+        TcpListener::accept(self).await.map(Some)
+    }
+}
+
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+impl Listener for tokio::net::UnixListener {
+    type Io = tokio::net::UnixStream;
+    type Addr = tokio::net::unix::SocketAddr;
+    async fn accept(&mut self) -> io::Result<Option<(Self::Io, Self::Addr)>> {
+        tokio::net::UnixListener::accept(self).await.map(Some)
+    }
+}
+
+/// A [`Listener`] over a stream of already established connections, which have no address.
+pub struct StreamListener<S> {
+    stream: S,
+}
+
+impl<S, Io> StreamListener<S>
+where
+    S: Stream<Item = Io> + Send + Unpin + 'static,
+    Io: AsyncRead + AsyncWrite + Send + 'static,
+{
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+}
+
+impl<S, Io> Listener for StreamListener<S>
+where
+    S: Stream<Item = Io> + Send + Unpin + 'static,
+    Io: AsyncRead + AsyncWrite + Send + 'static,
+{
+    type Io = Io;
+    type Addr = ();
+    async fn accept(&mut self) -> io::Result<Option<(Self::Io, Self::Addr)>> {
+        Ok(self.stream.next().await.map(|io| (io, ())))
+    }
+}
+
+/// A connection accepted by a [`ListenerServer`], available in the extensions of every request
+/// and notification received over it.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo<A> {
+    /// sequence number of the connection, unique for a server
+    pub id: u64,
+    pub peer_addr: A,
+    /// cancel to close this connection only
+    pub ct: CancellationToken,
+}
+
+#[derive(Debug, Clone)]
+pub struct ListenerServerConfig {
+    /// maximum number of connections served at once, further connections wait to be accepted
+    pub max_connections: Option<usize>,
+    /// cancel to stop accepting connections and close the served ones
+    pub ct: CancellationToken,
+}
+
+impl Default for ListenerServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: None,
+            ct: CancellationToken::new(),
+        }
+    }
+}
+
+pub struct ListenerServer<L> {
+    listener: L,
+    pub config: ListenerServerConfig,
+}
+
+impl<L: Listener> ListenerServer<L> {
+    pub fn new(listener: L) -> Self {
+        Self::with_config(listener, ListenerServerConfig::default())
+    }
+
+    pub fn with_config(listener: L, config: ListenerServerConfig) -> Self {
+        Self { listener, config }
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = Some(max_connections);
+        self
+    }
+
+    pub fn cancel(&self) {
+        self.config.ct.cancel();
+    }
+
+    /// Serve in the background, returns the token to stop the server.
+    pub fn with_service<S, F>(self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        let ct = self.config.ct.clone();
+        tokio::spawn(self.serve(service_provider));
+        ct
+    }
+
+    /// Accept and serve connections until cancelled or the listener is exhausted, then wait for
+    /// the served connections to close.
+    pub async fn serve<S, F>(mut self, service_provider: F)
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        let ct = self.config.ct.clone();
+        let permits = self
+            .config
+            .max_connections
+            .map(|max| Arc::new(Semaphore::new(max)));
+        let mut connections = JoinSet::new();
+        let mut next_id = 0;
+        loop {
+            let permit = match &permits {
+                Some(permits) => tokio::select! {
+                    permit = permits.clone().acquire_owned() => Some(permit.expect("semaphore is never closed")),
+                    _ = ct.cancelled() => break,
+                },
+                None => None,
+            };
+            let (io, peer_addr) = tokio::select! {
+                accepted = self.listener.accept() => match accepted {
+                    Ok(Some(accepted)) => accepted,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to accept connection");
+                        tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                        continue;
+                    }
+                },
+                _ = ct.cancelled() => break,
+            };
+            // reap the finished connections
+            while connections.try_join_next().is_some() {}
+
+            let info = ConnectionInfo {
+                id: next_id,
+                peer_addr,
+                ct: ct.child_token(),
+            };
+            next_id += 1;
+            let span = tracing::info_span!("connection", id = info.id, peer_addr = ?info.peer_addr);
+            let service = service_provider();
+            connections.spawn(
+                async move {
+                    let _permit = permit;
+                    let ct = info.ct.clone();
+                    let transport = ConnectionTransport::new(io, info);
+                    // the initialization doesn't observe the token
+                    let server = tokio::select! {
+                        server = service.serve_with_ct(transport, ct.clone()) => server,
+                        _ = ct.cancelled() => return,
+                    };
+                    match server {
+                        Ok(server) => match server.waiting().await {
+                            Ok(reason) => tracing::debug!(?reason, "connection closed"),
+                            Err(e) => tracing::error!(error = %e, "connection task failed"),
+                        },
+                        Err(e) => tracing::warn!(error = %e, "failed to initialize connection"),
+                    }
+                }
+                .instrument(span),
+            );
+        }
+        while connections.join_next().await.is_some() {}
+    }
+}
+
+/// An [`AsyncRwTransport`] which attaches the [`ConnectionInfo`] to every received message.
+struct ConnectionTransport<Io: AsyncRead + AsyncWrite, A> {
+    inner: AsyncRwTransport<RoleServer, ReadHalf<Io>, WriteHalf<Io>>,
+    info: ConnectionInfo<A>,
+}
+
+impl<Io, A> ConnectionTransport<Io, A>
+where
+    Io: AsyncRead + AsyncWrite + Send + 'static,
+{
+    fn new(io: Io, info: ConnectionInfo<A>) -> Self {
+        let (read, write) = tokio::io::split(io);
+        Self {
+            inner: AsyncRwTransport::new_server(read, write),
+            info,
+        }
+    }
+}
+
+impl<Io, A> Transport<RoleServer> for ConnectionTransport<Io, A>
+where
+    Io: AsyncRead + AsyncWrite + Send + 'static,
+    A: Clone + Send + Sync + 'static,
+{
+    type Error = io::Error;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<RoleServer>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        self.inner.send(item)
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<RoleServer>> {
+        let mut message = self.inner.receive().await?;
+        message.insert_extension(self.info.clone());
+        Some(message)
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        Transport::<RoleServer>::close(&mut self.inner).await
+    }
+}
@@ -0,0 +1,110 @@
+//! In-memory transports, for connecting a client and a server in the same process.
+//!
+//! Messages are still serialized, so a service behaves as it would over a real connection:
+//! extensions don't cross the transport, a message which can't be serialized fails to send,
+//! and a received message which can't be deserialized is logged and dropped.
+//!
+//! ```rust,no_run
+//! # use rmcp::{ServerHandler, ServiceExt, transport::memory};
+//! # #[derive(Clone)] struct Counter;
+//! # impl ServerHandler for Counter {}
+//! # async fn run() -> anyhow::Result<()> {
+//! let (client_transport, server_transport) = memory::pair();
+//! let (server, client) = tokio::try_join!(
+//!     async { Ok::<_, anyhow::Error>(Counter.serve(server_transport).await?) },
+//!     async { Ok(().serve(client_transport).await?) },
+//! )?;
+//! let tools = client.list_all_tools().await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::marker::PhantomData;
+
+use tokio::sync::mpsc;
+
+use super::Transport;
+use crate::service::{RxJsonRpcMessage, ServiceRole, TxJsonRpcMessage};
+
+/// Number of messages buffered in each direction by [`pair`].
+pub const DEFAULT_CAPACITY: usize = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryTransportError {
+    #[error("the other end of the transport is closed")]
+    Closed,
+    #[error("failed to serialize message: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
+
+/// One end of an in-memory connection, created by [`pair`].
+pub struct MemoryTransport<R: ServiceRole> {
+    tx: Option<mpsc::Sender<serde_json::Value>>,
+    rx: mpsc::Receiver<serde_json::Value>,
+    _role: PhantomData<fn() -> R>,
+}
+
+impl<R: ServiceRole> MemoryTransport<R> {
+    /// Create two connected ends, for any pair of roles.
+    ///
+    /// [`pair`] is the usual client to server connection.
+    pub fn connect<P: ServiceRole>(capacity: usize) -> (Self, MemoryTransport<P>) {
+        let (a_tx, a_rx) = mpsc::channel(capacity);
+        let (b_tx, b_rx) = mpsc::channel(capacity);
+        (
+            Self {
+                tx: Some(a_tx),
+                rx: b_rx,
+                _role: PhantomData,
+            },
+            MemoryTransport {
+                tx: Some(b_tx),
+                rx: a_rx,
+                _role: PhantomData,
+            },
+        )
+    }
+}
+
+impl<R: ServiceRole> Transport<R> for MemoryTransport<R> {
+    type Error = MemoryTransportError;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<R>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        let tx = self.tx.clone();
+        let value = serde_json::to_value(item);
+        async move {
+            let tx = tx.ok_or(MemoryTransportError::Closed)?;
+            tx.send(value?)
+                .await
+                .map_err(|_| MemoryTransportError::Closed)
+        }
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<R>> {
+        loop {
+            let value = self.rx.recv().await?;
+            match serde_json::from_value(value) {
+                Ok(message) => return Some(message),
+                Err(e) => tracing::error!("failed to deserialize message: {e}"),
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.tx = None;
+        self.rx.close();
+        Ok(())
+    }
+}
+
+/// Create a connected client and server transport pair.
+#[cfg(all(feature = "client", feature = "server"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "client", feature = "server"))))]
+pub fn pair() -> (
+    MemoryTransport<crate::RoleClient>,
+    MemoryTransport<crate::RoleServer>,
+) {
+    MemoryTransport::connect(DEFAULT_CAPACITY)
+}
@@ -0,0 +1,479 @@
+//! Record the traffic of a transport, and replay it against a live service.
+//!
+//! [`RecordingTransport`] wraps any transport and writes every message it sends or receives to a
+//! JSONL file, one [`RecordedMessage`] per line:
+//!
+//! ```json
+//! {"timestamp":"2025-07-01T09:30:00.125Z","direction":"received","message":{"jsonrpc":"2.0","id":0,"method":"ping"}}
+//! ```
+//!
+//! [`ReplayTransport`] plays back the received messages of a [`Recording`] to a service, and
+//! compares the messages the service sends with the recorded ones. The differences are
+//! collected in a [`ReplayReport`], so a recording from a bug report can become a regression
+//! test:
+//!
+//! ```rust,no_run
+//! # use rmcp::{ServerHandler, ServiceExt, transport::record::{Recording, ReplayTransport}};
+//! # #[derive(Clone)] struct Counter;
+//! # impl ServerHandler for Counter {}
+//! # async fn run() -> anyhow::Result<()> {
+//! let recording = Recording::from_path("tests/recordings/list_tools.jsonl")?;
+//! let transport = ReplayTransport::new(recording)?.ignore("/result/serverInfo/version");
+//! let report = transport.report();
+//!
+//! let server = Counter.serve(transport).await?;
+//! server.waiting().await?;
+//! assert!(report.divergences().is_empty(), "{:#?}", report.divergences());
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Transport;
+use crate::service::{QuitReason, RxJsonRpcMessage, ServiceRole, TxJsonRpcMessage};
+
+/// How long a replay waits for the service to send a recorded message.
+pub const DEFAULT_REPLAY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// sent by the recorded service
+    Sent,
+    /// received by the recorded service
+    Received,
+}
+
+impl Direction {
+    pub fn reversed(self) -> Self {
+        match self {
+            Direction::Sent => Direction::Received,
+            Direction::Received => Direction::Sent,
+        }
+    }
+}
+
+/// A line of a recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub timestamp: DateTime<Utc>,
+    pub direction: Direction,
+    pub message: Value,
+}
+
+enum RecorderCommand {
+    Record(RecordedMessage),
+    Flush(tokio::sync::oneshot::Sender<()>),
+}
+
+/// Wraps a transport and records its traffic.
+///
+/// The messages are written by a dedicated thread, so a slow file doesn't block the executor.
+/// Recording errors are logged and don't affect the transport.
+pub struct RecordingTransport<T> {
+    inner: T,
+    recorder: std::sync::mpsc::Sender<RecorderCommand>,
+}
+
+impl<T> RecordingTransport<T> {
+    /// Record to a file, truncating it.
+    pub fn to_path(transport: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(transport, BufWriter::new(File::create(path)?))
+    }
+
+    /// Record to any writer, flushed whenever the recorder catches up and when the transport is
+    /// closed.
+    ///
+    /// Fails if the recorder thread can't be spawned.
+    pub fn new<W: Write + Send + 'static>(transport: T, writer: W) -> io::Result<Self> {
+        let (recorder, commands) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("rmcp-recorder".into())
+            .spawn(move || write_records(writer, commands))?;
+        Ok(Self {
+            inner: transport,
+            recorder,
+        })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&self, direction: Direction, message: &impl Serialize) {
+        match serde_json::to_value(message) {
+            Ok(message) => {
+                let line = RecordedMessage {
+                    timestamp: Utc::now(),
+                    direction,
+                    message,
+                };
+                // the recorder only stops once the transport is dropped
+                let _ = self.recorder.send(RecorderCommand::Record(line));
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to record message"),
+        }
+    }
+}
+
+fn write_records<W: Write>(mut writer: W, commands: std::sync::mpsc::Receiver<RecorderCommand>) {
+    fn warn(result: io::Result<()>) {
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "failed to record message");
+        }
+    }
+    while let Ok(command) = commands.recv() {
+        let mut next = Some(command);
+        // write everything queued before flushing
+        while let Some(command) = next {
+            match command {
+                RecorderCommand::Record(line) => warn(
+                    serde_json::to_writer(&mut writer, &line)
+                        .map_err(io::Error::from)
+                        .and_then(|()| writer.write_all(b"\n")),
+                ),
+                RecorderCommand::Flush(done) => {
+                    warn(writer.flush());
+                    let _ = done.send(());
+                }
+            }
+            next = commands.try_recv().ok();
+        }
+        warn(writer.flush());
+    }
+}
+
+impl<R, T> Transport<R> for RecordingTransport<T>
+where
+    R: ServiceRole,
+    T: Transport<R>,
+{
+    type Error = T::Error;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<R>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        self.record(Direction::Sent, &item);
+        self.inner.send(item)
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<R>> {
+        let message = self.inner.receive().await?;
+        self.record(Direction::Received, &message);
+        Some(message)
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        let (done, flushed) = tokio::sync::oneshot::channel();
+        if self.recorder.send(RecorderCommand::Flush(done)).is_ok() {
+            let _ = flushed.await;
+        }
+        self.inner.close().await
+    }
+
+    fn quit_reason(&mut self) -> Option<QuitReason> {
+        self.inner.quit_reason()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("failed to read recording: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid recording at line {line}: {source}")]
+    Invalid {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("recorded message {index} is not a valid received message: {source}")]
+    InvalidMessage {
+        index: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// The messages of a recording, in order.
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    pub messages: Vec<RecordedMessage>,
+}
+
+impl Recording {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a recording, blank lines are skipped.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, RecordingError> {
+        let mut messages = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message =
+                serde_json::from_str(&line).map_err(|source| RecordingError::Invalid {
+                    line: index + 1,
+                    source,
+                })?;
+            messages.push(message);
+        }
+        Ok(Self { messages })
+    }
+
+    /// The same traffic from the point of view of the other side.
+    ///
+    /// A recording made by a client can be replayed against a server once reversed.
+    pub fn reversed(mut self) -> Self {
+        for message in &mut self.messages {
+            message.direction = message.direction.reversed();
+        }
+        self
+    }
+}
+
+/// A difference between the recorded and the replayed traffic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Divergence {
+    /// the service sent a different message than the recorded one
+    Mismatch {
+        /// index of the message in the recording
+        index: usize,
+        expected: Value,
+        actual: Value,
+    },
+    /// the service sent a message which wasn't expected at this point
+    Unexpected { actual: Value },
+    /// the service didn't send a recorded message in time
+    Missing { index: usize, expected: Value },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Divergence::Mismatch {
+                index,
+                expected,
+                actual,
+            } => write!(f, "message {index}: expected {expected}, sent {actual}"),
+            Divergence::Unexpected { actual } => write!(f, "unexpected message {actual}"),
+            Divergence::Missing { index, expected } => {
+                write!(f, "message {index}: expected {expected}, nothing sent")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    divergences: Vec<Divergence>,
+    finished: bool,
+}
+
+/// The outcome of a replay, shared with its [`ReplayTransport`].
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl ReplayReport {
+    pub fn divergences(&self) -> Vec<Divergence> {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .divergences
+            .clone()
+    }
+
+    /// Whether every recorded message was replayed or waited for.
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().expect("lock poisoned").finished
+    }
+
+    fn push(&self, divergence: Divergence) {
+        tracing::warn!(%divergence, "replay diverged");
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .divergences
+            .push(divergence);
+    }
+}
+
+enum Step<R: ServiceRole> {
+    Receive(RxJsonRpcMessage<R>),
+    Expect { index: usize, expected: Value },
+}
+
+/// Plays back the received messages of a [`Recording`] to a service, and checks what it sends.
+///
+/// A received message is delivered once the service sent every message recorded before it, so
+/// the replay doesn't depend on timing. Messages the service sends between two received
+/// messages may come in any order. The transport closes after the last recorded message.
+pub struct ReplayTransport<R: ServiceRole> {
+    steps: VecDeque<Step<R>>,
+    ignored: Vec<String>,
+    timeout: Duration,
+    deadline: Option<tokio::time::Instant>,
+    report: ReplayReport,
+}
+
+impl<R: ServiceRole> ReplayTransport<R> {
+    /// Fails if a received message isn't a valid message for this role.
+    pub fn new(recording: Recording) -> Result<Self, RecordingError> {
+        let steps = recording
+            .messages
+            .into_iter()
+            .enumerate()
+            .map(|(index, recorded)| match recorded.direction {
+                Direction::Received => serde_json::from_value(recorded.message)
+                    .map(Step::Receive)
+                    .map_err(|source| RecordingError::InvalidMessage { index, source }),
+                Direction::Sent => Ok(Step::Expect {
+                    index,
+                    expected: recorded.message,
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            steps,
+            ignored: Vec::new(),
+            timeout: DEFAULT_REPLAY_TIMEOUT,
+            deadline: None,
+            report: ReplayReport::default(),
+        })
+    }
+
+    /// Leave out the value at this JSON pointer when comparing messages, like a version or a
+    /// timestamp which changes between runs.
+    pub fn ignore(mut self, pointer: impl Into<String>) -> Self {
+        self.ignored.push(pointer.into());
+        self
+    }
+
+    /// How long to wait for the service to send a recorded message, see
+    /// [`DEFAULT_REPLAY_TIMEOUT`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn report(&self) -> ReplayReport {
+        self.report.clone()
+    }
+
+    fn normalize(&self, mut value: Value) -> Value {
+        for pointer in &self.ignored {
+            let Some((parent, key)) = pointer.rsplit_once('/') else {
+                continue;
+            };
+            let key = key.replace("~1", "/").replace("~0", "~");
+            match value.pointer_mut(parent) {
+                Some(Value::Object(object)) => {
+                    object.remove(&key);
+                }
+                Some(Value::Array(array)) => {
+                    if let Ok(index) = key.parse::<usize>() {
+                        if index < array.len() {
+                            array[index] = Value::Null;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        value
+    }
+}
+
+impl<R: ServiceRole> Transport<R> for ReplayTransport<R> {
+    type Error = std::convert::Infallible;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<R>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        let actual = match serde_json::to_value(&item) {
+            Ok(actual) => self.normalize(actual),
+            Err(e) => {
+                tracing::error!(error = %e, "failed to serialize message");
+                return std::future::ready(Ok(()));
+            }
+        };
+        // the messages expected before the next received one
+        let pending = self
+            .steps
+            .iter()
+            .take_while(|step| matches!(step, Step::Expect { .. }))
+            .count();
+        let mut first = None;
+        for position in 0..pending {
+            let Step::Expect { expected, .. } = &self.steps[position] else {
+                unreachable!()
+            };
+            if self.normalize(expected.clone()) == actual {
+                self.steps.remove(position);
+                self.deadline = None;
+                return std::future::ready(Ok(()));
+            }
+            first.get_or_insert(position);
+        }
+        match first.and_then(|position| self.steps.remove(position)) {
+            Some(Step::Expect { index, expected }) => {
+                self.deadline = None;
+                self.report.push(Divergence::Mismatch {
+                    index,
+                    expected: self.normalize(expected),
+                    actual,
+                })
+            }
+            _ => self.report.push(Divergence::Unexpected { actual }),
+        }
+        std::future::ready(Ok(()))
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<R>> {
+        loop {
+            match self.steps.front()? {
+                Step::Receive(_) => {
+                    let Some(Step::Receive(message)) = self.steps.pop_front() else {
+                        unreachable!()
+                    };
+                    return Some(message);
+                }
+                Step::Expect { .. } => {
+                    // kept across calls, this future is dropped whenever the service sends
+                    let deadline = *self
+                        .deadline
+                        .get_or_insert_with(|| tokio::time::Instant::now() + self.timeout);
+                    tokio::time::sleep_until(deadline).await;
+                    self.deadline = None;
+                    if let Some(Step::Expect { index, expected }) = self.steps.pop_front() {
+                        let expected = self.normalize(expected);
+                        self.report.push(Divergence::Missing { index, expected });
+                    }
+                }
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.report.state.lock().expect("lock poisoned");
+        state.finished = self.steps.is_empty();
+        Ok(())
+    }
+}
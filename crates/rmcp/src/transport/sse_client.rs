@@ -2,8 +2,7 @@
 use std::{pin::Pin, sync::Arc};
 
 use futures::{StreamExt, future::BoxFuture};
-use http::Uri;
-use reqwest::header::HeaderValue;
+use http::{HeaderValue, Uri};
 use sse_stream::Error as SseError;
 use thiserror::Error;
 
@@ -39,6 +38,7 @@ pub enum SseTransportError<E: std::error::Error + Send + Sync + 'static> {
     InvalidUriParts(#[from] http::uri::InvalidUriParts),
 }
 
+#[cfg(feature = "__reqwest")]
 impl From<reqwest::Error> for SseTransportError<reqwest::Error> {
     fn from(e: reqwest::Error) -> Self {
         SseTransportError::Client(e)
@@ -1,11 +1,12 @@
 use std::{collections::HashMap, io, net::SocketAddr, sync::Arc, time::Duration};
 
 use axum::{
-    Extension, Json, Router,
+    Extension, Router,
+    body::Body,
     extract::{NestedPath, Query, State},
     http::{StatusCode, request::Parts},
     response::{
-        Response,
+        IntoResponse, Response,
         sse::{Event, KeepAlive, Sse},
     },
     routing::{get, post},
@@ -19,7 +20,12 @@ use crate::{
     RoleServer, Service,
     model::ClientJsonRpcMessage,
     service::{RxJsonRpcMessage, TxJsonRpcMessage, serve_directly_with_ct},
-    transport::common::server_side_http::{DEFAULT_AUTO_PING_INTERVAL, SessionId, session_id},
+    transport::{
+        PayloadLimits,
+        common::server_side_http::{
+            DEFAULT_AUTO_PING_INTERVAL, SessionId, expect_json, insert_request_parts, session_id,
+        },
+    },
 };
 
 type TxStore =
@@ -32,12 +38,14 @@ struct App {
     transport_tx: tokio::sync::mpsc::UnboundedSender<SseServerTransport>,
     post_path: Arc<str>,
     sse_ping_interval: Duration,
+    limits: PayloadLimits,
 }
 
 impl App {
     pub fn new(
         post_path: String,
         sse_ping_interval: Duration,
+        limits: PayloadLimits,
     ) -> (
         Self,
         tokio::sync::mpsc::UnboundedReceiver<SseServerTransport>,
@@ -49,6 +57,7 @@ impl App {
                 transport_tx,
                 post_path: post_path.into(),
                 sse_ping_interval,
+                limits,
             },
             transport_rx,
         )
@@ -65,19 +74,22 @@ async fn post_event_handler(
     State(app): State<App>,
     Query(PostEventQuery { session_id }): Query<PostEventQuery>,
     parts: Parts,
-    Json(mut message): Json<ClientJsonRpcMessage>,
-) -> Result<StatusCode, StatusCode> {
+    body: Body,
+) -> Result<StatusCode, Response> {
+    let mut message = expect_json(body, &app.limits)
+        .await
+        .map_err(IntoResponse::into_response)?;
     tracing::debug!(session_id, ?parts, ?message, "new client message");
     let tx = {
         let rg = app.txs.read().await;
         rg.get(session_id.as_str())
-            .ok_or(StatusCode::NOT_FOUND)?
+            .ok_or(StatusCode::NOT_FOUND.into_response())?
             .clone()
     };
-    message.insert_extension(parts);
+    insert_request_parts(&mut message, parts);
     if tx.send(message).await.is_err() {
         tracing::error!("send message error");
-        return Err(StatusCode::GONE);
+        return Err(StatusCode::GONE.into_response());
     }
     Ok(StatusCode::ACCEPTED)
 }
@@ -221,6 +233,8 @@ pub struct SseServerConfig {
     pub post_path: String,
     pub ct: CancellationToken,
     pub sse_keep_alive: Option<Duration>,
+    /// Limits on the posted messages, a message over them is rejected with a JSON-RPC error.
+    pub limits: PayloadLimits,
 }
 
 #[derive(Debug)]
@@ -237,6 +251,7 @@ impl SseServer {
             post_path: "/message".to_string(),
             ct: CancellationToken::new(),
             sse_keep_alive: None,
+            limits: PayloadLimits::default(),
         })
         .await
     }
@@ -263,6 +278,7 @@ impl SseServer {
         let (app, transport_rx) = App::new(
             config.post_path.clone(),
             config.sse_keep_alive.unwrap_or(DEFAULT_AUTO_PING_INTERVAL),
+            config.limits,
         );
         let router = Router::new()
             .route(&config.sse_path, get(sse_handler))
@@ -1,15 +1,19 @@
 use std::{borrow::Cow, sync::Arc, time::Duration};
 
-use futures::{Stream, StreamExt, future::BoxFuture, stream::BoxStream};
+use futures::{FutureExt, Stream, StreamExt, future::BoxFuture, stream::BoxStream};
 pub use sse_stream::Error as SseError;
 use sse_stream::Sse;
 use thiserror::Error;
+use tokio::task::JoinSet;
 use tokio_util::sync::CancellationToken;
 
 use super::common::client_side_sse::{ExponentialBackoff, SseRetryPolicy, SseStreamReconnect};
 use crate::{
     RoleClient,
-    model::{ClientJsonRpcMessage, ServerJsonRpcMessage},
+    model::{
+        ClientJsonRpcMessage, ErrorCode, ErrorData, InitializeResult, RequestId,
+        ServerJsonRpcMessage, ServerResult,
+    },
     transport::{
         common::client_side_sse::SseAutoReconnectStream,
         worker::{Worker, WorkerQuitReason, WorkerSendRequest, WorkerTransport},
@@ -36,6 +40,8 @@ pub enum StreamableHttpError<E: std::error::Error + Send + Sync + 'static> {
     SeverDoesNotSupportSse,
     #[error("Server does not support delete session")]
     SeverDoesNotSupportDeleteSession,
+    #[error("Session expired")]
+    SessionExpired,
     #[error("Tokio join error: {0}")]
     TokioJoinError(#[from] tokio::task::JoinError),
     #[error("Deserialize error: {0}")]
@@ -48,6 +54,7 @@ pub enum StreamableHttpError<E: std::error::Error + Send + Sync + 'static> {
     Auth(#[from] crate::transport::auth::AuthError),
 }
 
+#[cfg(feature = "__reqwest")]
 impl From<reqwest::Error> for StreamableHttpError<reqwest::Error> {
     fn from(e: reqwest::Error) -> Self {
         StreamableHttpError::Client(e)
@@ -205,7 +212,94 @@ impl<C: StreamableHttpClient> StreamableHttpClientWorker<C> {
     }
 }
 
+/// A session with the server, replaced when it expires and is initialized again.
+struct Session {
+    id: Option<Arc<str>>,
+    /// cancels the event streams of this session
+    ct: CancellationToken,
+    generation: u64,
+}
+
+/// How an event stream ended.
+struct StreamOutcome<E: std::error::Error + Send + Sync + 'static> {
+    generation: u64,
+    /// the request whose response is expected on the stream, `None` for the common stream
+    request_id: Option<RequestId>,
+    /// whether the response was received
+    result: Result<bool, StreamableHttpError<E>>,
+}
+
+/// What the worker keeps across sessions.
+struct WorkerState<E: std::error::Error + Send + Sync + 'static> {
+    /// the event streams of the current session
+    streams: JoinSet<StreamOutcome<E>>,
+    sse_worker_tx: tokio::sync::mpsc::Sender<ServerJsonRpcMessage>,
+    /// the session deleted when the transport closes
+    current_session_id: Arc<std::sync::Mutex<Option<Arc<str>>>>,
+    transport_task_ct: CancellationToken,
+}
+
+/// A new session initialized after the previous one expired.
+#[derive(Debug, Clone)]
+pub struct ReinitializedSession {
+    pub previous_session_id: Option<Arc<str>>,
+    pub session_id: Option<Arc<str>>,
+    pub server_info: InitializeResult,
+}
+
+type ReinitializedCallback = Arc<dyn Fn(&ReinitializedSession) + Send + Sync>;
+
+/// What the client does when the server reports its session expired.
+///
+/// By default the requests fail. When re-initializing, the initialization is replayed to get a
+/// new session, the request is sent again and the callback lets the application restore the
+/// state of the old session, like its resource subscriptions.
+#[derive(Clone, Default)]
+pub struct SessionRecovery {
+    pub reinitialize: bool,
+    on_reinitialized: Option<ReinitializedCallback>,
+}
+
+impl SessionRecovery {
+    pub fn reinitialize() -> Self {
+        Self {
+            reinitialize: true,
+            on_reinitialized: None,
+        }
+    }
+
+    /// Called once a new session is initialized.
+    pub fn on_reinitialized(
+        mut self,
+        callback: impl Fn(&ReinitializedSession) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_reinitialized = Some(Arc::new(callback));
+        self
+    }
+}
+
+impl std::fmt::Debug for SessionRecovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionRecovery")
+            .field("reinitialize", &self.reinitialize)
+            .field("on_reinitialized", &self.on_reinitialized.is_some())
+            .finish()
+    }
+}
+
+fn response_lost(id: RequestId) -> ServerJsonRpcMessage {
+    ServerJsonRpcMessage::error(
+        ErrorData::new(
+            ErrorCode::RESPONSE_LOST,
+            "the response was lost with its event stream",
+            None,
+        ),
+        id,
+    )
+}
+
 impl<C: StreamableHttpClient> StreamableHttpClientWorker<C> {
+    /// Forward the messages of a stream, returns whether a response was received.
     async fn execute_sse_stream(
         sse_stream: impl Stream<Item = Result<ServerJsonRpcMessage, StreamableHttpError<C::Error>>>
         + Send
@@ -213,7 +307,7 @@ impl<C: StreamableHttpClient> StreamableHttpClientWorker<C> {
         sse_worker_tx: tokio::sync::mpsc::Sender<ServerJsonRpcMessage>,
         close_on_response: bool,
         ct: CancellationToken,
-    ) -> Result<(), StreamableHttpError<C::Error>> {
+    ) -> Result<bool, StreamableHttpError<C::Error>> {
         let mut sse_stream = std::pin::pin!(sse_stream);
         loop {
             let message = tokio::select! {
@@ -228,7 +322,10 @@ impl<C: StreamableHttpClient> StreamableHttpClientWorker<C> {
             let Some(message) = message.transpose()? else {
                 break;
             };
-            let is_response = matches!(message, ServerJsonRpcMessage::Response(_));
+            let is_response = matches!(
+                message,
+                ServerJsonRpcMessage::Response(_) | ServerJsonRpcMessage::Error(_)
+            );
             let yield_result = sse_worker_tx.send(message).await;
             if yield_result.is_err() {
                 tracing::trace!("streamable http transport worker dropped, exiting");
@@ -236,9 +333,161 @@ impl<C: StreamableHttpClient> StreamableHttpClientWorker<C> {
             }
             if close_on_response && is_response {
                 tracing::debug!("got response, closing sse stream");
-                break;
+                return Ok(true);
             }
         }
+        Ok(false)
+    }
+
+    fn spawn_sse_stream(
+        &self,
+        streams: &mut JoinSet<StreamOutcome<C::Error>>,
+        stream: BoxedSseStream,
+        session: &Session,
+        request_id: Option<RequestId>,
+        sse_worker_tx: &tokio::sync::mpsc::Sender<ServerJsonRpcMessage>,
+    ) {
+        let close_on_response = request_id.is_some();
+        let ct = session.ct.child_token();
+        let execution = if let Some(session_id) = &session.id {
+            let sse_stream = SseAutoReconnectStream::new(
+                stream,
+                StreamableHttpClientReconnect {
+                    client: self.client.clone(),
+                    session_id: session_id.clone(),
+                    uri: self.config.uri.clone(),
+                },
+                self.config.retry_config.clone(),
+            )
+            .resume_on_end();
+            Self::execute_sse_stream(sse_stream, sse_worker_tx.clone(), close_on_response, ct)
+                .boxed()
+        } else {
+            let sse_stream = SseAutoReconnectStream::never_reconnect(
+                stream,
+                StreamableHttpError::<C::Error>::UnexpectedEndOfStream,
+            );
+            Self::execute_sse_stream(sse_stream, sse_worker_tx.clone(), close_on_response, ct)
+                .boxed()
+        };
+        let generation = session.generation;
+        streams.spawn(async move {
+            StreamOutcome {
+                generation,
+                request_id,
+                result: execution.await,
+            }
+        });
+    }
+
+    /// Open the general purpose event stream of the session, if the server has one.
+    // `&mut self` keeps the future `Send` without requiring the client to be `Sync`
+    async fn spawn_common_stream(
+        &mut self,
+        streams: &mut JoinSet<StreamOutcome<C::Error>>,
+        session: &Session,
+        sse_worker_tx: &tokio::sync::mpsc::Sender<ServerJsonRpcMessage>,
+    ) -> Result<(), WorkerQuitReason> {
+        let Some(session_id) = &session.id else {
+            return Ok(());
+        };
+        match self
+            .client
+            .get_stream(self.config.uri.clone(), session_id.clone(), None, None)
+            .await
+        {
+            Ok(stream) => {
+                self.spawn_sse_stream(streams, stream, session, None, sse_worker_tx);
+                tracing::debug!("got common stream");
+                Ok(())
+            }
+            Err(StreamableHttpError::SeverDoesNotSupportSse) => {
+                tracing::debug!("server doesn't support sse, skip common stream");
+                Ok(())
+            }
+            Err(e) => {
+                // fail to get common stream
+                tracing::error!("fail to get common stream: {e}");
+                Err(WorkerQuitReason::fatal(
+                    "fail to get general purpose event stream",
+                    "get general purpose event stream",
+                ))
+            }
+        }
+    }
+
+    /// Replace an expired session by a new one.
+    ///
+    /// The streams of the old session are closed, so the requests still waiting for a response
+    /// fail as [`ErrorCode::RESPONSE_LOST`].
+    async fn reinitialize(
+        &mut self,
+        session: &mut Session,
+        initialize_request: &ClientJsonRpcMessage,
+        initialized_notification: &ClientJsonRpcMessage,
+        state: &mut WorkerState<C::Error>,
+    ) -> Result<(), WorkerQuitReason> {
+        tracing::info!(session_id = ?session.id, "session expired, initializing a new one");
+        let uri = self.config.uri.clone();
+        let (message, session_id) = self
+            .client
+            .post_message(uri.clone(), initialize_request.clone(), None, None)
+            .await
+            .map_err(WorkerQuitReason::fatal_context("send initialize request"))?
+            .expect_initialized::<StreamableHttpError<C::Error>>()
+            .await
+            .map_err(WorkerQuitReason::fatal_context(
+                "process initialize response",
+            ))?;
+        let Some((ServerResult::InitializeResult(server_info), _)) = message.into_response() else {
+            return Err(WorkerQuitReason::fatal(
+                "unexpected initialize response",
+                "process initialize response",
+            ));
+        };
+        if session_id.is_none() && !self.config.allow_stateless {
+            return Err(WorkerQuitReason::fatal(
+                "missing session id in initialize response",
+                "process initialize response",
+            ));
+        }
+        let session_id: Option<Arc<str>> = session_id.map(Into::into);
+        self.client
+            .post_message(
+                uri,
+                initialized_notification.clone(),
+                session_id.clone(),
+                None,
+            )
+            .await
+            .map_err(WorkerQuitReason::fatal_context(
+                "send initialized notification",
+            ))?
+            .expect_accepted::<StreamableHttpError<C::Error>>()
+            .map_err(WorkerQuitReason::fatal_context(
+                "process initialized notification response",
+            ))?;
+
+        session.ct.cancel();
+        let previous = std::mem::replace(
+            session,
+            Session {
+                id: session_id,
+                ct: state.transport_task_ct.child_token(),
+                generation: session.generation + 1,
+            },
+        );
+        *state.current_session_id.lock().expect("lock poisoned") = session.id.clone();
+        self.spawn_common_stream(&mut state.streams, session, &state.sse_worker_tx)
+            .await?;
+        tracing::info!(session_id = ?session.id, "initialized a new session");
+        if let Some(callback) = &self.config.session_recovery.on_reinitialized {
+            callback(&ReinitializedSession {
+                previous_session_id: previous.id,
+                session_id: session.id.clone(),
+                server_info,
+            });
+        }
         Ok(())
     }
 }
@@ -259,7 +508,7 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
         }
     }
     async fn run(
-        self,
+        mut self,
         mut context: super::worker::WorkerContext<Self>,
     ) -> Result<(), WorkerQuitReason> {
         let channel_buffer_capacity = self.config.channel_buffer_capacity;
@@ -275,7 +524,7 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
         let _ = responder.send(Ok(()));
         let (message, session_id) = self
             .client
-            .post_message(config.uri.clone(), initialize_request, None, None)
+            .post_message(config.uri.clone(), initialize_request.clone(), None, None)
             .await
             .map_err(WorkerQuitReason::fatal_context("send initialize request"))?
             .expect_initialized::<Self::Error>()
@@ -294,14 +543,19 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
             }
             None
         };
-        // delete session when drop guard is dropped
-        if let Some(session_id) = &session_id {
+        // delete the current session when drop guard is dropped
+        let current_session_id = Arc::new(std::sync::Mutex::new(session_id.clone()));
+        if session_id.is_some() {
             let ct = transport_task_ct.clone();
             let client = self.client.clone();
-            let session_id = session_id.clone();
+            let current_session_id = current_session_id.clone();
             let url = config.uri.clone();
             tokio::spawn(async move {
                 ct.cancelled().await;
+                let Some(session_id) = current_session_id.lock().expect("lock poisoned").clone()
+                else {
+                    return;
+                };
                 let delete_session_result =
                     client.delete_session(url, session_id.clone(), None).await;
                 match delete_session_result {
@@ -326,6 +580,8 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
 
         context.send_to_handler(message).await?;
         let initialized_notification = context.recv_from_handler().await?;
+        // kept to initialize a new session when this one expires
+        let initialized_message = initialized_notification.message.clone();
         // expect a initialized response
         self.client
             .post_message(
@@ -346,49 +602,24 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
         enum Event<W: Worker, E: std::error::Error + Send + Sync + 'static> {
             ClientMessage(WorkerSendRequest<W>),
             ServerMessage(ServerJsonRpcMessage),
-            StreamResult(Result<(), StreamableHttpError<E>>),
-        }
-        let mut streams = tokio::task::JoinSet::new();
-        if let Some(session_id) = &session_id {
-            match self
-                .client
-                .get_stream(config.uri.clone(), session_id.clone(), None, None)
-                .await
-            {
-                Ok(stream) => {
-                    let sse_stream = SseAutoReconnectStream::new(
-                        stream,
-                        StreamableHttpClientReconnect {
-                            client: self.client.clone(),
-                            session_id: session_id.clone(),
-                            uri: config.uri.clone(),
-                        },
-                        self.config.retry_config.clone(),
-                    );
-                    streams.spawn(Self::execute_sse_stream(
-                        sse_stream,
-                        sse_worker_tx.clone(),
-                        false,
-                        transport_task_ct.child_token(),
-                    ));
-                    tracing::debug!("got common stream");
-                }
-                Err(StreamableHttpError::SeverDoesNotSupportSse) => {
-                    tracing::debug!("server doesn't support sse, skip common stream");
-                }
-                Err(e) => {
-                    // fail to get common stream
-                    tracing::error!("fail to get common stream: {e}");
-                    return Err(WorkerQuitReason::fatal(
-                        "fail to get general purpose event stream",
-                        "get general purpose event stream",
-                    ));
-                }
-            }
+            StreamResult(Result<StreamOutcome<E>, tokio::task::JoinError>),
         }
+        let mut session = Session {
+            id: session_id,
+            ct: transport_task_ct.child_token(),
+            generation: 0,
+        };
+        let mut state = WorkerState {
+            streams: JoinSet::new(),
+            sse_worker_tx,
+            current_session_id,
+            transport_task_ct,
+        };
+        self.spawn_common_stream(&mut state.streams, &session, &state.sse_worker_tx)
+            .await?;
         loop {
             let event = tokio::select! {
-                _ = transport_task_ct.cancelled() => {
+                _ = state.transport_task_ct.cancelled() => {
                     tracing::debug!("cancelled");
                     return Err(WorkerQuitReason::Cancelled);
                 }
@@ -403,11 +634,9 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
                     };
                     Event::ServerMessage(message)
                 },
-                terminated_stream = streams.join_next(), if !streams.is_empty() => {
+                terminated_stream = state.streams.join_next(), if !state.streams.is_empty() => {
                     match terminated_stream {
-                        Some(result) => {
-                            Event::StreamResult(result.map_err(StreamableHttpError::TokioJoinError).and_then(std::convert::identity))
-                        }
+                        Some(result) => Event::StreamResult(result),
                         None => {
                             continue
                         }
@@ -417,10 +646,34 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
             match event {
                 Event::ClientMessage(send_request) => {
                     let WorkerSendRequest { message, responder } = send_request;
-                    let response = self
+                    let request_id = match &message {
+                        ClientJsonRpcMessage::Request(request) => Some(request.id.clone()),
+                        _ => None,
+                    };
+                    let mut response = self
                         .client
-                        .post_message(config.uri.clone(), message, session_id.clone(), None)
+                        .post_message(
+                            config.uri.clone(),
+                            message.clone(),
+                            session.id.clone(),
+                            None,
+                        )
                         .await;
+                    if matches!(response, Err(StreamableHttpError::SessionExpired))
+                        && config.session_recovery.reinitialize
+                    {
+                        self.reinitialize(
+                            &mut session,
+                            &initialize_request,
+                            &initialized_message,
+                            &mut state,
+                        )
+                        .await?;
+                        response = self
+                            .client
+                            .post_message(config.uri.clone(), message, session.id.clone(), None)
+                            .await;
+                    }
                     let send_result = match response {
                         Err(e) => Err(e),
                         Ok(StreamableHttpPostResponse::Accepted) => {
@@ -432,34 +685,13 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
                             Ok(())
                         }
                         Ok(StreamableHttpPostResponse::Sse(stream, ..)) => {
-                            if let Some(session_id) = &session_id {
-                                let sse_stream = SseAutoReconnectStream::new(
-                                    stream,
-                                    StreamableHttpClientReconnect {
-                                        client: self.client.clone(),
-                                        session_id: session_id.clone(),
-                                        uri: config.uri.clone(),
-                                    },
-                                    self.config.retry_config.clone(),
-                                );
-                                streams.spawn(Self::execute_sse_stream(
-                                    sse_stream,
-                                    sse_worker_tx.clone(),
-                                    true,
-                                    transport_task_ct.child_token(),
-                                ));
-                            } else {
-                                let sse_stream = SseAutoReconnectStream::never_reconnect(
-                                    stream,
-                                    StreamableHttpError::<C::Error>::UnexpectedEndOfStream,
-                                );
-                                streams.spawn(Self::execute_sse_stream(
-                                    sse_stream,
-                                    sse_worker_tx.clone(),
-                                    true,
-                                    transport_task_ct.child_token(),
-                                ));
-                            }
+                            self.spawn_sse_stream(
+                                &mut state.streams,
+                                stream,
+                                &session,
+                                request_id,
+                                &state.sse_worker_tx,
+                            );
                             tracing::trace!("got new sse stream");
                             Ok(())
                         }
@@ -470,12 +702,38 @@ impl<C: StreamableHttpClient> Worker for StreamableHttpClientWorker<C> {
                     // send the message to the handler
                     context.send_to_handler(json_rpc_message).await?;
                 }
-                Event::StreamResult(result) => {
-                    if result.is_err() {
-                        tracing::warn!(
-                            "sse client event stream terminated with error: {:?}",
-                            result
-                        );
+                Event::StreamResult(Err(e)) => {
+                    tracing::warn!("sse client event stream task failed: {e}");
+                }
+                Event::StreamResult(Ok(StreamOutcome {
+                    generation,
+                    request_id,
+                    result,
+                })) => {
+                    if let Err(e) = &result {
+                        tracing::warn!("sse client event stream terminated with error: {e}");
+                    }
+                    let session_expired =
+                        matches!(result, Err(StreamableHttpError::SessionExpired));
+                    match request_id {
+                        Some(request_id) if !matches!(result, Ok(true)) => {
+                            context.send_to_handler(response_lost(request_id)).await?;
+                        }
+                        Some(_) => {}
+                        None => {
+                            if session_expired
+                                && generation == session.generation
+                                && config.session_recovery.reinitialize
+                            {
+                                self.reinitialize(
+                                    &mut session,
+                                    &initialize_request,
+                                    &initialized_message,
+                                    &mut state,
+                                )
+                                .await?;
+                            }
+                        }
                     }
                 }
             }
@@ -498,6 +756,7 @@ pub struct StreamableHttpClientTransportConfig {
     pub channel_buffer_capacity: usize,
     /// if true, the transport will not require a session to be established
     pub allow_stateless: bool,
+    pub session_recovery: SessionRecovery,
 }
 
 impl StreamableHttpClientTransportConfig {
@@ -516,6 +775,7 @@ impl Default for StreamableHttpClientTransportConfig {
             retry_config: Arc::new(ExponentialBackoff::default()),
             channel_buffer_capacity: 16,
             allow_stateless: true,
+            session_recovery: SessionRecovery::default(),
         }
     }
 }
@@ -10,18 +10,19 @@ use tokio_stream::wrappers::ReceiverStream;
 use super::session::SessionManager;
 use crate::{
     RoleServer,
-    model::{ClientJsonRpcMessage, ClientRequest, GetExtensions},
+    model::{ClientJsonRpcMessage, ClientRequest},
     serve_server,
     service::serve_directly,
     transport::{
-        OneshotTransport, TransportAdapterIdentity,
+        OneshotTransport, PayloadLimits, TransportAdapterIdentity,
         common::{
             http_header::{
                 EVENT_STREAM_MIME_TYPE, HEADER_LAST_EVENT_ID, HEADER_SESSION_ID, JSON_MIME_TYPE,
             },
             server_side_http::{
                 BoxResponse, ServerSseMessage, accepted_response, expect_json,
-                internal_error_response, sse_stream_response, unexpected_message_response,
+                insert_request_parts, internal_error_response, sse_stream_response,
+                unexpected_message_response,
             },
         },
     },
@@ -33,6 +34,8 @@ pub struct StreamableHttpServerConfig {
     pub sse_keep_alive: Option<Duration>,
     /// If true, the server will create a session for each request and keep it alive.
     pub stateful_mode: bool,
+    /// Limits on the request bodies, a body over them is rejected with a JSON-RPC error.
+    pub limits: PayloadLimits,
 }
 
 impl Default for StreamableHttpServerConfig {
@@ -40,6 +43,7 @@ impl Default for StreamableHttpServerConfig {
         Self {
             sse_keep_alive: Some(Duration::from_secs(15)),
             stateful_mode: true,
+            limits: PayloadLimits::default(),
         }
     }
 }
@@ -248,7 +252,7 @@ where
 
         // json deserialize request body
         let (part, body) = request.into_parts();
-        let mut message = match expect_json(body).await {
+        let mut message = match expect_json(body, &self.config.limits).await {
             Ok(message) => message,
             Err(response) => return Ok(response),
         };
@@ -275,17 +279,7 @@ where
                 }
 
                 // inject request part to extensions
-                match &mut message {
-                    ClientJsonRpcMessage::Request(req) => {
-                        req.request.extensions_mut().insert(part);
-                    }
-                    ClientJsonRpcMessage::Notification(not) => {
-                        not.notification.extensions_mut().insert(part);
-                    }
-                    _ => {
-                        // skip
-                    }
-                }
+                insert_request_parts(&mut message, part);
 
                 match message {
                     ClientJsonRpcMessage::Request(_) => {
@@ -319,15 +313,15 @@ where
                     .create_session()
                     .await
                     .map_err(internal_error_response("create session"))?;
-                if let ClientJsonRpcMessage::Request(req) = &mut message {
+                if let ClientJsonRpcMessage::Request(req) = &message {
                     if !matches!(req.request, ClientRequest::InitializeRequest(_)) {
                         return Err(unexpected_message_response("initialize request"));
                     }
-                    // inject request part to extensions
-                    req.request.extensions_mut().insert(part);
                 } else {
                     return Err(unexpected_message_response("initialize request"));
                 }
+                // inject request part to extensions
+                insert_request_parts(&mut message, part);
                 let service = self
                     .get_service()
                     .map_err(internal_error_response("get service"))?;
@@ -388,10 +382,10 @@ where
                 .get_service()
                 .map_err(internal_error_response("get service"))?;
             match message {
-                ClientJsonRpcMessage::Request(mut request) => {
-                    request.request.extensions_mut().insert(part);
-                    let (transport, receiver) =
-                        OneshotTransport::<RoleServer>::new(ClientJsonRpcMessage::Request(request));
+                ClientJsonRpcMessage::Request(request) => {
+                    let mut message = ClientJsonRpcMessage::Request(request);
+                    insert_request_parts(&mut message, part);
+                    let (transport, receiver) = OneshotTransport::<RoleServer>::new(message);
                     let service = serve_directly(service, transport, None);
                     tokio::spawn(async move {
                         // on service created
@@ -0,0 +1,184 @@
+//! # WASI StdIO Transport
+//!
+//! Stdio transport for servers compiled to `wasm32-wasip2`, built on the `wasi:cli` streams instead
+//! of `tokio/io-std`, which relies on threads WASI doesn't have.
+//!
+//! ```rust,ignore
+//! fn main() {
+//!     rmcp::transport::wasi::block_on(async {
+//!         let server = Calculator::new().serve(rmcp::transport::wasi::stdio()).await?;
+//!         server.waiting().await?;
+//!         anyhow::Ok(())
+//!     })
+//!     .unwrap();
+//! }
+//! ```
+//!
+//! Build it with `cargo build --target wasm32-wasip2`, and run it with
+//! `wasmtime target/wasm32-wasip2/debug/my-server.wasm`.
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use ::wasi::{
+    cli::{
+        stdin::{InputStream, get_stdin},
+        stdout::{OutputStream, get_stdout},
+    },
+    io::{poll::Pollable, streams::StreamError},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{Instant, Sleep},
+};
+
+/// How soon a pending stream first checks whether it became ready.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// How often an idle stream checks whether it became ready.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Create a pair of [`WasiStdin`] and [`WasiStdout`].
+pub fn stdio() -> (WasiStdin, WasiStdout) {
+    (
+        WasiStdin {
+            readiness: Readiness::default(),
+            inner: get_stdin(),
+        },
+        WasiStdout {
+            readiness: Readiness::default(),
+            inner: get_stdout(),
+        },
+    )
+}
+
+/// Run a future to completion on a single threaded runtime.
+///
+/// WASI components have a single thread, so tasks are spawned onto the current thread, and the
+/// streams of [`stdio`] are checked on the runtime timer while they are pending.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to build the current thread runtime")
+        .block_on(future)
+}
+
+/// The stdin of a WASI component.
+#[derive(Debug)]
+pub struct WasiStdin {
+    // dropped first, a pollable must not outlive its stream
+    readiness: Readiness,
+    inner: InputStream,
+}
+
+/// The stdout of a WASI component.
+#[derive(Debug)]
+pub struct WasiStdout {
+    // dropped first, a pollable must not outlive its stream
+    readiness: Readiness,
+    inner: OutputStream,
+}
+
+fn stream_error(error: StreamError) -> io::Error {
+    match error {
+        StreamError::Closed => io::ErrorKind::BrokenPipe.into(),
+        StreamError::LastOperationFailed(error) => io::Error::other(error.to_debug_string()),
+    }
+}
+
+/// Waits for a stream to become ready.
+///
+/// The tokio runtime can't wait on WASI pollables, so the pollable of the stream is checked on a
+/// single timer per stream, which wakes the last task polling it. The interval doubles from
+/// [`MIN_POLL_INTERVAL`] to [`MAX_POLL_INTERVAL`] while the stream stays pending, so an idle
+/// server lets the runtime sleep.
+#[derive(Debug, Default)]
+struct Readiness {
+    pollable: Option<Pollable>,
+    timer: Option<Pin<Box<Sleep>>>,
+    interval: Duration,
+}
+
+impl Readiness {
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+        subscribe: impl FnOnce() -> Pollable,
+    ) -> Poll<()> {
+        let pollable = self.pollable.get_or_insert_with(subscribe);
+        loop {
+            if pollable.ready() {
+                self.pollable = None;
+                self.interval = MIN_POLL_INTERVAL;
+                return Poll::Ready(());
+            }
+            let timer = self
+                .timer
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep(Duration::ZERO)));
+            ready!(timer.as_mut().poll(cx));
+            self.interval = self.interval.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL);
+            timer.as_mut().reset(Instant::now() + self.interval);
+            self.interval = (self.interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+}
+
+impl AsyncRead for WasiStdin {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        let Self { readiness, inner } = self.get_mut();
+        loop {
+            let bytes = match inner.read(buf.remaining() as u64) {
+                Ok(bytes) => bytes,
+                // end of file
+                Err(StreamError::Closed) => return Poll::Ready(Ok(())),
+                Err(error) => return Poll::Ready(Err(stream_error(error))),
+            };
+            if bytes.is_empty() {
+                ready!(readiness.poll_ready(cx, || inner.subscribe()));
+                continue;
+            }
+            buf.put_slice(&bytes);
+            return Poll::Ready(Ok(()));
+        }
+    }
+}
+
+impl AsyncWrite for WasiStdout {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let Self { readiness, inner } = self.get_mut();
+        loop {
+            let writable = inner.check_write().map_err(stream_error)?;
+            if writable == 0 {
+                ready!(readiness.poll_ready(cx, || inner.subscribe()));
+                continue;
+            }
+            let len = buf.len().min(writable as usize);
+            inner.write(&buf[..len]).map_err(stream_error)?;
+            return Poll::Ready(Ok(len));
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.blocking_flush().map_err(stream_error)?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+};
+use rmcp::{
+    model::ClientJsonRpcMessage,
+    transport::{
+        auth::{
+            AuthClient, AuthError, AuthEvent, AuthorizationManager, CredentialStore,
+            EncryptedFileCredentialStore, InMemoryCredentialStore, OAuthTokenResponse,
+            StoredCredentials,
+        },
+        streamable_http_client::{StreamableHttpClient, StreamableHttpPostResponse},
+    },
+};
+use serde_json::json;
+
+#[derive(Clone)]
+struct MockAuthServer {
+    addr: SocketAddr,
+}
+
+async fn metadata(State(server): State<MockAuthServer>) -> Json<serde_json::Value> {
+    let base = format!("http://{}", server.addr);
+    Json(json!({
+        "issuer": base,
+        "authorization_endpoint": format!("{base}/authorize"),
+        "token_endpoint": format!("{base}/token"),
+        "registration_endpoint": format!("{base}/register"),
+    }))
+}
+
+#[derive(serde::Deserialize)]
+struct RefreshForm {
+    grant_type: String,
+    refresh_token: String,
+}
+
+async fn token(Form(form): Form<RefreshForm>) -> impl IntoResponse {
+    assert_eq!(form.grant_type, "refresh_token");
+    if form.refresh_token == "revoked-refresh" {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "invalid_grant", "error_description": "token revoked" })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(json!({
+            "access_token": "new-access",
+            "token_type": "bearer",
+            "expires_in": 3600,
+            "refresh_token": "rotated-refresh",
+        })),
+    )
+}
+
+async fn mcp(headers: HeaderMap) -> StatusCode {
+    match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+        Some("Bearer new-access") => StatusCode::ACCEPTED,
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
+async fn start_mock_server() -> anyhow::Result<String> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let router = Router::new()
+        .route("/.well-known/oauth-authorization-server", get(metadata))
+        .route("/token", post(token))
+        .route("/mcp", post(mcp))
+        .with_state(MockAuthServer { addr });
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(format!("http://{addr}/mcp"))
+}
+
+fn stored_credentials(refresh_token: &str, expires_in: Duration) -> StoredCredentials {
+    let token_response: OAuthTokenResponse = serde_json::from_value(json!({
+        "access_token": "old-access",
+        "token_type": "bearer",
+        "refresh_token": refresh_token,
+    }))
+    .unwrap();
+    let expires_at = SystemTime::now() + expires_in;
+    let mut stored = StoredCredentials::new("test-client");
+    stored.token_response = Some(token_response);
+    stored.expires_at = Some(
+        expires_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    );
+    stored
+}
+
+async fn manager_with(
+    url: &str,
+    store: Arc<InMemoryCredentialStore>,
+) -> anyhow::Result<AuthorizationManager> {
+    let mut manager = AuthorizationManager::new(url).await?;
+    manager.with_credential_store(store);
+    assert!(manager.load_credentials().await?);
+    Ok(manager)
+}
+
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!(
+        "rmcp-credentials-{}-{nanos}.bin",
+        std::process::id()
+    ))
+}
+
+#[tokio::test]
+async fn test_encrypted_file_store_roundtrip() -> anyhow::Result<()> {
+    let path = temp_path();
+    let key = EncryptedFileCredentialStore::generate_key();
+    let store = EncryptedFileCredentialStore::new(&path, key);
+    assert!(store.load("http://a.example.com/mcp").await?.is_none());
+
+    store
+        .save(
+            "http://a.example.com/mcp",
+            stored_credentials("secret-refresh", Duration::from_secs(3600)),
+        )
+        .await?;
+    store
+        .save(
+            "http://b.example.com/mcp",
+            StoredCredentials::new("other-client"),
+        )
+        .await?;
+
+    let raw = std::fs::read(&path)?;
+    assert!(!String::from_utf8_lossy(&raw).contains("secret-refresh"));
+
+    // a new instance with the same key reads the same entries
+    let store = EncryptedFileCredentialStore::new(&path, key);
+    let loaded = store.load("http://a.example.com/mcp").await?.unwrap();
+    assert_eq!(loaded.client_id, "test-client");
+    assert!(loaded.token_response.is_some());
+    assert_eq!(
+        store
+            .load("http://b.example.com/mcp")
+            .await?
+            .unwrap()
+            .client_id,
+        "other-client"
+    );
+
+    store.clear("http://a.example.com/mcp").await?;
+    assert!(store.load("http://a.example.com/mcp").await?.is_none());
+
+    let wrong_key = EncryptedFileCredentialStore::new(&path, [0u8; 32]);
+    assert!(matches!(
+        wrong_key.load("http://b.example.com/mcp").await,
+        Err(AuthError::CredentialStoreError(_))
+    ));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_proactive_refresh_before_expiry() -> anyhow::Result<()> {
+    let url = start_mock_server().await?;
+    let store = Arc::new(InMemoryCredentialStore::new());
+    store
+        .save(&url, stored_credentials("refresh", Duration::from_secs(10)))
+        .await?;
+    let manager = manager_with(&url, store.clone()).await?;
+    let mut events = manager.subscribe();
+
+    assert_eq!(manager.get_access_token().await?, "new-access");
+    assert_eq!(
+        events.recv().await?,
+        AuthEvent::Refreshed {
+            refresh_token_rotated: true
+        }
+    );
+
+    // the rotated refresh token is persisted
+    let stored = store.load(&url).await?.unwrap();
+    let token_response = serde_json::to_value(stored.token_response.unwrap())?;
+    assert_eq!(token_response["refresh_token"], "rotated-refresh");
+    assert_eq!(token_response["access_token"], "new-access");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_revoked_refresh_token_clears_store() -> anyhow::Result<()> {
+    let url = start_mock_server().await?;
+    let store = Arc::new(InMemoryCredentialStore::new());
+    store
+        .save(
+            &url,
+            stored_credentials("revoked-refresh", Duration::from_secs(0)),
+        )
+        .await?;
+    let manager = manager_with(&url, store.clone()).await?;
+    let mut events = manager.subscribe();
+
+    assert!(manager.get_access_token().await.is_err());
+    assert!(matches!(events.recv().await?, AuthEvent::Revoked { .. }));
+    // the tokens are discarded, the client registration is kept
+    let stored = store.load(&url).await?.unwrap();
+    assert!(stored.token_response.is_none());
+    assert_eq!(stored.client_id, "test-client");
+    assert!(matches!(
+        manager.get_access_token().await,
+        Err(AuthError::AuthorizationRequired)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_once_on_unauthorized() -> anyhow::Result<()> {
+    let url = start_mock_server().await?;
+    let store = Arc::new(InMemoryCredentialStore::new());
+    store
+        .save(
+            &url,
+            stored_credentials("refresh", Duration::from_secs(3600)),
+        )
+        .await?;
+    let manager = manager_with(&url, store).await?;
+    let mut events = manager.subscribe();
+    let client = AuthClient::new(reqwest::Client::new(), manager);
+    assert_eq!(client.get_access_token().await?, "old-access");
+
+    let message: ClientJsonRpcMessage = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/initialized",
+    }))?;
+    let response = client
+        .post_message(url.as_str().into(), message, None, None)
+        .await?;
+    assert!(matches!(response, StreamableHttpPostResponse::Accepted));
+    assert!(matches!(events.recv().await?, AuthEvent::Refreshed { .. }));
+    assert_eq!(client.get_access_token().await?, "new-access");
+    Ok(())
+}
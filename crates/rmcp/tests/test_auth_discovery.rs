@@ -0,0 +1,299 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::{StatusCode, header::WWW_AUTHENTICATE},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+};
+use rmcp::transport::auth::{
+    AuthorizationManager, CredentialStore, InMemoryCredentialStore, OAuthClientConfig,
+    resource_metadata_url_from_challenge,
+};
+use serde_json::json;
+
+#[derive(Clone)]
+struct MockServer {
+    addr: SocketAddr,
+    registrations: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    fn base(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+/// protected resource metadata pointing at an authorization server with a path
+async fn protected_resource(State(server): State<MockServer>) -> Json<serde_json::Value> {
+    Json(json!({
+        "resource": format!("{}/mcp", server.base()),
+        "authorization_servers": [format!("{}/tenant", server.base())],
+    }))
+}
+
+/// protected resource metadata only reachable through the `WWW-Authenticate` challenge
+async fn custom_protected_resource(State(server): State<MockServer>) -> Json<serde_json::Value> {
+    Json(json!({
+        "resource": format!("{}/other", server.base()),
+        "authorization_servers": [server.base()],
+    }))
+}
+
+/// OpenID configuration with the well-known segment appended to the issuer path
+async fn openid_configuration(State(server): State<MockServer>) -> Json<serde_json::Value> {
+    let issuer = format!("{}/tenant", server.base());
+    Json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+    }))
+}
+
+/// RFC 8414 metadata of the root authorization server
+async fn authorization_server(State(server): State<MockServer>) -> Json<serde_json::Value> {
+    let base = server.base();
+    Json(json!({
+        "issuer": base,
+        "authorization_endpoint": format!("{base}/authorize"),
+        "token_endpoint": format!("{base}/token"),
+        "registration_endpoint": format!("{base}/register"),
+    }))
+}
+
+/// a protected MCP endpoint, pointing at its metadata in the challenge
+async fn unauthorized(State(server): State<MockServer>) -> impl IntoResponse {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(
+            WWW_AUTHENTICATE,
+            format!(
+                r#"Bearer resource_metadata="{}/custom-resource-metadata""#,
+                server.base()
+            ),
+        )],
+    )
+}
+
+/// a single page application answering every path
+async fn spa_fallback() -> Html<&'static str> {
+    Html("<!doctype html><html><body>app</body></html>")
+}
+
+async fn register(State(server): State<MockServer>) -> impl IntoResponse {
+    let n = server.registrations.fetch_add(1, Ordering::SeqCst) + 1;
+    (
+        StatusCode::CREATED,
+        Json(json!({
+            "client_id": format!("client-{n}"),
+            "client_name": "test",
+            "redirect_uris": ["http://localhost/callback"],
+        })),
+    )
+}
+
+async fn token(
+    State(server): State<MockServer>,
+    Form(form): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    // the token must be bound to the resource from the protected resource metadata
+    if form.get("resource") != Some(&format!("{}/mcp", server.base())) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "invalid_target" })),
+        );
+    }
+    (
+        StatusCode::OK,
+        Json(json!({ "access_token": "bound-token", "token_type": "bearer" })),
+    )
+}
+
+async fn start_mock_server() -> anyhow::Result<MockServer> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let server = MockServer {
+        addr: listener.local_addr()?,
+        registrations: Default::default(),
+    };
+    let router = Router::new()
+        .route(
+            "/.well-known/oauth-protected-resource/mcp",
+            get(protected_resource),
+        )
+        .route("/custom-resource-metadata", get(custom_protected_resource))
+        .route(
+            "/tenant/.well-known/openid-configuration",
+            get(openid_configuration),
+        )
+        .route(
+            "/.well-known/oauth-authorization-server",
+            get(authorization_server),
+        )
+        .route("/probed", get(unauthorized))
+        .route(
+            "/.well-known/oauth-protected-resource/spa",
+            get(spa_fallback),
+        )
+        .route("/register", post(register))
+        .route("/tenant/token", post(token))
+        .with_state(server.clone());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok(server)
+}
+
+#[test]
+fn test_parse_resource_metadata_from_challenge() {
+    assert_eq!(
+        resource_metadata_url_from_challenge(
+            r#"Bearer error="invalid_token", resource_metadata="https://mcp.example.com/.well-known/oauth-protected-resource""#
+        )
+        .as_deref(),
+        Some("https://mcp.example.com/.well-known/oauth-protected-resource")
+    );
+    assert_eq!(
+        resource_metadata_url_from_challenge(
+            "bearer realm=mcp, resource_metadata=https://example.com/meta"
+        )
+        .as_deref(),
+        Some("https://example.com/meta")
+    );
+    assert_eq!(
+        resource_metadata_url_from_challenge(r#"Bearer error="invalid_token""#),
+        None
+    );
+    assert_eq!(
+        resource_metadata_url_from_challenge(r#"Basic realm="mcp""#),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_discovery_chain_with_resource_indicator() -> anyhow::Result<()> {
+    let server = start_mock_server().await?;
+    let url = format!("{}/mcp", server.base());
+    let mut manager = AuthorizationManager::new(url.as_str()).await?;
+
+    // protected resource metadata -> path aware RFC 8414 (missing) -> OpenID configuration
+    let metadata = manager.discover_metadata().await?;
+    assert_eq!(
+        metadata.token_endpoint,
+        format!("{}/tenant/token", server.base())
+    );
+    assert!(metadata.registration_endpoint.is_empty());
+    assert_eq!(manager.resource_indicator().await, url);
+
+    manager.set_metadata(metadata);
+    manager.configure_client(OAuthClientConfig {
+        client_id: "client".to_string(),
+        client_secret: Some("secret".to_string()),
+        scopes: vec![],
+        redirect_uri: "http://localhost/callback".to_string(),
+    })?;
+    manager.exchange_client_credentials(&[]).await?;
+    assert_eq!(manager.get_access_token().await?, "bound-token");
+
+    let auth_url = manager.get_authorization_url(&["mcp"]).await?;
+    let auth_url = reqwest::Url::parse(&auth_url)?;
+    assert!(
+        auth_url
+            .query_pairs()
+            .any(|(key, value)| key == "resource" && value == url)
+    );
+
+    // no registration endpoint
+    assert!(
+        manager
+            .register_client("test", "http://localhost/callback")
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_discovery_from_challenge() -> anyhow::Result<()> {
+    let server = start_mock_server().await?;
+    let manager = AuthorizationManager::new(format!("{}/other", server.base())).await?;
+    let challenge = format!(
+        r#"Bearer error="invalid_token", resource_metadata="{}/custom-resource-metadata""#,
+        server.base()
+    );
+    let metadata = manager.discover_metadata_from_challenge(&challenge).await?;
+    assert_eq!(metadata.token_endpoint, format!("{}/token", server.base()));
+    assert_eq!(
+        manager.resource_indicator().await,
+        format!("{}/other", server.base())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_discovery_probes_challenge() -> anyhow::Result<()> {
+    let server = start_mock_server().await?;
+    let manager = AuthorizationManager::new(format!("{}/probed", server.base())).await?;
+    let metadata = manager.discover_metadata().await?;
+    assert_eq!(metadata.token_endpoint, format!("{}/token", server.base()));
+    assert_eq!(
+        manager.resource_indicator().await,
+        format!("{}/other", server.base())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_discovery_skips_invalid_candidates() -> anyhow::Result<()> {
+    let server = start_mock_server().await?;
+    let manager = AuthorizationManager::new(format!("{}/spa", server.base())).await?;
+    // the HTML answer of the path aware url is skipped
+    let metadata = manager.discover_metadata().await?;
+    assert_eq!(metadata.token_endpoint, format!("{}/token", server.base()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registration_is_persisted() -> anyhow::Result<()> {
+    let server = start_mock_server().await?;
+    let url = format!("{}/legacy", server.base());
+    let store = Arc::new(InMemoryCredentialStore::new());
+
+    for _ in 0..2 {
+        let mut manager = AuthorizationManager::new(url.as_str()).await?;
+        manager.with_credential_store(store.clone());
+        // no protected resource metadata, falls back to the server origin
+        let metadata = manager.discover_metadata().await?;
+        assert_eq!(
+            metadata.registration_endpoint,
+            format!("{}/register", server.base())
+        );
+        manager.set_metadata(metadata);
+        let config = manager
+            .register_client("test", "http://localhost/callback")
+            .await?;
+        assert_eq!(config.client_id, "client-1");
+    }
+    assert_eq!(server.registrations.load(Ordering::SeqCst), 1);
+
+    // a different redirect uri needs a new registration
+    let mut manager = AuthorizationManager::new(url.as_str()).await?;
+    manager.with_credential_store(store.clone());
+    let metadata = manager.discover_metadata().await?;
+    manager.set_metadata(metadata);
+    let config = manager
+        .register_client("test", "http://localhost/other-callback")
+        .await?;
+    assert_eq!(config.client_id, "client-2");
+    let stored = store.load(&url).await?.unwrap();
+    assert_eq!(
+        stored.redirect_uri.as_deref(),
+        Some("http://localhost/other-callback")
+    );
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+};
+use rmcp::{
+    model::ClientJsonRpcMessage,
+    transport::{
+        auth::{AuthClient, AuthorizationManager, OAuthClientConfig},
+        streamable_http_client::{StreamableHttpClient, StreamableHttpPostResponse},
+    },
+};
+use serde_json::json;
+
+#[derive(Clone)]
+struct MockAuthServer {
+    addr: SocketAddr,
+    tokens_issued: Arc<AtomicUsize>,
+    device_polls: Arc<AtomicUsize>,
+}
+
+async fn metadata(State(server): State<MockAuthServer>) -> Json<serde_json::Value> {
+    let base = format!("http://{}", server.addr);
+    Json(json!({
+        "issuer": base,
+        "authorization_endpoint": format!("{base}/authorize"),
+        "token_endpoint": format!("{base}/token"),
+        "registration_endpoint": format!("{base}/register"),
+        "device_authorization_endpoint": format!("{base}/device"),
+    }))
+}
+
+async fn device(
+    State(server): State<MockAuthServer>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<serde_json::Value> {
+    assert_eq!(form["client_id"], "device-client");
+    assert_eq!(form["scope"], "mcp");
+    Json(json!({
+        "device_code": "device-code",
+        "user_code": "ABCD-EFGH",
+        "verification_uri": format!("http://{}/activate", server.addr),
+        "expires_in": 600,
+        "interval": 0,
+    }))
+}
+
+async fn token(
+    State(server): State<MockAuthServer>,
+    headers: HeaderMap,
+    Form(form): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    match form["grant_type"].as_str() {
+        "client_credentials" => {
+            // machine-to-machine clients authenticate with http basic auth
+            let expected = format!(
+                "Basic {}",
+                base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    "m2m-client:m2m-secret"
+                )
+            );
+            if headers.get("authorization").and_then(|v| v.to_str().ok()) != Some(&expected) {
+                return (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({ "error": "invalid_client" })),
+                );
+            }
+            let n = server.tokens_issued.fetch_add(1, Ordering::SeqCst) + 1;
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "access_token": format!("m2m-{n}"),
+                    "token_type": "bearer",
+                    "expires_in": 3600,
+                })),
+            )
+        }
+        "urn:ietf:params:oauth:grant-type:device_code" => {
+            assert_eq!(form["device_code"], "device-code");
+            if server.device_polls.fetch_add(1, Ordering::SeqCst) < 2 {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "error": "authorization_pending" })),
+                );
+            }
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "access_token": "device-access",
+                    "token_type": "bearer",
+                    "expires_in": 3600,
+                    "refresh_token": "device-refresh",
+                })),
+            )
+        }
+        grant_type => panic!("unexpected grant type {grant_type}"),
+    }
+}
+
+async fn mcp(headers: HeaderMap) -> StatusCode {
+    match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+        Some("Bearer device-access") | Some("Bearer m2m-1") | Some("Bearer m2m-2") => {
+            StatusCode::ACCEPTED
+        }
+        _ => StatusCode::UNAUTHORIZED,
+    }
+}
+
+async fn start_mock_server() -> anyhow::Result<(String, MockAuthServer)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = MockAuthServer {
+        addr,
+        tokens_issued: Default::default(),
+        device_polls: Default::default(),
+    };
+    let router = Router::new()
+        .route("/.well-known/oauth-authorization-server", get(metadata))
+        .route("/device", post(device))
+        .route("/token", post(token))
+        .route("/mcp", post(mcp))
+        .with_state(server.clone());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    Ok((format!("http://{addr}/mcp"), server))
+}
+
+async fn manager_for(
+    url: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+) -> anyhow::Result<AuthorizationManager> {
+    let mut manager = AuthorizationManager::new(url).await?;
+    let metadata = manager.discover_metadata().await?;
+    manager.set_metadata(metadata);
+    manager.configure_client(OAuthClientConfig {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.map(str::to_string),
+        scopes: vec![],
+        redirect_uri: url.to_string(),
+    })?;
+    Ok(manager)
+}
+
+async fn post_initialized<C: StreamableHttpClient>(
+    client: &C,
+    url: &str,
+) -> anyhow::Result<StreamableHttpPostResponse> {
+    let message: ClientJsonRpcMessage = serde_json::from_value(json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/initialized",
+    }))?;
+    Ok(client.post_message(url.into(), message, None, None).await?)
+}
+
+#[tokio::test]
+async fn test_client_credentials_grant() -> anyhow::Result<()> {
+    let (url, server) = start_mock_server().await?;
+    let mut manager = manager_for(&url, "m2m-client", Some("m2m-secret")).await?;
+    manager.with_refresh_threshold(Duration::ZERO);
+    manager.exchange_client_credentials(&["mcp"]).await?;
+
+    let client = AuthClient::new(reqwest::Client::new(), manager);
+    assert_eq!(client.get_access_token().await?, "m2m-1");
+    let response = post_initialized(&client, &url).await?;
+    assert!(matches!(response, StreamableHttpPostResponse::Accepted));
+
+    // there is no refresh token, a new token is requested once the current one expires
+    client
+        .auth_manager
+        .lock()
+        .await
+        .with_refresh_threshold(Duration::from_secs(7200));
+    assert_eq!(client.get_access_token().await?, "m2m-2");
+    assert_eq!(server.tokens_issued.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_credentials_rejected() -> anyhow::Result<()> {
+    let (url, _server) = start_mock_server().await?;
+    let manager = manager_for(&url, "m2m-client", Some("wrong-secret")).await?;
+    assert!(manager.exchange_client_credentials(&["mcp"]).await.is_err());
+    assert!(manager.get_access_token().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_device_authorization_grant() -> anyhow::Result<()> {
+    let (url, server) = start_mock_server().await?;
+    let manager = manager_for(&url, "device-client", None).await?;
+
+    let details = manager.request_device_authorization(&["mcp"]).await?;
+    assert_eq!(details.user_code().secret(), "ABCD-EFGH");
+    assert!(details.verification_uri().ends_with("/activate"));
+
+    let token = manager
+        .poll_device_token(&details, Some(Duration::from_secs(10)))
+        .await?;
+    assert_eq!(
+        oauth2::TokenResponse::access_token(&token).secret(),
+        "device-access"
+    );
+    assert_eq!(server.device_polls.load(Ordering::SeqCst), 3);
+
+    let client = AuthClient::new(reqwest::Client::new(), manager);
+    let response = post_initialized(&client, &url).await?;
+    assert!(matches!(response, StreamableHttpPostResponse::Accepted));
+    Ok(())
+}
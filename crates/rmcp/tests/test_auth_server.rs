@@ -0,0 +1,250 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use http::{Request, StatusCode, header::WWW_AUTHENTICATE};
+use http_body_util::{BodyExt, Full};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use rmcp::{
+    ServerHandler,
+    handler::server::{router::tool::ToolRouter, tool::Extension},
+    model::{ServerCapabilities, ServerInfo},
+    tool, tool_handler, tool_router,
+    transport::{
+        StreamableHttpServerConfig, StreamableHttpService,
+        auth_server::{
+            AuthClaims, BearerAuthConfig, BearerAuthLayer, JwtValidator, ProtectedResourceMetadata,
+            TokenValidationError,
+        },
+        streamable_http_server::session::local::LocalSessionManager,
+    },
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+const SECRET: &[u8] = b"test-secret-key-for-hs256-signing";
+const ISSUER: &str = "https://auth.example.com";
+const RESOURCE: &str = "https://mcp.example.com/mcp";
+
+#[derive(Debug, Clone)]
+struct WhoAmI {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl WhoAmI {
+    fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    #[tool(description = "Return the subject of the access token")]
+    fn whoami(&self, Extension(claims): Extension<AuthClaims>) -> String {
+        claims.subject.unwrap_or_default()
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for WhoAmI {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn jwks() -> String {
+    use base64::Engine;
+    let k = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(SECRET);
+    serde_json::json!({
+        "keys": [{ "kty": "oct", "kid": "test-key", "alg": "HS256", "k": k }]
+    })
+    .to_string()
+}
+
+fn sign(claims: serde_json::Value) -> String {
+    let mut header = Header::new(Algorithm::HS256);
+    header.kid = Some("test-key".to_string());
+    jsonwebtoken::encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn validator() -> JwtValidator {
+    JwtValidator::from_jwks_json(&jwks())
+        .unwrap()
+        .with_algorithms([Algorithm::HS256])
+        .with_issuer(ISSUER)
+        .with_audience(RESOURCE)
+        .with_leeway(Duration::ZERO)
+}
+
+fn protected_service(
+    config: BearerAuthConfig,
+) -> impl Service<
+    Request<Full<Bytes>>,
+    Response = http::Response<impl http_body::Body<Data = Bytes, Error: std::fmt::Debug>>,
+    Error: std::fmt::Debug,
+> {
+    let service = StreamableHttpService::new(
+        || Ok(WhoAmI::new()),
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig {
+            stateful_mode: false,
+            sse_keep_alive: None,
+            ..Default::default()
+        },
+    );
+    BearerAuthLayer::new(validator(), config).layer(service)
+}
+
+fn config() -> BearerAuthConfig {
+    BearerAuthConfig::new(
+        ProtectedResourceMetadata::new(RESOURCE, [ISSUER]).with_scopes_supported(["mcp"]),
+    )
+}
+
+fn call_tool_request(token: Option<&str>) -> Request<Full<Bytes>> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": "whoami", "arguments": {} }
+    });
+    let mut builder = Request::post("/mcp")
+        .header("accept", "application/json, text/event-stream")
+        .header("content-type", "application/json");
+    if let Some(token) = token {
+        builder = builder.header("authorization", format!("Bearer {token}"));
+    }
+    builder
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_serve_protected_resource_metadata() -> anyhow::Result<()> {
+    let mut service = protected_service(config());
+    let response = service
+        .call(
+            Request::get("/.well-known/oauth-protected-resource/mcp")
+                .body(Full::new(Bytes::new()))?,
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let metadata: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(metadata["resource"], RESOURCE);
+    assert_eq!(metadata["authorization_servers"][0], ISSUER);
+    assert_eq!(metadata["scopes_supported"][0], "mcp");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reject_missing_and_invalid_token() -> anyhow::Result<()> {
+    let mut service = protected_service(config());
+    let response = service.call(call_tool_request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str()?;
+    assert_eq!(
+        challenge,
+        "Bearer resource_metadata=\"https://mcp.example.com/.well-known/oauth-protected-resource\""
+    );
+
+    let expired = sign(serde_json::json!({
+        "sub": "alice", "iss": ISSUER, "aud": RESOURCE, "exp": now() - 10,
+    }));
+    let response = service
+        .call(call_tool_request(Some(&expired)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str()?;
+    assert!(challenge.contains("error=\"invalid_token\""));
+
+    let wrong_audience = sign(serde_json::json!({
+        "sub": "alice", "iss": ISSUER, "aud": "https://other.example.com", "exp": now() + 60,
+    }));
+    let response = service
+        .call(call_tool_request(Some(&wrong_audience)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_insufficient_scope() -> anyhow::Result<()> {
+    let mut service = protected_service(config().with_required_scopes(["admin"]));
+    let token = sign(serde_json::json!({
+        "sub": "alice", "iss": ISSUER, "aud": RESOURCE, "exp": now() + 60, "scope": "mcp read",
+    }));
+    let response = service.call(call_tool_request(Some(&token))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let challenge = response.headers()[WWW_AUTHENTICATE].to_str()?;
+    assert!(challenge.contains("error=\"insufficient_scope\""));
+    assert!(challenge.contains("scope=\"admin\""));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_claims_injected_into_extensions() -> anyhow::Result<()> {
+    let mut service = protected_service(config().with_required_scopes(["mcp"]));
+    let token = sign(serde_json::json!({
+        "sub": "alice", "iss": ISSUER, "aud": RESOURCE, "exp": now() + 60, "scope": "mcp",
+    }));
+    let response = service.call(call_tool_request(Some(&token))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec())?;
+    assert!(
+        body.contains(r#""text":"alice""#),
+        "unexpected body: {body}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_introspection_hook_for_opaque_token() -> anyhow::Result<()> {
+    let validator = validator().with_introspection(|token: String| async move {
+        if token == "opaque-token" {
+            let claims = serde_json::json!({ "active": true, "sub": "bob", "scope": "mcp" });
+            Ok(AuthClaims::from_claims(
+                claims.as_object().cloned().unwrap(),
+            ))
+        } else {
+            Err(TokenValidationError::InvalidToken("inactive".to_string()))
+        }
+    });
+    let service = StreamableHttpService::new(
+        || Ok(WhoAmI::new()),
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig {
+            stateful_mode: false,
+            sse_keep_alive: None,
+            ..Default::default()
+        },
+    );
+    let mut service = BearerAuthLayer::new(validator, config()).layer(service);
+    let response = service
+        .call(call_tool_request(Some("opaque-token")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8(body.to_vec())?.contains(r#""text":"bob""#));
+
+    let response = service
+        .call(call_tool_request(Some("revoked-token")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rmcp::{
+    RoleClient, ServiceExt,
+    model::*,
+    pool::{ClientPool, PoolError, ServerConfig, ServerStatus},
+    service::RunningServiceCancellationToken,
+    testing::MockServer,
+    transport::{memory, memory::MemoryTransport},
+};
+
+fn tool(name: &'static str) -> Tool {
+    Tool::new(name, "a test tool", Arc::new(JsonObject::default()))
+}
+
+/// A server answering every tool call with its own name.
+fn named_server(name: &'static str, tools: &[&'static str]) -> MockServer {
+    tools.iter().fold(MockServer::new(), |server, tool_name| {
+        server.tool(tool(tool_name), move |_, _| async move {
+            Ok(CallToolResult::success(vec![Content::text(name)]))
+        })
+    })
+}
+
+type Connections = Arc<Mutex<Vec<RunningServiceCancellationToken>>>;
+
+/// Connect a new instance of `server` in memory, and record it in `connections`.
+fn connect(
+    server: MockServer,
+    connections: Connections,
+) -> impl Fn() -> std::future::Ready<Result<MemoryTransport<RoleClient>, Infallible>>
++ Send
++ Sync
++ 'static {
+    move || {
+        let server = server.clone();
+        let connections = connections.clone();
+        let (client, transport) = memory::pair();
+        tokio::spawn(async move {
+            if let Ok(server) = server.serve(transport).await {
+                connections
+                    .lock()
+                    .unwrap()
+                    .push(server.cancellation_token());
+                let _ = server.waiting().await;
+            }
+        });
+        std::future::ready(Ok(client))
+    }
+}
+
+async fn call(pool: &ClientPool, name: &str) -> Result<CallToolResult, PoolError> {
+    pool.call_tool(CallToolRequestParam {
+        name: name.to_owned().into(),
+        arguments: None,
+    })
+    .await
+}
+
+/// Wait until `count` servers are connected.
+async fn connected(connections: &Connections, count: usize) -> anyhow::Result<()> {
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while connections.lock().unwrap().len() < count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await?;
+    Ok(())
+}
+
+fn text(result: &CallToolResult) -> &str {
+    &result.content.as_ref().unwrap()[0].as_text().unwrap().text
+}
+
+#[tokio::test]
+async fn test_pool_catalogue_and_routing() -> anyhow::Result<()> {
+    let connections = Connections::default();
+    let pool = ClientPool::new()
+        .with_server(
+            "git",
+            connect(named_server("git", &["status", "log"]), connections.clone()),
+        )
+        .with_server(
+            "search",
+            connect(named_server("search", &["query"]), connections.clone()),
+        )
+        .with_server_config(
+            "broken",
+            ServerConfig::Stdio {
+                command: "/nonexistent/rmcp-pool-server".into(),
+                args: vec![],
+                env: Default::default(),
+                cwd: None,
+            },
+        );
+    pool.start().await;
+
+    let status = pool.status().await;
+    assert_eq!(status[0], ("git".to_owned(), ServerStatus::Running));
+    assert_eq!(status[1], ("search".to_owned(), ServerStatus::Running));
+    assert!(matches!(status[2].1, ServerStatus::Failed(_)));
+
+    let mut names: Vec<_> = pool
+        .list_tools()
+        .await
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["git__log", "git__status", "search__query"]);
+
+    assert_eq!(text(&call(&pool, "search__query").await?), "search");
+    assert_eq!(text(&call(&pool, "git__status").await?), "git");
+    assert!(matches!(
+        call(&pool, "unknown__query").await,
+        Err(PoolError::UnknownTool(_))
+    ));
+    assert!(matches!(
+        call(&pool, "broken__query").await,
+        Err(PoolError::NotRunning { .. })
+    ));
+    assert!(matches!(
+        pool.restart("unknown").await,
+        Err(PoolError::UnknownServer(_))
+    ));
+
+    pool.shutdown().await;
+    assert!(
+        pool.status()
+            .await
+            .iter()
+            .all(|(_, status)| *status != ServerStatus::Running)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_restarts_failed_servers() -> anyhow::Result<()> {
+    let connections = Connections::default();
+    let pool = ClientPool::new().with_server(
+        "flaky",
+        connect(named_server("flaky", &["work"]), connections.clone()),
+    );
+    pool.start().await;
+    let timeout = Duration::from_millis(200);
+    assert_eq!(
+        pool.check_health(timeout).await,
+        [("flaky".to_owned(), ServerStatus::Running)]
+    );
+    connected(&connections, 1).await?;
+
+    let peer = pool.peer("flaky").await?;
+    connections.lock().unwrap().remove(0).cancel();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while !peer.is_transport_closed() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await?;
+    assert_eq!(
+        pool.check_health(timeout).await,
+        [("flaky".to_owned(), ServerStatus::Running)]
+    );
+    connected(&connections, 1).await?;
+    assert_eq!(text(&call(&pool, "flaky__work").await?), "flaky");
+
+    // the watcher restarts it too
+    let watcher = pool.watch(Duration::from_millis(20), timeout);
+    connections.lock().unwrap().remove(0).cancel();
+    connected(&connections, 1).await?;
+    assert_eq!(text(&call(&pool, "flaky__work").await?), "flaky");
+
+    watcher.abort();
+    pool.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_keeps_stopped_servers_stopped() -> anyhow::Result<()> {
+    let connections = Connections::default();
+    let pool = ClientPool::new().with_server(
+        "idle",
+        connect(named_server("idle", &["work"]), connections.clone()),
+    );
+    pool.start().await;
+    connected(&connections, 1).await?;
+    pool.shutdown().await;
+
+    let timeout = Duration::from_millis(200);
+    assert_eq!(
+        pool.check_health(timeout).await,
+        [("idle".to_owned(), ServerStatus::Stopped)]
+    );
+    let watcher = pool.watch(Duration::from_millis(10), timeout);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    watcher.abort();
+    assert_eq!(
+        pool.status().await,
+        [("idle".to_owned(), ServerStatus::Stopped)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_connects_without_locking() -> anyhow::Result<()> {
+    let connections = Connections::default();
+    let server = connect(named_server("slow", &["work"]), connections.clone());
+    let (release, released) = tokio::sync::watch::channel(false);
+    let pool = ClientPool::new().with_server("slow", move || {
+        let transport = server();
+        let mut released = released.clone();
+        async move {
+            let _ = released.wait_for(|released| *released).await;
+            transport.await
+        }
+    });
+
+    // the status is readable while the server is connecting
+    let (restarted, status) = tokio::time::timeout(
+        Duration::from_secs(2),
+        futures::future::join(pool.restart("slow"), async {
+            let status = pool.status().await;
+            release.send(true).unwrap();
+            status
+        }),
+    )
+    .await?;
+    restarted?;
+    assert_eq!(status, [("slow".to_owned(), ServerStatus::Stopped)]);
+    assert_eq!(text(&call(&pool, "slow__work").await?), "slow");
+    pool.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,390 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use rmcp::{
+    ErrorData, ServiceExt,
+    gateway::{Collision, Gateway, Namespacing, Upstream},
+    model::*,
+    service::{PeerRequestOptions, RequestContext, RoleServer},
+    testing::{self, MockClient, MockServer},
+    transport::memory,
+};
+use tokio::sync::Notify;
+
+fn tool(name: &'static str) -> Tool {
+    Tool::new(name, "a test tool", Arc::new(JsonObject::default()))
+}
+
+fn text(result: &CallToolResult) -> &str {
+    &result.content.as_ref().unwrap()[0].as_text().unwrap().text
+}
+
+/// A server answering every tool call with its own name.
+fn named_server(name: &'static str, tools: &[&'static str]) -> MockServer {
+    tools.iter().fold(MockServer::new(), |server, tool_name| {
+        server.tool(tool(tool_name), move |_, _| async move {
+            Ok(CallToolResult::success(vec![Content::text(name)]))
+        })
+    })
+}
+
+fn upstream(name: &'static str, server: MockServer) -> Upstream {
+    Upstream::new(name, move || {
+        let server = server.clone();
+        async move {
+            let (client, transport) = memory::pair();
+            tokio::spawn(async move {
+                if let Ok(server) = server.serve(transport).await {
+                    let _ = server.waiting().await;
+                }
+            });
+            Ok::<_, Infallible>(client)
+        }
+    })
+}
+
+async fn call(
+    client: &rmcp::service::RunningService<rmcp::RoleClient, MockClient>,
+    name: &str,
+) -> Result<CallToolResult, rmcp::ServiceError> {
+    client
+        .call_tool(CallToolRequestParam {
+            name: name.to_owned().into(),
+            arguments: None,
+        })
+        .await
+}
+
+fn names(tools: &[Tool]) -> Vec<&str> {
+    tools.iter().map(|tool| tool.name.as_ref()).collect()
+}
+
+#[tokio::test]
+async fn test_namespacing() -> anyhow::Result<()> {
+    let gateway = Gateway::new()
+        .upstream(upstream("a", named_server("a", &["echo", "only_a"])))
+        .upstream(upstream("b", named_server("b", &["echo"])).prefix("beta"));
+
+    let connection = testing::connect(gateway.clone()).await?;
+    let tools = connection.client.list_all_tools().await?;
+    assert_eq!(names(&tools), ["a__echo", "a__only_a", "beta__echo"]);
+    assert_eq!(text(&call(&connection.client, "beta__echo").await?), "b");
+    assert!(call(&connection.client, "echo").await.is_err());
+    connection.close().await;
+
+    let connection = testing::connect(gateway.clone().namespacing(Namespacing::OnCollision {
+        separator: ".".into(),
+    }))
+    .await?;
+    let tools = connection.client.list_all_tools().await?;
+    assert_eq!(names(&tools), ["a.echo", "only_a", "beta.echo"]);
+    // routes are resolved without listing first
+    let connection_b = testing::connect(gateway.clone()).await?;
+    assert_eq!(text(&call(&connection_b.client, "a__only_a").await?), "a");
+    connection_b.close().await;
+    connection.close().await;
+
+    for (collision, winner) in [(Collision::KeepFirst, "a"), (Collision::KeepLast, "b")] {
+        let connection = testing::connect(
+            gateway
+                .clone()
+                .namespacing(Namespacing::None)
+                .on_collision(collision),
+        )
+        .await?;
+        let tools = connection.client.list_all_tools().await?;
+        assert_eq!(tools.len(), 2);
+        assert_eq!(text(&call(&connection.client, "echo").await?), winner);
+        connection.close().await;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_upstream_is_left_out() -> anyhow::Result<()> {
+    let gateway = Gateway::new()
+        .upstream(upstream("a", named_server("a", &["echo"])))
+        .upstream(Upstream::new("down", || async {
+            Err::<memory::MemoryTransport<rmcp::RoleClient>, _>(std::io::Error::other(
+                "connection refused",
+            ))
+        }));
+    let connection = testing::connect(gateway).await?;
+    assert_eq!(connection.server.service().connected_upstreams(), ["a"]);
+    assert_eq!(
+        names(&connection.client.list_all_tools().await?),
+        ["a__echo"]
+    );
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_progress_and_cancellation() -> anyhow::Result<()> {
+    let cancelled = Arc::new(Notify::new());
+    let server = MockServer::new()
+        .tool(
+            tool("report"),
+            |_, context: RequestContext<RoleServer>| async move {
+                let progress_token = context.meta.get_progress_token().unwrap();
+                let _ = context
+                    .peer
+                    .notify_progress(ProgressNotificationParam {
+                        progress_token,
+                        progress: 1,
+                        total: Some(2),
+                        message: None,
+                    })
+                    .await;
+                Ok(CallToolResult::success(vec![]))
+            },
+        )
+        .tool(tool("wait"), {
+            let cancelled = cancelled.clone();
+            move |_, context: RequestContext<RoleServer>| {
+                let cancelled = cancelled.clone();
+                async move {
+                    context.ct.cancelled().await;
+                    cancelled.notify_one();
+                    Ok(CallToolResult::success(vec![]))
+                }
+            }
+        });
+    let gateway = Gateway::new().upstream(upstream("a", server));
+    let connection = testing::connect(gateway).await?;
+
+    let handle = connection
+        .client
+        .send_request_with_option(
+            ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+                name: "a__report".into(),
+                arguments: None,
+            })),
+            PeerRequestOptions::no_options(),
+        )
+        .await?;
+    let progress_token = handle.progress_token.clone();
+    handle.await_response().await?;
+    let progress = connection
+        .mock_client()
+        .expect_progress(Duration::from_secs(1))
+        .await?;
+    assert_eq!(progress.progress_token, progress_token);
+    assert_eq!(progress.total, Some(2));
+
+    let handle = connection
+        .client
+        .send_request_with_option(
+            ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+                name: "a__wait".into(),
+                arguments: None,
+            })),
+            PeerRequestOptions::no_options(),
+        )
+        .await?;
+    handle.cancel(Some("changed my mind".to_owned())).await?;
+    tokio::time::timeout(Duration::from_secs(1), cancelled.notified()).await?;
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_relay_to_downstream() -> anyhow::Result<()> {
+    let server = MockServer::new().tool(
+        tool("ask"),
+        |_, context: RequestContext<RoleServer>| async move {
+            let roots = context
+                .peer
+                .list_roots()
+                .await
+                .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+            let message = context
+                .peer
+                .create_message(CreateMessageRequestParam {
+                    messages: vec![SamplingMessage {
+                        role: Role::User,
+                        content: Content::text("hello"),
+                    }],
+                    model_preferences: None,
+                    system_prompt: None,
+                    include_context: None,
+                    temperature: None,
+                    max_tokens: 16,
+                    stop_sequences: None,
+                    metadata: None,
+                })
+                .await
+                .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+            let _ = context.peer.notify_tool_list_changed().await;
+            let reply = message.message.content.as_text().unwrap().text.clone();
+            Ok(CallToolResult::success(vec![Content::text(format!(
+                "{} {reply}",
+                roots.roots[0].uri
+            ))]))
+        },
+    );
+    let client = MockClient::new()
+        .with_roots(vec![Root {
+            uri: "file:///work".into(),
+            name: None,
+        }])
+        .on_request(|request, _| async move {
+            let ServerRequest::CreateMessageRequest(_) = request else {
+                return Err(ErrorData::invalid_request("unexpected request", None));
+            };
+            Ok(ClientResult::CreateMessageResult(CreateMessageResult {
+                message: SamplingMessage {
+                    role: Role::Assistant,
+                    content: Content::text("hi"),
+                },
+                model: "test".into(),
+                stop_reason: None,
+            }))
+        });
+    let gateway = Gateway::new().upstream(upstream("a", server));
+    let connection = testing::connect_with(client, gateway).await?;
+    let result = call(&connection.client, "a__ask").await?;
+    assert_eq!(text(&result), "file:///work hi");
+    connection
+        .mock_client()
+        .expect_tool_list_changed(Duration::from_secs(1))
+        .await?;
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resources() -> anyhow::Result<()> {
+    fn resource_server(
+        name: &'static str,
+        uri: &'static str,
+        template: &'static str,
+    ) -> MockServer {
+        MockServer::new()
+            .with_info(ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_resources().build(),
+                ..Default::default()
+            })
+            .on_request(move |request, _| async move {
+                match request {
+                    ClientRequest::ListResourcesRequest(_) => {
+                        Ok(ServerResult::ListResourcesResult(ListResourcesResult {
+                            resources: vec![RawResource::new(uri, name).no_annotation()],
+                            next_cursor: None,
+                        }))
+                    }
+                    ClientRequest::ListResourceTemplatesRequest(_) => Ok(
+                        ServerResult::ListResourceTemplatesResult(ListResourceTemplatesResult {
+                            resource_templates: vec![
+                                RawResourceTemplate {
+                                    uri_template: template.into(),
+                                    name: name.into(),
+                                    description: None,
+                                    mime_type: None,
+                                }
+                                .no_annotation(),
+                            ],
+                            next_cursor: None,
+                        }),
+                    ),
+                    ClientRequest::ReadResourceRequest(request) => {
+                        Ok(ServerResult::ReadResourceResult(ReadResourceResult {
+                            contents: vec![ResourceContents::text(name, request.params.uri)],
+                        }))
+                    }
+                    _ => Err(ErrorData::invalid_request("unexpected request", None)),
+                }
+            })
+    }
+    let gateway = Gateway::new()
+        .upstream(upstream(
+            "a",
+            resource_server("a", "mem://a/readme", "mem://a/{path}"),
+        ))
+        .upstream(upstream(
+            "b",
+            resource_server("b", "mem://b/readme", "mem://b/{path}"),
+        ));
+    let connection = testing::connect(gateway).await?;
+    let resources = connection.client.list_all_resources().await?;
+    assert_eq!(resources.len(), 2);
+    for (uri, owner) in [
+        ("mem://b/readme", "b"),
+        ("mem://a/notes", "a"),
+        ("mem://b/src/lib.rs", "b"),
+    ] {
+        let result = connection
+            .client
+            .read_resource(ReadResourceRequestParam { uri: uri.into() })
+            .await?;
+        let ResourceContents::TextResourceContents { text, .. } = &result.contents[0] else {
+            panic!("expected text contents");
+        };
+        assert_eq!(text, owner);
+    }
+    assert!(
+        connection
+            .client
+            .read_resource(ReadResourceRequestParam {
+                uri: "mem://c/readme".into()
+            })
+            .await
+            .is_err()
+    );
+    let info = connection.client.peer_info().unwrap();
+    assert!(info.capabilities.resources.is_some());
+    assert!(info.capabilities.tools.is_none());
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_broadcast_failures_are_aggregated() -> anyhow::Result<()> {
+    fn logging_server(error: Option<ErrorData>) -> MockServer {
+        MockServer::new()
+            .with_info(ServerInfo {
+                capabilities: ServerCapabilities::builder().enable_logging().build(),
+                ..Default::default()
+            })
+            .on_request(move |_, _| {
+                let error = error.clone();
+                async move {
+                    match error {
+                        Some(error) => Err(error),
+                        None => Ok(ServerResult::empty(())),
+                    }
+                }
+            })
+    }
+    let gateway = Gateway::new()
+        .upstream(upstream(
+            "a",
+            logging_server(Some(ErrorData::invalid_params("bad level", None))),
+        ))
+        .upstream(upstream("b", logging_server(None)))
+        .upstream(upstream(
+            "c",
+            logging_server(Some(ErrorData::internal_error("broken", None))),
+        ));
+    let connection = testing::connect(gateway).await?;
+    let error = connection
+        .client
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Info,
+        })
+        .await
+        .unwrap_err();
+    let rmcp::ServiceError::McpError(error) = error else {
+        panic!("expected an error response, got {error}");
+    };
+    assert_eq!(error.code, ErrorCode::INTERNAL_ERROR);
+    assert_eq!(
+        error.message,
+        "upstream servers failed: a: bad level; c: broken"
+    );
+    let data = error.data.unwrap();
+    assert_eq!(data["a"]["code"], ErrorCode::INVALID_PARAMS.0);
+    assert_eq!(data["c"]["message"], "broken");
+    assert!(data.get("b").is_none());
+    connection.close().await;
+    Ok(())
+}
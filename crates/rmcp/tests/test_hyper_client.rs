@@ -0,0 +1,347 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    http::{HeaderMap, StatusCode},
+    response::{
+        IntoResponse,
+        sse::{Event, Sse},
+    },
+    routing::get,
+};
+use futures::stream;
+use rmcp::{
+    ServerHandler, ServiceExt,
+    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    model::{
+        CallToolRequestParam, ServerCapabilities, ServerInfo, ServerJsonRpcMessage,
+        ServerNotification,
+    },
+    service::QuitReason,
+    testing::MockClient,
+    tool, tool_handler, tool_router,
+    transport::{
+        HyperClient, SseServer, StreamableHttpClientTransport, Transport,
+        common::client_side_sse::FixedInterval,
+        sse_client::{SseClientConfig, SseClientTransport},
+        sse_server::SseServerConfig,
+        streamable_http_client::StreamableHttpClientTransportConfig,
+        streamable_http_server::{
+            StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+        },
+    },
+};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone)]
+struct Calculator {
+    tool_router: ToolRouter<Self>,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct SumRequest {
+    a: i32,
+    b: i32,
+}
+
+#[tool_router]
+impl Calculator {
+    fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    #[tool(description = "Calculate the sum of two numbers")]
+    fn sum(&self, Parameters(SumRequest { a, b }): Parameters<SumRequest>) -> String {
+        (a + b).to_string()
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Calculator {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn streamable_http_router(stateful_mode: bool) -> Router {
+    let service = StreamableHttpService::new(
+        || Ok(Calculator::new()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig {
+            stateful_mode,
+            ..Default::default()
+        },
+    );
+    Router::new().nest_service("/mcp", service)
+}
+
+async fn serve_tcp(router: Router) -> anyhow::Result<(String, CancellationToken)> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let ct = CancellationToken::new();
+    let shutdown = ct.clone();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+    });
+    Ok((format!("http://{addr}"), ct))
+}
+
+async fn call_sum<C: rmcp::transport::streamable_http_client::StreamableHttpClient>(
+    transport: StreamableHttpClientTransport<C>,
+) -> anyhow::Result<()> {
+    let client = ().serve(transport).await?;
+    let tools = client.list_all_tools().await?;
+    assert_eq!(tools.len(), 1);
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "sum".into(),
+            arguments: json!({ "a": 1, "b": 2 }).as_object().cloned(),
+        })
+        .await?;
+    assert_eq!(result.content.unwrap()[0].as_text().unwrap().text, "3");
+    assert!(matches!(client.cancel().await?, QuitReason::Cancelled));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_streamable_http() -> anyhow::Result<()> {
+    for stateful_mode in [true, false] {
+        let (base, ct) = serve_tcp(streamable_http_router(stateful_mode)).await?;
+        let transport = StreamableHttpClientTransport::with_client(
+            HyperClient::new(),
+            StreamableHttpClientTransportConfig::with_uri(format!("{base}/mcp")),
+        );
+        call_sum(transport).await?;
+        ct.cancel();
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_streamable_http_unix_socket() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("rmcp-hyper-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    let server = tokio::spawn(axum::serve(listener, streamable_http_router(true)).into_future());
+
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::unix(&path),
+        StreamableHttpClientTransportConfig::with_uri("http://localhost/mcp"),
+    );
+    call_sum(transport).await?;
+    server.abort();
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sse() -> anyhow::Result<()> {
+    let ct = CancellationToken::new();
+    let (sse_server, router) = SseServer::new(SseServerConfig {
+        bind: "127.0.0.1:0".parse()?,
+        sse_path: "/sse".to_string(),
+        post_path: "/message".to_string(),
+        ct: ct.clone(),
+        sse_keep_alive: None,
+        limits: Default::default(),
+    });
+    let (base, server_ct) = serve_tcp(router).await?;
+    sse_server.with_service(Calculator::new);
+
+    let transport = SseClientTransport::start_with_client(
+        HyperClient::new(),
+        SseClientConfig {
+            sse_endpoint: format!("{base}/sse").into(),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let client = ().serve(transport).await?;
+    assert_eq!(client.list_all_tools().await?.len(), 1);
+    client.cancel().await?;
+    ct.cancel();
+    server_ct.cancel();
+    Ok(())
+}
+
+fn tool_list_changed(id: &str) -> Result<Event, std::io::Error> {
+    Ok(Event::default()
+        .id(id)
+        .data(r#"{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}"#))
+}
+
+/// Aborts the response once the previous events were flushed, the client sees the connection
+/// drop.
+fn broken_stream() -> impl futures::Stream<Item = Result<Event, std::io::Error>> {
+    stream::once(async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::ConnectionReset,
+            "stream broken",
+        ))
+    })
+}
+
+/// Every reconnection of the event stream, with its `Last-Event-Id`.
+type Reconnections = Arc<Mutex<Vec<Option<String>>>>;
+
+fn last_event_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+#[tokio::test]
+async fn test_sse_reconnect() -> anyhow::Result<()> {
+    let connections = Reconnections::default();
+    let router = Router::new().route(
+        "/sse",
+        get({
+            let connections = connections.clone();
+            move |headers: HeaderMap| async move {
+                let last_event_id = last_event_id(&headers);
+                let first = {
+                    let mut connections = connections.lock().unwrap();
+                    connections.push(last_event_id);
+                    connections.len() == 1
+                };
+                // the first stream breaks after a message, the next one stays open
+                let events = if first {
+                    let endpoint = Event::default().event("endpoint").data("/message");
+                    futures::StreamExt::boxed(futures::StreamExt::chain(
+                        stream::iter([Ok(endpoint), tool_list_changed("1")]),
+                        broken_stream(),
+                    ))
+                } else {
+                    futures::StreamExt::boxed(futures::StreamExt::chain(
+                        stream::iter([tool_list_changed("2")]),
+                        stream::pending(),
+                    ))
+                };
+                Sse::new(events)
+            }
+        }),
+    );
+    let (base, ct) = serve_tcp(router).await?;
+
+    let mut transport = SseClientTransport::start_with_client(
+        HyperClient::new(),
+        SseClientConfig {
+            sse_endpoint: format!("{base}/sse").into(),
+            retry_policy: Arc::new(FixedInterval {
+                max_times: Some(3),
+                duration: Duration::from_millis(10),
+            }),
+            ..Default::default()
+        },
+    )
+    .await?;
+    for _ in 0..2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), transport.receive())
+            .await?
+            .expect("a message");
+        assert!(matches!(message, ServerJsonRpcMessage::Notification(_)));
+    }
+    assert_eq!(*connections.lock().unwrap(), [None, Some("1".to_string())]);
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_streamable_http_reconnect() -> anyhow::Result<()> {
+    let connections = Reconnections::default();
+    let router = Router::new().route(
+        "/mcp",
+        get({
+            let connections = connections.clone();
+            move |headers: HeaderMap| async move {
+                let last_event_id = last_event_id(&headers);
+                let first = {
+                    let mut connections = connections.lock().unwrap();
+                    connections.push(last_event_id);
+                    connections.len() == 1
+                };
+                // the first stream breaks after a message, the next one stays open
+                let events = if first {
+                    futures::StreamExt::boxed(futures::StreamExt::chain(
+                        stream::iter([tool_list_changed("1")]),
+                        broken_stream(),
+                    ))
+                } else {
+                    futures::StreamExt::boxed(futures::StreamExt::chain(
+                        stream::iter([tool_list_changed("2")]),
+                        stream::pending(),
+                    ))
+                };
+                Sse::new(events)
+            }
+        })
+        .post(|body: String| async move {
+            let message: serde_json::Value = serde_json::from_str(&body).unwrap();
+            if message["method"] == "initialize" {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "result": {
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": {},
+                        "serverInfo": { "name": "test", "version": "0" }
+                    }
+                });
+                (
+                    StatusCode::OK,
+                    [
+                        ("content-type", "application/json"),
+                        ("mcp-session-id", "session"),
+                    ],
+                    response.to_string(),
+                )
+                    .into_response()
+            } else {
+                StatusCode::ACCEPTED.into_response()
+            }
+        }),
+    );
+    let (base, ct) = serve_tcp(router).await?;
+
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::new(),
+        StreamableHttpClientTransportConfig {
+            retry_config: Arc::new(FixedInterval {
+                max_times: Some(3),
+                duration: Duration::from_millis(10),
+            }),
+            ..StreamableHttpClientTransportConfig::with_uri(format!("{base}/mcp"))
+        },
+    );
+    let client = MockClient::new().serve(transport).await?;
+    for _ in 0..2 {
+        client
+            .service()
+            .expect_notification(Duration::from_secs(5), |notification| {
+                matches!(
+                    notification,
+                    ServerNotification::ToolListChangedNotification(_)
+                )
+                .then_some(())
+            })
+            .await?;
+    }
+    assert_eq!(*connections.lock().unwrap(), [None, Some("1".to_string())]);
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
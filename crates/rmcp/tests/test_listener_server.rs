@@ -0,0 +1,160 @@
+use std::{net::SocketAddr, time::Duration};
+
+use rmcp::{
+    ServerHandler, ServiceExt,
+    model::{CallToolRequestParam, CallToolResult, Content, ErrorData},
+    service::{QuitReason, RequestContext, RoleServer},
+    transport::listener::{ConnectionInfo, ListenerServer, StreamListener},
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+/// Tells the client its address, or closes the connection on `disconnect`.
+#[derive(Clone)]
+struct WhoAmI;
+
+impl ServerHandler for WhoAmI {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let peer_addr = match context.extensions.get::<ConnectionInfo<SocketAddr>>() {
+            Some(info) if request.name == "disconnect" => {
+                info.ct.cancel();
+                return Ok(CallToolResult::success(vec![]));
+            }
+            Some(info) => info.peer_addr.to_string(),
+            None => "unknown".to_string(),
+        };
+        Ok(CallToolResult::success(vec![Content::text(peer_addr)]))
+    }
+}
+
+fn call(name: &'static str) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: None,
+    }
+}
+
+fn text(result: CallToolResult) -> String {
+    result.content.unwrap()[0].as_text().unwrap().text.clone()
+}
+
+async fn start_server(
+    max_connections: Option<usize>,
+) -> anyhow::Result<(SocketAddr, CancellationToken)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let mut server = ListenerServer::new(listener);
+    server.config.max_connections = max_connections;
+    Ok((addr, server.with_service(|| WhoAmI)))
+}
+
+#[tokio::test]
+async fn test_peer_addr_in_extensions() -> anyhow::Result<()> {
+    let (addr, ct) = start_server(None).await?;
+    for _ in 0..2 {
+        let stream = TcpStream::connect(addr).await?;
+        let local_addr = stream.local_addr()?;
+        let client = ().serve(stream).await?;
+        let result = client.call_tool(call("whoami")).await?;
+        assert_eq!(text(result), local_addr.to_string());
+        client.cancel().await?;
+    }
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connection_limit() -> anyhow::Result<()> {
+    let (addr, ct) = start_server(Some(1)).await?;
+    let first = ().serve(TcpStream::connect(addr).await?).await?;
+
+    // the second connection waits until the first one is closed
+    let stream = TcpStream::connect(addr).await?;
+    let second = tokio::spawn(().serve(stream));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(!second.is_finished());
+
+    first.cancel().await?;
+    let second = tokio::time::timeout(Duration::from_secs(5), second).await???;
+    second.call_tool(call("whoami")).await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_per_connection_cancellation() -> anyhow::Result<()> {
+    let (addr, ct) = start_server(None).await?;
+    let first = ().serve(TcpStream::connect(addr).await?).await?;
+    let second = ().serve(TcpStream::connect(addr).await?).await?;
+
+    // the response may or may not make it before the connection is closed
+    let _ = first.call_tool(call("disconnect")).await;
+    let reason = tokio::time::timeout(Duration::from_secs(5), first.waiting()).await??;
+    assert!(matches!(reason, QuitReason::Closed));
+
+    second.call_tool(call("whoami")).await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_graceful_stop() -> anyhow::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = ListenerServer::new(listener);
+    let ct = server.config.ct.clone();
+    let serving = tokio::spawn(server.serve(|| WhoAmI));
+
+    let client = ().serve(TcpStream::connect(addr).await?).await?;
+    client.call_tool(call("whoami")).await?;
+
+    // stops accepting, closes the connections and waits for them
+    ct.cancel();
+    tokio::time::timeout(Duration::from_secs(5), serving).await??;
+    let reason = tokio::time::timeout(Duration::from_secs(5), client.waiting()).await??;
+    assert!(matches!(reason, QuitReason::Closed));
+    assert!(TcpStream::connect(addr).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stream_listener() -> anyhow::Result<()> {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let server = ListenerServer::new(StreamListener::new(
+        tokio_stream::wrappers::UnboundedReceiverStream::new(rx),
+    ));
+    let serving = tokio::spawn(server.serve(|| WhoAmI));
+
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    tx.send(server_io)?;
+    let client = ().serve(client_io).await?;
+    // there is no address for these connections
+    assert_eq!(text(client.call_tool(call("whoami")).await?), "unknown");
+
+    // the server stops once the stream ended and its connections are closed
+    drop(tx);
+    assert!(!serving.is_finished());
+    client.cancel().await?;
+    tokio::time::timeout(Duration::from_secs(5), serving).await??;
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("rmcp-listener-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let ct = ListenerServer::new(tokio::net::UnixListener::bind(&path)?).with_service(|| WhoAmI);
+
+    let client = ().serve(tokio::net::UnixStream::connect(&path).await?).await?;
+    // a unix socket peer has its own address type
+    assert_eq!(text(client.call_tool(call("whoami")).await?), "unknown");
+    client.cancel().await?;
+    ct.cancel();
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
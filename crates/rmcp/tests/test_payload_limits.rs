@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use http::{Request, StatusCode};
+use http_body_util::{BodyExt, Full};
+use rmcp::{
+    ServiceExt,
+    model::{CallToolRequestParam, CallToolResult, Content, JsonObject, Tool},
+    testing::MockServer,
+    transport::{
+        PayloadLimits, SseServer, StreamableHttpServerConfig, StreamableHttpService,
+        async_rw::AsyncRwTransport, common::limits::DEFAULT_MAX_MESSAGE_BYTES,
+        sse_server::SseServerConfig, streamable_http_server::session::local::LocalSessionManager,
+    },
+};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tower_service::Service;
+
+fn limits() -> PayloadLimits {
+    PayloadLimits::default()
+        .max_message_bytes(1024)
+        .max_batch_size(2)
+        .max_depth(8)
+}
+
+fn nested(depth: usize) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":1,"method":"ping","params":{{"_meta":{}{}}}}}"#,
+        "[".repeat(depth),
+        "]".repeat(depth)
+    )
+}
+
+fn initialize() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "0" }
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_async_rw_rejects_and_continues() -> anyhow::Result<()> {
+    let (client, server) = tokio::io::duplex(4096);
+    let (server_read, server_write) = tokio::io::split(server);
+    let transport = AsyncRwTransport::with_limits(server_read, server_write, limits());
+    let server = tokio::spawn(MockServer::new().serve(transport));
+
+    let (client_read, mut client_write) = tokio::io::split(client);
+    let mut lines = BufReader::new(client_read).lines();
+    let mut exchange = async |line: String| -> anyhow::Result<Value> {
+        client_write.write_all(line.as_bytes()).await?;
+        client_write.write_all(b"\n").await?;
+        let reply = lines.next_line().await?.expect("a reply");
+        Ok(serde_json::from_str(&reply)?)
+    };
+
+    let reply = exchange("{not json".into()).await?;
+    assert_eq!(reply["id"], Value::Null);
+    assert_eq!(reply["error"]["code"], -32700);
+
+    let reply = exchange(r#"{"jsonrpc":"2.0","id":"a","method":5}"#.into()).await?;
+    assert_eq!(reply["id"], "a");
+    assert_eq!(reply["error"]["code"], -32600);
+
+    let reply = exchange(format!(r#"{{"padding":"{}"}}"#, "x".repeat(2048))).await?;
+    assert_eq!(reply["error"]["code"], -32600);
+
+    let reply = exchange(nested(16)).await?;
+    assert_eq!(reply["error"]["code"], -32600);
+
+    // the connection is still usable
+    let reply = exchange(initialize().to_string()).await?;
+    assert_eq!(reply["id"], 0);
+    assert!(reply["result"]["serverInfo"].is_object());
+
+    let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    client_write
+        .write_all(format!("{initialized}\n").as_bytes())
+        .await?;
+    client_write.shutdown().await?;
+    server.await??.waiting().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clients_read_oversized_responses() -> anyhow::Result<()> {
+    let size = 3 * DEFAULT_MAX_MESSAGE_BYTES / 2;
+    let tool = Tool::new("image", "a large result", Arc::new(JsonObject::default()));
+    let server = MockServer::new().tool(tool, move |_, _| async move {
+        Ok(CallToolResult::success(vec![Content::text(
+            "x".repeat(size),
+        )]))
+    });
+    let (client, server_io) = tokio::io::duplex(64 * 1024);
+    let server = tokio::spawn(server.serve(tokio::io::split(server_io)));
+    let client = ().serve(tokio::io::split(client)).await?;
+
+    let result = client
+        .call_tool(CallToolRequestParam {
+            name: "image".into(),
+            arguments: None,
+        })
+        .await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text.len(),
+        size
+    );
+    client.cancel().await?;
+    server.await??.waiting().await?;
+    Ok(())
+}
+
+fn post(body: impl Into<Bytes>, uri: &str) -> Request<Full<Bytes>> {
+    Request::post(uri)
+        .header("accept", "application/json, text/event-stream")
+        .header("content-type", "application/json")
+        .body(Full::new(body.into()))
+        .unwrap()
+}
+
+async fn rejection<S>(service: &mut S, request: Request<Full<Bytes>>) -> (StatusCode, Value)
+where
+    S: Service<Request<Full<Bytes>>, Response = http::Response<axum::body::Body>>,
+    S::Error: std::fmt::Debug,
+{
+    let response = service.call(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_streamable_http_rejects_payloads() -> anyhow::Result<()> {
+    let service = StreamableHttpService::new(
+        || Ok(MockServer::new()),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig {
+            limits: limits(),
+            ..Default::default()
+        },
+    );
+    let mut service = axum::Router::new().nest_service("/mcp", service);
+
+    let (status, body) = rejection(&mut service, post("x".repeat(2048), "/mcp")).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["error"]["code"], -32600);
+
+    let (status, body) = rejection(&mut service, post("{\"jsonrpc\"", "/mcp")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["id"], Value::Null);
+    assert_eq!(body["error"]["code"], -32700);
+
+    let (status, body) = rejection(&mut service, post(nested(16), "/mcp")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], -32600);
+
+    let batch = json!([initialize(), initialize(), initialize()]).to_string();
+    let (status, body) = rejection(&mut service, post(batch, "/mcp")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], -32600);
+
+    let response = service.call(post(initialize().to_string(), "/mcp")).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sse_server_rejects_payloads() -> anyhow::Result<()> {
+    let (_server, mut router) = SseServer::new(SseServerConfig {
+        bind: "127.0.0.1:0".parse()?,
+        sse_path: "/sse".to_string(),
+        post_path: "/message".to_string(),
+        ct: Default::default(),
+        sse_keep_alive: None,
+        limits: limits(),
+    });
+
+    let uri = "/message?sessionId=unknown";
+    let (status, body) = rejection(&mut router, post("x".repeat(2048), uri)).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["error"]["code"], -32600);
+
+    let (status, body) = rejection(&mut router, post("{oops}", uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["code"], -32700);
+    Ok(())
+}
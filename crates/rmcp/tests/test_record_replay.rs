@@ -0,0 +1,192 @@
+use std::{path::PathBuf, time::Duration};
+
+use rmcp::{
+    RoleServer, ServiceExt,
+    model::*,
+    testing::MockServer,
+    transport::{
+        memory,
+        record::{Direction, Divergence, Recording, RecordingTransport, ReplayTransport},
+    },
+};
+use serde_json::json;
+
+fn recording_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rmcp-{name}-{}.jsonl", std::process::id()))
+}
+
+fn echo_server(prefix: &'static str, delay: Duration) -> MockServer {
+    MockServer::new().tool(
+        Tool::new("echo", "Echo the text", JsonObject::new()),
+        move |arguments, _context| async move {
+            tokio::time::sleep(delay).await;
+            let text = arguments["text"].as_str().unwrap_or_default();
+            Ok(CallToolResult::success(vec![Content::text(format!(
+                "{prefix}{text}"
+            ))]))
+        },
+    )
+}
+
+/// Records a session with the server, from the server side or the client side.
+async fn record_session(path: &PathBuf, server_side: bool) -> anyhow::Result<()> {
+    let (client_transport, server_transport) = memory::pair();
+    let (server, client) = if server_side {
+        let server_transport = RecordingTransport::to_path(server_transport, path)?;
+        tokio::try_join!(
+            async {
+                Ok::<_, anyhow::Error>(
+                    echo_server("", Duration::ZERO)
+                        .serve(server_transport)
+                        .await?,
+                )
+            },
+            async { Ok(().serve(client_transport).await?) },
+        )?
+    } else {
+        let client_transport = RecordingTransport::to_path(client_transport, path)?;
+        tokio::try_join!(
+            async {
+                Ok::<_, anyhow::Error>(
+                    echo_server("", Duration::ZERO)
+                        .serve(server_transport)
+                        .await?,
+                )
+            },
+            async { Ok(().serve(client_transport).await?) },
+        )?
+    };
+    client.list_all_tools().await?;
+    client
+        .call_tool(CallToolRequestParam {
+            name: "echo".into(),
+            arguments: json!({ "text": "hello" }).as_object().cloned(),
+        })
+        .await?;
+    client.cancel().await?;
+    server.waiting().await?;
+    Ok(())
+}
+
+async fn replay(
+    recording: Recording,
+    server: MockServer,
+    timeout: Duration,
+) -> anyhow::Result<Vec<Divergence>> {
+    let transport = ReplayTransport::<RoleServer>::new(recording)?.timeout(timeout);
+    let report = transport.report();
+    let server = server.serve(transport).await?;
+    server.waiting().await?;
+    assert!(report.is_finished());
+    Ok(report.divergences())
+}
+
+#[tokio::test]
+async fn test_record_and_replay() -> anyhow::Result<()> {
+    let path = recording_path("record-server");
+    record_session(&path, true).await?;
+    let recording = Recording::from_path(&path)?;
+    std::fs::remove_file(&path)?;
+
+    let directions = recording
+        .messages
+        .iter()
+        .map(|message| message.direction)
+        .collect::<Vec<_>>();
+    use Direction::*;
+    assert_eq!(
+        directions,
+        [Received, Sent, Received, Received, Sent, Received, Sent]
+    );
+    assert_eq!(recording.messages[0].message["method"], "initialize");
+    assert_eq!(
+        recording.messages[6].message["result"]["content"][0]["text"],
+        "hello"
+    );
+
+    // the same server behaves the same, however slow it is
+    let divergences = replay(
+        recording.clone(),
+        echo_server("", Duration::from_millis(50)),
+        Duration::from_secs(5),
+    )
+    .await?;
+    assert_eq!(divergences, []);
+
+    // a changed server is reported
+    let divergences = replay(
+        recording,
+        echo_server("echo: ", Duration::ZERO),
+        Duration::from_secs(5),
+    )
+    .await?;
+    let [
+        Divergence::Mismatch {
+            index: 6, actual, ..
+        },
+    ] = &divergences[..]
+    else {
+        panic!("unexpected divergences {divergences:?}");
+    };
+    assert_eq!(actual["result"]["content"][0]["text"], "echo: hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_missing() -> anyhow::Result<()> {
+    let path = recording_path("record-missing");
+    record_session(&path, true).await?;
+    let recording = Recording::from_path(&path)?;
+    std::fs::remove_file(&path)?;
+
+    // the tool result comes too late
+    let divergences = replay(
+        recording,
+        echo_server("", Duration::from_millis(300)),
+        Duration::from_millis(100),
+    )
+    .await?;
+    assert!(
+        matches!(divergences[0], Divergence::Missing { index: 6, .. }),
+        "{divergences:?}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_client_recording() -> anyhow::Result<()> {
+    let path = recording_path("record-client");
+    record_session(&path, false).await?;
+    let recording = Recording::from_path(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(recording.messages[0].direction, Direction::Sent);
+
+    let divergences = replay(
+        recording.reversed(),
+        echo_server("", Duration::ZERO),
+        Duration::from_secs(5),
+    )
+    .await?;
+    assert_eq!(divergences, []);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ignore_pointer() -> anyhow::Result<()> {
+    let path = recording_path("record-ignore");
+    record_session(&path, true).await?;
+    let mut recording = Recording::from_path(&path)?;
+    std::fs::remove_file(&path)?;
+    recording.messages[1].message["result"]["serverInfo"]["version"] = json!("0.0.0");
+
+    let transport = ReplayTransport::<RoleServer>::new(recording)?
+        .ignore("/result/serverInfo/version")
+        .ignore("/result/content/0/text");
+    let report = transport.report();
+    let server = echo_server("changed ", Duration::ZERO)
+        .serve(transport)
+        .await?;
+    server.waiting().await?;
+    assert_eq!(report.divergences(), []);
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use std::{sync::Arc, time::Duration};
+
+use futures::FutureExt;
+use rmcp::{
+    ErrorData, ServerHandler,
+    handler::server::{
+        registry::{PromptRoute, Registry, ResourceRoute},
+        router::tool::ToolRoute,
+    },
+    model::*,
+    service::{NotificationContext, RequestContext, RoleServer},
+    testing::connect,
+    tool, tool_handler, tool_router,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone)]
+struct Plugins {
+    registry: Registry<Self>,
+}
+
+#[tool_router]
+impl Plugins {
+    #[tool(description = "Say hello")]
+    async fn hello(&self) -> String {
+        "hello".into()
+    }
+}
+
+#[tool_handler(router = self.registry)]
+impl ServerHandler for Plugins {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .enable_prompts()
+                .enable_prompts_list_changed()
+                .enable_resources()
+                .enable_resources_list_changed()
+                .build(),
+            ..Default::default()
+        }
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.registry.attach(context.peer);
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, ErrorData> {
+        Ok(ListPromptsResult::with_all_items(
+            self.registry.list_prompts(),
+        ))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        self.registry.get_prompt(self, request, context).await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        Ok(ListResourcesResult::with_all_items(
+            self.registry.list_resources(),
+        ))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        self.registry.read_resource(self, request, context).await
+    }
+}
+
+fn tool(name: &'static str, text: &'static str) -> ToolRoute<Plugins> {
+    ToolRoute::new_dyn(
+        Tool::new(name, text, Arc::new(JsonObject::new())),
+        move |_| async move { Ok(CallToolResult::success(vec![Content::text(text)])) }.boxed(),
+    )
+}
+
+fn call(name: &'static str) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: None,
+    }
+}
+
+async fn attached(registry: &Registry<Plugins>, count: usize) {
+    while registry.peer_count() < count {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_registry_tools() -> anyhow::Result<()> {
+    let registry = Registry::with_debounce(DEBOUNCE).with_tools(Plugins::tool_router());
+    let first = connect(Plugins {
+        registry: registry.clone(),
+    })
+    .await?;
+    let second = connect(Plugins {
+        registry: registry.clone(),
+    })
+    .await?;
+    attached(&registry, 2).await;
+
+    assert!(registry.add_tool(tool("install", "installed")));
+    assert!(!registry.add_tool(tool("install", "ignored")));
+    assert!(
+        registry
+            .replace_tool(tool("install", "reinstalled"))
+            .is_some()
+    );
+    assert!(registry.remove_tool("hello").is_some());
+    assert!(registry.remove_tool("hello").is_none());
+
+    for connection in [&first, &second] {
+        connection
+            .mock_client()
+            .expect_tool_list_changed(DEBOUNCE * 10)
+            .await?;
+    }
+    tokio::time::sleep(DEBOUNCE * 3).await;
+    // the changes were announced once
+    for connection in [&first, &second] {
+        assert!(connection.mock_client().take_notifications().is_empty());
+    }
+
+    let tools = second.client.list_all_tools().await?;
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].name, "install");
+    let result = first.client.call_tool(call("install")).await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        "reinstalled"
+    );
+    assert!(first.client.call_tool(call("hello")).await.is_err());
+
+    second.close().await;
+    assert_eq!(registry.peer_count(), 1);
+    first.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_changes_outside_runtime() -> anyhow::Result<()> {
+    let registry = Registry::with_debounce(DEBOUNCE);
+    let connection = connect(Plugins {
+        registry: registry.clone(),
+    })
+    .await?;
+    attached(&registry, 1).await;
+
+    // a single thread adds the tool, without a runtime to announce it
+    let added = std::thread::scope(|scope| {
+        let threads = (0..4)
+            .map(|_| scope.spawn(|| registry.add_tool(tool("install", "installed"))))
+            .collect::<Vec<_>>();
+        threads
+            .into_iter()
+            .filter_map(|thread| thread.join().unwrap().then_some(()))
+            .count()
+    });
+    assert_eq!(added, 1);
+    let client = connection.mock_client();
+    tokio::time::sleep(DEBOUNCE * 3).await;
+    assert!(client.take_notifications().is_empty());
+
+    // the next change announces both
+    registry.add_prompt(PromptRoute::new(
+        Prompt::new("greet", Some("Greet someone"), None),
+        |_, _, _| {
+            async {
+                Ok(GetPromptResult {
+                    description: None,
+                    messages: Vec::new(),
+                })
+            }
+            .boxed()
+        },
+    ));
+    client.expect_tool_list_changed(DEBOUNCE * 10).await?;
+    client.expect_prompt_list_changed(DEBOUNCE * 10).await?;
+    connection.close().await;
+    Ok(())
+}
+
+#[cfg(feature = "schema-validation")]
+#[tokio::test]
+async fn test_registry_input_validation() -> anyhow::Result<()> {
+    use rmcp::{ServiceError, handler::server::router::tool::ToolRouter};
+
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "required": ["name"]
+    });
+    let greet = ToolRoute::new_dyn(
+        Tool::new(
+            "greet",
+            "Greet someone",
+            Arc::new(schema.as_object().unwrap().clone()),
+        ),
+        |_| async { Ok(CallToolResult::success(vec![Content::text("hello")])) }.boxed(),
+    );
+    let registry = Registry::with_debounce(DEBOUNCE)
+        .with_tools(ToolRouter::new().with_input_validation().with_route(greet))
+        .with_tools(Plugins::tool_router());
+    let connection = connect(Plugins {
+        registry: registry.clone(),
+    })
+    .await?;
+
+    let result = connection
+        .client
+        .call_tool(CallToolRequestParam {
+            name: "greet".into(),
+            arguments: serde_json::json!({ "name": "ada" }).as_object().cloned(),
+        })
+        .await?;
+    assert_eq!(result.content.unwrap()[0].as_text().unwrap().text, "hello");
+    let error = connection
+        .client
+        .call_tool(call("greet"))
+        .await
+        .unwrap_err();
+    let ServiceError::McpError(error) = error else {
+        panic!("unexpected error {error}");
+    };
+    assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_registry_prompts_and_resources() -> anyhow::Result<()> {
+    let registry = Registry::with_debounce(DEBOUNCE);
+    let connection = connect(Plugins {
+        registry: registry.clone(),
+    })
+    .await?;
+    attached(&registry, 1).await;
+
+    registry.add_prompt(PromptRoute::new(
+        Prompt::new("greet", Some("Greet someone"), None),
+        |_, arguments, _| {
+            let name = arguments
+                .and_then(|arguments| arguments.get("name").cloned())
+                .and_then(|name| name.as_str().map(str::to_owned))
+                .unwrap_or_default();
+            async move {
+                Ok(GetPromptResult {
+                    description: None,
+                    messages: vec![PromptMessage::new_text(
+                        PromptMessageRole::User,
+                        format!("Hello {name}"),
+                    )],
+                })
+            }
+            .boxed()
+        },
+    ));
+    registry.add_resource(ResourceRoute::new(
+        RawResource::new("memo://today", "today").no_annotation(),
+        |_, _| {
+            async {
+                Ok(ReadResourceResult {
+                    contents: vec![ResourceContents::text("sunny", "memo://today")],
+                })
+            }
+            .boxed()
+        },
+    ));
+    let client = connection.mock_client();
+    client.expect_prompt_list_changed(DEBOUNCE * 10).await?;
+    client.expect_resource_list_changed(DEBOUNCE * 10).await?;
+
+    let prompt = connection
+        .client
+        .get_prompt(GetPromptRequestParam {
+            name: "greet".into(),
+            arguments: Some(object(serde_json::json!({ "name": "Ada" }))),
+        })
+        .await?;
+    let PromptMessageContent::Text { text } = &prompt.messages[0].content else {
+        panic!("expected a text message");
+    };
+    assert_eq!(text, "Hello Ada");
+
+    let resources = connection.client.list_all_resources().await?;
+    assert_eq!(resources[0].uri, "memo://today");
+    let read = connection
+        .client
+        .read_resource(ReadResourceRequestParam {
+            uri: "memo://today".into(),
+        })
+        .await?;
+    assert_eq!(read.contents.len(), 1);
+
+    assert!(registry.remove_resource("memo://today").is_some());
+    client.expect_resource_list_changed(DEBOUNCE * 10).await?;
+    let error = connection
+        .client
+        .read_resource(ReadResourceRequestParam {
+            uri: "memo://today".into(),
+        })
+        .await
+        .unwrap_err();
+    let rmcp::ServiceError::McpError(error) = error else {
+        panic!("unexpected error {error}");
+    };
+    assert_eq!(error.code, ErrorCode::RESOURCE_NOT_FOUND);
+    connection.close().await;
+    Ok(())
+}
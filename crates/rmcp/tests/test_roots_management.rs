@@ -0,0 +1,152 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use rmcp::{
+    ServerHandler, ServiceExt,
+    handler::{client::roots::RootsManager, server::roots::RootsCache},
+    model::{ErrorCode, Root},
+    service::{NotificationContext, RoleServer},
+    transport::memory,
+};
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Default)]
+struct FileServer {
+    roots: RootsCache,
+    changed: Arc<Notify>,
+}
+
+impl ServerHandler for FileServer {
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        self.roots.invalidate(&context.peer);
+        self.changed.notify_one();
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rmcp-roots-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::create_dir_all(dir.join("b")).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+fn uris(roots: &[Root]) -> Vec<&str> {
+    roots.iter().map(|root| root.uri.as_str()).collect()
+}
+
+#[tokio::test]
+async fn test_roots_cache_and_guard() -> anyhow::Result<()> {
+    let dir = temp_dir("guard");
+    let a = Root::from_path(dir.join("a"));
+    let b = Root::from_path(dir.join("b"));
+    let roots = RootsManager::new().with_root(a.clone());
+    let server = FileServer::default();
+
+    let (client_transport, server_transport) = memory::pair();
+    let (server, client) = tokio::join!(
+        server.serve(server_transport),
+        roots.clone().serve(client_transport)
+    );
+    let (server, client) = (server?, client?);
+    let cache = &server.service().roots;
+    let changed = server.service().changed.clone();
+    assert_eq!(
+        server
+            .peer_info()
+            .unwrap()
+            .capabilities
+            .roots
+            .as_ref()
+            .unwrap()
+            .list_changed,
+        Some(true)
+    );
+
+    assert_eq!(uris(&cache.roots(server.peer()).await?), [a.uri.as_str()]);
+    let guard = cache.guard(server.peer()).await?;
+    assert_eq!(
+        guard.check(dir.join("a/notes.txt").to_str().unwrap())?,
+        dir.join("a/notes.txt")
+    );
+    let error = guard
+        .check(dir.join("a/../b/notes.txt").to_str().unwrap())
+        .unwrap_err();
+    assert_eq!(error.code, ErrorCode::FORBIDDEN);
+    assert!(guard.check(&format!("{}/notes.txt", b.uri)).is_err());
+
+    // the client attached itself when the roots were listed
+    assert!(roots.add(b.clone()));
+    tokio::time::timeout(Duration::from_secs(1), changed.notified()).await?;
+    let listed = cache.roots(server.peer()).await?;
+    assert_eq!(uris(&listed), [a.uri.as_str(), b.uri.as_str()]);
+    assert!(
+        cache
+            .guard(server.peer())
+            .await?
+            .check(&format!("{}/notes.txt", b.uri))
+            .is_ok()
+    );
+
+    assert!(roots.remove(&a.uri));
+    tokio::time::timeout(Duration::from_secs(1), changed.notified()).await?;
+    assert_eq!(uris(&cache.roots(server.peer()).await?), [b.uri.as_str()]);
+
+    client.cancel().await?;
+    server.cancel().await?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_roots_watcher() -> anyhow::Result<()> {
+    let dir = temp_dir("watch");
+    let a = Root::from_path(dir.join("a"));
+    let b = Root::from_path(dir.join("b"));
+    let missing = Root::from_path(dir.join("c"));
+    let roots = RootsManager::new()
+        .with_root(a.clone())
+        .with_root(b.clone())
+        .with_root(missing.clone())
+        .with_root(Root::new("https://example.com/repo"));
+    assert_eq!(roots.roots().len(), 4);
+    assert_eq!(
+        uris(&roots.list_roots().roots),
+        [a.uri.as_str(), b.uri.as_str(), "https://example.com/repo"]
+    );
+
+    let server = FileServer::default();
+    let (client_transport, server_transport) = memory::pair();
+    let (server, client) = tokio::join!(
+        server.serve(server_transport),
+        roots.clone().serve(client_transport)
+    );
+    let (server, client) = (server?, client?);
+    roots.attach(client.peer().clone());
+    let cache = &server.service().roots;
+    let changed = server.service().changed.clone();
+    let watcher = roots.watch(Duration::from_millis(10));
+
+    std::fs::remove_dir(dir.join("b"))?;
+    tokio::time::timeout(Duration::from_secs(1), changed.notified()).await?;
+    assert_eq!(
+        uris(&cache.roots(server.peer()).await?),
+        [a.uri.as_str(), "https://example.com/repo"]
+    );
+
+    std::fs::create_dir(dir.join("c"))?;
+    tokio::time::timeout(Duration::from_secs(1), changed.notified()).await?;
+    assert_eq!(
+        uris(&cache.roots(server.peer()).await?),
+        [
+            a.uri.as_str(),
+            missing.uri.as_str(),
+            "https://example.com/repo"
+        ]
+    );
+
+    watcher.abort();
+    client.cancel().await?;
+    server.cancel().await?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
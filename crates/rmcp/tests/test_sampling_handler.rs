@@ -0,0 +1,223 @@
+use futures::future::BoxFuture;
+use rmcp::{
+    RoleClient, RoleServer, ServiceExt,
+    handler::client::sampling::{SamplingApproval, SamplingHandler},
+    model::*,
+    service::{RunningService, ServiceError},
+    testing::{FakeSamplingProvider, MockServer},
+    transport::memory,
+};
+
+type Connection = (
+    RunningService<RoleServer, MockServer>,
+    RunningService<RoleClient, SamplingHandler>,
+);
+
+async fn serve(client: SamplingHandler) -> anyhow::Result<Connection> {
+    let (client_transport, server_transport) = memory::pair();
+    let (server, client) = tokio::join!(
+        MockServer::new().serve(server_transport),
+        client.serve(client_transport)
+    );
+    Ok((server?, client?))
+}
+
+fn request(text: &str, hint: Option<&str>) -> CreateMessageRequestParam {
+    CreateMessageRequestParam {
+        messages: vec![SamplingMessage {
+            role: Role::User,
+            content: Content::text(text),
+        }],
+        model_preferences: hint.map(|hint| ModelPreferences {
+            hints: Some(vec![ModelHint {
+                name: Some(hint.to_string()),
+            }]),
+            cost_priority: None,
+            speed_priority: None,
+            intelligence_priority: None,
+        }),
+        system_prompt: None,
+        include_context: Some(ContextInclusion::AllServers),
+        temperature: None,
+        max_tokens: 500,
+        stop_sequences: None,
+        metadata: None,
+    }
+}
+
+fn text(result: &CreateMessageResult) -> &str {
+    &result.message.content.as_text().unwrap().text
+}
+
+#[tokio::test]
+async fn test_sampling_routes_hints_and_enforces_limits() -> anyhow::Result<()> {
+    let claude = FakeSamplingProvider::new("claude-3-5-sonnet");
+    let gpt = FakeSamplingProvider::new("gpt-4o").with_reply("from gpt");
+    let (server, client) = serve(
+        SamplingHandler::new()
+            .with_model("claude-3-5-sonnet", claude.clone())
+            .with_model("gpt-4o", gpt.clone())
+            .with_context_inclusion(ContextInclusion::ThisServer)
+            .with_max_tokens(100),
+    )
+    .await?;
+    assert!(server.peer_info().unwrap().capabilities.sampling.is_some());
+
+    let result = server.create_message(request("hello", Some("gpt"))).await?;
+    assert_eq!(result.model, "gpt-4o");
+    assert_eq!(text(&result), "from gpt");
+
+    let result = server.create_message(request("hello", None)).await?;
+    assert_eq!(result.model, "claude-3-5-sonnet");
+    assert_eq!(text(&result), "hello");
+
+    let sent = gpt.requests();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].max_tokens, 100);
+    assert_eq!(sent[0].include_context, Some(ContextInclusion::ThisServer));
+    assert_eq!(claude.requests().len(), 1);
+    server.cancel().await?;
+    client.cancel().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sampling_token_budget() -> anyhow::Result<()> {
+    let provider = FakeSamplingProvider::new("local");
+    let handler = SamplingHandler::new()
+        .with_model("local", provider.clone())
+        .with_token_budget(700);
+    let (server, client) = serve(handler.clone()).await?;
+
+    server.create_message(request("first", None)).await?;
+    server.create_message(request("second", None)).await?;
+    assert_eq!(handler.remaining_budget(), Some(0));
+    let error = server
+        .create_message(request("third", None))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ServiceError::McpError(ErrorData {
+            code: ErrorCode::INVALID_REQUEST,
+            ..
+        })
+    ));
+    let granted: Vec<_> = provider.requests().iter().map(|r| r.max_tokens).collect();
+    assert_eq!(granted, [500, 200]);
+    server.cancel().await?;
+    client.cancel().await?;
+    Ok(())
+}
+
+struct RejectResults;
+
+impl SamplingApproval for RejectResults {
+    fn review_result<'a>(
+        &'a self,
+        _request: &'a CreateMessageRequestParam,
+        _result: CreateMessageResult,
+    ) -> BoxFuture<'a, Option<CreateMessageResult>> {
+        Box::pin(std::future::ready(None))
+    }
+}
+
+#[tokio::test]
+async fn test_sampling_token_budget_settlement() -> anyhow::Result<()> {
+    let handler = SamplingHandler::new()
+        .with_model("local", FakeSamplingProvider::new("local").with_usage(120))
+        .with_token_budget(700);
+    let (server, client) = serve(handler.clone()).await?;
+    // the unused tokens are given back
+    server.create_message(request("first", None)).await?;
+    assert_eq!(handler.remaining_budget(), Some(580));
+    server.cancel().await?;
+    client.cancel().await?;
+
+    let handler = SamplingHandler::new()
+        .with_model("local", FakeSamplingProvider::new("local"))
+        .with_approval(RejectResults)
+        .with_token_budget(700);
+    let (server, client) = serve(handler.clone()).await?;
+    // a rejected result gives back all of its tokens
+    let error = server
+        .create_message(request("rejected", None))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ServiceError::McpError(ErrorData {
+            code: ErrorCode::USER_REJECTED,
+            ..
+        })
+    ));
+    assert_eq!(handler.remaining_budget(), Some(700));
+    server.cancel().await?;
+    client.cancel().await?;
+    Ok(())
+}
+
+struct Reviewer;
+
+impl SamplingApproval for Reviewer {
+    fn review_request(
+        &self,
+        mut request: CreateMessageRequestParam,
+    ) -> BoxFuture<'_, Option<CreateMessageRequestParam>> {
+        let approved = text_of(&request) != "forbidden";
+        request.system_prompt = Some("be brief".into());
+        Box::pin(async move { approved.then_some(request) })
+    }
+
+    fn review_result<'a>(
+        &'a self,
+        _request: &'a CreateMessageRequestParam,
+        mut result: CreateMessageResult,
+    ) -> BoxFuture<'a, Option<CreateMessageResult>> {
+        result.message.content = Content::text("edited");
+        Box::pin(async move { Some(result) })
+    }
+}
+
+fn text_of(request: &CreateMessageRequestParam) -> &str {
+    &request.messages[0].content.as_text().unwrap().text
+}
+
+#[tokio::test]
+async fn test_sampling_approval() -> anyhow::Result<()> {
+    let provider = FakeSamplingProvider::new("local");
+    let (server, client) = serve(
+        SamplingHandler::new()
+            .with_model("local", provider.clone())
+            .with_approval(Reviewer),
+    )
+    .await?;
+
+    let result = server.create_message(request("hello", None)).await?;
+    assert_eq!(text(&result), "edited");
+    assert_eq!(
+        provider.requests()[0].system_prompt.as_deref(),
+        Some("be brief")
+    );
+    // no context is allowed by default
+    assert_eq!(
+        provider.requests()[0].include_context,
+        Some(ContextInclusion::None)
+    );
+
+    let error = server
+        .create_message(request("forbidden", None))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ServiceError::McpError(ErrorData {
+            code: ErrorCode::USER_REJECTED,
+            ..
+        })
+    ));
+    assert_eq!(provider.requests().len(), 1);
+    server.cancel().await?;
+    client.cancel().await?;
+    Ok(())
+}
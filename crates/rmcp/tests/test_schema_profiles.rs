@@ -0,0 +1,114 @@
+use rmcp::{
+    Json, ServerHandler,
+    handler::server::{router::tool::ToolRouter, schema_profile::SchemaProfile, tool::Parameters},
+    model::{ClientInfo, Implementation, ServerCapabilities, ServerInfo, Tool},
+    testing::{MockClient, connect_with},
+    tool, tool_handler, tool_router,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+struct Address {
+    street: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+enum Delivery {
+    Pickup,
+    Ship(Address),
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Order {
+    item: String,
+    #[schemars(email)]
+    email: String,
+    note: Option<String>,
+    billing: Option<Address>,
+    delivery: Delivery,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Receipt {
+    shipped_to: Option<Address>,
+}
+
+#[derive(Debug, Clone)]
+struct Shop {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Shop {
+    #[tool(description = "Place an order")]
+    async fn order(&self, Parameters(_order): Parameters<Order>) -> Json<Receipt> {
+        Json(Receipt { shipped_to: None })
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Shop {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+async fn list_as(name: &str) -> anyhow::Result<Tool> {
+    let client = MockClient::new().with_info(ClientInfo {
+        client_info: Implementation {
+            name: name.into(),
+            version: "1".into(),
+        },
+        ..Default::default()
+    });
+    let shop = Shop {
+        tool_router: Shop::tool_router()
+            .with_client_schema_profile("gemini", SchemaProfile::portable()),
+    };
+    let connection = connect_with(client, shop).await?;
+    let mut tools = connection.client.list_all_tools().await?;
+    connection.close().await;
+    Ok(tools.remove(0))
+}
+
+#[tokio::test]
+async fn test_schema_profile_by_client() -> anyhow::Result<()> {
+    let tool = list_as("inspector").await?;
+    let input = serde_json::to_string(&tool.input_schema)?;
+    assert!(input.contains("$ref"));
+    assert!(input.contains("\"format\":\"email\""));
+
+    let tool = list_as("Gemini CLI").await?;
+    for schema in [
+        serde_json::Value::Object((*tool.input_schema).clone()),
+        serde_json::Value::Object((*tool.output_schema.unwrap()).clone()),
+    ] {
+        let text = schema.to_string();
+        for keyword in ["$ref", "definitions", "nullable", "format", "oneOf", "null"] {
+            assert!(!text.contains(keyword), "{keyword} in {text}");
+        }
+    }
+    let input = &tool.input_schema;
+    let mut required = input["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|name| name.as_str().unwrap())
+        .collect::<Vec<_>>();
+    required.sort();
+    assert_eq!(required, ["delivery", "email", "item"]);
+    assert_eq!(input["properties"]["note"]["type"], "string");
+    assert_eq!(
+        input["properties"]["billing"]["properties"]["street"]["type"],
+        "string"
+    );
+    assert!(input["properties"]["delivery"]["anyOf"].is_array());
+    Ok(())
+}
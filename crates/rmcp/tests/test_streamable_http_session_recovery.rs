@@ -0,0 +1,234 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, Sse},
+    },
+    routing::get,
+};
+use futures::{StreamExt, stream};
+use rmcp::{
+    ServiceError, ServiceExt,
+    model::ErrorCode,
+    transport::{
+        HyperClient, StreamableHttpClientTransport,
+        streamable_http_client::{
+            ReinitializedSession, SessionRecovery, StreamableHttpClientTransportConfig,
+        },
+    },
+};
+use serde_json::{Value, json};
+use tokio_util::sync::CancellationToken;
+
+/// How the server answers requests.
+#[derive(Clone, Copy, PartialEq)]
+enum Scenario {
+    /// the event stream of a request ends before its response, which is sent on resumption
+    Resume,
+    /// the first session expires as soon as it is initialized
+    Expire,
+    /// the event stream of a request ends without its response
+    Lost,
+}
+
+#[derive(Clone)]
+struct Server {
+    scenario: Scenario,
+    sessions: Arc<Mutex<usize>>,
+    pending: Arc<Mutex<Option<Value>>>,
+    resumptions: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            sessions: Default::default(),
+            pending: Default::default(),
+            resumptions: Default::default(),
+        }
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+fn tools_list_response(id: &Value) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "result": { "tools": [] } }).to_string()
+}
+
+fn tool_list_changed() -> Event {
+    Event::default().data(r#"{"jsonrpc":"2.0","method":"notifications/tools/list_changed"}"#)
+}
+
+async fn post(State(server): State<Server>, headers: HeaderMap, body: String) -> Response {
+    let message: Value = serde_json::from_str(&body).unwrap();
+    if message["method"] == "initialize" {
+        let session_id = {
+            let mut sessions = server.sessions.lock().unwrap();
+            *sessions += 1;
+            format!("s{sessions}")
+        };
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": message["id"],
+            "result": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "test", "version": "0" }
+            }
+        });
+        return (
+            [
+                ("content-type", "application/json".to_string()),
+                ("mcp-session-id", session_id),
+            ],
+            response.to_string(),
+        )
+            .into_response();
+    }
+    let Some(id) = message.get("id").cloned() else {
+        return StatusCode::ACCEPTED.into_response();
+    };
+    let session_id = header(&headers, "mcp-session-id");
+    match server.scenario {
+        Scenario::Resume => {
+            *server.pending.lock().unwrap() = Some(id);
+            let events = stream::iter([Ok::<_, std::io::Error>(tool_list_changed().id("e1"))]);
+            Sse::new(events).into_response()
+        }
+        Scenario::Expire if session_id.as_deref() == Some("s1") => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Scenario::Expire => (
+            [("content-type", "application/json")],
+            tools_list_response(&id),
+        )
+            .into_response(),
+        Scenario::Lost => {
+            let events = stream::iter([Ok::<_, std::io::Error>(tool_list_changed())]);
+            Sse::new(events).into_response()
+        }
+    }
+}
+
+async fn get_stream(State(server): State<Server>, headers: HeaderMap) -> Response {
+    // there is no general purpose event stream, only resumptions
+    let Some(last_event_id) = header(&headers, "last-event-id") else {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    };
+    server.resumptions.lock().unwrap().push(last_event_id);
+    let Some(id) = server.pending.lock().unwrap().take() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let response = Event::default().id("e2").data(tools_list_response(&id));
+    let events = stream::iter([Ok::<_, std::io::Error>(response)]).chain(stream::pending());
+    Sse::new(events).into_response()
+}
+
+async fn serve(server: Server) -> anyhow::Result<(String, CancellationToken)> {
+    let router = Router::new()
+        .route("/mcp", get(get_stream).post(post))
+        .with_state(server);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let ct = CancellationToken::new();
+    let shutdown = ct.clone();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, router)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+    });
+    Ok((format!("http://{addr}/mcp"), ct))
+}
+
+#[tokio::test]
+async fn test_resume_request_stream() -> anyhow::Result<()> {
+    let server = Server::new(Scenario::Resume);
+    let (uri, ct) = serve(server.clone()).await?;
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::new(),
+        StreamableHttpClientTransportConfig::with_uri(uri),
+    );
+    let client = ().serve(transport).await?;
+    let tools = client.list_tools(None).await?;
+    assert!(tools.tools.is_empty());
+    assert_eq!(*server.resumptions.lock().unwrap(), ["e1"]);
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reinitialize_expired_session() -> anyhow::Result<()> {
+    let server = Server::new(Scenario::Expire);
+    let (uri, ct) = serve(server.clone()).await?;
+    let reinitialized = Arc::new(Mutex::new(Vec::<ReinitializedSession>::new()));
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::new(),
+        StreamableHttpClientTransportConfig {
+            session_recovery: SessionRecovery::reinitialize().on_reinitialized({
+                let reinitialized = reinitialized.clone();
+                move |session| reinitialized.lock().unwrap().push(session.clone())
+            }),
+            ..StreamableHttpClientTransportConfig::with_uri(uri)
+        },
+    );
+    let client = ().serve(transport).await?;
+    let tools = client.list_tools(None).await?;
+    assert!(tools.tools.is_empty());
+    {
+        let reinitialized = reinitialized.lock().unwrap();
+        assert_eq!(reinitialized.len(), 1);
+        assert_eq!(reinitialized[0].previous_session_id.as_deref(), Some("s1"));
+        assert_eq!(reinitialized[0].session_id.as_deref(), Some("s2"));
+        assert_eq!(reinitialized[0].server_info.server_info.name, "test");
+    }
+    assert_eq!(*server.sessions.lock().unwrap(), 2);
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_session_without_recovery() -> anyhow::Result<()> {
+    let server = Server::new(Scenario::Expire);
+    let (uri, ct) = serve(server.clone()).await?;
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::new(),
+        StreamableHttpClientTransportConfig::with_uri(uri),
+    );
+    let client = ().serve(transport).await?;
+    let result = client.list_tools(None).await;
+    assert!(matches!(result, Err(ServiceError::TransportSend(_))));
+    assert_eq!(*server.sessions.lock().unwrap(), 1);
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_response_lost() -> anyhow::Result<()> {
+    let (uri, ct) = serve(Server::new(Scenario::Lost)).await?;
+    let transport = StreamableHttpClientTransport::with_client(
+        HyperClient::new(),
+        StreamableHttpClientTransportConfig::with_uri(uri),
+    );
+    let client = ().serve(transport).await?;
+    let result = client.list_tools(None).await;
+    let Err(ServiceError::McpError(error)) = result else {
+        panic!("expected the response to be lost, got {result:?}");
+    };
+    assert_eq!(error.code, ErrorCode::RESPONSE_LOST);
+    client.cancel().await?;
+    ct.cancel();
+    Ok(())
+}
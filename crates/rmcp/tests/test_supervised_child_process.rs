@@ -0,0 +1,187 @@
+#![cfg(unix)]
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use rmcp::{
+    ServiceExt,
+    service::QuitReason,
+    transport::child_process::{ResourceLimits, RestartPolicy, SupervisedChildProcess},
+};
+
+/// A minimal MCP server, which crashes with the exit code in `$CRASH_CODE` when asked to list its
+/// tools on its first run.
+const SERVER: &str = r#"
+runs=$(cat "$1" 2>/dev/null || echo 0)
+runs=$((runs + 1))
+echo "$runs" > "$1"
+echo "run=$runs home=$HOME visible=$VISIBLE pwd=$(pwd) nofile=$(ulimit -n)" >&2
+while IFS= read -r line; do
+  id=$(echo "$line" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$line" in
+    *'"method":"initialize"'*)
+      echo '{"jsonrpc":"2.0","id":'"$id"',"result":{"protocolVersion":"2025-03-26","capabilities":{"tools":{}},"serverInfo":{"name":"sh","version":"'"$runs"'"}}}'
+      ;;
+    *'"method":"tools/list"'*)
+      if [ "$runs" = 1 ] && [ -n "$CRASH_CODE" ]; then exit "$CRASH_CODE"; fi
+      echo '{"jsonrpc":"2.0","id":'"$id"',"result":{"tools":[]}}'
+      ;;
+  esac
+done
+"#;
+
+fn temp_path(name: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("rmcp-{name}-{}-{nanos}", std::process::id()))
+}
+
+fn server_command(script: &str, runs: &PathBuf) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new("/bin/sh");
+    command.arg("-c").arg(script).arg("sh").arg(runs);
+    command
+}
+
+#[derive(Clone, Default)]
+struct StderrLines(Arc<Mutex<Vec<String>>>);
+
+impl StderrLines {
+    fn push(&self, line: &str) {
+        self.0.lock().unwrap().push(line.to_string());
+    }
+
+    async fn wait_for(&self, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            let lines = self.0.lock().unwrap().clone();
+            if lines.len() >= count {
+                return lines;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!(
+            "expected {count} stderr lines, got {:?}",
+            self.0.lock().unwrap()
+        );
+    }
+}
+
+fn fast_restart() -> RestartPolicy {
+    RestartPolicy {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_restart_after_crash() -> anyhow::Result<()> {
+    let runs = temp_path("runs");
+    let stderr = StderrLines::default();
+    let transport = SupervisedChildProcess::builder(server_command(SERVER, &runs))
+        .env("CRASH_CODE", "3")
+        .restart_policy(fast_restart())
+        .on_stderr({
+            let stderr = stderr.clone();
+            move |line| stderr.push(line)
+        })
+        .spawn()?;
+    let client = ().serve(transport).await?;
+
+    // the request in flight when the server crashed fails
+    let error = client.list_all_tools().await.unwrap_err();
+    assert!(error.to_string().contains("exit status: 3"), "{error}");
+
+    // the restarted server is initialized again transparently
+    let tools = client.list_all_tools().await?;
+    assert!(tools.is_empty());
+    assert_eq!(std::fs::read_to_string(&runs)?.trim(), "2");
+    let lines = stderr.wait_for(2).await;
+    assert!(lines[0].starts_with("run=1"));
+    assert!(lines[1].starts_with("run=2"));
+
+    client.cancel().await?;
+    std::fs::remove_file(&runs)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_exit_status_in_quit_reason() -> anyhow::Result<()> {
+    let runs = temp_path("runs");
+    let transport = SupervisedChildProcess::builder(server_command(SERVER, &runs))
+        .env("CRASH_CODE", "7")
+        .restart_policy(RestartPolicy::never())
+        .spawn()?;
+    let client = ().serve(transport).await?;
+    assert!(client.list_all_tools().await.is_err());
+    match client.waiting().await? {
+        QuitReason::ProcessExited(status) => assert_eq!(status.code(), Some(7)),
+        reason => panic!("unexpected quit reason {reason:?}"),
+    }
+    std::fs::remove_file(&runs)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sandboxed_environment() -> anyhow::Result<()> {
+    let runs = temp_path("runs");
+    let dir = std::env::temp_dir().canonicalize()?;
+    let stderr = StderrLines::default();
+    let transport = SupervisedChildProcess::builder(server_command(SERVER, &runs))
+        .env_allow(["PATH"])
+        .env("VISIBLE", "yes")
+        .current_dir(&dir)
+        .resource_limits(ResourceLimits {
+            open_files: Some(64),
+            ..Default::default()
+        })
+        .on_stderr({
+            let stderr = stderr.clone();
+            move |line| stderr.push(line)
+        })
+        .spawn()?;
+    let client = ().serve(transport).await?;
+    let lines = stderr.wait_for(1).await;
+    assert_eq!(
+        lines[0],
+        format!("run=1 home= visible=yes pwd={} nofile=64", dir.display())
+    );
+    client.cancel().await?;
+    std::fs::remove_file(&runs)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: &str) -> bool {
+    // a zombie has already been killed, it's just not reaped yet
+    std::fs::read_to_string(format!("/proc/{pid}/stat"))
+        .is_ok_and(|stat| stat.split_whitespace().nth(2) != Some("Z"))
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_process_group_killed_on_drop() -> anyhow::Result<()> {
+    let runs = temp_path("runs");
+    let pid_file = temp_path("pid");
+    let script = format!(
+        "sleep 1000 </dev/null >/dev/null 2>&1 & echo $! > {}\n{SERVER}",
+        pid_file.display()
+    );
+    let transport = SupervisedChildProcess::new(server_command(&script, &runs))?;
+    let client = ().serve(transport).await?;
+    let pid = std::fs::read_to_string(&pid_file)?.trim().to_string();
+    assert!(is_running(&pid));
+
+    drop(client);
+    for _ in 0..100 {
+        if !is_running(&pid) {
+            std::fs::remove_file(&runs)?;
+            std::fs::remove_file(&pid_file)?;
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("grandchild process {pid} is still running");
+}
@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use rmcp::{
+    ServerHandler,
+    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    model::*,
+    service::RoleServer,
+    testing::{self, MockClient, MockServer},
+    tool, tool_handler, tool_router,
+};
+use serde_json::json;
+
+const WITHIN: Duration = Duration::from_millis(500);
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct InstallRequest {
+    name: String,
+}
+
+#[derive(Clone)]
+struct Plugins {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Plugins {
+    fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    #[tool(description = "Install a plugin")]
+    async fn install(
+        &self,
+        Parameters(InstallRequest { name }): Parameters<InstallRequest>,
+        peer: rmcp::Peer<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        peer.notify_logging_message(LoggingMessageNotificationParam {
+            level: LoggingLevel::Info,
+            logger: None,
+            data: json!(format!("installed {name}")),
+        })
+        .await
+        .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        peer.notify_tool_list_changed()
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        Ok(CallToolResult::success(vec![]))
+    }
+
+    #[tool(description = "List the roots of the client")]
+    async fn roots(&self, peer: rmcp::Peer<RoleServer>) -> Result<String, ErrorData> {
+        let roots = peer
+            .list_roots()
+            .await
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?;
+        Ok(roots
+            .roots
+            .into_iter()
+            .map(|root| root.uri)
+            .collect::<Vec<_>>()
+            .join(","))
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Plugins {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn call(name: &'static str, arguments: serde_json::Value) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: arguments.as_object().cloned(),
+    }
+}
+
+#[tokio::test]
+async fn test_expect_notifications() -> anyhow::Result<()> {
+    let connection = testing::connect(Plugins::new()).await?;
+    let mock = connection.mock_client();
+    connection
+        .client
+        .call_tool(call("install", json!({ "name": "git" })))
+        .await?;
+
+    // expectations may be checked in any order
+    mock.expect_tool_list_changed(WITHIN).await?;
+    let message = mock.expect_logging_message(WITHIN).await?;
+    assert_eq!(message.data, json!("installed git"));
+    assert!(mock.notifications().is_empty());
+
+    // a matched notification is consumed
+    let error = mock
+        .expect_tool_list_changed(Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(error.pending.is_empty());
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expect_notification_sent_later() -> anyhow::Result<()> {
+    let connection = testing::connect(Plugins::new()).await?;
+    let peer = connection.server.peer().clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        peer.notify_resource_updated(ResourceUpdatedNotificationParam {
+            uri: "file:///b".into(),
+        })
+        .await
+    });
+    connection
+        .mock_client()
+        .expect_resource_updated("file:///b", WITHIN)
+        .await?;
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_client_roots() -> anyhow::Result<()> {
+    let client = MockClient::new().with_roots(vec![
+        Root {
+            uri: "file:///a".into(),
+            name: None,
+        },
+        Root {
+            uri: "file:///b".into(),
+            name: None,
+        },
+    ]);
+    let connection = testing::connect_with(client, Plugins::new()).await?;
+    let result = connection
+        .client
+        .call_tool(call("roots", json!({})))
+        .await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        "file:///a,file:///b"
+    );
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_client_on_request() -> anyhow::Result<()> {
+    let client = MockClient::new().on_request(|request, _context| async move {
+        match request {
+            ServerRequest::CreateMessageRequest(_) => {
+                Ok(ClientResult::CreateMessageResult(CreateMessageResult {
+                    model: "mock".into(),
+                    stop_reason: None,
+                    message: SamplingMessage {
+                        role: Role::Assistant,
+                        content: Content::text("hello"),
+                    },
+                }))
+            }
+            _ => Err(ErrorData::internal_error("unexpected request", None)),
+        }
+    });
+    let connection = testing::connect_with(client, Plugins::new()).await?;
+    let result = connection
+        .server
+        .create_message(CreateMessageRequestParam {
+            messages: vec![],
+            model_preferences: None,
+            system_prompt: None,
+            include_context: None,
+            temperature: None,
+            max_tokens: 16,
+            stop_sequences: None,
+            metadata: None,
+        })
+        .await?;
+    assert_eq!(result.model, "mock");
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_server() -> anyhow::Result<()> {
+    let server = MockServer::new()
+        .tool(
+            Tool::new("echo", "Echo the arguments", JsonObject::new()),
+            |arguments, _context| async move {
+                Ok(CallToolResult::success(vec![Content::json(arguments)?]))
+            },
+        )
+        .on_request(|request, _context| async move {
+            match request {
+                ClientRequest::ListPromptsRequest(_) => {
+                    Ok(ServerResult::ListPromptsResult(ListPromptsResult::default()))
+                }
+                _ => Err(ErrorData::internal_error("unexpected request", None)),
+            }
+        });
+    let connection = testing::connect(server.clone()).await?;
+    let client = &connection.client;
+    let info = client.peer_info().unwrap();
+    assert!(info.capabilities.tools.is_some());
+
+    let tools = client.list_all_tools().await?;
+    assert_eq!(tools.len(), 1);
+    let result = client.call_tool(call("echo", json!({ "a": 1 }))).await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        r#"{"a":1}"#
+    );
+    let error = client
+        .call_tool(call("missing", json!({})))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("tool missing not found"));
+    client.list_all_prompts().await?;
+    let error = client.list_all_resources().await.unwrap_err();
+    assert!(error.to_string().contains("unexpected request"));
+
+    assert!(matches!(
+        server.notifications()[..],
+        [ClientNotification::InitializedNotification(_)]
+    ));
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_deterministic_request_ids() -> anyhow::Result<()> {
+    for _ in 0..2 {
+        let server = MockServer::new();
+        let connection = testing::connect(server.clone()).await?;
+        connection
+            .client
+            .send_request(ClientRequest::PingRequest(Default::default()))
+            .await?;
+        let error = connection.client.list_all_tools().await.unwrap_err();
+        assert!(error.to_string().contains("tools/list"));
+
+        let ids = server
+            .requests()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                RequestId::Number(0),
+                RequestId::Number(1),
+                RequestId::Number(2)
+            ]
+        );
+        connection.close().await;
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use rmcp::{
+    ServerHandler, ServiceError,
+    handler::server::router::tool::ToolRouter,
+    model::{
+        CallToolRequestParam, ClientInfo, ErrorCode, Implementation, ServerCapabilities, ServerInfo,
+    },
+    service::{RequestContext, RoleServer},
+    testing::{MockClient, connect_with},
+    tool, tool_handler, tool_router,
+};
+
+/// Only the client named `admin` may use the guarded tools.
+fn admin(context: &RequestContext<RoleServer>) -> bool {
+    context
+        .peer
+        .peer_info()
+        .is_some_and(|info| info.client_info.name == "admin")
+}
+
+#[derive(Debug, Clone)]
+struct Console {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Console {
+    #[tool(description = "Read the status")]
+    async fn status(&self) -> String {
+        "ok".into()
+    }
+
+    #[tool(description = "Restart the service", guard = admin)]
+    async fn restart(&self) -> String {
+        "restarted".into()
+    }
+}
+
+#[tool_router(router = maintenance_router)]
+impl Console {
+    #[tool(description = "Wipe the cache")]
+    async fn wipe(&self) -> String {
+        "wiped".into()
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Console {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn client(name: &str) -> MockClient {
+    MockClient::new().with_info(ClientInfo {
+        client_info: Implementation {
+            name: name.into(),
+            version: "0".into(),
+        },
+        ..Default::default()
+    })
+}
+
+fn console() -> Console {
+    Console {
+        tool_router: Console::tool_router() + Console::maintenance_router().with_guard(admin),
+    }
+}
+
+fn call(name: &'static str) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: None,
+    }
+}
+
+#[tokio::test]
+async fn test_guards_filter_listing() -> anyhow::Result<()> {
+    for (name, expected) in [
+        ("admin", vec!["restart", "status", "wipe"]),
+        ("guest", vec!["status"]),
+    ] {
+        let connection = connect_with(client(name), console()).await?;
+        let mut tools = connection
+            .client
+            .list_all_tools()
+            .await?
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect::<Vec<_>>();
+        tools.sort();
+        assert_eq!(tools, expected);
+        connection.close().await;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guards_forbid_calls() -> anyhow::Result<()> {
+    let connection = connect_with(client("admin"), console()).await?;
+    let result = connection.client.call_tool(call("restart")).await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        "restarted"
+    );
+    connection.close().await;
+
+    let connection = connect_with(client("guest"), console()).await?;
+    assert!(connection.client.call_tool(call("status")).await.is_ok());
+    for tool in ["restart", "wipe"] {
+        let ServiceError::McpError(error) =
+            connection.client.call_tool(call(tool)).await.unwrap_err()
+        else {
+            panic!("expected an mcp error");
+        };
+        assert_eq!(error.code, ErrorCode::FORBIDDEN);
+        assert_eq!(error.message, format!("access to tool {tool} is forbidden"));
+        assert_eq!(error.data.unwrap()["tool"], tool);
+    }
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_guard_of_hand_built_route() -> anyhow::Result<()> {
+    let console = Console {
+        tool_router: ToolRouter::new().with_route(Console::restart_tool_route()),
+    };
+    let connection = connect_with(client("guest"), console).await?;
+    assert!(connection.client.list_all_tools().await?.is_empty());
+    let ServiceError::McpError(error) = connection
+        .client
+        .call_tool(call("restart"))
+        .await
+        .unwrap_err()
+    else {
+        panic!("expected an mcp error");
+    };
+    assert_eq!(error.code, ErrorCode::FORBIDDEN);
+    connection.close().await;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use futures::FutureExt;
+use rmcp::{
+    ServerHandler, ServiceError,
+    handler::server::{
+        router::tool::{ToolRoute, ToolRouter},
+        tool::Parameters,
+    },
+    model::{
+        CallToolRequestParam, CallToolResult, Content, ErrorCode, ServerCapabilities, ServerInfo,
+        Tool, object,
+    },
+    testing::connect,
+    tool, tool_handler, tool_router,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Fast,
+    Slow,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SignUp {
+    #[schemars(regex(pattern = r"^[a-z]+$"))]
+    name: String,
+    #[schemars(email)]
+    email: String,
+    #[schemars(range(min = 1, max = 10))]
+    seats: u32,
+    mode: Mode,
+}
+
+#[derive(Debug, Clone)]
+struct Registry {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Registry {
+    fn new(validate: bool) -> Self {
+        let tool_router = Self::tool_router().with_route(ToolRoute::new_dyn(
+            Tool::new(
+                "has_arguments",
+                "Whether the call has arguments",
+                object(json!({ "type": "object" })),
+            ),
+            |context| {
+                let text = context.arguments.is_some().to_string();
+                async move { Ok(CallToolResult::success(vec![Content::text(text)])) }.boxed()
+            },
+        ));
+        Self {
+            tool_router: if validate {
+                tool_router.with_input_validation()
+            } else {
+                tool_router
+            },
+        }
+    }
+
+    #[tool(description = "Sign up")]
+    async fn sign_up(&self, Parameters(sign_up): Parameters<SignUp>) -> String {
+        format!("{} x{}", sign_up.name, sign_up.seats)
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Registry {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn sign_up(arguments: serde_json::Value) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: "sign_up".into(),
+        arguments: Some(object(arguments)),
+    }
+}
+
+#[tokio::test]
+async fn test_input_validation() -> anyhow::Result<()> {
+    let connection = connect(Registry::new(true)).await?;
+    let client = &connection.client;
+
+    let result = client
+        .call_tool(sign_up(json!({
+            "name": "ada",
+            "email": "ada@example.com",
+            "seats": 2,
+            "mode": "fast"
+        })))
+        .await?;
+    assert_eq!(result.content.unwrap()[0].as_text().unwrap().text, "ada x2");
+
+    let error = client
+        .call_tool(sign_up(json!({
+            "name": "Ada!",
+            "email": "not an email",
+            "seats": 11,
+            "mode": "turbo"
+        })))
+        .await
+        .unwrap_err();
+    let ServiceError::McpError(error) = error else {
+        panic!("unexpected error {error}");
+    };
+    assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    let mut pointers = error.data.unwrap()["violations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|violation| violation["pointer"].as_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    pointers.sort();
+    assert_eq!(pointers, ["/email", "/mode", "/name", "/seats"]);
+    assert!(
+        error
+            .message
+            .contains("/seats: 11 is greater than the maximum of 10")
+    );
+
+    let error = client.call_tool(sign_up(json!({}))).await.unwrap_err();
+    let ServiceError::McpError(error) = error else {
+        panic!("unexpected error {error}");
+    };
+    assert!(error.message.contains("\"name\" is a required property"));
+
+    for (arguments, has_arguments) in [(None, "false"), (Some(object(json!({}))), "true")] {
+        let result = client
+            .call_tool(CallToolRequestParam {
+                name: "has_arguments".into(),
+                arguments,
+            })
+            .await?;
+        assert_eq!(
+            result.content.unwrap()[0].as_text().unwrap().text,
+            has_arguments
+        );
+    }
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_without_input_validation() -> anyhow::Result<()> {
+    let connection = connect(Registry::new(false)).await?;
+    let result = connection
+        .client
+        .call_tool(sign_up(json!({
+            "name": "Ada!",
+            "email": "not an email",
+            "seats": 11,
+            "mode": "slow"
+        })))
+        .await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        "Ada! x11"
+    );
+    connection.close().await;
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use rmcp::{
+    ErrorData, Json, ServerHandler, ServiceError,
+    handler::server::{router::tool::ToolRouter, tool::cached_schema_for_type},
+    model::{CallToolRequestParam, CallToolResult, ErrorCode, ServerCapabilities, ServerInfo},
+    testing::connect,
+    tool, tool_handler, tool_router,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Report {
+    #[schemars(range(min = 0))]
+    count: i64,
+    label: String,
+}
+
+#[derive(Debug, Clone)]
+struct Reporter {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Reporter {
+    fn new(tool_router: ToolRouter<Self>) -> Self {
+        Self { tool_router }
+    }
+
+    #[tool(description = "A valid report")]
+    async fn report(&self) -> Json<Report> {
+        Json(Report {
+            count: 2,
+            label: "done".into(),
+        })
+    }
+
+    #[tool(description = "A failed report")]
+    async fn failed(&self) -> Result<Json<Report>, String> {
+        Err("no report".into())
+    }
+
+    #[tool(
+        description = "A report which doesn't match its schema",
+        output_schema = cached_schema_for_type::<Report>()
+    )]
+    async fn broken(&self) -> Result<CallToolResult, ErrorData> {
+        Ok(CallToolResult::structured(
+            json!({ "count": -1, "label": 3 }),
+        ))
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Reporter {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn call(name: &'static str) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: None,
+    }
+}
+
+#[tokio::test]
+async fn test_output_validation() -> anyhow::Result<()> {
+    let router = Reporter::tool_router().with_output_validation();
+    let connection = connect(Reporter::new(router)).await?;
+    let client = &connection.client;
+
+    let result = client.call_tool(call("report")).await?;
+    let structured_content = result.structured_content.unwrap();
+    assert_eq!(structured_content, json!({ "count": 2, "label": "done" }));
+    let mirror = &result.content.unwrap()[0];
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&mirror.as_text().unwrap().text)?,
+        structured_content
+    );
+
+    let result = client.call_tool(call("failed")).await?;
+    assert_eq!(result.is_error, Some(true));
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        "no report"
+    );
+
+    let ServiceError::McpError(error) = client.call_tool(call("broken")).await.unwrap_err() else {
+        panic!("expected an mcp error");
+    };
+    assert_eq!(error.code, ErrorCode::INTERNAL_ERROR);
+    assert!(
+        error
+            .message
+            .contains("/count: -1 is less than the minimum of 0")
+    );
+    let mut pointers = error.data.unwrap()["violations"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|violation| violation["pointer"].as_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    pointers.sort();
+    assert_eq!(pointers, ["/count", "/label"]);
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_without_output_validation() -> anyhow::Result<()> {
+    let mut router = Reporter::tool_router();
+    router.output_validation = None;
+    let connection = connect(Reporter::new(router)).await?;
+
+    let result = connection.client.call_tool(call("broken")).await?;
+    assert_eq!(
+        result.content.unwrap()[0].as_text().unwrap().text,
+        r#"{"count":-1,"label":3}"#
+    );
+    connection.close().await;
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use rmcp::{
+    ErrorData, Peer, RoleServer, ServerHandler,
+    handler::server::router::tool::ToolRouter,
+    model::{CallToolRequestParam, ErrorCode, ServerCapabilities, ServerInfo, object},
+    service::RequestContext,
+    testing::connect,
+    tool, tool_handler, tool_router,
+};
+use serde_json::json;
+
+mod units {
+    /// A type with the name of an extractor.
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    pub struct Meta {
+        pub unit: String,
+    }
+}
+
+use units::Meta;
+
+#[derive(Debug, Clone)]
+struct Calculator {
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Calculator {
+    fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+        }
+    }
+
+    /// Add two numbers.
+    ///
+    /// # Arguments
+    ///
+    /// * `a` - The first number
+    /// * `b` - The second number,
+    ///   added to the first one
+    #[tool]
+    async fn add(
+        &self,
+        a: i64,
+        b: i64,
+        #[arg(description = "Printed before the sum")] label: Option<String>,
+    ) -> String {
+        match label {
+            Some(label) => format!("{label} {}", a + b),
+            None => (a + b).to_string(),
+        }
+    }
+
+    #[tool(description = "Echo the text with the request id")]
+    fn echo(&self, text: String, context: RequestContext<RoleServer>) -> String {
+        format!("{text} {}", context.id)
+    }
+
+    #[tool(description = "Attach a unit to the value")]
+    fn measure(&self, value: f64, #[arg] meta: Meta) -> String {
+        format!("{value} {}", meta.unit)
+    }
+
+    #[tool(description = "Convert the value")]
+    fn convert(&self, value: f64, to: units::Meta, meta: rmcp::model::Meta) -> String {
+        format!(
+            "{value} {} {}",
+            to.unit,
+            meta.get_progress_token().is_some()
+        )
+    }
+
+    #[tool(description = "Repeat the text")]
+    async fn repeat(
+        text: String,
+        times: usize,
+        _peer: Peer<RoleServer>,
+    ) -> Result<String, ErrorData> {
+        Ok(text.repeat(times))
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Calculator {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_plain_arguments_schema() {
+    let tool = Calculator::add_tool_attr();
+    assert_eq!(tool.description.as_deref(), Some("Add two numbers."));
+    let schema = serde_json::Value::Object((*tool.input_schema).clone());
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["required"], json!(["a", "b"]));
+    assert_eq!(schema["properties"]["a"]["description"], "The first number");
+    assert_eq!(
+        schema["properties"]["b"]["description"],
+        "The second number, added to the first one"
+    );
+    assert_eq!(
+        schema["properties"]["label"]["description"],
+        "Printed before the sum"
+    );
+
+    let schema = Calculator::echo_tool_attr().input_schema;
+    assert_eq!(schema["required"], json!(["text"]));
+    assert!(schema["properties"].get("context").is_none());
+
+    // extractors are told apart by their path, `#[arg]` opts out
+    let schema = Calculator::measure_tool_attr().input_schema;
+    assert_eq!(schema["required"], json!(["value", "meta"]));
+    let schema = Calculator::convert_tool_attr().input_schema;
+    assert_eq!(schema["required"], json!(["value", "to"]));
+}
+
+#[tokio::test]
+async fn test_call_plain_arguments() -> anyhow::Result<()> {
+    let calculator = Calculator::new();
+    assert_eq!(calculator.add(1, 2, None).await, "3");
+
+    let connection = connect(calculator).await?;
+    let client = &connection.client;
+    let call = |name: &'static str, arguments: serde_json::Value| {
+        client.call_tool(CallToolRequestParam {
+            name: name.into(),
+            arguments: Some(object(arguments)),
+        })
+    };
+    let text = |result: rmcp::model::CallToolResult| {
+        result.content.unwrap()[0].as_text().unwrap().text.clone()
+    };
+
+    let result = call("add", json!({ "a": 1, "b": 2, "label": "sum" })).await?;
+    assert_eq!(text(result), "sum 3");
+    let result = call("echo", json!({ "text": "hello" })).await?;
+    assert!(text(result).starts_with("hello "));
+    let result = call("measure", json!({ "value": 2.5, "meta": { "unit": "m" } })).await?;
+    assert_eq!(text(result), "2.5 m");
+    let result = call("convert", json!({ "value": 1, "to": { "unit": "ft" } })).await?;
+    // the meta of the request, with the progress token of the client
+    assert_eq!(text(result), "1 ft true");
+    let result = call("repeat", json!({ "text": "ab", "times": 3 })).await?;
+    assert_eq!(text(result), "ababab");
+
+    let error = call("add", json!({ "a": 1 })).await.unwrap_err();
+    let rmcp::ServiceError::McpError(error) = error else {
+        panic!("unexpected error {error}");
+    };
+    assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+    assert!(error.message.contains("missing field `b`"));
+    connection.close().await;
+    Ok(())
+}
@@ -0,0 +1,127 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use rmcp::{
+    ServerHandler,
+    handler::server::router::tool::ToolRouter,
+    model::{CallToolRequestParam, CallToolResult, ServerCapabilities, ServerInfo},
+    testing::connect,
+    tool, tool_handler, tool_router,
+};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone)]
+struct Worker {
+    stopped: Arc<AtomicBool>,
+    tool_router: ToolRouter<Self>,
+}
+
+#[tool_router]
+impl Worker {
+    fn new() -> Self {
+        Self {
+            stopped: Default::default(),
+            tool_router: Self::tool_router().with_default_timeout(Duration::from_millis(50)),
+        }
+    }
+
+    #[tool(
+        description = "Never returns, but stops its work on cancellation",
+        timeout = "20ms"
+    )]
+    async fn stuck(&self, ct: CancellationToken) -> String {
+        let stopped = self.stopped.clone();
+        tokio::spawn(async move {
+            ct.cancelled().await;
+            stopped.store(true, Ordering::SeqCst);
+        });
+        std::future::pending().await
+    }
+
+    #[tool(description = "Sleep longer than the default timeout")]
+    async fn sleepy(&self) -> String {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        "awake".into()
+    }
+
+    #[tool(description = "Sleep, but within its own timeout", timeout = "5s")]
+    async fn patient(&self) -> String {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        "done".into()
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for Worker {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+fn call(name: &'static str) -> CallToolRequestParam {
+    CallToolRequestParam {
+        name: name.into(),
+        arguments: None,
+    }
+}
+
+fn text(result: CallToolResult) -> String {
+    result.content.unwrap()[0].as_text().unwrap().text.clone()
+}
+
+#[tokio::test]
+async fn test_tool_timeout_cancels_token() -> anyhow::Result<()> {
+    let worker = Worker::new();
+    let stopped = worker.stopped.clone();
+    let connection = connect(worker).await?;
+
+    let result = connection.client.call_tool(call("stuck")).await?;
+    assert_eq!(result.is_error, Some(true));
+    assert_eq!(text(result), "tool stuck timed out after 20ms");
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !stopped.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await?;
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_default_timeout() -> anyhow::Result<()> {
+    let connection = connect(Worker::new()).await?;
+
+    let result = connection.client.call_tool(call("sleepy")).await?;
+    assert_eq!(result.is_error, Some(true));
+    assert_eq!(text(result), "tool sleepy timed out after 50ms");
+
+    let result = connection.client.call_tool(call("patient")).await?;
+    assert_eq!(result.is_error, Some(false));
+    assert_eq!(text(result), "done");
+    connection.close().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeout_of_hand_built_route() -> anyhow::Result<()> {
+    let worker = Worker {
+        stopped: Default::default(),
+        tool_router: ToolRouter::new().with_route(Worker::stuck_tool_route()),
+    };
+    let connection = connect(worker).await?;
+
+    let result = connection.client.call_tool(call("stuck")).await?;
+    assert_eq!(result.is_error, Some(true));
+    assert_eq!(text(result), "tool stuck timed out after 20ms");
+    connection.close().await;
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use common::calculator::Calculator;
-use rmcp::{serve_client, serve_server};
+use rmcp::{serve_client, transport::ListenerServer};
 
 mod common;
 #[tokio::main]
@@ -11,13 +11,9 @@ async fn main() -> anyhow::Result<()> {
 
 async fn server() -> anyhow::Result<()> {
     let tcp_listener = tokio::net::TcpListener::bind("127.0.0.1:8001").await?;
-    while let Ok((stream, _)) = tcp_listener.accept().await {
-        tokio::spawn(async move {
-            let server = serve_server(Calculator::new(), stream).await?;
-            server.waiting().await?;
-            anyhow::Ok(())
-        });
-    }
+    ListenerServer::new(tcp_listener)
+        .serve(Calculator::new)
+        .await;
     Ok(())
 }
 
@@ -6,27 +6,14 @@ async fn main() -> anyhow::Result<()> {
     use std::fs;
 
     use common::calculator::Calculator;
-    use rmcp::{serve_client, serve_server};
+    use rmcp::{serve_client, transport::ListenerServer};
     use tokio::net::{UnixListener, UnixStream};
 
     const SOCKET_PATH: &str = "/tmp/rmcp_example.sock";
     async fn server(unix_listener: UnixListener) -> anyhow::Result<()> {
-        while let Ok((stream, addr)) = unix_listener.accept().await {
-            println!("Client connected: {:?}", addr);
-            tokio::spawn(async move {
-                match serve_server(Calculator::new(), stream).await {
-                    Ok(server) => {
-                        println!("Server initialized successfully");
-                        if let Err(e) = server.waiting().await {
-                            println!("Error while server waiting: {}", e);
-                        }
-                    }
-                    Err(e) => println!("Server initialization failed: {}", e),
-                }
-
-                anyhow::Ok(())
-            });
-        }
+        ListenerServer::new(unix_listener)
+            .serve(Calculator::new)
+            .await;
         Ok(())
     }
 